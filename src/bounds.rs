@@ -0,0 +1,353 @@
+//! Axis-aligned and oriented bounding boxes
+//!
+//! The oriented box starts from the smallest by volume among boxes with one
+//! axis along a candidate direction: the coordinate axes, the principal axes
+//! of the vertices and the normals of the largest flat areas of the mesh.
+//! For each direction the cross-section is fitted exactly with the minimum
+//! area rectangle around the convex hull of the projected vertices. The axis
+//! of the best candidate is then tilted in ever smaller steps while that
+//! shrinks the box. This finds the optimum for prismatic and machined parts
+//! and a local minimum otherwise, so other shapes get a close but not
+//! always the smallest fit.
+
+use std::collections::HashMap;
+use glam::{DMat3, DVec3, Mat3, Quat, Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+use crate::geometry::{triangle_normal, Mesh};
+use crate::math::{symmetric_eigen, Placement};
+
+/// Most face normal directions tried when fitting an oriented box
+const MAX_FACE_DIRECTIONS: usize = 64;
+
+/// First and last tilt, in radians, when refining an oriented box
+const REFINE_START_ANGLE: f32 = 0.5;
+const REFINE_END_ANGLE: f32 = 1e-4;
+
+/// Most tilts tried when refining an oriented box
+const MAX_REFINE_STEPS: usize = 256;
+
+/// Box aligned with the coordinate axes
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// The box containing nothing, which is the identity of `union`
+    pub const EMPTY: Aabb = Aabb { min: Vec3::splat(f32::INFINITY), max: Vec3::splat(f32::NEG_INFINITY) };
+
+    /// Smallest box containing the points
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Aabb {
+        points.into_iter().fold(Aabb::EMPTY, |b, p| Aabb { min: b.min.min(p), max: b.max.max(p) })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.cmpgt(self.max).any()
+    }
+
+    /// Whether the boxes overlap or touch
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        self.min.cmple(other.max).all() && other.min.cmple(self.max).all()
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb { min: self.min.min(other.min), max: self.max.max(other.max) }
+    }
+
+    /// Distance between the boxes, 0 if they touch or overlap
+    pub fn gap(&self, other: &Aabb) -> f32 {
+        (self.min - other.max).max(other.min - self.max).max(Vec3::ZERO).length()
+    }
+
+    pub fn size(&self) -> Vec3 {
+        if self.is_empty() { Vec3::ZERO } else { self.max - self.min }
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) / 2.0
+    }
+
+    pub fn corners(&self) -> [Vec3; 8] {
+        std::array::from_fn(|i| Vec3::select(glam::BVec3::new(i & 1 != 0, i & 2 != 0, i & 4 != 0), self.max, self.min))
+    }
+
+    /// Axis-aligned box around this box after moving it by `placement`
+    pub fn transformed(&self, placement: &Placement) -> Aabb {
+        if self.is_empty() {
+            return *self;
+        }
+        Aabb::from_points(self.corners().map(|p| placement.transform_point(p)))
+    }
+}
+
+/// Box with its own orientation
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Obb {
+    pub center: Vec3,
+    /// Turns the box's axes into the frame it is expressed in
+    pub rotation: Quat,
+    /// Edge lengths along the box's own X, Y and Z axes
+    pub size: Vec3,
+}
+
+impl Obb {
+    pub fn volume(&self) -> f32 {
+        self.size.x * self.size.y * self.size.z
+    }
+
+    pub fn corners(&self) -> [Vec3; 8] {
+        let half = self.size / 2.0;
+        std::array::from_fn(|i| {
+            let sign = Vec3::new(
+                if i & 1 != 0 { 1.0 } else { -1.0 },
+                if i & 2 != 0 { 1.0 } else { -1.0 },
+                if i & 4 != 0 { 1.0 } else { -1.0 },
+            );
+            self.center + self.rotation * (sign * half)
+        })
+    }
+
+    /// This box moved by `placement`
+    pub fn transformed(&self, placement: &Placement) -> Obb {
+        Obb {
+            center: placement.transform_point(self.center),
+            rotation: (placement.rotation * self.rotation).normalize(),
+            size: self.size,
+        }
+    }
+
+    /// Oriented box around a mesh, the best candidate refined to a local
+    /// minimum of the volume
+    pub fn fit(mesh: &Mesh) -> Obb {
+        let (points, triangles) = mesh.welded();
+        if points.is_empty() {
+            return Obb { center: Vec3::ZERO, rotation: Quat::IDENTITY, size: Vec3::ZERO };
+        }
+
+        let mut directions = vec![Vec3::X, Vec3::Y, Vec3::Z];
+        directions.extend(principal_axes(&points));
+        // Group faces by normal direction, ignoring sign, and keep the
+        // directions covering the most area
+        let mut areas: HashMap<[i32; 3], (Vec3, f32)> = HashMap::new();
+        for [a, b, c] in &triangles {
+            let [a, b, c] = [a, b, c].map(|i| points[*i as usize]);
+            let normal = triangle_normal(a, b, c);
+            if normal == Vec3::ZERO {
+                continue;
+            }
+            // Opposite faces give the same direction
+            let leading = normal.to_array().into_iter().find(|x| x.abs() > 1e-6).unwrap_or(1.0);
+            let normal = if leading < 0.0 { -normal } else { normal };
+            let key = (normal * 1e4).round().as_ivec3().to_array();
+            let entry = areas.entry(key).or_insert((normal, 0.0));
+            entry.1 += (b - a).cross(c - a).length() / 2.0;
+        }
+        let mut faces: Vec<(Vec3, f32)> = areas.into_values().collect();
+        faces.sort_by(|x, y| y.1.total_cmp(&x.1).then(x.0.to_array().partial_cmp(&y.0.to_array()).unwrap()));
+        directions.extend(faces.into_iter().take(MAX_FACE_DIRECTIONS).map(|(normal, _)| normal));
+
+        let best = directions
+            .into_iter()
+            .map(|axis| fit_along(&points, axis))
+            .min_by(|a, b| a.volume().total_cmp(&b.volume()))
+            .unwrap();
+        refine(&points, best)
+    }
+}
+
+/// Tilt the fitting axis of a box, halving the angle whenever no tilt
+/// makes the box smaller
+fn refine(points: &[Vec3], mut best: Obb) -> Obb {
+    let mut angle = REFINE_START_ANGLE;
+    for _ in 0..MAX_REFINE_STEPS {
+        if angle < REFINE_END_ANGLE {
+            break;
+        }
+        let axis = best.rotation * Vec3::Z;
+        let (u, v) = axis.any_orthonormal_pair();
+        let tilted = [u, -u, v, -v]
+            .into_iter()
+            .map(|pivot| fit_along(points, (Quat::from_axis_angle(pivot, angle) * axis).normalize()))
+            .min_by(|a, b| a.volume().total_cmp(&b.volume()))
+            .unwrap();
+        // Require a real gain so rounding cannot keep the search going
+        if tilted.volume() < best.volume() * (1.0 - 1e-6) {
+            best = tilted;
+        } else {
+            angle /= 2.0;
+        }
+    }
+    best
+}
+
+/// Eigenvectors of the covariance of the points
+fn principal_axes(points: &[Vec3]) -> [Vec3; 3] {
+    let mean = points.iter().map(|p| p.as_dvec3()).sum::<DVec3>() / points.len() as f64;
+    let mut covariance = DMat3::ZERO;
+    for p in points {
+        let d = p.as_dvec3() - mean;
+        covariance += DMat3::from_cols(d * d.x, d * d.y, d * d.z);
+    }
+    let (_, axes) = symmetric_eigen(covariance);
+    [axes.x_axis, axes.y_axis, axes.z_axis].map(|a| a.as_vec3())
+}
+
+/// Smallest box with one axis along `axis`
+fn fit_along(points: &[Vec3], axis: Vec3) -> Obb {
+    let (u, v) = axis.any_orthonormal_pair();
+    let projected: Vec<Vec2> = points.iter().map(|p| Vec2::new(p.dot(u), p.dot(v))).collect();
+    let (low, high) = points.iter().fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), p| {
+        let d = p.dot(axis);
+        (lo.min(d), hi.max(d))
+    });
+    let (direction, min, max) = min_area_rectangle(&convex_hull(projected));
+
+    // Box axes: the rectangle's sides in the plane, then the fitting axis
+    let x = u * direction.x + v * direction.y;
+    let y = axis.cross(x);
+    let center2 = (min + max) / 2.0;
+    let center = x * center2.x + y * center2.y + axis * (low + high) / 2.0;
+    Obb {
+        center,
+        rotation: Quat::from_mat3(&Mat3::from_cols(x, y, axis)).normalize(),
+        size: Vec3::new(max.x - min.x, max.y - min.y, high - low),
+    }
+}
+
+/// Convex hull in counter-clockwise order, by Andrew's monotone chain
+fn convex_hull(mut points: Vec<Vec2>) -> Vec<Vec2> {
+    points.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+    let cross = |o: Vec2, a: Vec2, b: Vec2| (a - o).perp_dot(b - o);
+    let mut hull: Vec<Vec2> = Vec::with_capacity(points.len() * 2);
+    for pass in [points.clone(), points.into_iter().rev().collect()] {
+        let start = hull.len();
+        for p in pass {
+            while hull.len() >= start + 2 && cross(hull[hull.len() - 2], hull[hull.len() - 1], p) <= 0.0 {
+                hull.pop();
+            }
+            hull.push(p);
+        }
+        hull.pop();
+    }
+    hull
+}
+
+/// Side direction of the smallest rectangle around a convex polygon, and
+/// its corners in the frame of that direction and its perpendicular
+fn min_area_rectangle(hull: &[Vec2]) -> (Vec2, Vec2, Vec2) {
+    let extents = |direction: Vec2| {
+        let frame = |p: &Vec2| Vec2::new(p.dot(direction), p.dot(direction.perp()));
+        hull.iter().fold((Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)), |(min, max), p| (min.min(frame(p)), max.max(frame(p))))
+    };
+    // One side of the smallest rectangle lies along an edge of the hull
+    let mut best = (Vec2::X, extents(Vec2::X));
+    for i in 0..hull.len() {
+        let Some(direction) = (hull[(i + 1) % hull.len()] - hull[i]).try_normalize() else { continue };
+        let candidate = extents(direction);
+        let area = |(min, max): (Vec2, Vec2)| (max - min).x * (max - min).y;
+        if area(candidate) < area(best.1) - 1e-9 {
+            best = (direction, candidate);
+        }
+    }
+    (best.0, best.1 .0, best.1 .1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{generate_box_mesh, generate_cylinder_mesh};
+
+    fn sorted(v: Vec3) -> [f32; 3] {
+        let mut a = v.to_array();
+        a.sort_by(f32::total_cmp);
+        a
+    }
+
+    fn contains(obb: &Obb, p: Vec3) -> bool {
+        let local = obb.rotation.inverse() * (p - obb.center);
+        local.abs().cmple(obb.size / 2.0 + 1e-4).all()
+    }
+
+    #[test]
+    fn test_aabb() {
+        let aabb = Aabb::from_points([Vec3::new(1.0, 2.0, 3.0), Vec3::new(-1.0, 0.0, 5.0)]);
+        assert_eq!(aabb, Aabb { min: Vec3::new(-1.0, 0.0, 3.0), max: Vec3::new(1.0, 2.0, 5.0) });
+        assert_eq!(aabb.size(), Vec3::new(2.0, 2.0, 2.0));
+        assert_eq!(aabb.center(), Vec3::new(0.0, 1.0, 4.0));
+        assert!(Aabb::EMPTY.is_empty());
+        assert_eq!(Aabb::EMPTY.size(), Vec3::ZERO);
+        assert_eq!(Aabb::EMPTY.union(&aabb), aabb);
+        assert_eq!(aabb.gap(&Aabb::from_points([Vec3::new(4.0, 6.0, 4.0)])), 5.0);
+        assert_eq!(aabb.gap(&Aabb::from_points([Vec3::new(0.0, 2.0, 4.0)])), 0.0);
+
+        let turned = aabb.transformed(&Placement::from_position_rotation(Vec3::X, Quat::from_rotation_z(std::f32::consts::FRAC_PI_2)));
+        assert!((turned.min - Vec3::new(-1.0, -1.0, 3.0)).length() < 1e-5);
+        assert!((turned.max - Vec3::new(1.0, 1.0, 5.0)).length() < 1e-5);
+    }
+
+    #[test]
+    fn test_obb_recovers_turned_box() {
+        let placement = Placement::from_position_rotation(
+            Vec3::new(5.0, -2.0, 1.0),
+            Quat::from_euler(glam::EulerRot::XYZ, 0.3, -0.5, 0.8),
+        );
+        let mesh = generate_box_mesh(40.0, 20.0, 10.0).transformed(&placement);
+        let obb = Obb::fit(&mesh);
+        let size = sorted(obb.size);
+        for (actual, expected) in size.iter().zip([10.0, 20.0, 40.0]) {
+            assert!((actual - expected).abs() < 1e-3, "{:?}", size);
+        }
+        assert!((obb.center - placement.position).length() < 1e-3);
+        let aabb = Aabb::from_points((0..mesh.vertex_count()).map(|i| mesh.vertex(i)));
+        assert!(obb.volume() < aabb.size().x * aabb.size().y * aabb.size().z);
+        assert!((0..mesh.vertex_count()).all(|i| contains(&obb, mesh.vertex(i))));
+    }
+
+    #[test]
+    fn test_obb_of_cylinder() {
+        let mesh = generate_cylinder_mesh(2.0, 10.0);
+        let obb = Obb::fit(&mesh);
+        let size = sorted(obb.size);
+        assert!((size[2] - 10.0).abs() < 1e-4);
+        assert!(size[0] <= 4.0 + 1e-4 && size[0] > 3.9);
+        assert!((0..mesh.vertex_count()).all(|i| contains(&obb, mesh.vertex(i))));
+
+        let moved = obb.transformed(&Placement::from_position_rotation(Vec3::Z, Quat::IDENTITY));
+        assert_eq!(moved.center, obb.center + Vec3::Z);
+        assert_eq!(moved.size, obb.size);
+    }
+
+    #[test]
+    fn test_obb_refines_off_candidate_axes() {
+        // The smallest box of a regular tetrahedron is the cube it is cut
+        // from, whose axes are none of the candidate directions once turned
+        let corners = [Vec3::ONE, Vec3::new(1.0, -1.0, -1.0), Vec3::new(-1.0, 1.0, -1.0), Vec3::new(-1.0, -1.0, 1.0)];
+        let rotation = Quat::from_euler(glam::EulerRot::XYZ, 0.4, -0.7, 1.1);
+        let mut mesh = Mesh::new();
+        for [a, b, c] in [[0, 1, 2], [0, 3, 1], [0, 2, 3], [1, 3, 2]] {
+            for i in [a, b, c] {
+                mesh.vertices.extend((rotation * corners[i]).to_array());
+            }
+        }
+        mesh.indices = (0..12).collect();
+        let obb = Obb::fit(&mesh);
+        assert!((obb.volume() - 8.0).abs() < 0.05, "{:?}", obb.size);
+        assert!((0..mesh.vertex_count()).all(|i| contains(&obb, mesh.vertex(i))));
+    }
+
+    #[test]
+    fn test_min_area_rectangle() {
+        // A diamond fits best in a square turned by 45 degrees
+        let hull = convex_hull(vec![Vec2::X, Vec2::Y, -Vec2::X, -Vec2::Y, Vec2::ZERO]);
+        assert_eq!(hull.len(), 4);
+        let (direction, min, max) = min_area_rectangle(&hull);
+        assert!((direction.x.abs() - direction.y.abs()).abs() < 1e-6);
+        assert!(((max - min).x * (max - min).y - 2.0).abs() < 1e-5);
+        assert_eq!(Obb::fit(&Mesh::new()).size, Vec3::ZERO);
+    }
+}
@@ -0,0 +1,425 @@
+//! Ray casting against meshes, accelerated by a bounding volume hierarchy
+//!
+//! The hierarchy is a binary tree of axis-aligned boxes over the triangles,
+//! split at the median of the triangle centers along the widest axis until
+//! a few triangles remain per leaf. Rays hit triangles from either side.
+//! Two hierarchies are descended together to find the nearest triangles of
+//! two meshes, and a hierarchy over plain boxes finds those overlapping a box.
+
+use std::ops::ControlFlow;
+use glam::Vec3;
+use serde::Serialize;
+use crate::bounds::Aabb;
+use crate::geometry::{triangle_normal, Mesh};
+use crate::math::Placement;
+
+/// Most triangles in a leaf
+const LEAF_SIZE: usize = 4;
+
+/// A half-line from `origin` along `direction`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    pub origin: Vec3,
+    /// Unit direction
+    pub direction: Vec3,
+}
+
+impl Ray {
+    /// Ray through `origin` along `direction`, or `None` if the direction is zero
+    pub fn new(origin: Vec3, direction: Vec3) -> Option<Ray> {
+        Some(Ray { origin, direction: direction.try_normalize()? })
+    }
+
+    pub fn at(&self, t: f32) -> Vec3 {
+        self.origin + self.direction * t
+    }
+
+    /// The ray moved by `placement`
+    pub fn transformed(&self, placement: &Placement) -> Ray {
+        Ray { origin: placement.transform_point(self.origin), direction: placement.rotation * self.direction }
+    }
+
+    /// Distance along the ray where it enters a box, or 0 if it starts inside,
+    /// unless it misses the box or only reaches it beyond `max_t`
+    fn enters(&self, aabb: &Aabb, max_t: f32) -> Option<f32> {
+        let inverse = self.direction.recip();
+        let a = (aabb.min - self.origin) * inverse;
+        let b = (aabb.max - self.origin) * inverse;
+        // NaN from 0 * inf (a ray in the box's plane) is ignored by min and max
+        let near = a.min(b).max_element().max(0.0);
+        let far = a.max(b).min_element().min(max_t);
+        (near <= far).then_some(near)
+    }
+}
+
+/// Where a ray meets a mesh
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Hit {
+    /// Distance along the ray
+    pub distance: f32,
+    pub point: Vec3,
+    /// Unit normal of the triangle, following its winding
+    pub normal: Vec3,
+    pub triangle: usize,
+}
+
+impl Hit {
+    /// The hit moved by `placement`
+    pub fn transformed(&self, placement: &Placement) -> Hit {
+        Hit { point: placement.transform_point(self.point), normal: placement.rotation * self.normal, ..*self }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    /// Children are at `index + 1` and `right`
+    Branch { aabb: Aabb, right: usize },
+    /// Triangles `start..end` of the hierarchy's order
+    Leaf { aabb: Aabb, start: usize, end: usize },
+}
+
+impl Node {
+    fn aabb(&self) -> &Aabb {
+        match self {
+            Node::Branch { aabb, .. } | Node::Leaf { aabb, .. } => aabb,
+        }
+    }
+}
+
+/// Bounding volume hierarchy over the triangles of one mesh, or over any
+/// list of boxes
+#[derive(Debug, Clone, Default)]
+pub struct Bvh {
+    nodes: Vec<Node>,
+    /// Triangle (or box) indices, grouped by leaf
+    triangles: Vec<usize>,
+}
+
+impl Bvh {
+    pub fn build(mesh: &Mesh) -> Bvh {
+        let boxes: Vec<Aabb> = mesh.triangles().map(Aabb::from_points).collect();
+        Bvh::from_boxes(&boxes)
+    }
+
+    /// Hierarchy over boxes, whose leaves refer to them by index
+    pub fn from_boxes(boxes: &[Aabb]) -> Bvh {
+        let mut bvh = Bvh { nodes: Vec::new(), triangles: (0..boxes.len()).collect() };
+        if !boxes.is_empty() {
+            let centers: Vec<Vec3> = boxes.iter().map(Aabb::center).collect();
+            bvh.split(boxes, &centers, 0, boxes.len());
+        }
+        bvh
+    }
+
+    fn split(&mut self, boxes: &[Aabb], centers: &[Vec3], start: usize, end: usize) {
+        let aabb = self.triangles[start..end].iter().fold(Aabb::EMPTY, |aabb, t| aabb.union(&boxes[*t]));
+        let index = self.nodes.len();
+        if end - start <= LEAF_SIZE {
+            self.nodes.push(Node::Leaf { aabb, start, end });
+            return;
+        }
+        let spread = Aabb::from_points(self.triangles[start..end].iter().map(|t| centers[*t])).size();
+        let axis = if spread.x >= spread.y && spread.x >= spread.z { 0 } else if spread.y >= spread.z { 1 } else { 2 };
+        let middle = (start + end) / 2;
+        self.triangles[start..end].select_nth_unstable_by(middle - start, |a, b| centers[*a][axis].total_cmp(&centers[*b][axis]));
+
+        self.nodes.push(Node::Branch { aabb, right: 0 });
+        self.split(boxes, centers, start, middle);
+        let right = self.nodes.len();
+        self.nodes[index] = Node::Branch { aabb, right };
+        self.split(boxes, centers, middle, end);
+    }
+
+    /// Box around the whole mesh, `Aabb::EMPTY` for an empty one
+    pub fn aabb(&self) -> Aabb {
+        self.nodes.first().map_or(Aabb::EMPTY, |node| *node.aabb())
+    }
+
+    /// Nearest hit of a ray on the mesh the hierarchy was built for
+    pub fn intersect(&self, mesh: &Mesh, ray: &Ray) -> Option<Hit> {
+        let mut nearest: Option<(f32, usize)> = None;
+        let mut stack = Vec::new();
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+        while let Some(index) = stack.pop() {
+            let max_t = nearest.map_or(f32::INFINITY, |(t, _)| t);
+            let node = &self.nodes[index];
+            if ray.enters(node.aabb(), max_t).is_none() {
+                continue;
+            }
+            match node {
+                Node::Branch { right, .. } => {
+                    // Visit the nearer child first so that the other is more often culled
+                    let near = |i: usize| ray.enters(self.nodes[i].aabb(), max_t).unwrap_or(f32::INFINITY);
+                    let (first, second) = if near(index + 1) <= near(*right) { (index + 1, *right) } else { (*right, index + 1) };
+                    stack.push(second);
+                    stack.push(first);
+                }
+                Node::Leaf { start, end, .. } => {
+                    for &triangle in &self.triangles[*start..*end] {
+                        if let Some(t) = intersect_triangle(ray, mesh.triangle(triangle)) {
+                            if nearest.is_none_or(|(best, _)| t < best) {
+                                nearest = Some((t, triangle));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        nearest.map(|(distance, triangle)| {
+            let [a, b, c] = mesh.triangle(triangle);
+            Hit { distance, point: ray.at(distance), normal: triangle_normal(a, b, c), triangle }
+        })
+    }
+
+    /// Whether `test` holds for any item in a leaf whose box overlaps `aabb`
+    pub fn any_overlapping(&self, aabb: &Aabb, mut test: impl FnMut(usize) -> bool) -> bool {
+        let mut stack = Vec::new();
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !node.aabb().overlaps(aabb) {
+                continue;
+            }
+            match node {
+                Node::Branch { right, .. } => stack.extend([*right, index + 1]),
+                Node::Leaf { start, end, .. } => {
+                    if self.triangles[*start..*end].iter().any(|item| test(*item)) {
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// Nearest points of the items of two hierarchies, if they are no
+    /// further apart than `limit`
+    ///
+    /// Each hierarchy is moved into world space by its placement, and
+    /// `closest` gives the world space nearest points of an item of this
+    /// hierarchy and one of `other`. Pairs of nodes whose boxes are further
+    /// apart than the best distance so far are skipped, and the search stops
+    /// at the first contact.
+    pub fn nearest(
+        &self,
+        placement: &Placement,
+        other: &Bvh,
+        other_placement: &Placement,
+        limit: f32,
+        mut closest: impl FnMut(usize, usize) -> (Vec3, Vec3),
+    ) -> Option<(Vec3, Vec3)> {
+        let mut best: Option<(f32, Vec3, Vec3)> = None;
+        self.leaf_pairs(placement, other, other_placement, limit, |first, second| {
+            for &a in first {
+                for &b in second {
+                    let (p, q) = closest(a, b);
+                    let distance = p.distance(q);
+                    if distance <= best.map_or(limit, |(d, ..)| d) && best.is_none_or(|(d, ..)| distance < d) {
+                        best = Some((distance, p, q));
+                        if distance == 0.0 {
+                            return ControlFlow::Break(());
+                        }
+                    }
+                }
+            }
+            ControlFlow::Continue(best.map_or(limit, |(distance, ..)| distance))
+        });
+        best.map(|(_, p, q)| (p, q))
+    }
+
+    /// Whether `test` holds for any item of this hierarchy and one of
+    /// `other` whose leaves' boxes, placed in world space, are within `margin`
+    pub fn any_pair(
+        &self,
+        placement: &Placement,
+        other: &Bvh,
+        other_placement: &Placement,
+        margin: f32,
+        mut test: impl FnMut(usize, usize) -> bool,
+    ) -> bool {
+        let mut found = false;
+        self.leaf_pairs(placement, other, other_placement, margin, |first, second| {
+            found = first.iter().any(|a| second.iter().any(|b| test(*a, *b)));
+            if found { ControlFlow::Break(()) } else { ControlFlow::Continue(margin) }
+        });
+        found
+    }
+
+    /// Visit the items of pairs of leaves of two placed hierarchies whose
+    /// boxes are within a bound, nearer pairs first, until `visit` breaks
+    ///
+    /// `visit` returns the bound for the remaining pairs.
+    fn leaf_pairs(
+        &self,
+        placement: &Placement,
+        other: &Bvh,
+        other_placement: &Placement,
+        mut bound: f32,
+        mut visit: impl FnMut(&[usize], &[usize]) -> ControlFlow<(), f32>,
+    ) {
+        if self.nodes.is_empty() || other.nodes.is_empty() {
+            return;
+        }
+        let boxes = |bvh: &Bvh, placement: &Placement| -> Vec<Aabb> {
+            bvh.nodes.iter().map(|node| node.aabb().transformed(placement)).collect()
+        };
+        let (first, second) = (boxes(self, placement), boxes(other, other_placement));
+        let mut stack = vec![(0, 0, first[0].gap(&second[0]))];
+        while let Some((i, j, gap)) = stack.pop() {
+            if gap > bound {
+                continue;
+            }
+            match (&self.nodes[i], &other.nodes[j]) {
+                (Node::Leaf { start, end, .. }, Node::Leaf { start: other_start, end: other_end, .. }) => {
+                    match visit(&self.triangles[*start..*end], &other.triangles[*other_start..*other_end]) {
+                        ControlFlow::Continue(next) => bound = next,
+                        ControlFlow::Break(()) => return,
+                    }
+                }
+                (node, other_node) => {
+                    // Open the larger branch, and visit the nearer child first
+                    let open_first = match (node, other_node) {
+                        (Node::Branch { .. }, Node::Leaf { .. }) => true,
+                        (Node::Leaf { .. }, Node::Branch { .. }) => false,
+                        _ => first[i].size().length_squared() >= second[j].size().length_squared(),
+                    };
+                    let mut children = match (node, other_node) {
+                        (Node::Branch { right, .. }, _) if open_first => [i + 1, *right].map(|c| (c, j, first[c].gap(&second[j]))),
+                        (_, Node::Branch { right, .. }) => [j + 1, *right].map(|c| (i, c, first[i].gap(&second[c]))),
+                        _ => unreachable!("two leaves are visited above"),
+                    };
+                    children.sort_by(|a, b| b.2.total_cmp(&a.2));
+                    stack.extend(children);
+                }
+            }
+        }
+    }
+}
+
+/// Distance along a ray to a triangle, by the Möller-Trumbore algorithm
+pub fn intersect_triangle(ray: &Ray, [a, b, c]: [Vec3; 3]) -> Option<f32> {
+    let (edge1, edge2) = (b - a, c - a);
+    let p = ray.direction.cross(edge2);
+    let det = edge1.dot(p);
+    if det.abs() < f32::EPSILON * edge1.length_squared().max(edge2.length_squared()) {
+        return None;
+    }
+    let offset = ray.origin - a;
+    let u = offset.dot(p) / det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = offset.cross(edge1);
+    let v = ray.direction.dot(q) / det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = edge2.dot(q) / det;
+    (t >= 0.0).then_some(t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Quat;
+    use crate::geometry::{generate_box_mesh, generate_sphere_mesh};
+    use crate::measure::closest_between_triangles;
+
+    fn brute_force(mesh: &Mesh, ray: &Ray) -> Option<(f32, usize)> {
+        (0..mesh.triangle_count())
+            .filter_map(|i| intersect_triangle(ray, mesh.triangle(i)).map(|t| (t, i)))
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+    }
+
+    #[test]
+    fn test_box_hit() {
+        let mesh = generate_box_mesh(2.0, 2.0, 2.0);
+        let bvh = Bvh::build(&mesh);
+        let ray = Ray::new(Vec3::new(0.2, 0.3, 10.0), -Vec3::Z).unwrap();
+        let hit = bvh.intersect(&mesh, &ray).unwrap();
+        assert!((hit.distance - 9.0).abs() < 1e-5);
+        assert!((hit.point - Vec3::new(0.2, 0.3, 1.0)).length() < 1e-5);
+        assert_eq!(hit.normal, Vec3::Z);
+
+        // From inside, the far wall is hit
+        let inside = Ray::new(Vec3::ZERO, Vec3::X).unwrap();
+        assert!((bvh.intersect(&mesh, &inside).unwrap().distance - 1.0).abs() < 1e-5);
+        assert!(bvh.intersect(&mesh, &Ray::new(Vec3::new(0.0, 5.0, 10.0), -Vec3::Z).unwrap()).is_none());
+        assert!(Ray::new(Vec3::ZERO, Vec3::ZERO).is_none());
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        let mesh = generate_sphere_mesh(3.0);
+        let bvh = Bvh::build(&mesh);
+        assert_eq!(bvh.aabb(), Aabb::from_points(mesh.triangles().flatten()));
+        for i in 0..200 {
+            let angle = i as f32 * 0.37;
+            let origin = Vec3::new(angle.cos() * 8.0, (angle * 1.3).sin() * 8.0, (angle * 0.7).cos() * 8.0);
+            let target = Vec3::new((angle * 2.1).sin(), (angle * 1.7).cos(), (angle * 0.3).sin()) * 3.5;
+            let ray = Ray::new(origin, target - origin).unwrap();
+            let expected = brute_force(&mesh, &ray);
+            let hit = bvh.intersect(&mesh, &ray);
+            assert_eq!(hit.map(|h| h.distance), expected.map(|e| e.0), "ray {}", i);
+        }
+    }
+
+    #[test]
+    fn test_axis_parallel_rays_and_empty_meshes() {
+        // Rays lying in a face plane of the root box must not produce NaN misses
+        let mesh = generate_box_mesh(2.0, 2.0, 2.0);
+        let bvh = Bvh::build(&mesh);
+        let ray = Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::X).unwrap();
+        assert!((bvh.intersect(&mesh, &ray).unwrap().distance - 4.0).abs() < 1e-5);
+        assert!(Bvh::build(&Mesh::new()).intersect(&Mesh::new(), &ray).is_none());
+    }
+
+    #[test]
+    fn test_nearest_matches_brute_force() {
+        let (sphere, cube) = (generate_sphere_mesh(1.5), generate_box_mesh(2.0, 1.0, 3.0));
+        let (first, second) = (Bvh::build(&sphere), Bvh::build(&cube));
+        for (i, position) in [Vec3::new(4.0, 0.5, -0.3), Vec3::new(-1.0, 2.6, 1.2), Vec3::new(0.3, 0.2, 3.5)].into_iter().enumerate() {
+            let placements = [Placement::new(), Placement::from_position_rotation(position, Quat::from_euler(glam::EulerRot::XYZ, 0.4, 1.1, -0.7))];
+            let (a, b) = (sphere.transformed(&placements[0]), cube.transformed(&placements[1]));
+            let expected = a
+                .triangles()
+                .flat_map(|s| b.triangles().map(move |t| closest_between_triangles(s, t)))
+                .map(|(p, q)| p.distance(q))
+                .fold(f32::INFINITY, f32::min);
+            let closest = |s: usize, t: usize| closest_between_triangles(a.triangle(s), b.triangle(t));
+            let (p, q) = first.nearest(&placements[0], &second, &placements[1], f32::INFINITY, closest).unwrap();
+            assert!((p.distance(q) - expected).abs() < 1e-5, "placement {}", i);
+            assert!(first.nearest(&placements[0], &second, &placements[1], expected * 0.9, closest).is_none());
+        }
+        assert!(Bvh::default().nearest(&Placement::new(), &first, &Placement::new(), f32::INFINITY, |_, _| (Vec3::ZERO, Vec3::ZERO)).is_none());
+    }
+
+    #[test]
+    fn test_any_overlapping_matches_brute_force() {
+        let boxes: Vec<Aabb> = (0..300)
+            .map(|i| {
+                let i = i as f32;
+                let min = Vec3::new((i * 0.73).sin() * 10.0, (i * 1.31).cos() * 10.0, 0.0);
+                Aabb { min, max: min + Vec3::new(1.0 + (i * 0.17).sin().abs() * 3.0, 0.5 + (i * 0.29).cos().abs() * 2.0, 0.0) }
+            })
+            .collect();
+        let bvh = Bvh::from_boxes(&boxes);
+        for i in 0..100 {
+            let point = Vec3::new((i as f32 * 2.3).sin() * 12.0, (i as f32 * 1.9).cos() * 12.0, 0.0);
+            let query = Aabb { min: point, max: point + Vec3::new(0.1, 0.2, 0.0) * (i % 3) as f32 };
+            let mut found: Vec<usize> = Vec::new();
+            assert!(!bvh.any_overlapping(&query, |item| {
+                found.push(item);
+                false
+            }));
+            found.sort_unstable();
+            let expected: Vec<usize> = (0..boxes.len()).filter(|b| boxes[*b].overlaps(&query)).collect();
+            assert!(expected.iter().all(|b| found.binary_search(b).is_ok()), "query {}", i);
+            assert_eq!(bvh.any_overlapping(&query, |item| boxes[item].overlaps(&query)), !expected.is_empty());
+        }
+    }
+}
@@ -0,0 +1,360 @@
+//! Interference and clearance checks between objects
+//!
+//! A sweep over the world space bounding boxes picks the pairs of objects
+//! that may come within the clearance of each other. For those the objects'
+//! bounding volume hierarchies are descended together: solids interfere
+//! where their triangles cross, where faces lying in one plane overlap
+//! facing the same way, or where one solid holds the other by winding
+//! number. The nearest points of the surfaces are found exactly, triangle
+//! against triangle, and the volume interfering solids share is estimated by
+//! sampling a grid over the overlap of their boxes. Meshes are assumed to be
+//! closed.
+
+use glam::Vec3;
+use serde::Serialize;
+use crate::bounds::Aabb;
+use crate::bvh::Bvh;
+use crate::document::Document;
+use crate::geometry::triangle_normal;
+use crate::math::Placement;
+use crate::measure::closest_between_triangles;
+
+/// Grid samples along each axis of the overlap of two boxes
+const SAMPLES: usize = 12;
+
+/// Fraction of the size of a pair of objects within which surfaces touch
+const CONTACT_TOLERANCE: f32 = 1e-5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClashKind {
+    /// The solids share volume
+    Interference,
+    /// The surfaces touch without the solids overlapping
+    Contact,
+    /// The surfaces are closer than the clearance
+    Clearance,
+}
+
+/// Two objects that overlap, touch or come too close
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Clash {
+    pub objects: [u32; 2],
+    pub kind: ClashKind,
+    /// Minimum distance between the surfaces, 0 where they cross
+    pub distance: f32,
+    /// Nearest points on the surfaces of the first and second object
+    pub points: [Vec3; 2],
+    /// Estimated volume shared by the solids, in cubic document units
+    pub volume: f32,
+}
+
+/// How many times a closed surface winds around a point: 1 inside, 0
+/// outside and -1 inside a surface turned inside out
+pub fn winding_number(triangles: &[[Vec3; 3]], point: Vec3) -> f64 {
+    let point = point.as_dvec3();
+    let solid_angle: f64 = triangles
+        .iter()
+        .map(|t| {
+            // Van Oosterom and Strackee's formula for the solid angle of a triangle
+            let [a, b, c] = t.map(|v| v.as_dvec3() - point);
+            let (la, lb, lc) = (a.length(), b.length(), c.length());
+            let numerator = a.dot(b.cross(c));
+            let denominator = la * lb * lc + a.dot(b) * lc + a.dot(c) * lb + b.dot(c) * la;
+            2.0 * numerator.atan2(denominator)
+        })
+        .sum();
+    solid_angle / (4.0 * std::f64::consts::PI)
+}
+
+/// Volume inside both closed surfaces, sampled over the overlap of their boxes
+fn shared_volume(first: &[[Vec3; 3]], second: &[[Vec3; 3]], overlap: &Aabb) -> f32 {
+    let size = overlap.size();
+    if overlap.is_empty() || size.min_element() <= 0.0 {
+        return 0.0;
+    }
+    let cell = size / SAMPLES as f32;
+    let mut inside = 0;
+    for i in 0..SAMPLES.pow(3) {
+        let index = Vec3::new((i % SAMPLES) as f32, (i / SAMPLES % SAMPLES) as f32, (i / SAMPLES / SAMPLES) as f32);
+        let point = overlap.min + (index + 0.5) * cell;
+        if winding_number(first, point).abs() > 0.5 && winding_number(second, point).abs() > 0.5 {
+            inside += 1;
+        }
+    }
+    (size.x * size.y * size.z) * inside as f32 / SAMPLES.pow(3) as f32
+}
+
+/// Whether two triangles meet over more than `tolerance` in a way that
+/// makes their solids share volume
+///
+/// Triangles in different planes must cross, each passing through the
+/// other's plane along a common segment. Triangles in one plane must face
+/// the same way and overlap, as the sides of solids flush with each other do.
+fn penetrates(first: [Vec3; 3], second: [Vec3; 3], tolerance: f32) -> bool {
+    let normals = [first, second].map(|[a, b, c]| triangle_normal(a, b, c));
+    if normals.contains(&Vec3::ZERO) {
+        return false;
+    }
+    // Signed distances of each triangle's corners from the other's plane
+    let first_heights = first.map(|p| normals[1].dot(p - second[0]));
+    let second_heights = second.map(|p| normals[0].dot(p - first[0]));
+    if second_heights.iter().all(|h| h.abs() <= tolerance) {
+        return normals[0].dot(normals[1]) > 0.0 && overlap_in_plane(first, second, normals[0], tolerance);
+    }
+    let straddles = |heights: &[f32; 3]| heights.iter().any(|h| *h > tolerance) && heights.iter().any(|h| *h < -tolerance);
+    if !straddles(&first_heights) || !straddles(&second_heights) {
+        return false;
+    }
+    // Both cuts lie on the line where the planes meet
+    let Some(line) = normals[0].cross(normals[1]).try_normalize() else {
+        return false;
+    };
+    let cut = |triangle: [Vec3; 3], heights: [f32; 3]| {
+        let mut range = (f32::INFINITY, f32::NEG_INFINITY);
+        let mut extend = |point: Vec3| range = (range.0.min(line.dot(point)), range.1.max(line.dot(point)));
+        for i in 0..3 {
+            let (p, q, hp, hq) = (triangle[i], triangle[(i + 1) % 3], heights[i], heights[(i + 1) % 3]);
+            if hp.abs() <= tolerance {
+                extend(p);
+            }
+            if (hp < -tolerance && hq > tolerance) || (hp > tolerance && hq < -tolerance) {
+                extend(p + (q - p) * (hp / (hp - hq)));
+            }
+        }
+        range
+    };
+    let (a, b) = (cut(first, first_heights), cut(second, second_heights));
+    a.1.min(b.1) - a.0.max(b.0) > tolerance
+}
+
+/// Whether two triangles in the plane with unit `normal` overlap by more
+/// than `tolerance`, by separating axes across their sides
+fn overlap_in_plane(first: [Vec3; 3], second: [Vec3; 3], normal: Vec3, tolerance: f32) -> bool {
+    let range = |triangle: [Vec3; 3], axis: Vec3| {
+        triangle.iter().fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), p| (min.min(axis.dot(*p)), max.max(axis.dot(*p))))
+    };
+    [first, second].iter().flat_map(|t| [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])]).all(|(p, q)| {
+        let Some(axis) = normal.cross(q - p).try_normalize() else {
+            return true;
+        };
+        let (a, b) = (range(first, axis), range(second, axis));
+        a.1.min(b.1) - a.0.max(b.0) > tolerance
+    })
+}
+
+/// Whether every surface point not on the other surface is inside it, for
+/// a surface that nowhere crosses the other, judged by the first triangle
+/// center clearly inside or outside
+fn inside(triangles: &[[Vec3; 3]], other: &[[Vec3; 3]]) -> bool {
+    for [a, b, c] in triangles {
+        let winding = winding_number(other, (*a + *b + *c) / 3.0).abs();
+        if winding > 0.99 {
+            return true;
+        }
+        if winding < 0.01 {
+            return false;
+        }
+    }
+    false
+}
+
+/// A surface in world space, with its object's hierarchy and placement
+struct Surface<'a> {
+    id: u32,
+    aabb: Aabb,
+    triangles: Vec<[Vec3; 3]>,
+    bvh: &'a Bvh,
+    placement: Placement,
+}
+
+/// Check a pair of objects whose boxes are within the clearance
+fn check_pair(first: &Surface, second: &Surface, clearance: f32) -> Option<Clash> {
+    let (a, b) = (&first.aabb, &second.aabb);
+    let tolerance = CONTACT_TOLERANCE * a.union(b).size().length();
+    let (placement, other_placement) = (&first.placement, &second.placement);
+    let crossing = first.bvh.any_pair(placement, second.bvh, other_placement, tolerance, |i, j| {
+        penetrates(first.triangles[i], second.triangles[j], tolerance)
+    });
+    // Without crossings, one solid can only hold the other whole
+    let holds = |outer: &Aabb, inner: &Aabb| outer.min.cmple(inner.min + tolerance).all() && inner.max.cmple(outer.max + tolerance).all();
+    let interferes = crossing
+        || (holds(a, b) && inside(&second.triangles, &first.triangles))
+        || (holds(b, a) && inside(&first.triangles, &second.triangles));
+
+    let limit = if interferes { f32::INFINITY } else { clearance.max(tolerance) };
+    let closest = |i: usize, j: usize| closest_between_triangles(first.triangles[i], second.triangles[j]);
+    let (p, q) = first.bvh.nearest(placement, second.bvh, other_placement, limit, closest)?;
+    let distance = p.distance(q);
+    let (kind, volume) = if interferes {
+        let overlap = Aabb { min: a.min.max(b.min), max: a.max.min(b.max) };
+        (ClashKind::Interference, shared_volume(&first.triangles, &second.triangles, &overlap))
+    } else if distance <= tolerance {
+        (ClashKind::Contact, 0.0)
+    } else {
+        (ClashKind::Clearance, 0.0)
+    };
+    Some(Clash { objects: [first.id, second.id], kind, distance, points: [p, q], volume })
+}
+
+/// Pairs of the given objects, or all objects if `ids` is empty, whose solids
+/// overlap, touch or come within `clearance` of each other
+///
+/// Clashes are ordered by object ids.
+pub fn find_clashes(doc: &Document, ids: &[u32], clearance: f32) -> Vec<Clash> {
+    let mut objects: Vec<(u32, Aabb)> = doc
+        .resolve_ids(ids)
+        .into_iter()
+        .filter_map(|id| Some((id, doc.get_object(id)?.world_aabb()?)))
+        .filter(|(_, aabb)| !aabb.is_empty())
+        .collect();
+    // Sweep along X: only boxes starting before this one ends can be near it
+    objects.sort_by(|a, b| a.1.min.x.total_cmp(&b.1.min.x));
+    let mut candidates = Vec::new();
+    for (i, (_, aabb)) in objects.iter().enumerate() {
+        for (j, (_, other)) in objects.iter().enumerate().skip(i + 1) {
+            if other.min.x > aabb.max.x + clearance {
+                break;
+            }
+            if aabb.gap(other) <= clearance {
+                candidates.push((i, j));
+            }
+        }
+    }
+
+    let mut surfaces: Vec<Option<Surface>> = (0..objects.len()).map(|_| None).collect();
+    for &(i, j) in &candidates {
+        for k in [i, j] {
+            if surfaces[k].is_none() {
+                let (id, aabb) = objects[k];
+                surfaces[k] = doc.get_object(id).and_then(|object| {
+                    let bvh = object.bvh()?;
+                    let triangles = object.world_mesh()?.triangles().collect();
+                    Some(Surface { id, aabb, triangles, bvh, placement: object.placement() })
+                });
+            }
+        }
+    }
+    let mut clashes: Vec<Clash> = candidates
+        .into_iter()
+        .filter_map(|(i, j)| {
+            // Report each pair with the smaller id first
+            let (i, j) = if objects[i].0 < objects[j].0 { (i, j) } else { (j, i) };
+            check_pair(surfaces[i].as_ref()?, surfaces[j].as_ref()?, clearance)
+        })
+        .collect();
+    clashes.sort_by_key(|clash| clash.objects);
+    clashes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_4;
+    use glam::Quat;
+    use crate::geometry::generate_box_mesh;
+    use crate::math::Placement;
+    use crate::object::ShapeType;
+    use crate::property::Property;
+
+    fn add_box(doc: &mut Document, size: Vec3, position: Vec3) -> u32 {
+        let id = doc.add_object(ShapeType::Box { length: size.x, width: size.y, height: size.z });
+        let placement = Placement::from_position_rotation(position, Quat::IDENTITY);
+        doc.set_object_property(id, "Placement".to_string(), Property::Placement(placement));
+        doc.recompute();
+        id
+    }
+
+    #[test]
+    fn test_winding_number() {
+        let triangles: Vec<[Vec3; 3]> = generate_box_mesh(2.0, 2.0, 2.0).triangles().collect();
+        assert!((winding_number(&triangles, Vec3::new(0.3, -0.2, 0.9)) - 1.0).abs() < 1e-9);
+        assert!(winding_number(&triangles, Vec3::new(3.0, 0.0, 0.0)).abs() < 1e-9);
+        assert!(winding_number(&triangles, Vec3::new(0.0, 0.0, 1.5)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_interference_volumes() {
+        let mut doc = Document::new();
+        let a = add_box(&mut doc, Vec3::splat(2.0), Vec3::ZERO);
+        let b = add_box(&mut doc, Vec3::splat(2.0), Vec3::new(1.5, 0.0, 0.0));
+        // Crossed bars share a cube without any corner inside the other
+        let c = add_box(&mut doc, Vec3::new(6.0, 1.0, 1.0), Vec3::new(0.0, 10.0, 0.0));
+        let d = add_box(&mut doc, Vec3::new(1.0, 6.0, 1.0), Vec3::new(0.0, 10.0, 0.0));
+        // A box inside another without their surfaces meeting
+        let e = add_box(&mut doc, Vec3::splat(4.0), Vec3::new(0.0, 20.0, 0.0));
+        let f = add_box(&mut doc, Vec3::ONE, Vec3::new(0.0, 20.0, 0.0));
+
+        let clashes = find_clashes(&doc, &[], 0.0);
+        let pairs: Vec<[u32; 2]> = clashes.iter().map(|c| c.objects).collect();
+        assert_eq!(pairs, vec![[a, b], [c, d], [e, f]]);
+        assert!(clashes.iter().all(|c| c.kind == ClashKind::Interference));
+        assert!((clashes[0].volume - 2.0).abs() < 1e-4);
+        assert_eq!(clashes[0].distance, 0.0);
+        assert!((clashes[1].volume - 1.0).abs() < 1e-4);
+        assert!((clashes[2].volume - 1.0).abs() < 1e-4);
+        assert!((clashes[2].distance - 1.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_contact_and_clearance() {
+        let mut doc = Document::new();
+        let a = add_box(&mut doc, Vec3::splat(2.0), Vec3::ZERO);
+        let touching = add_box(&mut doc, Vec3::splat(2.0), Vec3::new(0.0, 0.0, 2.0));
+        let near = add_box(&mut doc, Vec3::splat(2.0), Vec3::new(2.5, 0.0, 0.0));
+        let far = add_box(&mut doc, Vec3::splat(2.0), Vec3::new(-5.0, 0.0, 0.0));
+
+        let clashes = find_clashes(&doc, &[], 1.0);
+        assert_eq!(clashes.len(), 3);
+        assert_eq!((clashes[0].objects, clashes[0].kind, clashes[0].volume), ([a, touching], ClashKind::Contact, 0.0));
+        assert_eq!((clashes[1].objects, clashes[1].kind), ([a, near], ClashKind::Clearance));
+        assert!((clashes[1].distance - 0.5).abs() < 1e-5);
+        assert!((clashes[1].points[0].x - 1.0).abs() < 1e-5 && (clashes[1].points[1].x - 1.5).abs() < 1e-5);
+        // The near box's corner is within a clearance of the touching one's
+        assert_eq!((clashes[2].objects, clashes[2].kind), ([touching, near], ClashKind::Clearance));
+        assert!(clashes.iter().all(|c| !c.objects.contains(&far)));
+
+        // Tighter clearances and restricting the objects leave fewer pairs
+        assert_eq!(find_clashes(&doc, &[], 0.2).len(), 1);
+        assert!(find_clashes(&doc, &[a, far], 1.0).is_empty());
+    }
+
+    #[test]
+    fn test_thin_penetration() {
+        // A corner pushed slightly through a face leaves no grid sample in both solids
+        let mut doc = Document::new();
+        let a = add_box(&mut doc, Vec3::splat(2.0), Vec3::ZERO);
+        let b = add_box(&mut doc, Vec3::splat(2.0), Vec3::ZERO);
+        let placement = Placement::from_position_rotation(Vec3::new(1.0 + 2f32.sqrt() - 0.05, 0.0, 0.0), Quat::from_rotation_z(FRAC_PI_4));
+        doc.set_object_property(b, "Placement".to_string(), Property::Placement(placement));
+        doc.recompute();
+
+        let clashes = find_clashes(&doc, &[], 0.0);
+        assert_eq!(clashes.len(), 1);
+        assert_eq!((clashes[0].objects, clashes[0].kind, clashes[0].distance), ([a, b], ClashKind::Interference, 0.0));
+        assert!(clashes[0].volume >= 0.0 && clashes[0].volume < 0.01);
+        // Backed off to just touch, the corner only makes contact, found within a
+        // clearance as rounding may leave the boxes apart
+        let placement = Placement::from_position_rotation(Vec3::new(1.0 + 2f32.sqrt(), 0.0, 0.0), Quat::from_rotation_z(FRAC_PI_4));
+        doc.set_object_property(b, "Placement".to_string(), Property::Placement(placement));
+        doc.recompute();
+        let clashes = find_clashes(&doc, &[], 0.01);
+        assert_eq!((clashes.len(), clashes[0].kind), (1, ClashKind::Contact));
+    }
+
+    #[test]
+    fn test_penetrates() {
+        let tolerance = 1e-5;
+        let flat = [Vec3::new(-1.0, -1.0, 0.0), Vec3::new(1.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0)];
+        let upright = [Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 0.5, 1.0), Vec3::new(0.0, -0.5, 1.0)];
+        assert!(penetrates(flat, upright, tolerance));
+        // Standing on the plane, or crossing it beside the triangle, is no penetration
+        assert!(!penetrates(flat, upright.map(|p| p + Vec3::Z), tolerance));
+        assert!(!penetrates(flat, upright.map(|p| p + Vec3::X * 3.0), tolerance));
+        // In one plane only overlapping triangles facing the same way penetrate
+        let shifted = flat.map(|p| p + Vec3::X * 0.5);
+        assert!(penetrates(flat, shifted, tolerance));
+        assert!(!penetrates(flat, [shifted[0], shifted[2], shifted[1]], tolerance));
+        assert!(!penetrates(flat, flat.map(|p| p + Vec3::X * 2.0), tolerance));
+    }
+}
@@ -0,0 +1,477 @@
+//! Simplification of meshes by collapsing edges
+//!
+//! Follows Garland and Heckbert's quadric error metrics: each vertex carries
+//! the sum of squared distances to the planes of the faces merged into it,
+//! and the edge whose collapse moves the surface least goes first. As that
+//! sum bounds the distance to each plane, the error of a collapse is how far
+//! the surface may have moved. Open boundaries and sharp creases are kept:
+//! vertices on them only slide along them, and vertices where they meet or
+//! end stay where they are.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashSet};
+use glam::{DMat3, DVec3, Vec3};
+use serde::Serialize;
+use crate::geometry::{mesh_edges, Mesh};
+use crate::projection::DEFAULT_CREASE_ANGLE;
+use crate::repair::recompute_normals;
+
+/// How far `Mesh::decimate` simplifies
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecimateOptions {
+    /// Stop once no more triangles than this are left
+    pub target_triangles: usize,
+    /// Furthest the surface may move, in mesh units; a millionth of the mesh
+    /// size is always allowed for rounding
+    pub max_error: f32,
+    /// Angle in radians between faces above which their edge is kept
+    pub crease_angle: f32,
+}
+
+impl Default for DecimateOptions {
+    /// Only collapse edges that do not change the shape
+    fn default() -> Self {
+        Self { target_triangles: 0, max_error: 0.0, crease_angle: DEFAULT_CREASE_ANGLE }
+    }
+}
+
+/// What `Mesh::decimate` did
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DecimationReport {
+    pub triangles_before: usize,
+    pub triangles_after: usize,
+    /// Furthest the surface moved, by the quadric error of the collapses
+    pub error: f32,
+}
+
+/// Sum of squared distances to planes, as `p·Ap + 2b·p + c`
+#[derive(Debug, Clone, Copy)]
+struct Quadric {
+    a: DMat3,
+    b: DVec3,
+    c: f64,
+}
+
+impl Quadric {
+    const ZERO: Quadric = Quadric { a: DMat3::ZERO, b: DVec3::ZERO, c: 0.0 };
+
+    /// Squared distance to the plane through `point` with unit `normal`
+    fn plane(normal: DVec3, point: DVec3) -> Quadric {
+        let d = -normal.dot(point);
+        Quadric { a: DMat3::from_cols(normal * normal.x, normal * normal.y, normal * normal.z), b: normal * d, c: d * d }
+    }
+
+    fn error(&self, p: DVec3) -> f64 {
+        (p.dot(self.a * p) + 2.0 * self.b.dot(p) + self.c).max(0.0)
+    }
+
+    /// Point of least error, unless the planes leave it free along a line or
+    /// plane
+    fn minimum(&self) -> Option<DVec3> {
+        let scale = self.a.x_axis.x + self.a.y_axis.y + self.a.z_axis.z;
+        (self.a.determinant().abs() > 1e-6 * scale.powi(3)).then(|| -(self.a.inverse() * self.b))
+    }
+}
+
+impl std::ops::Add for Quadric {
+    type Output = Quadric;
+
+    fn add(self, other: Quadric) -> Quadric {
+        Quadric { a: self.a + other.a, b: self.b + other.b, c: self.c + other.c }
+    }
+}
+
+/// Where a vertex may go
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Free,
+    /// On a boundary or crease, along which it may slide
+    Line,
+    /// Where boundaries or creases meet or end
+    Corner,
+}
+
+/// A candidate collapse of `from` into `into`, moved to `position`
+#[derive(Debug, Clone, Copy)]
+struct Collapse {
+    cost: f64,
+    into: u32,
+    from: u32,
+    position: DVec3,
+    /// Versions of the two vertices it was computed for
+    versions: [u32; 2],
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Collapse {
+    /// Cheapest first in a max-heap
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost).then_with(|| (other.into, other.from).cmp(&(self.into, self.from)))
+    }
+}
+
+struct Decimator {
+    positions: Vec<DVec3>,
+    quadrics: Vec<Quadric>,
+    kinds: Vec<Kind>,
+    versions: Vec<u32>,
+    alive: Vec<bool>,
+    triangles: Vec<[u32; 3]>,
+    removed: Vec<bool>,
+    /// Triangles at each vertex, including removed ones
+    around: Vec<Vec<usize>>,
+    /// Boundary and crease edges, the smaller vertex first
+    features: HashSet<[u32; 2]>,
+}
+
+impl Decimator {
+    fn new(positions: &[Vec3], triangles: Vec<[u32; 3]>, crease_angle: f32) -> Self {
+        let positions: Vec<DVec3> = positions.iter().map(Vec3::as_dvec3).collect();
+        let corners = |t: &[u32; 3]| t.map(|v| positions[v as usize]);
+        let normals: Vec<DVec3> = triangles
+            .iter()
+            .map(|t| {
+                let [a, b, c] = corners(t);
+                (b - a).cross(c - a).normalize_or_zero()
+            })
+            .collect();
+
+        let mut quadrics = vec![Quadric::ZERO; positions.len()];
+        let mut around = vec![Vec::new(); positions.len()];
+        for (t, triangle) in triangles.iter().enumerate() {
+            let plane = Quadric::plane(normals[t], positions[triangle[0] as usize]);
+            for &v in triangle {
+                quadrics[v as usize] = quadrics[v as usize] + plane;
+                around[v as usize].push(t);
+            }
+        }
+
+        let min_cos = f64::from(crease_angle.cos()) - 1e-6;
+        let mut features = HashSet::new();
+        for edge in mesh_edges(&triangles) {
+            let [u, v] = edge.vertices;
+            let sharp = match edge.triangles[..] {
+                [s, t] => normals[s].dot(normals[t]) < min_cos,
+                _ => true,
+            };
+            if !sharp {
+                continue;
+            }
+            features.insert(edge.vertices);
+            if let [t] = edge.triangles[..] {
+                // Keep open boundaries from drawing in across the surface
+                let (p, q) = (positions[u as usize], positions[v as usize]);
+                let across = (q - p).cross(normals[t]).normalize_or_zero();
+                let plane = Quadric::plane(across, p);
+                quadrics[u as usize] = quadrics[u as usize] + plane;
+                quadrics[v as usize] = quadrics[v as usize] + plane;
+            }
+        }
+        let mut counts = vec![0; positions.len()];
+        for [u, v] in &features {
+            counts[*u as usize] += 1;
+            counts[*v as usize] += 1;
+        }
+        let kinds = counts
+            .into_iter()
+            .map(|count| match count {
+                0 => Kind::Free,
+                2 => Kind::Line,
+                _ => Kind::Corner,
+            })
+            .collect();
+
+        let vertex_count = positions.len();
+        Self {
+            positions,
+            quadrics,
+            kinds,
+            versions: vec![0; vertex_count],
+            alive: vec![true; vertex_count],
+            removed: vec![false; triangles.len()],
+            triangles,
+            around,
+            features,
+        }
+    }
+
+    fn neighbours(&self, v: u32) -> Vec<u32> {
+        let mut neighbours: Vec<u32> = self.around[v as usize]
+            .iter()
+            .filter(|&&t| !self.removed[t])
+            .flat_map(|&t| self.triangles[t])
+            .filter(|&w| w != v)
+            .collect();
+        neighbours.sort_unstable();
+        neighbours.dedup();
+        neighbours
+    }
+
+    /// The collapse of the edge between `u` and `v`, if features allow one
+    fn collapse(&self, u: u32, v: u32) -> Option<Collapse> {
+        let (ku, kv) = (self.kinds[u as usize], self.kinds[v as usize]);
+        let along = self.features.contains(&[u.min(v), u.max(v)]);
+        // Which end must stay put, if either
+        let (into, from, fixed) = match (ku, kv) {
+            (Kind::Free, Kind::Free) => (u, v, false),
+            (_, Kind::Free) => (u, v, true),
+            (Kind::Free, _) => (v, u, true),
+            (Kind::Line, Kind::Line) if along => (u, v, false),
+            (Kind::Corner, Kind::Line) if along => (u, v, true),
+            (Kind::Line, Kind::Corner) if along => (v, u, true),
+            _ => return None,
+        };
+        let quadric = self.quadrics[u as usize] + self.quadrics[v as usize];
+        let (p, q) = (self.positions[into as usize], self.positions[from as usize]);
+        let position = if fixed {
+            p
+        } else {
+            let candidates = [p, q, (p + q) / 2.0];
+            quadric
+                .minimum()
+                .filter(|m| m.distance(p) <= 2.0 * p.distance(q))
+                .into_iter()
+                .chain(candidates)
+                .min_by(|a, b| quadric.error(*a).total_cmp(&quadric.error(*b)))?
+        };
+        Some(Collapse {
+            cost: quadric.error(position),
+            into,
+            from,
+            position,
+            versions: [self.versions[into as usize], self.versions[from as usize]],
+        })
+    }
+
+    /// Whether a collapse keeps the surface manifold and no face turns over
+    fn is_allowed(&self, collapse: &Collapse) -> bool {
+        let (into, from) = (collapse.into, collapse.from);
+        let shared = self.around[from as usize]
+            .iter()
+            .filter(|&&t| !self.removed[t] && self.triangles[t].contains(&into))
+            .count();
+        // The two ends may only share the neighbours across the faces between them
+        let common = self.neighbours(into).into_iter().filter(|w| self.neighbours(from).contains(w)).count();
+        if shared == 0 || common != shared {
+            return false;
+        }
+        for v in [into, from] {
+            for &t in &self.around[v as usize] {
+                let triangle = self.triangles[t];
+                if self.removed[t] || (triangle.contains(&into) && triangle.contains(&from)) {
+                    continue;
+                }
+                let [a, b, c] = triangle.map(|w| self.positions[w as usize]);
+                let [d, e, f] = triangle.map(|w| if w == into || w == from { collapse.position } else { self.positions[w as usize] });
+                let before = (b - a).cross(c - a).normalize_or_zero();
+                let after = (e - d).cross(f - d).normalize_or_zero();
+                if before.dot(after) <= 0.0 {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Collapse an edge, returning how many triangles went with it
+    fn apply(&mut self, collapse: &Collapse) -> usize {
+        let (into, from) = (collapse.into, collapse.from);
+        let mut removed = 0;
+        for t in std::mem::take(&mut self.around[from as usize]) {
+            if self.removed[t] {
+                continue;
+            }
+            if self.triangles[t].contains(&into) {
+                self.removed[t] = true;
+                removed += 1;
+            } else {
+                for w in &mut self.triangles[t] {
+                    if *w == from {
+                        *w = into;
+                    }
+                }
+                self.around[into as usize].push(t);
+            }
+        }
+        let moved: Vec<[u32; 2]> = self.features.iter().filter(|e| e.contains(&from)).copied().collect();
+        for edge in moved {
+            self.features.remove(&edge);
+            let other = if edge[0] == from { edge[1] } else { edge[0] };
+            if other != into {
+                self.features.insert([other.min(into), other.max(into)]);
+            }
+        }
+        self.positions[into as usize] = collapse.position;
+        self.quadrics[into as usize] = self.quadrics[into as usize] + self.quadrics[from as usize];
+        self.versions[into as usize] += 1;
+        self.alive[from as usize] = false;
+        removed
+    }
+}
+
+pub(crate) fn decimate(mesh: &mut Mesh, options: &DecimateOptions) -> DecimationReport {
+    let (positions, remap) = mesh.weld_map();
+    let extent = positions.iter().fold(0.0f32, |m, p| m.max(p.abs().max_element()));
+    let max_error = f64::from(options.max_error.max(0.0) + extent * 1e-6);
+    // The color of the first vertex at each welded one
+    let mut colors = vec![None; positions.len()];
+    for (i, &w) in remap.iter().enumerate() {
+        colors[w as usize] = colors[w as usize].or(mesh.color(i));
+    }
+    let triangles: Vec<[u32; 3]> = (0..mesh.triangle_count())
+        .map(|i| mesh.triangle_indices(i).map(|v| remap[v as usize]))
+        .filter(|[a, b, c]| a != b && b != c && a != c)
+        .collect();
+
+    let mut decimator = Decimator::new(&positions, triangles, options.crease_angle);
+    let mut heap = BinaryHeap::new();
+    for edge in mesh_edges(&decimator.triangles) {
+        heap.extend(decimator.collapse(edge.vertices[0], edge.vertices[1]));
+    }
+    let mut count = decimator.triangles.len();
+    let mut error = 0.0f64;
+    while count > options.target_triangles {
+        let Some(collapse) = heap.pop() else {
+            break;
+        };
+        let [into, from] = [collapse.into, collapse.from].map(|v| v as usize);
+        if !decimator.alive[into] || !decimator.alive[from] || collapse.versions != [decimator.versions[into], decimator.versions[from]] {
+            continue;
+        }
+        // The heap is cheapest first, so nothing after is within bounds either
+        if collapse.cost.sqrt() > max_error {
+            break;
+        }
+        if !decimator.is_allowed(&collapse) {
+            continue;
+        }
+        count -= decimator.apply(&collapse);
+        error = error.max(collapse.cost.sqrt());
+        for w in decimator.neighbours(collapse.into) {
+            heap.extend(decimator.collapse(collapse.into, w));
+        }
+    }
+
+    // Keep the vertices still in use, in order
+    let mut index = vec![u32::MAX; decimator.positions.len()];
+    let mut decimated = Mesh::new();
+    for (t, triangle) in decimator.triangles.iter().enumerate() {
+        if decimator.removed[t] {
+            continue;
+        }
+        for &v in triangle {
+            if index[v as usize] == u32::MAX {
+                index[v as usize] = decimated.vertex_count() as u32;
+                decimated.vertices.extend_from_slice(&decimator.positions[v as usize].as_vec3().to_array());
+                if let Some(color) = colors[v as usize] {
+                    decimated.colors.extend_from_slice(&color.to_array());
+                }
+            }
+            decimated.indices.push(index[v as usize]);
+        }
+    }
+    recompute_normals(&mut decimated, options.crease_angle);
+
+    let report = DecimationReport {
+        triangles_before: mesh.triangle_count(),
+        triangles_after: decimated.triangle_count(),
+        error: error as f32,
+    };
+    *mesh = decimated;
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{generate_cylinder_mesh, generate_sphere_mesh};
+
+    /// A flat square of `n` by `n` quads
+    fn grid(n: u32) -> Mesh {
+        let mut mesh = Mesh::new();
+        for j in 0..=n {
+            for i in 0..=n {
+                mesh.vertices.extend_from_slice(&[i as f32, j as f32, 0.0]);
+                mesh.normals.extend_from_slice(&[0.0, 0.0, 1.0]);
+            }
+        }
+        for j in 0..n {
+            for i in 0..n {
+                let a = j * (n + 1) + i;
+                let (b, c, d) = (a + 1, a + n + 2, a + n + 1);
+                mesh.indices.extend_from_slice(&[a, b, c, a, c, d]);
+            }
+        }
+        mesh
+    }
+
+    fn area(mesh: &Mesh) -> f32 {
+        mesh.triangles().map(|[a, b, c]| (b - a).cross(c - a).length() / 2.0).sum()
+    }
+
+    #[test]
+    fn test_flat_collapses_to_corners() {
+        let mut square = grid(8);
+        let report = square.decimate(&DecimateOptions::default());
+        assert_eq!(report.triangles_before, 128);
+        assert_eq!(report.triangles_after, 2);
+        assert_eq!(square.triangle_count(), 2);
+        assert!((area(&square) - 64.0).abs() < 1e-3);
+        for i in 0..square.vertex_count() {
+            let p = square.vertex(i);
+            assert!((p.x == 0.0 || p.x == 8.0) && (p.y == 0.0 || p.y == 8.0), "{}", p);
+        }
+    }
+
+    #[test]
+    fn test_creases_are_kept() {
+        let mut cylinder = generate_cylinder_mesh(1.0, 2.0);
+        let before = cylinder.triangle_count();
+        // Only the flat caps can go without changing the shape
+        let report = cylinder.decimate(&DecimateOptions::default());
+        assert_eq!(report.triangles_after, before - 4);
+        assert!(cylinder.validate().is_valid());
+
+        // Rims only slide around themselves, so the ends stay flat
+        let mut coarse = generate_cylinder_mesh(1.0, 2.0);
+        coarse.decimate(&DecimateOptions { target_triangles: 40, max_error: 1.0, ..Default::default() });
+        assert!(coarse.triangle_count() <= 40);
+        assert!(coarse.validate().is_watertight());
+        for i in 0..coarse.vertex_count() {
+            assert_eq!(coarse.vertex(i).y.abs(), 1.0);
+        }
+    }
+
+    #[test]
+    fn test_error_bound_and_target() {
+        let mut sphere = generate_sphere_mesh(1.0);
+        let before = sphere.triangle_count();
+        let report = sphere.decimate(&DecimateOptions { max_error: 0.01, ..Default::default() });
+        assert!(report.error <= 0.01);
+        assert!(report.triangles_after < before);
+        for i in 0..sphere.vertex_count() {
+            assert!((sphere.vertex(i).length() - 1.0).abs() < 0.02);
+        }
+
+        let mut sphere = generate_sphere_mesh(1.0);
+        let report = sphere.decimate(&DecimateOptions { target_triangles: 200, max_error: f32::INFINITY, ..Default::default() });
+        assert!(report.triangles_after <= 200 && report.triangles_after > 150);
+        let validation = sphere.validate();
+        assert!(validation.is_valid(), "{:?}", validation);
+        let volume: f32 = sphere.triangles().map(|[a, b, c]| a.dot(b.cross(c)) / 6.0).sum();
+        assert!((volume - 4.0 / 3.0 * std::f32::consts::PI).abs() < 0.3, "{}", volume);
+    }
+}
@@ -0,0 +1,576 @@
+//! Dimension annotations on drawings
+//!
+//! Dimensions are measured in world space, either taken automatically from
+//! object parameters or added by hand and stored with the document. Each is
+//! drawn in the first view that shows it at true size, pushed outwards until
+//! it clears the views and the dimensions already placed. The drafting
+//! standard decides the look: ISO text sits above dimension lines and reads
+//! along them, ANSI text is always horizontal and breaks the line.
+
+use std::f32::consts::PI;
+use glam::{Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+use crate::document::Document;
+use crate::drawing::DrawingView;
+use crate::object::ShapeType;
+use crate::projection::View;
+use crate::units::Units;
+
+/// Drafting standard of a document's drawings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DraftingStandard {
+    /// ISO 128 and 129, first-angle projection
+    #[default]
+    Iso,
+    /// ASME Y14.5, third-angle projection
+    Ansi,
+}
+
+impl DraftingStandard {
+    pub const ALL: [DraftingStandard; 2] = [DraftingStandard::Iso, DraftingStandard::Ansi];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DraftingStandard::Iso => "ISO",
+            DraftingStandard::Ansi => "ANSI",
+        }
+    }
+
+    /// Parse a standard from its name, ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.name().eq_ignore_ascii_case(name))
+    }
+
+    fn style(self) -> Style {
+        match self {
+            DraftingStandard::Iso => Style {
+                text_height: 3.5,
+                arrow_length: 3.0,
+                arrow_width: 0.8,
+                extension_gap: 0.0,
+                extension_overshoot: 2.0,
+                aligned_text: true,
+            },
+            DraftingStandard::Ansi => Style {
+                text_height: 3.0,
+                arrow_length: 3.0,
+                arrow_width: 1.0,
+                extension_gap: 1.5,
+                extension_overshoot: 3.0,
+                aligned_text: false,
+            },
+        }
+    }
+}
+
+/// Sizes in sheet millimeters
+struct Style {
+    text_height: f32,
+    arrow_length: f32,
+    arrow_width: f32,
+    /// Space between the object and an extension line
+    extension_gap: f32,
+    /// Length of extension lines past the dimension line
+    extension_overshoot: f32,
+    /// Whether linear dimension text reads along the dimension line
+    aligned_text: bool,
+}
+
+/// Distance from the geometry to the first dimension line
+const FIRST_OFFSET: f32 = 10.0;
+/// Distance between stacked dimension lines
+const SPACING: f32 = 7.0;
+/// Attempts at finding a free position before giving up on a dimension
+const ATTEMPTS: usize = 24;
+
+/// A dimension measured in world space, in model units
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Dimension {
+    /// Distance between two points
+    Linear { start: Vec3, end: Vec3 },
+    /// Radius of a circle around `axis`, or of a sphere if there is no axis
+    Radius { center: Vec3, axis: Option<Vec3>, radius: f32 },
+    /// Diameter of a circle around `axis`, or of a sphere if there is no axis
+    Diameter { center: Vec3, axis: Option<Vec3>, radius: f32 },
+    /// Angle at `vertex` between the directions to `start` and `end`
+    Angle { vertex: Vec3, start: Vec3, end: Vec3 },
+}
+
+impl Dimension {
+    /// Measured value, in model units or degrees
+    pub fn value(&self) -> f32 {
+        match self {
+            Dimension::Linear { start, end } => start.distance(*end),
+            Dimension::Radius { radius, .. } => *radius,
+            Dimension::Diameter { radius, .. } => 2.0 * radius,
+            Dimension::Angle { vertex, start, end } => (*start - *vertex).angle_between(*end - *vertex).to_degrees(),
+        }
+    }
+
+    /// Check that the dimension measures something
+    pub fn validate(&self) -> Result<(), String> {
+        let finite = |v: Vec3| v.is_finite();
+        let ok = match self {
+            Dimension::Linear { start, end } => finite(*start) && finite(*end) && start != end,
+            Dimension::Radius { center, axis, radius } | Dimension::Diameter { center, axis, radius } => {
+                finite(*center) && axis.is_none_or(|a| finite(a) && a != Vec3::ZERO) && radius.is_finite() && *radius > 0.0
+            }
+            Dimension::Angle { vertex, start, end } => {
+                let (a, b) = (*start - *vertex, *end - *vertex);
+                finite(a) && finite(b) && a.cross(b).length_squared() > 0.0
+            }
+        };
+        if ok {
+            Ok(())
+        } else {
+            Err("Dimension does not measure anything".to_string())
+        }
+    }
+
+    /// Text shown on the drawing, e.g. "R5" or "⌀12.5"
+    pub fn label(&self, standard: DraftingStandard, units: Units) -> String {
+        let value = self.value();
+        match self {
+            Dimension::Linear { .. } => format_length(value, standard, units),
+            Dimension::Radius { axis, .. } => {
+                format!("{}R{}", if axis.is_none() { "S" } else { "" }, format_length(value, standard, units))
+            }
+            Dimension::Diameter { axis, .. } => {
+                format!("{}⌀{}", if axis.is_none() { "S" } else { "" }, format_length(value, standard, units))
+            }
+            Dimension::Angle { .. } => format!("{}°", trim_number(value, 1)),
+        }
+    }
+
+    /// Whether `view` shows the dimension at true size
+    fn shown_in(&self, view: View) -> bool {
+        let toward = view.toward_viewer();
+        match self {
+            Dimension::Linear { start, end } => (*end - *start).normalize().dot(toward).abs() < 1e-3,
+            Dimension::Radius { axis, .. } | Dimension::Diameter { axis, .. } => {
+                axis.is_none_or(|axis| axis.normalize().dot(toward).abs() > 1.0 - 1e-3)
+            }
+            Dimension::Angle { vertex, start, end } => {
+                (*start - *vertex).cross(*end - *vertex).normalize().dot(toward).abs() > 1.0 - 1e-3
+            }
+        }
+    }
+}
+
+/// Format a number with up to `decimals` decimals, without trailing zeros
+fn trim_number(value: f32, decimals: usize) -> String {
+    let text = format!("{:.*}", decimals, value);
+    let text = if text.contains('.') { text.trim_end_matches('0').trim_end_matches('.') } else { &text };
+    if text == "-0" { "0".to_string() } else { text.to_string() }
+}
+
+/// Format a length; ANSI drops the leading zero of inch values below one
+fn format_length(value: f32, standard: DraftingStandard, units: Units) -> String {
+    let decimals = match units {
+        Units::Inch | Units::Foot => 3,
+        _ => 2,
+    };
+    let text = trim_number(value, decimals);
+    match (standard, units) {
+        (DraftingStandard::Ansi, Units::Inch | Units::Foot) if text.starts_with("0.") => text[1..].to_string(),
+        _ => text,
+    }
+}
+
+/// Dimensions read from the parameters of the given objects (all if `ids` is empty)
+///
+/// Boxes get their length, width and height, cylinders their diameter and
+/// height, and spheres their diameter. Imported meshes have no parameters
+/// and get none.
+pub fn automatic_dimensions(doc: &Document, ids: &[u32]) -> Vec<Dimension> {
+    let mut dimensions = Vec::new();
+    for object in doc.resolve_ids(ids).into_iter().filter_map(|id| doc.get_object(id)) {
+        if object.mesh.is_none() {
+            continue;
+        }
+        let placement = object.placement();
+        let at = |x: f32, y: f32, z: f32| placement.transform_point(Vec3::new(x, y, z));
+        match object.shape_type {
+            ShapeType::Box { length, width, height } => {
+                let (x, y, z) = (length / 2.0, width / 2.0, height / 2.0);
+                dimensions.push(Dimension::Linear { start: at(-x, -y, z), end: at(x, -y, z) });
+                dimensions.push(Dimension::Linear { start: at(x, -y, z), end: at(x, y, z) });
+                dimensions.push(Dimension::Linear { start: at(-x, y, -z), end: at(-x, y, z) });
+            }
+            ShapeType::Cylinder { radius, height } => {
+                let axis = placement.rotation * Vec3::Y;
+                dimensions.push(Dimension::Diameter { center: placement.position, axis: Some(axis), radius });
+                dimensions.push(Dimension::Linear { start: at(radius, -height / 2.0, 0.0), end: at(radius, height / 2.0, 0.0) });
+            }
+            ShapeType::Sphere { radius } => {
+                dimensions.push(Dimension::Diameter { center: placement.position, axis: None, radius });
+            }
+            ShapeType::ImportedMesh { .. } => {}
+        }
+    }
+    dimensions
+}
+
+/// A dimension laid out on the sheet
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    /// Dimension, extension and leader lines
+    pub lines: Vec<[Vec2; 2]>,
+    /// Filled arrowheads, tip first
+    pub arrows: Vec<[Vec2; 3]>,
+    pub text: String,
+    /// Center of the text
+    pub text_position: Vec2,
+    /// Text direction in degrees, counter-clockwise from the sheet X axis
+    pub text_angle: f32,
+    pub text_height: f32,
+}
+
+/// Axis-aligned rectangle on the sheet
+#[derive(Debug, Clone, Copy)]
+struct Rect {
+    min: Vec2,
+    max: Vec2,
+}
+
+impl Rect {
+    fn around(points: &[Vec2], margin: f32) -> Rect {
+        let min = points.iter().fold(Vec2::splat(f32::MAX), |m, p| m.min(*p));
+        let max = points.iter().fold(Vec2::splat(f32::MIN), |m, p| m.max(*p));
+        Rect { min: min - margin, max: max + margin }
+    }
+
+    fn overlaps(&self, other: &Rect) -> bool {
+        self.min.cmplt(other.max).all() && other.min.cmplt(self.max).all()
+    }
+}
+
+/// Approximate extent of text along its own direction
+fn text_width(text: &str, height: f32) -> f32 {
+    text.chars().count() as f32 * 0.6 * height
+}
+
+/// Bounds of text centered at `center` and rotated by `angle` degrees
+fn text_rect(center: Vec2, text: &str, height: f32, angle: f32) -> Rect {
+    let direction = Vec2::from_angle(angle.to_radians());
+    let half = (text_width(text, height) / 2.0) * direction;
+    let up = (height / 2.0) * direction.perp();
+    Rect::around(&[center - half - up, center + half - up, center + half + up, center - half + up], 0.5)
+}
+
+fn arrow(tip: Vec2, direction: Vec2, style: &Style) -> [Vec2; 3] {
+    let back = tip - direction * style.arrow_length;
+    let side = direction.perp() * style.arrow_width / 2.0;
+    [tip, back + side, back - side]
+}
+
+/// Places annotations one at a time, keeping them clear of each other
+struct Placer<'a> {
+    views: &'a [DrawingView],
+    scale: f32,
+    style: Style,
+    obstacles: Vec<Rect>,
+}
+
+impl Placer<'_> {
+    fn is_free(&self, rects: &[Rect]) -> bool {
+        rects.iter().all(|r| self.obstacles.iter().all(|o| !o.overlaps(r)))
+    }
+
+    fn to_sheet(view: &DrawingView, scale: f32, p: Vec3) -> Vec2 {
+        view.origin + view.view.project(p) * scale
+    }
+
+    fn linear(&mut self, view: &DrawingView, start: Vec3, end: Vec3, text: String) -> Option<Annotation> {
+        let style = &self.style;
+        let (a, b) = (Self::to_sheet(view, self.scale, start), Self::to_sheet(view, self.scale, end));
+        let direction = (b - a).normalize();
+        // Put the dimension on the side of the edge facing away from the view's center
+        let center = (view.min + view.max) / 2.0;
+        let mut normal = direction.perp();
+        if normal.dot((a + b) / 2.0 - center) < 0.0 {
+            normal = -normal;
+        }
+        let corners = [view.min, Vec2::new(view.max.x, view.min.y), view.max, Vec2::new(view.min.x, view.max.y)];
+        let clearance = corners.iter().map(|c| (*c - a).dot(normal)).fold(0.0f32, f32::max);
+
+        // Text reads left to right or bottom to top
+        let mut text_direction = direction;
+        if text_direction.x < -1e-6 || (text_direction.x.abs() <= 1e-6 && text_direction.y < 0.0) {
+            text_direction = -text_direction;
+        }
+        let text_angle = if style.aligned_text { text_direction.y.atan2(text_direction.x).to_degrees() } else { 0.0 };
+        let width = text_width(&text, style.text_height);
+
+        for attempt in 0..ATTEMPTS {
+            let offset = clearance + FIRST_OFFSET + SPACING * attempt as f32;
+            let (da, db) = (a + normal * offset, b + normal * offset);
+            let middle = (da + db) / 2.0;
+            let text_position = if style.aligned_text {
+                // Above the line as read
+                let up = text_direction.perp();
+                middle + up * (style.text_height / 2.0 + 1.0)
+            } else {
+                middle
+            };
+            let text_box = text_rect(text_position, &text, style.text_height, text_angle);
+            let line_box = Rect::around(&[da, db], 1.0);
+            if !self.is_free(&[text_box, line_box]) {
+                continue;
+            }
+
+            let mut lines = vec![
+                [a + normal * style.extension_gap, da + normal * style.extension_overshoot],
+                [b + normal * style.extension_gap, db + normal * style.extension_overshoot],
+            ];
+            if style.aligned_text {
+                lines.push([da, db]);
+            } else {
+                // Break the dimension line around horizontal text
+                let half_gap = (width / 2.0 * direction.x.abs() + style.text_height / 2.0 * direction.y.abs()) + 1.0;
+                let length = da.distance(db);
+                if length / 2.0 > half_gap {
+                    lines.push([da, middle - direction * half_gap]);
+                    lines.push([middle + direction * half_gap, db]);
+                }
+            }
+            // Arrows point outwards from inside, or inwards from outside when the line is short
+            let inside = da.distance(db) > 2.5 * style.arrow_length;
+            let (tip_a, tip_b) = if inside { (-direction, direction) } else { (direction, -direction) };
+            let arrows = vec![arrow(da, tip_a, style), arrow(db, tip_b, style)];
+            if !inside {
+                lines.push([da - direction * 2.0 * style.arrow_length, da]);
+                lines.push([db, db + direction * 2.0 * style.arrow_length]);
+            }
+
+            self.obstacles.extend([text_box, line_box]);
+            return Some(Annotation { lines, arrows, text, text_position, text_angle, text_height: style.text_height });
+        }
+        None
+    }
+
+    fn radial(&mut self, view: &DrawingView, center: Vec3, radius: f32, diameter: bool, text: String) -> Option<Annotation> {
+        let style = &self.style;
+        let c = Self::to_sheet(view, self.scale, center);
+        let r = radius * self.scale;
+        let width = text_width(&text, style.text_height);
+        let angles = [45.0f32, 135.0, 315.0, 225.0, 20.0, 160.0, 340.0, 200.0, 70.0, 110.0, 290.0, 250.0];
+
+        for attempt in 0..ATTEMPTS {
+            let leader = 6.0 + SPACING * (attempt / 4) as f32;
+            for angle in angles {
+                let v = Vec2::from_angle(angle.to_radians());
+                let rim = c + v * r;
+                let elbow = rim + v * leader;
+                let side = if v.x >= 0.0 { 1.0 } else { -1.0 };
+                let shoulder = elbow + Vec2::new(side * (width + 2.0), 0.0);
+                let text_position = (elbow + shoulder) / 2.0 + Vec2::new(0.0, style.text_height / 2.0 + 1.0);
+                let text_box = text_rect(text_position, &text, style.text_height, 0.0);
+                let leader_box = Rect::around(&[elbow, shoulder], 0.5);
+                if !self.is_free(&[text_box, leader_box]) || text_box.overlaps(&Rect { min: view.min, max: view.max }) {
+                    continue;
+                }
+                let start = if diameter { c - v * r } else { c };
+                let mut arrows = vec![arrow(rim, v, style)];
+                if diameter {
+                    arrows.push(arrow(start, -v, style));
+                }
+                let lines = vec![[start, elbow], [elbow, shoulder]];
+                self.obstacles.extend([text_box, leader_box]);
+                return Some(Annotation { lines, arrows, text, text_position, text_angle: 0.0, text_height: style.text_height });
+            }
+        }
+        None
+    }
+
+    fn angular(&mut self, view: &DrawingView, vertex: Vec3, start: Vec3, end: Vec3, text: String) -> Option<Annotation> {
+        let style = &self.style;
+        let v = Self::to_sheet(view, self.scale, vertex);
+        let (a, b) = (Self::to_sheet(view, self.scale, start) - v, Self::to_sheet(view, self.scale, end) - v);
+        let from = a.y.atan2(a.x);
+        let mut sweep = b.y.atan2(b.x) - from;
+        if sweep > PI {
+            sweep -= 2.0 * PI;
+        } else if sweep < -PI {
+            sweep += 2.0 * PI;
+        }
+        let base = 0.6 * a.length().min(b.length());
+
+        for attempt in 0..ATTEMPTS {
+            let radius = base.max(FIRST_OFFSET) + SPACING * attempt as f32;
+            let point = |t: f32| v + Vec2::from_angle(from + sweep * t) * radius;
+            let segments = 24;
+            let arc: Vec<Vec2> = (0..=segments).map(|i| point(i as f32 / segments as f32)).collect();
+            let middle = Vec2::from_angle(from + sweep / 2.0);
+            let text_position = v + middle * (radius + style.text_height / 2.0 + 1.5);
+            let text_box = text_rect(text_position, &text, style.text_height, 0.0);
+            let arc_box = Rect::around(&arc, 0.5);
+            if !self.is_free(&[text_box]) {
+                continue;
+            }
+
+            let mut lines: Vec<[Vec2; 2]> = arc.windows(2).map(|w| [w[0], w[1]]).collect();
+            // Extend the sides to the arc when it lies beyond them
+            for side in [a, b] {
+                if side.length() < radius {
+                    let direction = side.normalize();
+                    lines.push([v + side + direction * style.extension_gap, v + direction * (radius + style.extension_overshoot)]);
+                }
+            }
+            let tangent = |t: f32| Vec2::from_angle(from + sweep * t).perp() * sweep.signum();
+            let arrows = vec![arrow(arc[0], -tangent(0.0), style), arrow(arc[segments], tangent(1.0), style)];
+            self.obstacles.extend([text_box, arc_box]);
+            return Some(Annotation { lines, arrows, text, text_position, text_angle: 0.0, text_height: style.text_height });
+        }
+        None
+    }
+}
+
+/// Views tried for a dimension, in order of preference
+const PREFERRED_VIEWS: [View; 3] = [View::Front, View::Top, View::Right];
+
+/// Lay out dimensions on the views of a drawing
+///
+/// Dimensions that no view shows at true size, or that find no free spot,
+/// are left out.
+pub fn place(
+    dimensions: &[Dimension],
+    views: &[DrawingView],
+    scale: f32,
+    standard: DraftingStandard,
+    units: Units,
+) -> Vec<Annotation> {
+    let mut placer = Placer {
+        views,
+        scale,
+        style: standard.style(),
+        obstacles: views.iter().map(|v| Rect { min: v.min, max: v.max }).collect(),
+    };
+    let mut annotations = Vec::new();
+    for dimension in dimensions {
+        let view = PREFERRED_VIEWS
+            .iter()
+            .filter(|v| dimension.shown_in(**v))
+            .find_map(|v| placer.views.iter().find(|d| d.view == *v));
+        let Some(view) = view else {
+            continue;
+        };
+        let text = dimension.label(standard, units);
+        let annotation = match dimension {
+            Dimension::Linear { start, end } => placer.linear(view, *start, *end, text),
+            Dimension::Radius { center, radius, .. } => placer.radial(view, *center, *radius, false, text),
+            Dimension::Diameter { center, radius, .. } => placer.radial(view, *center, *radius, true, text),
+            Dimension::Angle { vertex, start, end } => placer.angular(view, *vertex, *start, *end, text),
+        };
+        annotations.extend(annotation);
+    }
+    annotations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drawing::{layout, DrawingOptions};
+
+    #[test]
+    fn test_values_and_labels() {
+        let linear = Dimension::Linear { start: Vec3::ZERO, end: Vec3::new(3.0, 4.0, 0.0) };
+        assert_eq!(linear.label(DraftingStandard::Iso, Units::Millimeter), "5");
+        let radius = Dimension::Radius { center: Vec3::ZERO, axis: Some(Vec3::Y), radius: 0.25 };
+        assert_eq!(radius.label(DraftingStandard::Iso, Units::Inch), "R0.25");
+        assert_eq!(radius.label(DraftingStandard::Ansi, Units::Inch), "R.25");
+        assert_eq!(radius.label(DraftingStandard::Ansi, Units::Millimeter), "R0.25");
+        let sphere = Dimension::Diameter { center: Vec3::ZERO, axis: None, radius: 6.25 };
+        assert_eq!(sphere.label(DraftingStandard::Iso, Units::Millimeter), "S⌀12.5");
+        let angle = Dimension::Angle { vertex: Vec3::ZERO, start: Vec3::X, end: Vec3::new(1.0, 1.0, 0.0) };
+        assert_eq!(angle.label(DraftingStandard::Iso, Units::Millimeter), "45°");
+
+        assert!(angle.validate().is_ok());
+        assert!(Dimension::Linear { start: Vec3::ONE, end: Vec3::ONE }.validate().is_err());
+        assert!(Dimension::Radius { center: Vec3::ZERO, axis: None, radius: -1.0 }.validate().is_err());
+        assert_eq!(DraftingStandard::from_name("ansi"), Some(DraftingStandard::Ansi));
+    }
+
+    #[test]
+    fn test_automatic_dimensions() {
+        let mut doc = Document::new();
+        doc.add_object(ShapeType::Box { length: 30.0, width: 20.0, height: 10.0 });
+        doc.add_object(ShapeType::Cylinder { radius: 5.0, height: 8.0 });
+        doc.add_object(ShapeType::Box { length: -1.0, width: 1.0, height: 1.0 });
+        doc.recompute();
+        let values: Vec<f32> = automatic_dimensions(&doc, &[]).iter().map(Dimension::value).collect();
+        assert_eq!(values, vec![30.0, 20.0, 10.0, 10.0, 8.0]);
+    }
+
+    #[test]
+    fn test_every_dimension_is_placed_without_overlap() {
+        for standard in DraftingStandard::ALL {
+            let mut doc = Document::new();
+            doc.drafting_standard = standard;
+            doc.add_object(ShapeType::Box { length: 60.0, width: 40.0, height: 20.0 });
+            doc.dimensions.push(Dimension::Angle {
+                vertex: Vec3::new(-30.0, -20.0, 10.0),
+                start: Vec3::new(30.0, -20.0, 10.0),
+                end: Vec3::new(30.0, 20.0, 10.0),
+            });
+            doc.recompute();
+            let drawing = layout(&doc, &[], DrawingOptions { dimensions: true, ..Default::default() });
+            assert_eq!(drawing.annotations.len(), 4, "{:?}", standard);
+
+            let boxes: Vec<Rect> = drawing
+                .annotations
+                .iter()
+                .map(|a| text_rect(a.text_position, &a.text, a.text_height, a.text_angle))
+                .collect();
+            for (i, a) in boxes.iter().enumerate() {
+                for b in &boxes[i + 1..] {
+                    assert!(!a.overlaps(b));
+                }
+                for view in &drawing.views {
+                    assert!(!a.overlaps(&Rect { min: view.min, max: view.max }));
+                }
+                let sheet = drawing.options.sheet.size();
+                assert!(a.min.cmpgt(Vec2::ZERO).all() && a.max.cmplt(sheet).all());
+            }
+        }
+    }
+
+    #[test]
+    fn test_text_style() {
+        let mut doc = Document::new();
+        doc.add_object(ShapeType::Box { length: 60.0, width: 40.0, height: 20.0 });
+        doc.recompute();
+        let options = DrawingOptions { dimensions: true, ..Default::default() };
+        // The width runs vertically in the front view
+        let width = |doc: &Document| {
+            layout(doc, &[], options.clone()).annotations.into_iter().find(|a| a.text == "40").unwrap()
+        };
+        let iso = width(&doc);
+        assert_eq!(iso.text_angle, 90.0);
+        // An ISO dimension line is unbroken: two extension lines and one dimension line
+        assert_eq!(iso.lines.len(), 3);
+
+        doc.drafting_standard = DraftingStandard::Ansi;
+        let ansi = width(&doc);
+        assert_eq!(ansi.text_angle, 0.0);
+        assert_eq!(ansi.lines.len(), 4);
+    }
+
+    #[test]
+    fn test_radial_dimensions() {
+        let mut doc = Document::new();
+        doc.add_object(ShapeType::Cylinder { radius: 10.0, height: 30.0 });
+        doc.add_object(ShapeType::Sphere { radius: 5.0 });
+        doc.recompute();
+        let drawing = layout(&doc, &[1], DrawingOptions { dimensions: true, ..Default::default() });
+        let texts: Vec<&str> = drawing.annotations.iter().map(|a| a.text.as_str()).collect();
+        assert_eq!(texts, vec!["⌀20", "30"]);
+        let diameter = &drawing.annotations[0];
+        // Arrowheads on both sides of the circle, 20 mm apart at the chosen scale
+        let gap = diameter.arrows[0][0].distance(diameter.arrows[1][0]);
+        assert!((gap - 20.0 * drawing.scale).abs() < 1e-3);
+
+        let drawing = layout(&doc, &[2], DrawingOptions { dimensions: true, ..Default::default() });
+        assert_eq!(drawing.annotations[0].text, "S⌀10");
+    }
+}
@@ -1,16 +1,38 @@
 use std::collections::HashMap;
+use glam::Vec3;
 use petgraph::Graph;
 use serde::{Deserialize, Serialize};
-use crate::object::{Object, ShapeType};
+use crate::bounds::Aabb;
+use crate::bvh::{Hit, Ray};
+use crate::geometry::Mesh;
+use crate::history::{Command, History};
+use crate::mass::{MassError, MassProperties, DEFAULT_DENSITY};
+use crate::material::{builtin, Material, MaterialError};
+use crate::object::{Object, ObjectInfo, ShapeError, ShapeType, DEFAULT_COLOR};
 use crate::property::Property;
+use crate::units::Units;
+use crate::dimension::{Dimension, DraftingStandard};
 
 /// The CAD document containing all objects
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Document {
+    #[serde(serialize_with = "crate::io::serialize_sorted")]
     pub objects: HashMap<u32, Object>,
     #[serde(skip)]
     pub dependency_graph: Graph<u32, ()>,
     next_id: u32,
+    #[serde(default)]
+    pub units: Units,
+    #[serde(default)]
+    pub drafting_standard: DraftingStandard,
+    /// Dimensions added by hand, shown on drawings
+    #[serde(default)]
+    pub dimensions: Vec<Dimension>,
+    /// User-defined materials, next to the built-in library
+    #[serde(default)]
+    pub materials: Vec<Material>,
+    #[serde(skip)]
+    history: History,
 }
 
 impl Document {
@@ -20,6 +42,41 @@ impl Document {
             objects: HashMap::new(),
             dependency_graph: Graph::new(),
             next_id: 1,
+            units: Units::default(),
+            drafting_standard: DraftingStandard::default(),
+            dimensions: Vec::new(),
+            materials: Vec::new(),
+            history: History::default(),
+        }
+    }
+
+    /// The ID the next added object will receive
+    pub fn next_id(&self) -> u32 {
+        self.next_id
+    }
+
+    /// Rebuild derived state after deserialization
+    ///
+    /// Restores the dependency graph, makes sure `next_id` is past every
+    /// existing object and recomputes all meshes.
+    pub fn rebuild(&mut self) {
+        for id in self.objects.keys() {
+            if *id >= self.next_id {
+                self.next_id = id + 1;
+            }
+        }
+        self.rebuild_dependency_graph();
+        self.history.clear();
+        self.recompute();
+    }
+
+    /// Rebuild the dependency graph with one node per object
+    pub fn rebuild_dependency_graph(&mut self) {
+        let mut ids: Vec<u32> = self.objects.keys().copied().collect();
+        ids.sort_unstable();
+        self.dependency_graph = Graph::new();
+        for id in ids {
+            self.dependency_graph.add_node(id);
         }
     }
 
@@ -29,6 +86,7 @@ impl Document {
         self.next_id += 1;
         
         let object = Object::new(id, shape_type);
+        self.history.record(Command::AddObject { object: object.clone() });
         self.objects.insert(id, object);
         
         id
@@ -36,13 +94,30 @@ impl Document {
 
     /// Restore an object with a specific ID
     pub fn restore_object(&mut self, id: u32, shape_type: ShapeType) {
+        if let Some(mut previous) = self.objects.remove(&id) {
+            previous.mesh = None;
+            self.history.record(Command::DeleteObject { object: previous });
+        }
         let object = Object::new(id, shape_type);
+        self.history.record(Command::AddObject { object: object.clone() });
         self.objects.insert(id, object);
         if id >= self.next_id {
             self.next_id = id + 1;
         }
     }
 
+    /// Remove an object from the document
+    pub fn remove_object(&mut self, id: u32) -> bool {
+        match self.objects.remove(&id) {
+            Some(mut object) => {
+                object.mesh = None;
+                self.history.record(Command::DeleteObject { object });
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Get an object by ID
     pub fn get_object(&self, id: u32) -> Option<&Object> {
         self.objects.get(&id)
@@ -56,6 +131,8 @@ impl Document {
     /// Set a property on an object
     pub fn set_object_property(&mut self, id: u32, name: String, value: Property) -> bool {
         if let Some(object) = self.objects.get_mut(&id) {
+            let old = object.get_property(&name).cloned();
+            self.history.record(Command::SetProperty { id, name: name.clone(), old, new: value.clone() });
             object.set_property(name, value);
             true
         } else {
@@ -63,7 +140,136 @@ impl Document {
         }
     }
 
+    /// Replace the shape parameters of an object
+    pub fn set_object_shape(&mut self, id: u32, shape_type: ShapeType) -> bool {
+        if let Some(object) = self.objects.get_mut(&id) {
+            let old = std::mem::replace(&mut object.shape_type, shape_type.clone());
+            self.history.record(Command::SetShape { id, old, new: shape_type });
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Open a named transaction grouping the following changes
+    pub fn open_transaction(&mut self, name: &str) {
+        self.history.open_transaction(name);
+    }
+
+    /// Commit the open transaction to the undo stack
+    pub fn commit_transaction(&mut self) -> bool {
+        self.history.commit_transaction()
+    }
+
+    /// Whether a transaction is open, which blocks undo and redo
+    pub fn is_transaction_open(&self) -> bool {
+        self.history.is_open()
+    }
+
+    /// Revert and discard the open transaction, returning the affected object IDs
+    pub fn abort_transaction(&mut self) -> Vec<u32> {
+        match self.history.abort_transaction() {
+            Some(transaction) => {
+                for command in transaction.commands.iter().rev() {
+                    self.apply(command, false);
+                }
+                self.recompute();
+                transaction.affected_ids()
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// Undo the last transaction, returning the affected object IDs
+    ///
+    /// Returns `None` if there is nothing to undo or a transaction is open.
+    pub fn undo(&mut self) -> Option<Vec<u32>> {
+        if self.history.is_open() {
+            return None;
+        }
+        let transaction = self.history.pop_undo()?;
+        for command in transaction.commands.iter().rev() {
+            self.apply(command, false);
+        }
+        let ids = transaction.affected_ids();
+        self.history.push_redo(transaction);
+        self.recompute();
+        Some(ids)
+    }
+
+    /// Redo the last undone transaction, returning the affected object IDs
+    pub fn redo(&mut self) -> Option<Vec<u32>> {
+        if self.history.is_open() {
+            return None;
+        }
+        let transaction = self.history.pop_redo()?;
+        for command in &transaction.commands {
+            self.apply(command, true);
+        }
+        let ids = transaction.affected_ids();
+        self.history.push_undo(transaction);
+        self.recompute();
+        Some(ids)
+    }
+
+    /// Name of the transaction that would be undone next
+    pub fn undo_name(&self) -> Option<&str> {
+        self.history.undo_name()
+    }
+
+    /// Name of the transaction that would be redone next
+    pub fn redo_name(&self) -> Option<&str> {
+        self.history.redo_name()
+    }
+
+    /// Forget the undo and redo history
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+    }
+
+    /// Apply a command forwards (redo) or backwards (undo) without recording it
+    fn apply(&mut self, command: &Command, forward: bool) {
+        match command {
+            Command::AddObject { object } | Command::DeleteObject { object } => {
+                let insert = matches!(command, Command::AddObject { .. }) == forward;
+                if insert {
+                    self.objects.insert(object.id, object.clone());
+                } else {
+                    self.objects.remove(&object.id);
+                }
+            }
+            Command::SetProperty { id, name, old, new } => {
+                if let Some(object) = self.objects.get_mut(id) {
+                    match (forward, old) {
+                        (true, _) => object.set_property(name.clone(), new.clone()),
+                        (false, Some(old)) => object.set_property(name.clone(), old.clone()),
+                        (false, None) => {
+                            object.properties.remove(name);
+                        }
+                    }
+                }
+            }
+            Command::SetShape { id, old, new } => {
+                if let Some(object) = self.objects.get_mut(id) {
+                    object.shape_type = if forward { new.clone() } else { old.clone() };
+                }
+            }
+            Command::SetMaterials { old, new } => {
+                self.materials = if forward { new.clone() } else { old.clone() };
+            }
+            Command::SetDraftingStandard { old, new } => {
+                self.drafting_standard = if forward { *new } else { *old };
+            }
+            Command::SetDimensions { old, new } => {
+                self.dimensions = if forward { new.clone() } else { old.clone() };
+            }
+        }
+    }
+
     /// Recompute all objects in the document
+    ///
+    /// Objects with invalid parameters keep their error and are skipped;
+    /// the remaining objects are still recomputed.
     pub fn recompute(&mut self) {
         // For now, we iterate through all objects linearly
         // In the future, we can use the dependency_graph to compute in the correct order
@@ -75,6 +281,42 @@ impl Document {
         }
     }
 
+    /// Describe all objects in the document, sorted by ID
+    pub fn list_objects(&self) -> Vec<ObjectInfo> {
+        let mut infos: Vec<ObjectInfo> = self.objects.values().map(Object::info).collect();
+        infos.sort_by_key(|info| info.id);
+        infos
+    }
+
+    /// Restore an object from its description, including its placement
+    ///
+    /// Returns false if the type or its parameters are not recognised.
+    pub fn restore_from_info(&mut self, info: &ObjectInfo) -> bool {
+        let Some(shape) = ShapeType::from_params(&info.type_name, &info.params) else {
+            return false;
+        };
+        self.open_transaction("Restore Object");
+        self.restore_object(info.id, shape);
+        for (name, value) in &info.properties {
+            self.set_object_property(info.id, name.clone(), value.clone());
+        }
+        for (name, value) in &info.params {
+            self.set_object_property(info.id, name.clone(), Property::Float(*value));
+        }
+        self.set_object_property(info.id, "Placement".to_string(), Property::Placement(info.placement));
+        self.commit_transaction();
+        true
+    }
+
+    /// Get all objects currently in an error state, sorted by ID
+    pub fn errors(&self) -> Vec<(u32, &ShapeError)> {
+        let mut errors: Vec<(u32, &ShapeError)> = self.objects.values()
+            .filter_map(|obj| obj.error.as_ref().map(|err| (obj.id, err)))
+            .collect();
+        errors.sort_by_key(|(id, _)| *id);
+        errors
+    }
+
     /// Get the mesh data for an object as a flat buffer
     pub fn get_mesh_buffer(&self, id: u32) -> Option<Vec<f32>> {
         self.objects.get(&id)
@@ -82,6 +324,174 @@ impl Document {
             .map(|mesh| mesh.to_interleaved_buffer())
     }
 
+    /// Get the world-space mesh of an object
+    pub fn world_mesh(&self, id: u32) -> Option<Mesh> {
+        self.objects.get(&id).and_then(Object::world_mesh)
+    }
+
+    /// IDs of the given objects, or of every object if `ids` is empty, sorted
+    pub fn resolve_ids(&self, ids: &[u32]) -> Vec<u32> {
+        let mut ids: Vec<u32> = if ids.is_empty() {
+            self.objects.keys().copied().collect()
+        } else {
+            ids.iter().copied().filter(|id| self.objects.contains_key(id)).collect()
+        };
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+
+    /// Merge the world-space meshes of the given objects (all if `ids` is empty)
+    pub fn combined_world_mesh(&self, ids: &[u32]) -> Mesh {
+        let mut combined = Mesh::new();
+        for id in self.resolve_ids(ids) {
+            if let Some(mesh) = self.world_mesh(id) {
+                combined.append(&mesh);
+            }
+        }
+        combined
+    }
+
+    /// Set the drafting standard of the document's drawings
+    pub fn set_drafting_standard(&mut self, standard: DraftingStandard) {
+        let old = self.drafting_standard;
+        if old != standard {
+            self.drafting_standard = standard;
+            self.history.record(Command::SetDraftingStandard { old, new: standard });
+        }
+    }
+
+    /// Add a dimension to the document's drawings and return its index
+    pub fn add_dimension(&mut self, dimension: Dimension) -> usize {
+        let old = self.dimensions.clone();
+        self.dimensions.push(dimension);
+        self.history.record(Command::SetDimensions { old, new: self.dimensions.clone() });
+        self.dimensions.len() - 1
+    }
+
+    /// Remove the dimension at `index`
+    ///
+    /// Returns false if there is no such dimension.
+    pub fn remove_dimension(&mut self, index: usize) -> bool {
+        if index >= self.dimensions.len() {
+            return false;
+        }
+        let old = self.dimensions.clone();
+        self.dimensions.remove(index);
+        self.history.record(Command::SetDimensions { old, new: self.dimensions.clone() });
+        true
+    }
+
+    /// The user-defined or built-in material with the given name, ignoring case
+    pub fn material(&self, name: &str) -> Option<Material> {
+        self.materials
+            .iter()
+            .find(|m| m.name.eq_ignore_ascii_case(name))
+            .cloned()
+            .or_else(|| builtin(name))
+    }
+
+    /// Add a user-defined material, or replace the one with the same name
+    pub fn add_material(&mut self, material: Material) -> Result<(), MaterialError> {
+        material.validate()?;
+        if builtin(&material.name).is_some() {
+            return Err(MaterialError::BuiltIn(material.name));
+        }
+        let old = self.materials.clone();
+        match self.materials.iter_mut().find(|m| m.name.eq_ignore_ascii_case(&material.name)) {
+            Some(existing) => *existing = material,
+            None => self.materials.push(material),
+        }
+        self.history.record(Command::SetMaterials { old, new: self.materials.clone() });
+        Ok(())
+    }
+
+    /// Remove a user-defined material; objects using it fall back to defaults
+    pub fn remove_material(&mut self, name: &str) -> bool {
+        let old = self.materials.clone();
+        self.materials.retain(|m| !m.name.eq_ignore_ascii_case(name));
+        if self.materials.len() == old.len() {
+            return false;
+        }
+        self.history.record(Command::SetMaterials { old, new: self.materials.clone() });
+        true
+    }
+
+    /// The material an object refers to, if it exists
+    pub fn object_material(&self, id: u32) -> Option<Material> {
+        self.objects.get(&id)?.material_name().and_then(|name| self.material(name))
+    }
+
+    /// Density of an object in kg/m³: its "Density" property, else its
+    /// material's, else `DEFAULT_DENSITY`
+    ///
+    /// Fails if the density found is not a finite number above zero.
+    pub fn object_density(&self, id: u32) -> Result<f64, MassError> {
+        let explicit = match self.objects.get(&id) {
+            Some(object) => object.density()?,
+            None => None,
+        };
+        let density = explicit.or_else(|| self.object_material(id).map(|m| m.density)).unwrap_or(DEFAULT_DENSITY);
+        if density.is_finite() && density > 0.0 { Ok(density) } else { Err(MassError::InvalidDensity(density)) }
+    }
+
+    /// Linear RGB color of an object: its "Color" property, else its
+    /// material's, else `DEFAULT_COLOR`
+    pub fn object_color(&self, id: u32) -> Vec3 {
+        let explicit = self.objects.get(&id).and_then(|obj| obj.get_property("Color")).and_then(|p| p.as_vector());
+        explicit
+            .or_else(|| self.object_material(id).map(|m| m.color))
+            .unwrap_or(DEFAULT_COLOR)
+    }
+
+    /// Like `combined_world_mesh`, with every vertex colored: imported
+    /// vertex colors are kept, other meshes take their object's color
+    pub fn combined_colored_mesh(&self, ids: &[u32]) -> Mesh {
+        let mut combined = Mesh::new();
+        for id in self.resolve_ids(ids) {
+            let Some(mut mesh) = self.world_mesh(id) else { continue };
+            if mesh.colors.is_empty() {
+                let color = self.object_color(id);
+                mesh.colors = color.to_array().repeat(mesh.vertex_count());
+            }
+            combined.append(&mesh);
+        }
+        combined
+    }
+
+    /// World space bounding box of the given objects, or of every object if
+    /// `ids` is empty; `Aabb::EMPTY` if none has a mesh
+    pub fn bounding_box(&self, ids: &[u32]) -> Aabb {
+        self.resolve_ids(ids)
+            .into_iter()
+            .filter_map(|id| self.objects[&id].world_aabb())
+            .fold(Aabb::EMPTY, |b, other| b.union(&other))
+    }
+
+    /// The object nearest along a world space ray, and where the ray hits it
+    pub fn pick(&self, ray: &Ray) -> Option<(u32, Hit)> {
+        self.objects
+            .iter()
+            .filter_map(|(id, obj)| obj.pick(ray).map(|hit| (*id, hit)))
+            .min_by(|a, b| a.1.distance.total_cmp(&b.1.distance).then(a.0.cmp(&b.0)))
+    }
+
+    /// Combined mass properties of the given objects, or of every object if
+    /// `ids` is empty, in the document's units
+    ///
+    /// Fails with the ID of the first object that cannot be measured.
+    pub fn mass_properties(&self, ids: &[u32]) -> Result<MassProperties, (u32, MassError)> {
+        let parts = self
+            .resolve_ids(ids)
+            .into_iter()
+            .map(|id| {
+                let density = self.object_density(id).map_err(|err| (id, err))?;
+                self.objects[&id].mass_properties(density, self.units).map_err(|err| (id, err))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(MassProperties::combine(&parts))
+    }
+
     /// Get the mesh indices for an object
     pub fn get_mesh_indices(&self, id: u32) -> Option<Vec<u32>> {
         self.objects.get(&id)
@@ -99,6 +509,8 @@ impl Default for Document {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use glam::{Quat, Vec3};
+    use crate::math::Placement;
 
     #[test]
     fn test_document_creation() {
@@ -139,4 +551,235 @@ mod tests {
         let buffer = doc.get_mesh_buffer(id);
         assert!(buffer.is_some());
     }
+
+    #[test]
+    fn test_recompute_skips_invalid_objects() {
+        let mut doc = Document::new();
+        let bad = doc.add_object(ShapeType::Sphere { radius: f32::NAN });
+        let good = doc.add_object(ShapeType::Sphere { radius: 1.0 });
+        doc.recompute();
+
+        assert!(doc.get_object(bad).unwrap().mesh.is_none());
+        assert!(doc.get_object(good).unwrap().mesh.is_some());
+
+        let errors = doc.errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, bad);
+        assert_eq!(errors[0].1.to_string(), "Radius is NaN");
+    }
+
+    #[test]
+    fn test_rebuild_fixes_next_id() {
+        let json = r#"{"objects":{"7":{"id":7,"shape_type":{"Sphere":{"radius":1.0}},"properties":{}}},"next_id":1}"#;
+        let mut doc: Document = serde_json::from_str(json).unwrap();
+        doc.rebuild();
+        assert_eq!(doc.next_id(), 8);
+        assert_eq!(doc.units, Units::Millimeter);
+        assert!(doc.get_object(7).unwrap().mesh.is_some());
+        assert_eq!(doc.dependency_graph.node_count(), 1);
+    }
+
+    #[test]
+    fn test_list_and_restore_objects() {
+        let mut doc = Document::new();
+        let a = doc.add_object(ShapeType::Sphere { radius: 2.0 });
+        let b = doc.add_object(ShapeType::Box { length: 1.0, width: 2.0, height: 3.0 });
+        let placement = crate::math::Placement::from_position_rotation(
+            glam::Vec3::new(5.0, 0.0, 0.0),
+            glam::Quat::from_rotation_y(1.0),
+        );
+        doc.set_object_property(b, "Placement".to_string(), Property::Placement(placement));
+
+        let infos = doc.list_objects();
+        assert_eq!(infos.iter().map(|i| i.id).collect::<Vec<_>>(), vec![a, b]);
+
+        let mut restored = Document::new();
+        for info in &infos {
+            assert!(restored.restore_from_info(info));
+        }
+        let obj = restored.get_object(b).unwrap();
+        assert_eq!(obj.placement().position, placement.position);
+        assert_eq!(obj.placement().rotation, placement.rotation);
+        assert_eq!(obj.get_property("Height").unwrap().as_float(), Some(3.0));
+        assert_eq!(restored.add_object(ShapeType::Sphere { radius: 1.0 }), b + 1);
+    }
+
+    #[test]
+    fn test_undo_redo_transaction() {
+        let mut doc = Document::new();
+        doc.open_transaction("Add Box");
+        let id = doc.add_object(ShapeType::Box { length: 1.0, width: 1.0, height: 1.0 });
+        doc.set_object_property(id, "Length".to_string(), Property::Float(1.0));
+        doc.commit_transaction();
+        doc.recompute();
+        assert_eq!(doc.undo_name(), Some("Add Box"));
+
+        assert_eq!(doc.undo(), Some(vec![id]));
+        assert!(doc.get_object(id).is_none());
+        assert!(doc.undo().is_none());
+
+        assert_eq!(doc.redo(), Some(vec![id]));
+        let obj = doc.get_object(id).unwrap();
+        assert_eq!(obj.get_property("Length").unwrap().as_float(), Some(1.0));
+        assert!(obj.mesh.is_some());
+    }
+
+    #[test]
+    fn test_undo_delete_and_property() {
+        let mut doc = Document::new();
+        let id = doc.add_object(ShapeType::Sphere { radius: 1.0 });
+        doc.set_object_property(id, "Radius".to_string(), Property::Float(1.0));
+        doc.set_object_property(id, "Radius".to_string(), Property::Float(2.0));
+        doc.set_object_shape(id, ShapeType::Sphere { radius: 2.0 });
+        assert!(doc.remove_object(id));
+
+        assert_eq!(doc.undo(), Some(vec![id]));
+        assert!(doc.get_object(id).is_some());
+
+        doc.undo();
+        assert!(matches!(doc.get_object(id).unwrap().shape_type, ShapeType::Sphere { radius } if radius == 1.0));
+
+        doc.undo();
+        assert_eq!(doc.get_object(id).unwrap().get_property("Radius").unwrap().as_float(), Some(1.0));
+
+        doc.undo();
+        assert!(doc.get_object(id).unwrap().get_property("Radius").is_none());
+    }
+
+    #[test]
+    fn test_abort_transaction_reverts_changes() {
+        let mut doc = Document::new();
+        let id = doc.add_object(ShapeType::Sphere { radius: 1.0 });
+        doc.open_transaction("Resize");
+        doc.set_object_shape(id, ShapeType::Sphere { radius: 3.0 });
+        assert_eq!(doc.abort_transaction(), vec![id]);
+        assert!(matches!(doc.get_object(id).unwrap().shape_type, ShapeType::Sphere { radius } if radius == 1.0));
+        assert_eq!(doc.undo_name(), Some("Add Object"));
+    }
+
+    #[test]
+    fn test_mass_properties_use_density_and_placement() {
+        let mut doc = Document::new();
+        doc.units = Units::Meter;
+        let a = doc.add_object(ShapeType::Box { length: 1.0, width: 1.0, height: 1.0 });
+        let b = doc.add_object(ShapeType::Box { length: 1.0, width: 1.0, height: 1.0 });
+        doc.set_object_property(a, "Density".to_string(), Property::Float(3000.0));
+        let placement = Placement::from_position_rotation(Vec3::new(4.0, 0.0, 0.0), Quat::IDENTITY);
+        doc.set_object_property(b, "Placement".to_string(), Property::Placement(placement));
+        doc.recompute();
+
+        let props = doc.mass_properties(&[]).unwrap();
+        assert!((props.mass - 4000.0).abs() < 1e-6);
+        assert!((props.volume - 2.0).abs() < 1e-6);
+        // Three quarters of the mass sits at the origin
+        assert!((props.centroid.x - 1.0).abs() < 1e-6);
+        assert!((doc.mass_properties(&[b]).unwrap().centroid.x - 4.0).abs() < 1e-6);
+
+        let broken = doc.add_object(ShapeType::Sphere { radius: -1.0 });
+        doc.recompute();
+        assert_eq!(doc.mass_properties(&[]).unwrap_err(), (broken, MassError::NoMesh));
+    }
+
+    #[test]
+    fn test_materials_resolve_density_and_color() {
+        let mut doc = Document::new();
+        let id = doc.add_object(ShapeType::Box { length: 1.0, width: 1.0, height: 1.0 });
+        assert_eq!(doc.object_density(id), Ok(DEFAULT_DENSITY));
+        assert_eq!(doc.object_color(id), DEFAULT_COLOR);
+
+        doc.set_object_property(id, "Material".to_string(), Property::String("PLA".to_string()));
+        assert_eq!(doc.object_density(id), Ok(1240.0));
+        assert_eq!(doc.object_color(id), doc.material("PLA").unwrap().color);
+        // Explicit properties override the material
+        doc.set_object_property(id, "Density".to_string(), Property::Float(1300.0));
+        doc.set_object_property(id, "Color".to_string(), Property::Vector(Vec3::X));
+        assert_eq!(doc.object_density(id), Ok(1300.0));
+        assert_eq!(doc.object_color(id), Vec3::X);
+
+        let mut custom = doc.material("PLA").unwrap();
+        custom.name = "PLA silk".to_string();
+        custom.density = 1300.0;
+        doc.add_material(custom.clone()).unwrap();
+        custom.density = 1250.0;
+        doc.add_material(custom).unwrap();
+        assert_eq!(doc.materials.len(), 1);
+        assert_eq!(doc.material("pla SILK").unwrap().density, 1250.0);
+        assert!(matches!(doc.add_material(doc.material("Steel").unwrap()), Err(MaterialError::BuiltIn(_))));
+        assert!(doc.remove_material("PLA silk"));
+        assert!(!doc.remove_material("PLA silk"));
+    }
+
+    #[test]
+    fn test_invalid_density_is_an_error() {
+        let mut doc = Document::new();
+        let id = doc.add_object(ShapeType::Box { length: 1.0, width: 1.0, height: 1.0 });
+        doc.recompute();
+        for density in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            doc.set_object_property(id, "Density".to_string(), Property::Float(density));
+            assert!(matches!(doc.object_density(id), Err(MassError::InvalidDensity(_))));
+            assert!(matches!(doc.mass_properties(&[id]), Err((i, MassError::InvalidDensity(_))) if i == id));
+        }
+    }
+
+    #[test]
+    fn test_material_edits_are_undoable() {
+        let mut doc = Document::new();
+        let mut custom = doc.material("PLA").unwrap();
+        custom.name = "PLA silk".to_string();
+        doc.add_material(custom.clone()).unwrap();
+        assert_eq!(doc.undo_name(), Some("Edit Materials"));
+        custom.density = 1250.0;
+        doc.add_material(custom).unwrap();
+
+        assert_eq!(doc.undo(), Some(vec![]));
+        assert_eq!(doc.material("PLA silk").unwrap().density, 1240.0);
+        assert!(doc.remove_material("PLA silk"));
+        assert!(doc.material("PLA silk").is_none());
+        doc.undo();
+        assert_eq!(doc.material("PLA silk").unwrap().density, 1240.0);
+        doc.undo();
+        assert!(doc.materials.is_empty());
+        // A rejected material records nothing
+        assert!(doc.add_material(doc.material("Steel").unwrap()).is_err());
+        assert!(doc.undo_name().is_none());
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let mut doc = Document::new();
+        assert!(doc.bounding_box(&[]).is_empty());
+        let a = doc.add_object(ShapeType::Box { length: 2.0, width: 2.0, height: 2.0 });
+        let b = doc.add_object(ShapeType::Sphere { radius: 1.0 });
+        let placement = Placement::from_position_rotation(Vec3::new(5.0, 0.0, 0.0), Quat::IDENTITY);
+        doc.set_object_property(b, "Placement".to_string(), Property::Placement(placement));
+        doc.recompute();
+        let all = doc.bounding_box(&[]);
+        assert!((all.min - Vec3::splat(-1.0)).length() < 1e-5);
+        assert!((all.max.x - 6.0).abs() < 1e-5);
+        assert_eq!(doc.bounding_box(&[a]).max, Vec3::ONE);
+    }
+
+    #[test]
+    fn test_pick_nearest_object() {
+        let mut doc = Document::new();
+        let near = doc.add_object(ShapeType::Box { length: 2.0, width: 2.0, height: 2.0 });
+        let far = doc.add_object(ShapeType::Box { length: 2.0, width: 2.0, height: 2.0 });
+        let placement = Placement::from_position_rotation(Vec3::new(10.0, 0.0, 0.0), Quat::from_rotation_z(0.5));
+        doc.set_object_property(far, "Placement".to_string(), Property::Placement(placement));
+        doc.recompute();
+
+        let ray = Ray::new(Vec3::new(-10.0, 0.0, 0.0), Vec3::X).unwrap();
+        let (id, hit) = doc.pick(&ray).unwrap();
+        assert_eq!(id, near);
+        assert!((hit.point - Vec3::new(-1.0, 0.0, 0.0)).length() < 1e-5);
+        assert_eq!(hit.normal, -Vec3::X);
+
+        // Hits on the turned box come back in world space
+        let ray = Ray::new(Vec3::new(10.0, 0.0, 10.0), -Vec3::Z).unwrap();
+        let (id, hit) = doc.pick(&ray).unwrap();
+        assert_eq!(id, far);
+        assert!((hit.point - Vec3::new(10.0, 0.0, 1.0)).length() < 1e-5);
+        assert!((hit.normal - Vec3::Z).length() < 1e-5);
+        assert!(doc.pick(&Ray::new(Vec3::new(0.0, 5.0, 0.0), Vec3::X).unwrap()).is_none());
+    }
 }
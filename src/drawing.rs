@@ -0,0 +1,280 @@
+//! Technical drawings of document objects
+//!
+//! A drawing places the front, top, right and isometric views of the
+//! objects on a landscape sheet, in first-angle projection for ISO documents
+//! and third-angle for ANSI ones, at a standard scale chosen so that all
+//! views fit, with dimensions and a title block in the lower right corner.
+//! Coordinates are millimeters on the sheet with Y up.
+
+use glam::Vec2;
+use crate::dimension::{automatic_dimensions, place, Annotation, DraftingStandard};
+use crate::document::Document;
+use crate::geometry::Mesh;
+use crate::projection::{hidden_lines, ProjectedEdges, View, DEFAULT_CREASE_ANGLE};
+use crate::units::Units;
+
+/// Distance from the sheet edge to the border
+pub const MARGIN: f32 = 10.0;
+/// Size of the title block
+pub const TITLE_BLOCK_SIZE: Vec2 = Vec2::new(120.0, 32.0);
+/// Minimum space between views
+const VIEW_GAP: f32 = 20.0;
+
+/// Paper size, used in landscape orientation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Sheet {
+    A4,
+    #[default]
+    A3,
+    A2,
+    Letter,
+    Tabloid,
+}
+
+impl Sheet {
+    pub const ALL: [Sheet; 5] = [Sheet::A4, Sheet::A3, Sheet::A2, Sheet::Letter, Sheet::Tabloid];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Sheet::A4 => "A4",
+            Sheet::A3 => "A3",
+            Sheet::A2 => "A2",
+            Sheet::Letter => "Letter",
+            Sheet::Tabloid => "Tabloid",
+        }
+    }
+
+    /// Parse a sheet from its name, ignoring case
+    pub fn from_name(name: &str) -> Option<Sheet> {
+        Sheet::ALL.into_iter().find(|sheet| sheet.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Width and height in millimeters
+    pub fn size(self) -> Vec2 {
+        match self {
+            Sheet::A4 => Vec2::new(297.0, 210.0),
+            Sheet::A3 => Vec2::new(420.0, 297.0),
+            Sheet::A2 => Vec2::new(594.0, 420.0),
+            Sheet::Letter => Vec2::new(279.4, 215.9),
+            Sheet::Tabloid => Vec2::new(431.8, 279.4),
+        }
+    }
+}
+
+/// Title block contents, paper size and what to annotate
+#[derive(Debug, Clone, Default)]
+pub struct DrawingOptions {
+    pub title: String,
+    pub date: String,
+    pub sheet: Sheet,
+    /// Whether to dimension object parameters; the document's own
+    /// dimensions are always drawn
+    pub dimensions: bool,
+}
+
+/// One view placed on the sheet
+#[derive(Debug, Clone)]
+pub struct DrawingView {
+    pub view: View,
+    /// Sheet position of the model origin
+    pub origin: Vec2,
+    /// Edges in sheet coordinates
+    pub edges: ProjectedEdges,
+    /// Lower left and upper right corners of the view on the sheet
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+/// A laid out drawing sheet
+#[derive(Debug, Clone)]
+pub struct Drawing {
+    pub options: DrawingOptions,
+    pub units: Units,
+    pub standard: DraftingStandard,
+    /// Sheet millimeters per model unit
+    pub scale: f32,
+    /// Scale as a ratio of real size, e.g. "1:2"
+    pub scale_label: String,
+    pub views: Vec<DrawingView>,
+    pub annotations: Vec<Annotation>,
+}
+
+/// Standard drawing scales, from the largest reduction to the largest enlargement
+fn standard_scales() -> Vec<f32> {
+    (-6..=3).flat_map(|exponent| [1.0, 2.0, 5.0].map(|m| m * 10f32.powi(exponent))).collect()
+}
+
+/// Label of a scale ratio, e.g. "1:5" or "2:1"
+fn scale_label(ratio: f32) -> String {
+    if ratio >= 1.0 {
+        format!("{}:1", ratio.round())
+    } else {
+        format!("1:{}", (1.0 / ratio).round())
+    }
+}
+
+/// Projected bounds of meshes, or a point at the origin if there are none
+fn bounds(meshes: &[Mesh], view: View) -> (Vec2, Vec2) {
+    let mut points = meshes.iter().flat_map(|m| (0..m.vertex_count()).map(|i| view.project(m.vertex(i)))).peekable();
+    if points.peek().is_none() {
+        return (Vec2::ZERO, Vec2::ZERO);
+    }
+    points.fold((Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)), |(min, max), p| (min.min(p), max.max(p)))
+}
+
+/// Lay out a drawing of the given objects (all if `ids` is empty)
+pub fn layout(doc: &Document, ids: &[u32], options: DrawingOptions) -> Drawing {
+    let meshes: Vec<Mesh> = doc.resolve_ids(ids).into_iter().filter_map(|id| doc.world_mesh(id)).collect();
+    let size = options.sheet.size();
+
+    // Views sit in a 2 x 2 grid above the title block, aligned with the
+    // front view. In third-angle projection each view is on the side it
+    // looks at, in first-angle projection on the opposite side.
+    let standard = doc.drafting_standard;
+    let grid = match standard {
+        DraftingStandard::Ansi => [(View::Front, 0, 0), (View::Right, 1, 0), (View::Top, 0, 1), (View::Isometric, 1, 1)],
+        DraftingStandard::Iso => [(View::Front, 1, 1), (View::Right, 0, 1), (View::Top, 1, 0), (View::Isometric, 0, 0)],
+    };
+    let extents: Vec<(Vec2, Vec2)> = grid.iter().map(|(view, _, _)| bounds(&meshes, *view)).collect();
+    let mut columns = [0.0f32; 2];
+    let mut rows = [0.0f32; 2];
+    for ((_, column, row), (min, max)) in grid.iter().zip(&extents) {
+        columns[*column] = columns[*column].max(max.x - min.x);
+        rows[*row] = rows[*row].max(max.y - min.y);
+    }
+
+    let area_min = Vec2::new(MARGIN, MARGIN + TITLE_BLOCK_SIZE.y);
+    let area_size = size - Vec2::splat(MARGIN) - area_min;
+    let model = Vec2::new(columns[0] + columns[1], rows[0] + rows[1]);
+    let fit = ((area_size - 3.0 * VIEW_GAP) / model.max(Vec2::splat(f32::MIN_POSITIVE))).min_element();
+
+    // Scales are ratios of real size, so convert from model units first
+    let unit_mm = (doc.units.to_meters() * 1000.0) as f32;
+    let scales = standard_scales();
+    let ratio = scales.iter().copied().rfind(|s| s * unit_mm <= fit).unwrap_or(scales[0]);
+    let ratio = if model == Vec2::ZERO { 1.0 } else { ratio };
+    let scale = ratio * unit_mm;
+
+    // Spread the spare space evenly around the columns and rows
+    let spare = (area_size - model * scale) / 3.0;
+    let column_centers = [
+        area_min.x + spare.x + columns[0] * scale / 2.0,
+        area_min.x + 2.0 * spare.x + (columns[0] + columns[1] / 2.0) * scale,
+    ];
+    let row_centers = [
+        area_min.y + spare.y + rows[0] * scale / 2.0,
+        area_min.y + 2.0 * spare.y + (rows[0] + rows[1] / 2.0) * scale,
+    ];
+
+    let views = grid
+        .iter()
+        .zip(&extents)
+        .map(|((view, column, row), (min, max))| {
+            let center = Vec2::new(column_centers[*column], row_centers[*row]);
+            let origin = center - (*min + *max) / 2.0 * scale;
+            let to_sheet = |p: Vec2| origin + p * scale;
+            let edges = hidden_lines(&meshes, *view, DEFAULT_CREASE_ANGLE);
+            DrawingView {
+                view: *view,
+                origin,
+                edges: ProjectedEdges {
+                    visible: edges.visible.into_iter().map(|s| s.map(to_sheet)).collect(),
+                    hidden: edges.hidden.into_iter().map(|s| s.map(to_sheet)).collect(),
+                },
+                min: to_sheet(*min),
+                max: to_sheet(*max),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut dimensions = if options.dimensions { automatic_dimensions(doc, ids) } else { Vec::new() };
+    dimensions.extend(doc.dimensions.iter().cloned());
+    let annotations = place(&dimensions, &views, scale, standard, doc.units);
+
+    Drawing { options, units: doc.units, standard, scale, scale_label: scale_label(ratio), views, annotations }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::ShapeType;
+
+    fn box_document(length: f32, width: f32, height: f32) -> Document {
+        let mut doc = Document::new();
+        doc.add_object(ShapeType::Box { length, width, height });
+        doc.recompute();
+        doc
+    }
+
+    #[test]
+    fn test_sheet_names() {
+        for sheet in Sheet::ALL {
+            assert_eq!(Sheet::from_name(&sheet.name().to_lowercase()), Some(sheet));
+        }
+        assert_eq!(Sheet::from_name("B5"), None);
+    }
+
+    #[test]
+    fn test_scale_selection() {
+        assert_eq!(scale_label(1.0), "1:1");
+        assert_eq!(scale_label(0.2), "1:5");
+        assert_eq!(scale_label(5.0), "5:1");
+
+        // A 60 mm cube fits an A3 sheet at full size
+        let drawing = layout(&box_document(60.0, 60.0, 60.0), &[], DrawingOptions::default());
+        assert_eq!(drawing.scale_label, "1:1");
+        // A 1 m cube needs to be reduced
+        let drawing = layout(&box_document(1000.0, 1000.0, 1000.0), &[], DrawingOptions::default());
+        assert_eq!(drawing.scale_label, "1:20");
+        // A 10 mm cube is enlarged
+        let drawing = layout(&box_document(10.0, 10.0, 10.0), &[], DrawingOptions::default());
+        assert_eq!(drawing.scale_label, "5:1");
+
+        let mut doc = box_document(2.0, 2.0, 2.0);
+        doc.units = Units::Inch;
+        assert_eq!(layout(&doc, &[], DrawingOptions::default()).scale_label, "1:1");
+    }
+
+    #[test]
+    fn test_views_are_aligned_and_on_the_sheet() {
+        let options = DrawingOptions { sheet: Sheet::A4, ..Default::default() };
+        let mut doc = box_document(100.0, 40.0, 60.0);
+        doc.drafting_standard = DraftingStandard::Ansi;
+        let drawing = layout(&doc, &[], options.clone());
+        let view = |v: View| drawing.views.iter().find(|d| d.view == v).unwrap();
+        let (front, top, right) = (view(View::Front), view(View::Top), view(View::Right));
+
+        // Top sits above the front view, the right view beside it
+        assert!((front.min.x - top.min.x).abs() < 1e-3 && (front.max.x - top.max.x).abs() < 1e-3);
+        assert!(top.min.y > front.max.y);
+        assert!((front.min.y - right.min.y).abs() < 1e-3);
+        assert!(right.min.x > front.max.x);
+        // Box length 100 along X at the chosen scale
+        assert!((front.max.x - front.min.x - 100.0 * drawing.scale).abs() < 1e-3);
+
+        let size = Sheet::A4.size();
+        for view in &drawing.views {
+            assert!(view.min.cmpge(Vec2::new(MARGIN, MARGIN + TITLE_BLOCK_SIZE.y)).all());
+            assert!(view.max.cmple(size - MARGIN).all());
+            assert_eq!(view.edges.visible.len(), if view.view == View::Isometric { 9 } else { 4 });
+        }
+
+        // First-angle projection puts the top view below and the right view on the left
+        doc.drafting_standard = DraftingStandard::Iso;
+        let drawing = layout(&doc, &[], options);
+        let view = |v: View| drawing.views.iter().find(|d| d.view == v).unwrap();
+        let (front, top, right) = (view(View::Front), view(View::Top), view(View::Right));
+        assert!((front.min.x - top.min.x).abs() < 1e-3);
+        assert!(top.max.y < front.min.y);
+        assert!((front.min.y - right.min.y).abs() < 1e-3);
+        assert!(right.max.x < front.min.x);
+        assert!(drawing.annotations.is_empty());
+    }
+
+    #[test]
+    fn test_empty_drawing() {
+        let drawing = layout(&Document::new(), &[], DrawingOptions::default());
+        assert_eq!(drawing.scale_label, "1:1");
+        assert!(drawing.views.iter().all(|v| v.edges.visible.is_empty()));
+    }
+}
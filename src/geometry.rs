@@ -1,4 +1,6 @@
+use glam::Vec3;
 use serde::{Deserialize, Serialize};
+use crate::math::Placement;
 
 /// Mesh data structure containing vertices, normals, and indices
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -6,6 +8,9 @@ pub struct Mesh {
     pub vertices: Vec<f32>,
     pub normals: Vec<f32>,
     pub indices: Vec<u32>,
+    /// Optional linear RGB color per vertex, empty if the mesh has none
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub colors: Vec<f32>,
 }
 
 impl Mesh {
@@ -14,9 +19,33 @@ impl Mesh {
             vertices: Vec::new(),
             normals: Vec::new(),
             indices: Vec::new(),
+            colors: Vec::new(),
         }
     }
 
+    /// Color of a vertex, if the mesh has vertex colors
+    pub fn color(&self, i: usize) -> Option<Vec3> {
+        self.colors.get(i * 3..i * 3 + 3).map(Vec3::from_slice)
+    }
+
+    /// Replace the normals with area-weighted averages of the adjacent face normals
+    ///
+    /// Used for meshes read without normals, such as scans, whose vertices are
+    /// shared between faces.
+    pub fn compute_smooth_normals(&mut self) {
+        let mut normals = vec![Vec3::ZERO; self.vertex_count()];
+        for i in 0..self.triangle_count() {
+            let indices = self.triangle_indices(i);
+            let [a, b, c] = indices.map(|v| self.vertex(v as usize));
+            // The cross product's length is twice the area, which weights it
+            let normal = (b - a).cross(c - a);
+            for v in indices {
+                normals[v as usize] += normal;
+            }
+        }
+        self.normals = normals.into_iter().flat_map(|n| n.normalize_or_zero().to_array()).collect();
+    }
+
     /// Convert mesh data to a flat buffer for WebGL
     /// Interleaves position and normal data: [x, y, z, nx, ny, nz, ...]
     pub fn to_interleaved_buffer(&self) -> Vec<f32> {
@@ -31,6 +60,187 @@ impl Mesh {
         }
         buffer
     }
+
+    /// Number of vertices
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len() / 3
+    }
+
+    /// Number of triangles
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    /// Position of a vertex
+    pub fn vertex(&self, i: usize) -> Vec3 {
+        Vec3::new(self.vertices[i * 3], self.vertices[i * 3 + 1], self.vertices[i * 3 + 2])
+    }
+
+    /// Vertex indices of a triangle
+    pub fn triangle_indices(&self, i: usize) -> [u32; 3] {
+        [self.indices[i * 3], self.indices[i * 3 + 1], self.indices[i * 3 + 2]]
+    }
+
+    /// Corner positions of a triangle
+    pub fn triangle(&self, i: usize) -> [Vec3; 3] {
+        self.triangle_indices(i).map(|v| self.vertex(v as usize))
+    }
+
+    /// Iterate over the corner positions of all triangles
+    pub fn triangles(&self) -> impl Iterator<Item = [Vec3; 3]> + '_ {
+        (0..self.triangle_count()).map(move |i| self.triangle(i))
+    }
+
+    /// Copy of the mesh with positions and normals moved by a placement
+    pub fn transformed(&self, placement: &Placement) -> Mesh {
+        let mut mesh = self.clone();
+        for i in 0..self.vertex_count() {
+            let p = placement.transform_point(self.vertex(i));
+            mesh.vertices[i * 3..i * 3 + 3].copy_from_slice(&p.to_array());
+        }
+        for i in 0..self.normals.len() / 3 {
+            let n = Vec3::new(self.normals[i * 3], self.normals[i * 3 + 1], self.normals[i * 3 + 2]);
+            let n = placement.rotation * n;
+            mesh.normals[i * 3..i * 3 + 3].copy_from_slice(&n.to_array());
+        }
+        mesh
+    }
+
+    /// Positions with coincident vertices merged, and the merged index of
+    /// each vertex
+    ///
+    /// Vertices are merged when they agree to within a millionth of the mesh
+    /// size, which absorbs rounding along seams.
+    pub fn weld_map(&self) -> (Vec<Vec3>, Vec<u32>) {
+        let extent = self.vertices.iter().fold(0.0f32, |m, v| m.max(v.abs()));
+        let tolerance = (extent * 1e-6).max(f32::MIN_POSITIVE);
+
+        let mut positions = Vec::new();
+        let mut lookup = std::collections::HashMap::new();
+        let remap = (0..self.vertex_count())
+            .map(|i| {
+                let p = self.vertex(i);
+                let key = (p / tolerance).round().as_ivec3().to_array();
+                *lookup.entry(key).or_insert_with(|| {
+                    positions.push(p);
+                    positions.len() as u32 - 1
+                })
+            })
+            .collect();
+        (positions, remap)
+    }
+
+    /// Positions with coincident vertices merged, and the triangles using them
+    ///
+    /// Primitive meshes duplicate vertices along edges to get sharp normals;
+    /// formats that need connected topology use this instead. Vertices are
+    /// merged as by `weld_map`. Triangles that collapse because two of their
+    /// corners merged are dropped.
+    pub fn welded(&self) -> (Vec<Vec3>, Vec<[u32; 3]>) {
+        let (positions, remap) = self.weld_map();
+        let triangles = (0..self.triangle_count())
+            .map(|i| self.triangle_indices(i).map(|v| remap[v as usize]))
+            .filter(|[a, b, c]| a != b && b != c && a != c)
+            .collect();
+        (positions, triangles)
+    }
+
+    /// Intersection of the mesh with the plane through `origin` with `normal`
+    ///
+    /// Each crossing triangle contributes one segment, directed so that the
+    /// inside of a closed mesh lies to its left when seen from the side the
+    /// normal points to. Vertices on the plane count as above it, so no
+    /// segment is produced twice.
+    pub fn section(&self, origin: Vec3, normal: Vec3) -> Vec<[Vec3; 2]> {
+        let normal = normal.normalize();
+        let mut segments = Vec::new();
+        for triangle in self.triangles() {
+            let distance = triangle.map(|p| (p - origin).dot(normal));
+            let mut crossings = Vec::with_capacity(2);
+            for (i, j) in [(0, 1), (1, 2), (2, 0)] {
+                if (distance[i] >= 0.0) == (distance[j] >= 0.0) {
+                    continue;
+                }
+                // Interpolate from the lexicographically smaller end so both
+                // triangles sharing the edge compute the same point
+                let (a, b) = if triangle[i].to_array() < triangle[j].to_array() { (i, j) } else { (j, i) };
+                let t = distance[a] / (distance[a] - distance[b]);
+                crossings.push(triangle[a].lerp(triangle[b], t));
+            }
+            if let [p0, p1] = crossings[..] {
+                let face = triangle_normal(triangle[0], triangle[1], triangle[2]);
+                if (p1 - p0).dot(normal.cross(face)) >= 0.0 {
+                    segments.push([p0, p1]);
+                } else {
+                    segments.push([p1, p0]);
+                }
+            }
+        }
+        segments
+    }
+
+    /// Check whether the mesh bounds a solid: closed, manifold, consistently
+    /// wound outwards, and free of degenerate and self-intersecting triangles
+    pub fn validate(&self) -> crate::validation::ValidationReport {
+        crate::validation::validate(self)
+    }
+
+    /// Weld, clean up, close small holes in and reorient the mesh, then
+    /// recompute its normals, as `crate::repair` describes
+    pub fn repair(&mut self, options: &crate::repair::RepairOptions) -> crate::repair::RepairReport {
+        crate::repair::repair(self, options)
+    }
+
+    /// Simplify the mesh by collapsing edges until it is down to a number
+    /// of triangles or the next collapse would move it too far, as
+    /// `crate::decimate` describes
+    pub fn decimate(&mut self, options: &crate::decimate::DecimateOptions) -> crate::decimate::DecimationReport {
+        crate::decimate::decimate(self, options)
+    }
+
+    /// Append another mesh, offsetting its indices
+    ///
+    /// If only one of the meshes has vertex colors, the other's vertices
+    /// become white.
+    pub fn append(&mut self, other: &Mesh) {
+        let offset = self.vertex_count() as u32;
+        if self.colors.is_empty() != other.colors.is_empty() {
+            if self.colors.is_empty() {
+                self.colors = vec![1.0; self.vertices.len()];
+            }
+            let colors = if other.colors.is_empty() { vec![1.0; other.vertices.len()] } else { other.colors.clone() };
+            self.colors.extend(colors);
+        } else {
+            self.colors.extend_from_slice(&other.colors);
+        }
+        self.vertices.extend_from_slice(&other.vertices);
+        self.normals.extend_from_slice(&other.normals);
+        self.indices.extend(other.indices.iter().map(|i| i + offset));
+    }
+}
+
+/// An edge of a welded mesh and the triangles sharing it
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshEdge {
+    /// Welded vertex indices, the smaller first
+    pub vertices: [u32; 2],
+    pub triangles: Vec<usize>,
+}
+
+/// Edges of welded triangles, as returned by `Mesh::welded`, in vertex order
+pub fn mesh_edges(triangles: &[[u32; 3]]) -> Vec<MeshEdge> {
+    let mut edges: std::collections::BTreeMap<[u32; 2], Vec<usize>> = std::collections::BTreeMap::new();
+    for (index, [a, b, c]) in triangles.iter().enumerate() {
+        for (u, v) in [(a, b), (b, c), (c, a)] {
+            edges.entry([*u.min(v), *u.max(v)]).or_default().push(index);
+        }
+    }
+    edges.into_iter().map(|(vertices, triangles)| MeshEdge { vertices, triangles }).collect()
+}
+
+/// Unit normal of a counter-clockwise triangle, or zero if it is degenerate
+pub fn triangle_normal(a: Vec3, b: Vec3, c: Vec3) -> Vec3 {
+    (b - a).cross(c - a).normalize_or_zero()
 }
 
 impl Default for Mesh {
@@ -148,6 +358,7 @@ pub fn generate_box_mesh(length: f64, width: f64, height: f64) -> Mesh {
         vertices: final_vertices,
         normals,
         indices,
+        colors: Vec::new(),
     }
 }
 
@@ -234,7 +445,62 @@ pub fn generate_cylinder_mesh(radius: f64, height: f64) -> Mesh {
         vertices,
         normals,
         indices,
+        colors: Vec::new(),
+    }
+}
+
+/// Generate a cone frustum mesh along the Y axis, centered on the origin
+///
+/// A radius of zero closes that end in an apex without a cap.
+pub fn generate_cone_mesh(bottom_radius: f32, top_radius: f32, height: f32) -> Mesh {
+    let h = height / 2.0;
+    let segments = 32;
+    let mut mesh = Mesh::new();
+    let ring = |i: u32| {
+        let theta = (i as f32 / segments as f32) * std::f32::consts::TAU;
+        (theta.cos(), theta.sin())
+    };
+
+    for (radius, y, normal_y) in [(top_radius, h, 1.0), (bottom_radius, -h, -1.0)] {
+        if radius <= 0.0 {
+            continue;
+        }
+        let center = mesh.vertex_count() as u32;
+        mesh.vertices.extend_from_slice(&[0.0, y, 0.0]);
+        mesh.normals.extend_from_slice(&[0.0, normal_y, 0.0]);
+        for i in 0..=segments {
+            let (c, s) = ring(i);
+            mesh.vertices.extend_from_slice(&[radius * c, y, radius * s]);
+            mesh.normals.extend_from_slice(&[0.0, normal_y, 0.0]);
+        }
+        for i in 0..segments {
+            let (current, next) = (center + 1 + i, center + 2 + i);
+            if normal_y > 0.0 {
+                mesh.indices.extend_from_slice(&[center, next, current]);
+            } else {
+                mesh.indices.extend_from_slice(&[center, current, next]);
+            }
+        }
     }
+
+    // Side: the slant normal tilts up as the cone narrows towards the top
+    let side = mesh.vertex_count() as u32;
+    let slope = (bottom_radius - top_radius) / height;
+    for i in 0..=segments {
+        let (c, s) = ring(i);
+        let normal = Vec3::new(c, slope, s).normalize();
+        mesh.vertices.extend_from_slice(&[top_radius * c, h, top_radius * s]);
+        mesh.vertices.extend_from_slice(&[bottom_radius * c, -h, bottom_radius * s]);
+        mesh.normals.extend_from_slice(&normal.to_array());
+        mesh.normals.extend_from_slice(&normal.to_array());
+    }
+    for i in 0..segments {
+        let top = side + i * 2;
+        let bottom = top + 1;
+        mesh.indices.extend_from_slice(&[bottom, top, bottom + 2]);
+        mesh.indices.extend_from_slice(&[bottom + 2, top, top + 2]);
+    }
+    mesh
 }
 
 /// Generate a sphere mesh with the given radius
@@ -273,7 +539,7 @@ pub fn generate_sphere_mesh(radius: f64) -> Mesh {
     }
 
     for i in 0..stack_count {
-        let k1 = (i * (sector_count + 1)) as u32;
+        let k1 = i * (sector_count + 1);
         let k2 = k1 + sector_count + 1;
 
         for j in 0..sector_count {
@@ -295,6 +561,7 @@ pub fn generate_sphere_mesh(radius: f64) -> Mesh {
         vertices,
         normals,
         indices,
+        colors: Vec::new(),
     }
 }
 
@@ -319,4 +586,82 @@ mod tests {
         // Should have 6 floats per vertex (3 position + 3 normal) * 24 vertices
         assert_eq!(buffer.len(), 144);
     }
+
+    #[test]
+    fn test_mesh_transformed_and_append() {
+        let mesh = generate_box_mesh(2.0, 2.0, 2.0);
+        let placement = Placement::from_position_rotation(
+            Vec3::new(10.0, 0.0, 0.0),
+            glam::Quat::from_rotation_y(std::f32::consts::FRAC_PI_2),
+        );
+        let moved = mesh.transformed(&placement);
+        // Front face (+Z) rotates to +X
+        assert!((moved.vertex(0) - Vec3::new(11.0, -1.0, 1.0)).length() < 1e-5);
+        assert!((Vec3::from_slice(&moved.normals[0..3]) - Vec3::X).length() < 1e-5);
+
+        let mut combined = mesh.clone();
+        combined.append(&moved);
+        assert_eq!(combined.vertex_count(), 48);
+        assert_eq!(combined.triangle_count(), 24);
+        assert_eq!(combined.triangle_indices(12), [24, 25, 26]);
+    }
+
+    #[test]
+    fn test_triangle_normal() {
+        let n = triangle_normal(Vec3::ZERO, Vec3::X, Vec3::Y);
+        assert_eq!(n, Vec3::Z);
+        assert_eq!(triangle_normal(Vec3::ZERO, Vec3::X, Vec3::X * 2.0), Vec3::ZERO);
+    }
+
+    #[test]
+    fn test_welded() {
+        let (positions, triangles) = generate_box_mesh(1.0, 1.0, 1.0).welded();
+        assert_eq!(positions.len(), 8);
+        assert_eq!(triangles.len(), 12);
+
+        // Seam and pole vertices merge; no collapsed triangles remain
+        let sphere = generate_sphere_mesh(1.0);
+        let (positions, triangles) = sphere.welded();
+        assert_eq!(positions.len(), 32 * 15 + 2);
+        assert_eq!(triangles.len(), sphere.triangle_count());
+    }
+
+    #[test]
+    fn test_generate_cone_mesh() {
+        let cone = generate_cone_mesh(2.0, 0.0, 3.0);
+        // Bottom cap and side; the apex has no cap
+        assert_eq!(cone.triangle_count(), 32 * 3);
+        for [a, b, c] in cone.triangles() {
+            let center = (a + b + c) / 3.0;
+            let normal = triangle_normal(a, b, c);
+            // Every face points away from the axis or down through the base
+            assert!(normal.dot(center * Vec3::new(1.0, 0.0, 1.0)) >= 0.0 || normal.y < -0.99);
+        }
+
+        let frustum = generate_cone_mesh(1.0, 0.5, 1.0);
+        assert_eq!(frustum.triangle_count(), 32 * 4);
+    }
+
+    #[test]
+    fn test_section() {
+        let mesh = generate_box_mesh(2.0, 2.0, 2.0);
+        let segments = mesh.section(Vec3::ZERO, Vec3::Z);
+        // Two triangles on each of the four side faces cross the plane
+        assert_eq!(segments.len(), 8);
+        for [a, b] in &segments {
+            assert_eq!(a.z, 0.0);
+            // Counter-clockwise around +Z: the inside is to the left
+            assert!((*b - *a).cross(-*a).z > 0.0);
+        }
+        assert!(mesh.section(Vec3::new(0.0, 0.0, 5.0), Vec3::Z).is_empty());
+    }
+
+    #[test]
+    fn test_mesh_edges() {
+        let (_, triangles) = generate_box_mesh(1.0, 1.0, 1.0).welded();
+        let edges = mesh_edges(&triangles);
+        // 12 box edges and 6 face diagonals, each shared by two triangles
+        assert_eq!(edges.len(), 18);
+        assert!(edges.iter().all(|e| e.triangles.len() == 2 && e.vertices[0] < e.vertices[1]));
+    }
 }
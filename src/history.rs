@@ -0,0 +1,219 @@
+use crate::dimension::{Dimension, DraftingStandard};
+use crate::material::Material;
+use crate::object::{Object, ShapeType};
+use crate::property::Property;
+
+/// Maximum number of transactions kept on the undo stack
+const MAX_UNDO_DEPTH: usize = 100;
+
+/// A single reversible change to the document
+#[derive(Debug, Clone)]
+pub enum Command {
+    AddObject { object: Object },
+    DeleteObject { object: Object },
+    SetProperty { id: u32, name: String, old: Option<Property>, new: Property },
+    SetShape { id: u32, old: ShapeType, new: ShapeType },
+    SetMaterials { old: Vec<Material>, new: Vec<Material> },
+    SetDraftingStandard { old: DraftingStandard, new: DraftingStandard },
+    SetDimensions { old: Vec<Dimension>, new: Vec<Dimension> },
+}
+
+impl Command {
+    /// The ID of the object this command changes, if it changes one
+    pub fn object_id(&self) -> Option<u32> {
+        match self {
+            Command::AddObject { object } | Command::DeleteObject { object } => Some(object.id),
+            Command::SetProperty { id, .. } | Command::SetShape { id, .. } => Some(*id),
+            Command::SetMaterials { .. } | Command::SetDraftingStandard { .. } | Command::SetDimensions { .. } => None,
+        }
+    }
+
+    /// Default transaction name when the command is recorded on its own
+    fn name(&self) -> String {
+        match self {
+            Command::AddObject { .. } => "Add Object".to_string(),
+            Command::DeleteObject { .. } => "Delete Object".to_string(),
+            Command::SetProperty { name, .. } => format!("Set {}", name),
+            Command::SetShape { .. } => "Edit Shape".to_string(),
+            Command::SetMaterials { .. } => "Edit Materials".to_string(),
+            Command::SetDraftingStandard { .. } => "Set Drafting Standard".to_string(),
+            Command::SetDimensions { .. } => "Edit Dimensions".to_string(),
+        }
+    }
+}
+
+/// A named group of commands that is undone and redone as a whole
+#[derive(Debug, Clone)]
+pub struct Transaction {
+    pub name: String,
+    pub commands: Vec<Command>,
+}
+
+impl Transaction {
+    /// IDs of all objects touched by the transaction, sorted and deduplicated
+    pub fn affected_ids(&self) -> Vec<u32> {
+        let mut ids: Vec<u32> = self.commands.iter().filter_map(Command::object_id).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+}
+
+/// Undo and redo stacks of a document
+#[derive(Debug, Default)]
+pub struct History {
+    undo_stack: Vec<Transaction>,
+    redo_stack: Vec<Transaction>,
+    open: Option<Transaction>,
+    depth: u32,
+}
+
+impl History {
+    /// Open a transaction; nested calls join the outermost transaction
+    pub fn open_transaction(&mut self, name: &str) {
+        if self.open.is_none() {
+            self.open = Some(Transaction { name: name.to_string(), commands: Vec::new() });
+        }
+        self.depth += 1;
+    }
+
+    /// Close the innermost transaction, committing when the outermost one closes
+    ///
+    /// Returns false if no transaction was open.
+    pub fn commit_transaction(&mut self) -> bool {
+        if self.depth == 0 {
+            return false;
+        }
+        self.depth -= 1;
+        if self.depth == 0 {
+            if let Some(transaction) = self.open.take() {
+                self.push(transaction);
+            }
+        }
+        true
+    }
+
+    /// Drop the open transaction and return it so its commands can be reverted
+    pub fn abort_transaction(&mut self) -> Option<Transaction> {
+        self.depth = 0;
+        self.open.take()
+    }
+
+    /// Whether a transaction is currently open
+    pub fn is_open(&self) -> bool {
+        self.open.is_some()
+    }
+
+    /// Record a command in the open transaction, or as its own transaction
+    pub fn record(&mut self, command: Command) {
+        match self.open.as_mut() {
+            Some(transaction) => transaction.commands.push(command),
+            None => {
+                let name = command.name();
+                self.push(Transaction { name, commands: vec![command] });
+            }
+        }
+    }
+
+    fn push(&mut self, transaction: Transaction) {
+        if transaction.commands.is_empty() {
+            return;
+        }
+        self.redo_stack.clear();
+        self.undo_stack.push(transaction);
+        if self.undo_stack.len() > MAX_UNDO_DEPTH {
+            self.undo_stack.remove(0);
+        }
+    }
+
+    /// Take the transaction to undo, if any
+    pub fn pop_undo(&mut self) -> Option<Transaction> {
+        self.undo_stack.pop()
+    }
+
+    /// Take the transaction to redo, if any
+    pub fn pop_redo(&mut self) -> Option<Transaction> {
+        self.redo_stack.pop()
+    }
+
+    /// Put an undone transaction on the redo stack
+    pub fn push_redo(&mut self, transaction: Transaction) {
+        self.redo_stack.push(transaction);
+    }
+
+    /// Put a redone transaction back on the undo stack
+    pub fn push_undo(&mut self, transaction: Transaction) {
+        self.undo_stack.push(transaction);
+    }
+
+    /// Name of the transaction that would be undone next
+    pub fn undo_name(&self) -> Option<&str> {
+        self.undo_stack.last().map(|t| t.name.as_str())
+    }
+
+    /// Name of the transaction that would be redone next
+    pub fn redo_name(&self) -> Option<&str> {
+        self.redo_stack.last().map(|t| t.name.as_str())
+    }
+
+    /// Forget all recorded transactions
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.open = None;
+        self.depth = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set_length(id: u32, value: f64) -> Command {
+        Command::SetProperty { id, name: "Length".to_string(), old: None, new: Property::Float(value) }
+    }
+
+    #[test]
+    fn test_record_without_transaction() {
+        let mut history = History::default();
+        history.record(set_length(1, 2.0));
+        assert_eq!(history.undo_name(), Some("Set Length"));
+        assert!(history.redo_name().is_none());
+    }
+
+    #[test]
+    fn test_nested_transactions_join_outermost() {
+        let mut history = History::default();
+        history.open_transaction("Outer");
+        history.open_transaction("Inner");
+        history.record(set_length(1, 2.0));
+        assert!(history.commit_transaction());
+        assert!(history.undo_name().is_none());
+        history.record(set_length(2, 3.0));
+        assert!(history.commit_transaction());
+        assert!(!history.commit_transaction());
+
+        let transaction = history.pop_undo().unwrap();
+        assert_eq!(transaction.name, "Outer");
+        assert_eq!(transaction.affected_ids(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_empty_transaction_is_dropped() {
+        let mut history = History::default();
+        history.open_transaction("Nothing");
+        history.commit_transaction();
+        assert!(history.pop_undo().is_none());
+    }
+
+    #[test]
+    fn test_new_change_clears_redo() {
+        let mut history = History::default();
+        history.record(set_length(1, 2.0));
+        let transaction = history.pop_undo().unwrap();
+        history.push_redo(transaction);
+        assert_eq!(history.redo_name(), Some("Set Length"));
+        history.record(set_length(1, 3.0));
+        assert!(history.redo_name().is_none());
+    }
+}
@@ -0,0 +1,645 @@
+//! DXF import of 2D profiles and export of sections and outlines
+//!
+//! The reader takes LINE, ARC, CIRCLE, LWPOLYLINE, POLYLINE and SPLINE
+//! entities from the ENTITIES section and flattens them into polylines in
+//! the XY plane, which are then joined into a `Profile` for extruding or
+//! revolving. Other entities are counted and skipped. The writer produces
+//! R12 files, the version every CAM package reads, with one POLYLINE per
+//! chain of segments.
+
+use std::collections::BTreeMap;
+use std::f64::consts::TAU;
+use std::fmt::{Display, Write};
+use glam::{DVec2, DVec3, Vec2};
+use serde::Serialize;
+use crate::document::Document;
+use crate::io::ParseError;
+use crate::profile::{chain, Contour, Profile};
+use crate::projection::{silhouette_edges, View};
+use crate::section::{section, Plane};
+use crate::units::Units;
+
+/// Line segments used for a full circle
+const CIRCLE_SEGMENTS: f64 = 64.0;
+/// Layer of exported cross-sections
+pub const SECTION_LAYER: &str = "SECTION";
+/// Layer of exported outlines
+pub const OUTLINE_LAYER: &str = "OUTLINE";
+
+/// A flattened entity
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polyline {
+    pub layer: String,
+    pub points: Vec<Vec2>,
+    pub closed: bool,
+}
+
+/// A parsed DXF file
+#[derive(Debug, Clone, Default)]
+pub struct DxfModel {
+    /// Units from `$INSUNITS`, if the drawing declares any we support
+    pub units: Option<Units>,
+    pub polylines: Vec<Polyline>,
+    /// Number of unsupported entities of each type
+    pub skipped: BTreeMap<String, usize>,
+}
+
+/// Summary of turning a DXF file into a profile
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DxfReport {
+    /// Closed contours found
+    pub contours: usize,
+    /// Chains of entities whose ends did not meet, left out of the profile
+    pub open_chains: usize,
+    /// Number of unsupported entities of each type
+    pub skipped: BTreeMap<String, usize>,
+}
+
+/// `$INSUNITS` code of a unit system
+fn insunits(units: Units) -> u32 {
+    match units {
+        Units::Inch => 1,
+        Units::Foot => 2,
+        Units::Millimeter => 4,
+        Units::Centimeter => 5,
+        Units::Meter => 6,
+    }
+}
+
+fn units_from_insunits(code: u32) -> Option<Units> {
+    Units::ALL.into_iter().find(|u| insunits(*u) == code)
+}
+
+/// Joining tolerance for points spread over `points`
+fn tolerance<'a>(points: impl Iterator<Item = &'a Vec2>) -> f32 {
+    let extent = points.fold(0.0f32, |m, p| m.max(p.abs().max_element()));
+    (extent * 1e-5).max(1e-6)
+}
+
+impl DxfModel {
+    /// Join the polylines into a profile in `units`
+    ///
+    /// Drawings without units are taken to be in `units` already.
+    pub fn to_profile(&self, units: Units) -> (Profile, DxfReport) {
+        let scale = match self.units {
+            Some(from) => (from.to_meters() / units.to_meters()) as f32,
+            None => 1.0,
+        };
+        let tolerance = tolerance(self.polylines.iter().flat_map(|p| &p.points)) * scale;
+
+        let mut contours = Vec::new();
+        let mut open = Vec::new();
+        for polyline in &self.polylines {
+            let mut points: Vec<Vec2> = polyline.points.iter().map(|p| *p * scale).collect();
+            points.dedup_by(|a, b| a.distance(*b) <= tolerance);
+            if polyline.closed {
+                if points.len() > 1 && points[0].distance(*points.last().unwrap()) <= tolerance {
+                    points.pop();
+                }
+                if points.len() >= 3 {
+                    contours.push(Contour::new(points));
+                }
+            } else {
+                open.push(points);
+            }
+        }
+        let (chained, open) = chain(open, tolerance);
+        contours.extend(chained);
+
+        let report = DxfReport { contours: contours.len(), open_chains: open.len(), skipped: self.skipped.clone() };
+        (Profile::new(contours), report)
+    }
+}
+
+/// A group code and its value
+struct Group {
+    code: i32,
+    value: String,
+    line: usize,
+}
+
+fn read_groups(text: &str) -> Result<Vec<Group>, ParseError> {
+    let mut groups = Vec::new();
+    let mut lines = text.lines().enumerate();
+    while let Some((index, code)) = lines.next() {
+        let code = code.trim();
+        if code.is_empty() {
+            continue;
+        }
+        let code = code.parse().map_err(|_| ParseError::line(index + 1, format!("invalid group code '{}'", code)))?;
+        let (_, value) = lines.next().ok_or_else(|| ParseError::line(index + 1, "missing group value"))?;
+        groups.push(Group { code, value: value.trim().to_string(), line: index + 2 });
+    }
+    Ok(groups)
+}
+
+/// An entity's groups after its type
+struct Entity<'a> {
+    kind: &'a str,
+    groups: &'a [Group],
+}
+
+fn number(group: &Group) -> Result<f64, ParseError> {
+    group.value
+        .parse()
+        .ok()
+        .filter(|v: &f64| v.is_finite())
+        .ok_or_else(|| ParseError::line(group.line, format!("invalid number '{}'", group.value)))
+}
+
+impl Entity<'_> {
+    fn group(&self, code: i32) -> Option<&Group> {
+        self.groups.iter().find(|g| g.code == code)
+    }
+
+    fn real(&self, code: i32, default: f64) -> Result<f64, ParseError> {
+        self.group(code).map_or(Ok(default), number)
+    }
+
+    fn reals(&self, code: i32) -> Result<Vec<f64>, ParseError> {
+        self.groups.iter().filter(|g| g.code == code).map(number).collect()
+    }
+
+    fn flags(&self) -> Result<u32, ParseError> {
+        Ok(self.real(70, 0.0)? as u32)
+    }
+
+    fn point(&self, x_code: i32) -> Result<DVec2, ParseError> {
+        Ok(DVec2::new(self.real(x_code, 0.0)?, self.real(x_code + 10, 0.0)?))
+    }
+
+    /// Points from repeated x and y groups, in order
+    fn points(&self, x_code: i32) -> Result<Vec<DVec2>, ParseError> {
+        let xs = self.reals(x_code)?;
+        let ys = self.reals(x_code + 10)?;
+        Ok(xs.into_iter().zip(ys).map(|(x, y)| DVec2::new(x, y)).collect())
+    }
+
+    fn layer(&self) -> String {
+        self.group(8).map_or("0", |g| g.value.as_str()).to_string()
+    }
+
+    /// Whether the object coordinate system is flipped, i.e. the extrusion
+    /// direction is -Z, which mirrors X of 2D entities
+    fn mirrored(&self) -> Result<bool, ParseError> {
+        Ok(self.real(230, 1.0)? < 0.0)
+    }
+}
+
+/// Points along an arc; `sweep` is in radians, positive counter-clockwise,
+/// and at most a full turn
+fn arc_points(center: DVec2, radius: f64, start: f64, sweep: f64) -> Vec<DVec2> {
+    let segments = ((sweep.abs() / TAU * CIRCLE_SEGMENTS).ceil() as usize).clamp(1, CIRCLE_SEGMENTS as usize);
+    (0..=segments)
+        .map(|i| {
+            let angle = start + sweep * i as f64 / segments as f64;
+            center + radius * DVec2::new(angle.cos(), angle.sin())
+        })
+        .collect()
+}
+
+/// Points of a polyline segment with the given bulge, excluding its end
+///
+/// The bulge is the tangent of a quarter of the included angle, positive
+/// for counter-clockwise arcs.
+fn bulge_points(start: DVec2, end: DVec2, bulge: f64) -> Vec<DVec2> {
+    let chord = end - start;
+    if bulge.abs() < 1e-9 || chord.length() < 1e-12 {
+        return vec![start];
+    }
+    let sweep = 4.0 * bulge.atan();
+    let offset = chord.length() / 2.0 * (1.0 - bulge * bulge) / (2.0 * bulge);
+    let center = (start + end) / 2.0 + chord.perp().normalize() * offset;
+    let radius = (start - center).length();
+    let from = (start - center).y.atan2((start - center).x);
+    let mut points = arc_points(center, radius, from, sweep);
+    points.pop();
+    points
+}
+
+/// Flatten vertices with bulges into points
+fn bulged_polyline(vertices: &[(DVec2, f64)], closed: bool) -> Vec<DVec2> {
+    let mut points = Vec::new();
+    for (i, (point, bulge)) in vertices.iter().enumerate() {
+        let next = if i + 1 < vertices.len() {
+            Some(vertices[i + 1].0)
+        } else if closed && vertices.len() > 1 {
+            Some(vertices[0].0)
+        } else {
+            None
+        };
+        match next {
+            Some(next) => points.extend(bulge_points(*point, next, *bulge)),
+            None => points.push(*point),
+        }
+    }
+    points
+}
+
+/// Point on a NURBS curve by de Boor's algorithm, with weighted control points
+/// given as (x w, y w, w)
+fn nurbs_point(degree: usize, knots: &[f64], control: &[DVec3], t: f64) -> DVec2 {
+    let n = control.len();
+    let span = (degree..n).rev().find(|&k| knots[k] <= t).unwrap_or(degree);
+    let mut d: Vec<DVec3> = (0..=degree).map(|j| control[j + span - degree]).collect();
+    for r in 1..=degree {
+        for j in (r..=degree).rev() {
+            let i = j + span - degree;
+            let denominator = knots[i + degree + 1 - r] - knots[i];
+            let alpha = if denominator.abs() < 1e-12 { 0.0 } else { (t - knots[i]) / denominator };
+            d[j] = d[j - 1].lerp(d[j], alpha);
+        }
+    }
+    let p = d[degree];
+    DVec2::new(p.x, p.y) / p.z
+}
+
+fn spline_points(entity: &Entity) -> Result<Option<Vec<DVec2>>, ParseError> {
+    let degree = entity.real(71, 3.0)? as usize;
+    let knots = entity.reals(40)?;
+    let control = entity.points(10)?;
+    if control.is_empty() {
+        // Splines defined only by fit points are approximated by them
+        let fit = entity.points(11)?;
+        return Ok(if fit.len() >= 2 { Some(fit) } else { None });
+    }
+    let weights = entity.reals(41)?;
+    if degree == 0 || control.len() <= degree || knots.len() != control.len() + degree + 1 {
+        return Ok(None);
+    }
+    let control: Vec<DVec3> = control
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let w = weights.get(i).copied().unwrap_or(1.0);
+            DVec3::new(p.x * w, p.y * w, w)
+        })
+        .collect();
+    let (start, end) = (knots[degree], knots[control.len()]);
+    let samples = (control.len() * 8).max(16);
+    Ok(Some(
+        (0..=samples)
+            .map(|i| nurbs_point(degree, &knots, &control, start + (end - start) * i as f64 / samples as f64))
+            .collect(),
+    ))
+}
+
+/// Read the supported entities of a DXF file
+pub fn read(text: &str) -> Result<DxfModel, ParseError> {
+    let groups = read_groups(text)?;
+    let mut model = DxfModel::default();
+
+    // Split into sections, then the ENTITIES section into entities at each 0 group
+    let mut section = "";
+    let mut entities: Vec<Entity> = Vec::new();
+    let mut i = 0;
+    while i < groups.len() {
+        let group = &groups[i];
+        match (group.code, group.value.as_str()) {
+            (0, "SECTION") => {
+                section = groups.get(i + 1).filter(|g| g.code == 2).map_or("", |g| g.value.as_str());
+                i += 2;
+                continue;
+            }
+            (0, "ENDSECTION") => section = "",
+            (9, "$INSUNITS") if section == "HEADER" => {
+                if let Some(value) = groups.get(i + 1) {
+                    model.units = units_from_insunits(number(value)? as u32);
+                }
+            }
+            (0, kind) if section == "ENTITIES" => {
+                let end = groups[i + 1..].iter().position(|g| g.code == 0).map_or(groups.len(), |p| p + i + 1);
+                entities.push(Entity { kind, groups: &groups[i + 1..end] });
+                i = end;
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    let mut index = 0;
+    while index < entities.len() {
+        let entity = &entities[index];
+        index += 1;
+        let flip = |points: Vec<DVec2>, mirrored: bool| -> Vec<Vec2> {
+            points
+                .into_iter()
+                .map(|p| if mirrored { DVec2::new(-p.x, p.y) } else { p })
+                .map(|p| p.as_vec2())
+                .collect()
+        };
+        let (points, closed) = match entity.kind {
+            "LINE" => (flip(vec![entity.point(10)?, entity.point(11)?], false), false),
+            "CIRCLE" => {
+                let mut points = arc_points(entity.point(10)?, entity.real(40, 0.0)?, 0.0, TAU);
+                points.pop();
+                (flip(points, entity.mirrored()?), true)
+            }
+            "ARC" => {
+                let start = entity.real(50, 0.0)?.to_radians();
+                let end = entity.real(51, 360.0)?.to_radians();
+                // Counter-clockwise from start to end, a full turn if they meet
+                let sweep = match (end - start).rem_euclid(TAU) {
+                    sweep if sweep > 0.0 => sweep,
+                    _ => TAU,
+                };
+                let points = arc_points(entity.point(10)?, entity.real(40, 0.0)?, start, sweep);
+                (flip(points, entity.mirrored()?), false)
+            }
+            "LWPOLYLINE" => {
+                let mut vertices: Vec<(DVec2, f64)> = Vec::new();
+                for group in entity.groups {
+                    match group.code {
+                        10 => vertices.push((DVec2::new(number(group)?, 0.0), 0.0)),
+                        20 => if let Some(v) = vertices.last_mut() { v.0.y = number(group)? },
+                        42 => if let Some(v) = vertices.last_mut() { v.1 = number(group)? },
+                        _ => {}
+                    }
+                }
+                let closed = entity.flags()? & 1 != 0;
+                (flip(bulged_polyline(&vertices, closed), entity.mirrored()?), closed)
+            }
+            "POLYLINE" => {
+                let flags = entity.flags()?;
+                let mut vertices = Vec::new();
+                while index < entities.len() && entities[index].kind == "VERTEX" {
+                    let vertex = &entities[index];
+                    vertices.push((vertex.point(10)?, vertex.real(42, 0.0)?));
+                    index += 1;
+                }
+                if entities.get(index).is_some_and(|e| e.kind == "SEQEND") {
+                    index += 1;
+                }
+                // Polygon meshes and polyface meshes are 3D surfaces
+                if flags & (16 | 64) != 0 {
+                    *model.skipped.entry("POLYLINE".to_string()).or_default() += 1;
+                    continue;
+                }
+                let closed = flags & 1 != 0;
+                (flip(bulged_polyline(&vertices, closed), entity.mirrored()?), closed)
+            }
+            "SPLINE" => match spline_points(entity)? {
+                Some(points) => (flip(points, false), entity.flags()? & 1 != 0),
+                None => {
+                    *model.skipped.entry("SPLINE".to_string()).or_default() += 1;
+                    continue;
+                }
+            },
+            kind => {
+                *model.skipped.entry(kind.to_string()).or_default() += 1;
+                continue;
+            }
+        };
+        if points.len() >= 2 {
+            model.polylines.push(Polyline { layer: entity.layer(), points, closed });
+        }
+    }
+    Ok(model)
+}
+
+/// Builds an R12 DXF file
+#[derive(Default)]
+pub struct DxfWriter {
+    layers: Vec<String>,
+    entities: String,
+}
+
+fn pair(out: &mut String, code: i32, value: impl Display) {
+    let _ = write!(out, "{}\n{}\n", code, value);
+}
+
+impl DxfWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a polyline on `layer`, which is created on first use
+    pub fn polyline(&mut self, layer: &str, points: &[Vec2], closed: bool) {
+        if !self.layers.iter().any(|l| l == layer) {
+            self.layers.push(layer.to_string());
+        }
+        let out = &mut self.entities;
+        pair(out, 0, "POLYLINE");
+        pair(out, 8, layer);
+        pair(out, 66, 1);
+        pair(out, 10, 0.0);
+        pair(out, 20, 0.0);
+        pair(out, 30, 0.0);
+        pair(out, 70, if closed { 1 } else { 0 });
+        for p in points {
+            pair(out, 0, "VERTEX");
+            pair(out, 8, layer);
+            pair(out, 10, p.x);
+            pair(out, 20, p.y);
+            pair(out, 30, 0.0);
+        }
+        pair(out, 0, "SEQEND");
+        pair(out, 8, layer);
+    }
+
+    /// Join segments into polylines on `layer`
+    pub fn segments(&mut self, layer: &str, segments: &[[Vec2; 2]]) {
+        let tolerance = tolerance(segments.iter().flatten());
+        let (closed, open) = chain(segments.iter().map(|s| s.to_vec()).collect(), tolerance);
+        for contour in closed {
+            self.polyline(layer, &contour.points, true);
+        }
+        for points in open {
+            self.polyline(layer, &points, false);
+        }
+    }
+
+    /// Return the file, declaring `units` in the header
+    pub fn finish(self, units: Units) -> String {
+        let mut out = String::new();
+        pair(&mut out, 0, "SECTION");
+        pair(&mut out, 2, "HEADER");
+        pair(&mut out, 9, "$ACADVER");
+        pair(&mut out, 1, "AC1009");
+        pair(&mut out, 9, "$INSUNITS");
+        pair(&mut out, 70, insunits(units));
+        pair(&mut out, 0, "ENDSECTION");
+
+        pair(&mut out, 0, "SECTION");
+        pair(&mut out, 2, "TABLES");
+        pair(&mut out, 0, "TABLE");
+        pair(&mut out, 2, "LAYER");
+        pair(&mut out, 70, self.layers.len());
+        for layer in &self.layers {
+            pair(&mut out, 0, "LAYER");
+            pair(&mut out, 2, layer);
+            pair(&mut out, 70, 0);
+            pair(&mut out, 62, 7);
+            pair(&mut out, 6, "CONTINUOUS");
+        }
+        pair(&mut out, 0, "ENDTAB");
+        pair(&mut out, 0, "ENDSECTION");
+
+        pair(&mut out, 0, "SECTION");
+        pair(&mut out, 2, "ENTITIES");
+        out.push_str(&self.entities);
+        pair(&mut out, 0, "ENDSECTION");
+        pair(&mut out, 0, "EOF");
+        out
+    }
+}
+
+/// Cross-section of the given objects (all if `ids` is empty) as DXF
+///
+/// Each loop becomes a closed polyline in the plane's 2D coordinates, so
+/// the section is drawn as seen from the side the plane's normal points to.
+pub fn export_section(doc: &Document, ids: &[u32], plane: &Plane) -> String {
+    let mut writer = DxfWriter::new();
+    for object in section(doc, ids, plane) {
+        for region in &object.regions {
+            for contour in std::iter::once(&region.outer).chain(&region.holes) {
+                writer.polyline(SECTION_LAYER, &contour.points, true);
+            }
+        }
+        for points in &object.open {
+            writer.polyline(SECTION_LAYER, points, false);
+        }
+    }
+    writer.finish(doc.units)
+}
+
+/// Projected outline of the given objects (all if `ids` is empty) as DXF
+pub fn export_outline(doc: &Document, ids: &[u32], view: View) -> String {
+    let segments: Vec<[Vec2; 2]> = silhouette_edges(&doc.combined_world_mesh(ids), view)
+        .into_iter()
+        .map(|s| s.map(|p| view.project(p)))
+        .collect();
+    let mut writer = DxfWriter::new();
+    writer.segments(OUTLINE_LAYER, &segments);
+    writer.finish(doc.units)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::ShapeType;
+
+    /// Build an ENTITIES-only file from (code, value) pairs
+    fn entities(pairs: &[(i32, &str)]) -> String {
+        let mut out = String::from("0\nSECTION\n2\nENTITIES\n");
+        for (code, value) in pairs {
+            pair(&mut out, *code, value);
+        }
+        out.push_str("0\nENDSECTION\n0\nEOF\n");
+        out
+    }
+
+    #[test]
+    fn test_lines_chain_into_profile() {
+        let text = entities(&[
+            (0, "LINE"), (8, "CUT"), (10, "0"), (20, "0"), (11, "10"), (21, "0"),
+            (0, "LINE"), (10, "10"), (20, "0"), (11, "10"), (21, "5"),
+            (0, "LINE"), (10, "0"), (20, "5"), (11, "10"), (21, "5"),
+            (0, "LINE"), (10, "0"), (20, "5"), (11, "0"), (21, "0"),
+            (0, "TEXT"), (1, "label"),
+        ]);
+        let model = read(&text).unwrap();
+        assert_eq!(model.polylines.len(), 4);
+        assert_eq!(model.polylines[0].layer, "CUT");
+        assert_eq!(model.skipped.get("TEXT"), Some(&1));
+        let (profile, report) = model.to_profile(Units::Millimeter);
+        assert_eq!(report.contours, 1);
+        assert_eq!(report.open_chains, 0);
+        assert!((profile.contours[0].area().abs() - 50.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_circle_arc_and_bulge() {
+        let text = entities(&[
+            (0, "CIRCLE"), (10, "1"), (20, "2"), (40, "3"),
+            (0, "ARC"), (10, "0"), (20, "0"), (40, "1"), (50, "0"), (51, "90"),
+            // A 2 x 2 slot with round ends made of two semicircle bulges
+            (0, "LWPOLYLINE"), (90, "2"), (70, "1"), (10, "0"), (20, "0"), (42, "1"), (10, "2"), (20, "0"), (42, "1"),
+        ]);
+        let model = read(&text).unwrap();
+        let circle = &model.polylines[0];
+        assert!(circle.closed);
+        assert!(circle.points.iter().all(|p| (p.distance(Vec2::new(1.0, 2.0)) - 3.0).abs() < 1e-5));
+
+        let arc = &model.polylines[1];
+        assert!(arc.points[0].abs_diff_eq(Vec2::X, 1e-6));
+        assert!(arc.points.last().unwrap().abs_diff_eq(Vec2::Y, 1e-6));
+
+        // Two semicircles of the same center make a unit circle around (1, 0)
+        let slot = &model.polylines[2];
+        assert!(slot.closed);
+        assert!(slot.points.iter().all(|p| (p.distance(Vec2::X) - 1.0).abs() < 1e-5));
+        let area = Contour::new(slot.points.clone()).area();
+        assert!((area - std::f32::consts::PI).abs() < 0.01, "{}", area);
+    }
+
+    #[test]
+    fn test_spline() {
+        // A quadratic Bezier from (0, 0) through control (1, 2) to (2, 0)
+        let text = entities(&[
+            (0, "SPLINE"), (70, "8"), (71, "2"), (72, "6"), (73, "3"),
+            (40, "0"), (40, "0"), (40, "0"), (40, "1"), (40, "1"), (40, "1"),
+            (10, "0"), (20, "0"), (10, "1"), (20, "2"), (10, "2"), (20, "0"),
+        ]);
+        let model = read(&text).unwrap();
+        let points = &model.polylines[0].points;
+        assert!(points[0].abs_diff_eq(Vec2::ZERO, 1e-6));
+        assert!(points.last().unwrap().abs_diff_eq(Vec2::new(2.0, 0.0), 1e-6));
+        // The midpoint of the curve is at (1, 1)
+        assert!(points[points.len() / 2].abs_diff_eq(Vec2::new(1.0, 1.0), 1e-6));
+    }
+
+    #[test]
+    fn test_units_and_errors() {
+        let text = "0\nSECTION\n2\nHEADER\n9\n$INSUNITS\n70\n1\n0\nENDSECTION\n".to_string()
+            + &entities(&[(0, "CIRCLE"), (10, "0"), (20, "0"), (40, "1")]);
+        let model = read(&text).unwrap();
+        assert_eq!(model.units, Some(Units::Inch));
+        let (profile, _) = model.to_profile(Units::Millimeter);
+        assert!((profile.contours[0].points[0].x - 25.4).abs() < 1e-4);
+
+        let err = read(&entities(&[(0, "LINE"), (10, "abc")])).unwrap_err();
+        assert_eq!(err, ParseError::line(8, "invalid number 'abc'"));
+        let err = read(&entities(&[(0, "ARC"), (10, "0"), (20, "0"), (40, "1"), (50, "0"), (51, "inf")])).unwrap_err();
+        assert_eq!(err, ParseError::line(16, "invalid number 'inf'"));
+        // Angles far beyond a turn wrap around rather than multiply the segments
+        let model = read(&entities(&[(0, "ARC"), (10, "0"), (20, "0"), (40, "1"), (50, "0"), (51, "1e300")])).unwrap();
+        assert!(model.polylines[0].points.len() <= 65);
+        assert!(read("0\nSECTION\n2").is_err());
+    }
+
+    #[test]
+    fn test_write_read_round_trip() {
+        let mut writer = DxfWriter::new();
+        let square = [Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y];
+        writer.polyline("A", &square, true);
+        let text = writer.finish(Units::Centimeter);
+        assert!(text.contains("AC1009"));
+
+        let model = read(&text).unwrap();
+        assert_eq!(model.units, Some(Units::Centimeter));
+        assert_eq!(model.polylines, vec![Polyline { layer: "A".to_string(), points: square.to_vec(), closed: true }]);
+    }
+
+    #[test]
+    fn test_export_section_and_outline() {
+        let mut doc = Document::new();
+        doc.add_object(ShapeType::Box { length: 4.0, width: 2.0, height: 6.0 });
+        doc.recompute();
+
+        let model = read(&export_section(&doc, &[], &Plane::from_view(View::Front, 0.0))).unwrap();
+        assert_eq!(model.polylines.len(), 1);
+        assert_eq!(model.polylines[0].layer, SECTION_LAYER);
+        let (profile, _) = model.to_profile(Units::Millimeter);
+        // Counter-clockwise as seen from the front
+        assert!((profile.contours[0].area() - 8.0).abs() < 1e-4);
+
+        let model = read(&export_outline(&doc, &[], View::Top)).unwrap();
+        let (profile, report) = model.to_profile(Units::Millimeter);
+        assert_eq!(report.contours, 1);
+        assert!((profile.contours[0].area().abs() - 24.0).abs() < 1e-4);
+
+        assert!(read(&export_section(&doc, &[], &Plane::from_view(View::Front, 5.0))).unwrap().polylines.is_empty());
+    }
+}
@@ -0,0 +1,341 @@
+//! glTF 2.0 export, as JSON with a separate binary buffer or as GLB
+//!
+//! The scene has a single root node that scales document units to meters,
+//! the unit glTF prescribes. Each object becomes a child node carrying its
+//! placement as translation and rotation, with its local-space mesh and a
+//! PBR material built from the object's color and material.
+
+use serde_json::{json, Value};
+use crate::document::Document;
+use crate::geometry::Mesh;
+use crate::object::Object;
+
+/// glTF component type of 32-bit floats
+const FLOAT: u32 = 5126;
+/// glTF component type of 32-bit unsigned integers
+const UNSIGNED_INT: u32 = 5125;
+/// Buffer view target of vertex attributes
+const ARRAY_BUFFER: u32 = 34962;
+/// Buffer view target of indices
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const GLB_CHUNK_JSON: u32 = 0x4E4F_534A;
+const GLB_CHUNK_BIN: u32 = 0x004E_4942;
+
+/// A glTF asset: the JSON document and the contents of its single buffer
+#[derive(Debug, Clone)]
+pub struct GltfAsset {
+    pub json: Value,
+    pub bin: Vec<u8>,
+}
+
+/// Collects buffer views and accessors while the binary buffer is filled
+#[derive(Default)]
+struct BufferBuilder {
+    bin: Vec<u8>,
+    views: Vec<Value>,
+    accessors: Vec<Value>,
+}
+
+impl BufferBuilder {
+    fn push_view(&mut self, bytes: &[u8], target: u32) -> usize {
+        let offset = self.bin.len();
+        self.bin.extend_from_slice(bytes);
+        self.views.push(json!({
+            "buffer": 0,
+            "byteOffset": offset,
+            "byteLength": bytes.len(),
+            "target": target,
+        }));
+        self.views.len() - 1
+    }
+
+    fn push_vec3(&mut self, data: &[f32], with_bounds: bool) -> usize {
+        let bytes: Vec<u8> = data.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let view = self.push_view(&bytes, ARRAY_BUFFER);
+        let mut accessor = json!({
+            "bufferView": view,
+            "componentType": FLOAT,
+            "count": data.len() / 3,
+            "type": "VEC3",
+        });
+        if with_bounds {
+            let mut min = [f32::INFINITY; 3];
+            let mut max = [f32::NEG_INFINITY; 3];
+            for vertex in data.chunks_exact(3) {
+                for axis in 0..3 {
+                    min[axis] = min[axis].min(vertex[axis]);
+                    max[axis] = max[axis].max(vertex[axis]);
+                }
+            }
+            accessor["min"] = json!(min);
+            accessor["max"] = json!(max);
+        }
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    fn push_indices(&mut self, indices: &[u32]) -> usize {
+        let bytes: Vec<u8> = indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+        let view = self.push_view(&bytes, ELEMENT_ARRAY_BUFFER);
+        self.accessors.push(json!({
+            "bufferView": view,
+            "componentType": UNSIGNED_INT,
+            "count": indices.len(),
+            "type": "SCALAR",
+        }));
+        self.accessors.len() - 1
+    }
+
+    fn push_mesh(&mut self, mesh: &Mesh, material: usize, name: &str) -> Value {
+        let position = self.push_vec3(&mesh.vertices, true);
+        let normal = self.push_vec3(&mesh.normals, false);
+        let indices = self.push_indices(&mesh.indices);
+        json!({
+            "name": name,
+            "primitives": [{
+                "attributes": { "POSITION": position, "NORMAL": normal },
+                "indices": indices,
+                "material": material,
+            }],
+        })
+    }
+}
+
+/// PBR material of an object, from its color and its material's finish
+fn material(doc: &Document, object: &Object) -> Value {
+    let color = doc.object_color(object.id);
+    let assigned = doc.object_material(object.id);
+    let name = match &assigned {
+        Some(material) => material.name.clone(),
+        None => format!("{} material", object.name()),
+    };
+    let (metalness, roughness) = assigned.map_or((0.0, 0.5), |m| (m.metalness, m.roughness));
+    json!({
+        "name": name,
+        "pbrMetallicRoughness": {
+            "baseColorFactor": [color.x, color.y, color.z, 1.0],
+            "metallicFactor": metalness,
+            "roughnessFactor": roughness,
+        },
+    })
+}
+
+/// Build the glTF asset for the given objects (all if `ids` is empty)
+///
+/// Materials are shared between objects that would get identical ones.
+/// Objects without a mesh, e.g. because of invalid parameters, are skipped.
+pub fn export(doc: &Document, ids: &[u32]) -> GltfAsset {
+    let mut buffer = BufferBuilder::default();
+    let mut meshes = Vec::new();
+    let mut materials: Vec<Value> = Vec::new();
+    let mut nodes = vec![json!(null)];
+    let mut children = Vec::new();
+
+    for id in doc.resolve_ids(ids) {
+        let Some(object) = doc.get_object(id) else { continue };
+        let Some(mesh) = object.mesh.as_ref() else { continue };
+
+        let candidate = material(doc, object);
+        let material_index = match materials.iter().position(|m| m["pbrMetallicRoughness"] == candidate["pbrMetallicRoughness"]) {
+            Some(index) => index,
+            None => {
+                materials.push(candidate);
+                materials.len() - 1
+            }
+        };
+
+        let name = object.name();
+        meshes.push(buffer.push_mesh(mesh, material_index, &name));
+
+        let placement = object.placement();
+        nodes.push(json!({
+            "name": name,
+            "mesh": meshes.len() - 1,
+            "translation": placement.position.to_array(),
+            "rotation": placement.rotation.to_array(),
+            "extras": { "id": id },
+        }));
+        children.push(nodes.len() - 1);
+    }
+
+    let scale = doc.units.to_meters();
+    nodes[0] = json!({
+        "name": "RustyCAD",
+        "scale": [scale, scale, scale],
+        "children": children,
+    });
+
+    let mut json = json!({
+        "asset": {
+            "version": "2.0",
+            "generator": format!("rustycad {}", env!("CARGO_PKG_VERSION")),
+        },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": nodes,
+    });
+    if !meshes.is_empty() {
+        json["meshes"] = json!(meshes);
+        json["materials"] = json!(materials);
+        json["accessors"] = json!(buffer.accessors);
+        json["bufferViews"] = json!(buffer.views);
+        json["buffers"] = json!([{ "byteLength": buffer.bin.len() }]);
+    }
+    GltfAsset { json, bin: buffer.bin }
+}
+
+/// glTF JSON referencing the binary buffer at `bin_uri`
+pub fn to_gltf_json(asset: &GltfAsset, bin_uri: &str) -> String {
+    let mut json = asset.json.clone();
+    if let Some(buffer) = json.get_mut("buffers").and_then(|b| b.get_mut(0)) {
+        buffer["uri"] = json!(bin_uri);
+    }
+    serde_json::to_string_pretty(&json).expect("glTF JSON serialization cannot fail")
+}
+
+/// Pack an asset into a single binary GLB file
+pub fn to_glb(asset: &GltfAsset) -> Vec<u8> {
+    let mut json = serde_json::to_vec(&asset.json).expect("glTF JSON serialization cannot fail");
+    while !json.len().is_multiple_of(4) {
+        json.push(b' ');
+    }
+    let mut bin = asset.bin.clone();
+    while !bin.len().is_multiple_of(4) {
+        bin.push(0);
+    }
+
+    let has_bin = !bin.is_empty();
+    let total = 12 + 8 + json.len() + if has_bin { 8 + bin.len() } else { 0 };
+    let mut out = Vec::with_capacity(total);
+    out.extend_from_slice(&GLB_MAGIC.to_le_bytes());
+    out.extend_from_slice(&2u32.to_le_bytes());
+    out.extend_from_slice(&(total as u32).to_le_bytes());
+    out.extend_from_slice(&(json.len() as u32).to_le_bytes());
+    out.extend_from_slice(&GLB_CHUNK_JSON.to_le_bytes());
+    out.extend_from_slice(&json);
+    if has_bin {
+        out.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        out.extend_from_slice(&GLB_CHUNK_BIN.to_le_bytes());
+        out.extend_from_slice(&bin);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::{Quat, Vec3};
+    use crate::math::Placement;
+    use crate::object::ShapeType;
+    use crate::property::Property;
+    use crate::units::Units;
+
+    fn sample_document() -> Document {
+        let mut doc = Document::new();
+        let a = doc.add_object(ShapeType::Box { length: 1.0, width: 2.0, height: 3.0 });
+        let placement = Placement::from_position_rotation(Vec3::new(1.0, 2.0, 3.0), Quat::from_rotation_z(0.3));
+        doc.set_object_property(a, "Placement".to_string(), Property::Placement(placement));
+        doc.set_object_property(a, "Color".to_string(), Property::Vector(Vec3::new(1.0, 0.0, 0.0)));
+        doc.add_object(ShapeType::Sphere { radius: 1.0 });
+        doc.add_object(ShapeType::Cylinder { radius: 1.0, height: 1.0 });
+        doc.recompute();
+        doc
+    }
+
+    fn u32_at(data: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_scene_structure() {
+        let doc = sample_document();
+        let asset = export(&doc, &[]);
+        let json = &asset.json;
+
+        assert_eq!(json["nodes"][0]["scale"], json!([0.001, 0.001, 0.001]));
+        assert_eq!(json["nodes"][0]["children"], json!([1, 2, 3]));
+        assert_eq!(json["nodes"][1]["name"], "Box 1");
+        assert_eq!(json["nodes"][1]["translation"], json!([1.0, 2.0, 3.0]));
+        let rotation: Vec<f32> = serde_json::from_value(json["nodes"][1]["rotation"].clone()).unwrap();
+        assert!(Quat::from_slice(&rotation).abs_diff_eq(Quat::from_rotation_z(0.3), 1e-6));
+
+        // The red box gets its own material; sphere and cylinder share the default
+        assert_eq!(json["materials"].as_array().unwrap().len(), 2);
+        assert_eq!(json["materials"][0]["pbrMetallicRoughness"]["baseColorFactor"], json!([1.0, 0.0, 0.0, 1.0]));
+        assert_eq!(json["meshes"][2]["primitives"][0]["material"], 1);
+
+        let box_mesh = doc.get_object(1).unwrap().mesh.as_ref().unwrap();
+        let position = &json["accessors"][0];
+        assert_eq!(position["count"], box_mesh.vertex_count());
+        assert_eq!(position["min"], json!([-0.5, -1.0, -1.5]));
+        assert_eq!(json["accessors"][2]["count"], 36);
+        assert_eq!(json["buffers"][0]["byteLength"], asset.bin.len());
+    }
+
+    #[test]
+    fn test_materials_set_finish() {
+        let mut doc = sample_document();
+        doc.set_object_property(2, "Material".to_string(), Property::String("Aluminium 6061-T6".to_string()));
+        doc.set_object_property(3, "Material".to_string(), Property::String("aluminium 6061-t6".to_string()));
+        let json = export(&doc, &[]).json;
+        assert_eq!(json["materials"].as_array().unwrap().len(), 2);
+        let aluminium = &json["materials"][1];
+        assert_eq!(aluminium["name"], "Aluminium 6061-T6");
+        assert_eq!(aluminium["pbrMetallicRoughness"]["metallicFactor"], 1.0);
+        assert_eq!(aluminium["pbrMetallicRoughness"]["roughnessFactor"], json!(0.35f32));
+        let color = doc.material("Aluminium 6061-T6").unwrap().color;
+        assert_eq!(aluminium["pbrMetallicRoughness"]["baseColorFactor"], json!([color.x, color.y, color.z, 1.0]));
+        // The explicit color of the box wins over a material
+        doc.set_object_property(1, "Material".to_string(), Property::String("Steel".to_string()));
+        let json = export(&doc, &[1]).json;
+        assert_eq!(json["materials"][0]["pbrMetallicRoughness"]["baseColorFactor"], json!([1.0, 0.0, 0.0, 1.0]));
+    }
+
+    #[test]
+    fn test_units_scale_root() {
+        let mut doc = sample_document();
+        doc.units = Units::Meter;
+        let asset = export(&doc, &[2]);
+        assert_eq!(asset.json["nodes"][0]["scale"], json!([1.0, 1.0, 1.0]));
+        assert_eq!(asset.json["nodes"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_gltf_json_references_bin() {
+        let asset = export(&sample_document(), &[]);
+        let text = to_gltf_json(&asset, "part.bin");
+        let json: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(json["buffers"][0]["uri"], "part.bin");
+        assert!(asset.json["buffers"][0].get("uri").is_none());
+    }
+
+    #[test]
+    fn test_glb_layout() {
+        let asset = export(&sample_document(), &[]);
+        let glb = to_glb(&asset);
+        assert_eq!(u32_at(&glb, 0), GLB_MAGIC);
+        assert_eq!(u32_at(&glb, 4), 2);
+        assert_eq!(u32_at(&glb, 8) as usize, glb.len());
+
+        let json_len = u32_at(&glb, 12) as usize;
+        assert_eq!(json_len % 4, 0);
+        assert_eq!(u32_at(&glb, 16), GLB_CHUNK_JSON);
+        let json: Value = serde_json::from_slice(&glb[20..20 + json_len]).unwrap();
+        assert_eq!(json["asset"]["version"], "2.0");
+
+        let bin_offset = 20 + json_len;
+        assert_eq!(u32_at(&glb, bin_offset) as usize, asset.bin.len());
+        assert_eq!(u32_at(&glb, bin_offset + 4), GLB_CHUNK_BIN);
+        assert_eq!(&glb[bin_offset + 8..], &asset.bin[..]);
+    }
+
+    #[test]
+    fn test_empty_document() {
+        let asset = export(&Document::new(), &[]);
+        assert!(asset.json.get("buffers").is_none());
+        let glb = to_glb(&asset);
+        assert_eq!(u32_at(&glb, 8) as usize, glb.len());
+    }
+}
@@ -0,0 +1,188 @@
+//! Upgrading older native documents to the current format version
+//!
+//! Migrations run on the raw JSON before it is deserialized into a
+//! `Document`, one version step at a time. Each step records what it changed
+//! so the user can be told what happened to their file.
+//!
+//! Version 0 is a bare serialized `Document` without a file header, as
+//! written before the native format existed.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use crate::io::native::{FileError, FORMAT_NAME, FORMAT_VERSION};
+
+/// A single upgrade from `from` to `from + 1`
+pub struct Migration {
+    pub from: u32,
+    pub apply: fn(&mut Value, &mut Vec<String>),
+}
+
+/// All migrations, ordered by the version they upgrade from
+pub const MIGRATIONS: &[Migration] = &[
+    Migration { from: 0, apply: v0_add_header },
+];
+
+/// Summary of the upgrades applied to a loaded document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MigrationReport {
+    pub from_version: u32,
+    pub to_version: u32,
+    pub changes: Vec<String>,
+}
+
+impl MigrationReport {
+    /// Whether any migration was applied
+    pub fn migrated(&self) -> bool {
+        self.from_version != self.to_version
+    }
+}
+
+/// Detect the format version of a raw native document
+pub fn detect_version(value: &Value) -> Result<u32, FileError> {
+    if let Some(header) = value.get("header") {
+        if header.get("format").and_then(Value::as_str) != Some(FORMAT_NAME) {
+            return Err(FileError::NotADocument);
+        }
+        let version = header.get("version").and_then(Value::as_u64).ok_or(FileError::NotADocument)?;
+        return u32::try_from(version).map_err(|_| FileError::UnsupportedVersion {
+            found: u32::MAX,
+            supported: FORMAT_VERSION,
+        });
+    }
+    if value.get("objects").is_some_and(Value::is_object) {
+        return Ok(0);
+    }
+    Err(FileError::NotADocument)
+}
+
+/// Upgrade a raw native document to the current format version
+pub fn migrate(value: &mut Value) -> Result<MigrationReport, FileError> {
+    migrate_to(value, MIGRATIONS, FORMAT_VERSION)
+}
+
+/// Upgrade a raw document to `target` using the given migrations
+pub fn migrate_to(value: &mut Value, migrations: &[Migration], target: u32) -> Result<MigrationReport, FileError> {
+    let from_version = detect_version(value)?;
+    if from_version > target {
+        return Err(FileError::UnsupportedVersion { found: from_version, supported: target });
+    }
+
+    let mut changes = Vec::new();
+    for version in from_version..target {
+        let migration = migrations.iter()
+            .find(|m| m.from == version)
+            .ok_or(FileError::UnsupportedVersion { found: from_version, supported: target })?;
+        (migration.apply)(value, &mut changes);
+        if let Some(header) = value.get_mut("header") {
+            header["version"] = json!(version + 1);
+        }
+    }
+
+    Ok(MigrationReport { from_version, to_version: target, changes })
+}
+
+/// 0 -> 1: wrap the bare document in a file header
+fn v0_add_header(value: &mut Value, changes: &mut Vec<String>) {
+    let document = value.take();
+    let units = document.get("units").cloned().unwrap_or_else(|| json!("Millimeter"));
+    changes.push(format!("Added file header with units {}", units));
+    *value = json!({
+        "header": {
+            "format": FORMAT_NAME,
+            "version": 0,
+            "units": units,
+            "generator": "",
+        },
+        "document": document,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn legacy_document() -> Value {
+        json!({
+            "objects": {
+                "1": { "id": 1, "shape_type": { "Sphere": { "radius": 1.0 } }, "properties": {} }
+            },
+            "next_id": 2
+        })
+    }
+
+    #[test]
+    fn test_detect_version() {
+        assert_eq!(detect_version(&legacy_document()), Ok(0));
+        let current = json!({ "header": { "format": FORMAT_NAME, "version": 1, "units": "Meter" } });
+        assert_eq!(detect_version(&current), Ok(1));
+        assert_eq!(detect_version(&json!({ "foo": 1 })), Err(FileError::NotADocument));
+    }
+
+    #[test]
+    fn test_migrate_legacy_document() {
+        let mut value = legacy_document();
+        let report = migrate(&mut value).unwrap();
+        assert_eq!(report.from_version, 0);
+        assert_eq!(report.to_version, FORMAT_VERSION);
+        assert!(report.migrated());
+        assert_eq!(report.changes, vec!["Added file header with units \"Millimeter\"".to_string()]);
+        assert_eq!(value["header"]["version"], FORMAT_VERSION);
+        assert_eq!(value["document"]["next_id"], 2);
+    }
+
+    #[test]
+    fn test_current_version_is_untouched() {
+        let mut value = json!({ "header": { "format": FORMAT_NAME, "version": FORMAT_VERSION, "units": "Meter" }, "document": {} });
+        let original = value.clone();
+        let report = migrate(&mut value).unwrap();
+        assert!(!report.migrated());
+        assert!(report.changes.is_empty());
+        assert_eq!(value, original);
+    }
+
+    #[test]
+    fn test_future_version_fails() {
+        let mut value = json!({ "header": { "format": FORMAT_NAME, "version": FORMAT_VERSION + 1, "units": "Meter" } });
+        let original = value.clone();
+        assert_eq!(
+            migrate(&mut value),
+            Err(FileError::UnsupportedVersion { found: FORMAT_VERSION + 1, supported: FORMAT_VERSION })
+        );
+        assert_eq!(value, original);
+    }
+
+    #[test]
+    fn test_steps_apply_in_order() {
+        fn rename_radius(value: &mut Value, changes: &mut Vec<String>) {
+            let sphere = &mut value["document"]["objects"]["1"]["shape_type"]["Sphere"];
+            let radius = sphere["radius"].take();
+            sphere["diameter"] = json!(radius.as_f64().unwrap() * 2.0);
+            changes.push("Converted sphere radius to diameter".to_string());
+        }
+        fn add_color(value: &mut Value, changes: &mut Vec<String>) {
+            value["document"]["objects"]["1"]["properties"]["Color"] = json!("red");
+            changes.push("Added default color".to_string());
+        }
+        let migrations = [
+            Migration { from: 0, apply: v0_add_header },
+            Migration { from: 1, apply: rename_radius },
+            Migration { from: 2, apply: add_color },
+        ];
+
+        let mut value = legacy_document();
+        let report = migrate_to(&mut value, &migrations, 3).unwrap();
+        assert_eq!(report.changes.len(), 3);
+        assert_eq!(report.changes[2], "Added default color");
+        assert_eq!(value["header"]["version"], 3);
+        assert_eq!(value["document"]["objects"]["1"]["shape_type"]["Sphere"]["diameter"], 2.0);
+    }
+
+    #[test]
+    fn test_missing_step_fails() {
+        let mut value = legacy_document();
+        assert!(matches!(
+            migrate_to(&mut value, &[], 1),
+            Err(FileError::UnsupportedVersion { found: 0, supported: 1 })
+        ));
+    }
+}
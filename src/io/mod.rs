@@ -0,0 +1,150 @@
+//! Reading and writing documents and meshes in file formats
+
+pub mod native;
+pub mod migration;
+pub mod stl;
+pub mod obj;
+pub mod gltf;
+pub mod zip;
+pub mod threemf;
+pub mod step;
+pub mod dxf;
+pub mod svg;
+pub mod ply;
+pub mod off;
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use serde::{Serialize, Serializer};
+
+/// Where in the input a parse error occurred
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Location {
+    /// 1-based line number of a text format
+    Line(usize),
+    /// Byte offset into a binary format
+    Byte(usize),
+}
+
+/// Error raised while parsing an imported file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub location: Location,
+    pub message: String,
+}
+
+impl ParseError {
+    /// Error on a line of a text format
+    pub fn line(line: usize, message: impl Into<String>) -> Self {
+        Self { location: Location::Line(line), message: message.into() }
+    }
+
+    /// Error at a byte offset of a binary format
+    pub fn byte(offset: usize, message: impl Into<String>) -> Self {
+        Self { location: Location::Byte(offset), message: message.into() }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.location {
+            Location::Line(line) => write!(f, "line {}: {}", line, self.message),
+            Location::Byte(offset) => write!(f, "byte {}: {}", offset, self.message),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Serialize a `HashMap` with sorted keys so saved files are deterministic
+pub fn serialize_sorted<S, K, V>(map: &HashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    K: Ord + Serialize,
+    V: Serialize,
+{
+    map.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
+
+/// 8-bit sRGB value of a linear color channel, as used by file formats
+pub fn linear_to_srgb8(c: f32) -> u8 {
+    let c = c.clamp(0.0, 1.0);
+    let srgb = if c <= 0.003_130_8 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+    (srgb * 255.0).round() as u8
+}
+
+/// Linear color channel of an 8-bit sRGB value
+pub fn srgb8_to_linear(c: u8) -> f32 {
+    let c = c as f32 / 255.0;
+    if c <= 0.040_45 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+/// Escape text for use in XML content and attribute values
+pub fn escape_xml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// CRC-32 (IEEE 802.3) lookup table
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Compute the CRC-32 checksum of a byte buffer
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in data {
+        crc = CRC32_TABLE[((crc ^ *byte as u32) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn test_escape_xml() {
+        assert_eq!(escape_xml("Bolt <M6> & \"nut\""), "Bolt &lt;M6&gt; &amp; &quot;nut&quot;");
+        assert_eq!(escape_xml("plain"), "plain");
+    }
+
+    #[test]
+    fn test_srgb_conversion() {
+        assert_eq!(linear_to_srgb8(0.0), 0);
+        assert_eq!(linear_to_srgb8(1.0), 255);
+        assert!((srgb8_to_linear(128) - 0.2158).abs() < 1e-4);
+        assert_eq!(linear_to_srgb8(2.0), 255);
+        for c in 0..=255u8 {
+            assert_eq!(linear_to_srgb8(srgb8_to_linear(c)), c);
+        }
+    }
+}
@@ -0,0 +1,313 @@
+//! Native RustyCAD document format
+//!
+//! Documents are stored as JSON with a header carrying the format version and
+//! unit system. The same JSON can be wrapped in a compact binary container:
+//!
+//! ```text
+//! magic "RCAD" | u32 format version | u8 units | 3 reserved bytes |
+//! u32 payload length | payload (minified JSON) | u32 CRC-32 of payload
+//! ```
+//!
+//! All integers are little-endian.
+
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use crate::document::Document;
+use crate::io::crc32;
+use crate::io::migration::{self, MigrationReport};
+use crate::material::{builtin, MaterialError};
+use crate::units::Units;
+
+/// Name written into the header of every native file
+pub const FORMAT_NAME: &str = "rustycad";
+
+/// Current version of the native format
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Magic bytes at the start of the binary container
+pub const BINARY_MAGIC: &[u8; 4] = b"RCAD";
+
+/// Size of the binary container header in bytes
+const BINARY_HEADER_LEN: usize = 16;
+
+/// Header stored at the top of every native file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileHeader {
+    pub format: String,
+    pub version: u32,
+    pub units: Units,
+    #[serde(default)]
+    pub generator: String,
+}
+
+impl FileHeader {
+    /// Header for a document written by this version of the library
+    pub fn for_document(doc: &Document) -> Self {
+        Self {
+            format: FORMAT_NAME.to_string(),
+            version: FORMAT_VERSION,
+            units: doc.units,
+            generator: format!("rustycad {}", env!("CARGO_PKG_VERSION")),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct FileOut<'a> {
+    header: FileHeader,
+    document: &'a Document,
+}
+
+#[derive(Deserialize)]
+struct FileIn {
+    header: FileHeader,
+    document: Document,
+}
+
+/// Error raised while loading a native file
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileError {
+    Json(String),
+    NotADocument,
+    UnsupportedVersion { found: u32, supported: u32 },
+    Truncated,
+    ChecksumMismatch,
+    UnknownUnits(u8),
+    /// A user-defined material in the document is invalid
+    Material(MaterialError),
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileError::Json(msg) => write!(f, "Invalid document JSON: {}", msg),
+            FileError::NotADocument => write!(f, "Not a RustyCAD document"),
+            FileError::UnsupportedVersion { found, supported } => write!(
+                f,
+                "Document format version {} is newer than the supported version {}",
+                found, supported
+            ),
+            FileError::Truncated => write!(f, "Binary document is truncated"),
+            FileError::ChecksumMismatch => write!(f, "Binary document checksum does not match"),
+            FileError::UnknownUnits(code) => write!(f, "Unknown unit code {}", code),
+            FileError::Material(err) => write!(f, "Invalid material: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for FileError {}
+
+impl From<serde_json::Error> for FileError {
+    fn from(err: serde_json::Error) -> Self {
+        FileError::Json(err.to_string())
+    }
+}
+
+fn file_out(doc: &Document) -> FileOut<'_> {
+    FileOut { header: FileHeader::for_document(doc), document: doc }
+}
+
+/// Serialize a document to pretty-printed native JSON
+pub fn to_json(doc: &Document) -> String {
+    serde_json::to_string_pretty(&file_out(doc)).expect("document serialization cannot fail")
+}
+
+/// Load a document from native JSON, rebuilding its graph and meshes
+pub fn from_json(json: &str) -> Result<Document, FileError> {
+    from_json_with_report(json).map(|(doc, _)| doc)
+}
+
+/// Load a document from native JSON, upgrading older versions first
+///
+/// The report lists the migrations that were applied. User-defined materials
+/// are checked the same way `Document::add_material` checks them.
+pub fn from_json_with_report(json: &str) -> Result<(Document, MigrationReport), FileError> {
+    let mut value: serde_json::Value = serde_json::from_str(json)?;
+    let report = migration::migrate(&mut value)?;
+    let file: FileIn = serde_json::from_value(value)?;
+    let mut doc = file.document;
+    for material in &doc.materials {
+        material.validate().map_err(FileError::Material)?;
+        if builtin(&material.name).is_some() {
+            return Err(FileError::Material(MaterialError::BuiltIn(material.name.clone())));
+        }
+    }
+    doc.units = file.header.units;
+    doc.rebuild();
+    Ok((doc, report))
+}
+
+/// Serialize a document into the binary container
+pub fn to_binary(doc: &Document) -> Vec<u8> {
+    let payload = serde_json::to_vec(&file_out(doc)).expect("document serialization cannot fail");
+
+    let mut out = Vec::with_capacity(BINARY_HEADER_LEN + payload.len() + 4);
+    out.extend_from_slice(BINARY_MAGIC);
+    out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    out.push(doc.units.code());
+    out.extend_from_slice(&[0, 0, 0]);
+    out.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    out.extend_from_slice(&payload);
+    out.extend_from_slice(&crc32(&payload).to_le_bytes());
+    out
+}
+
+/// Extract and verify the JSON payload of a binary container
+pub fn binary_payload(data: &[u8]) -> Result<&[u8], FileError> {
+    if data.len() < 4 || &data[..4] != BINARY_MAGIC {
+        return Err(FileError::NotADocument);
+    }
+    if data.len() < BINARY_HEADER_LEN {
+        return Err(FileError::Truncated);
+    }
+    let units = data[8];
+    if Units::from_code(units).is_none() {
+        return Err(FileError::UnknownUnits(units));
+    }
+    let len = u32::from_le_bytes(data[12..16].try_into().unwrap()) as usize;
+    // The length comes from the file, so it may overflow a 32-bit usize
+    let end = BINARY_HEADER_LEN.checked_add(len).ok_or(FileError::Truncated)?;
+    if data.len().saturating_sub(4) < end {
+        return Err(FileError::Truncated);
+    }
+    let payload = &data[BINARY_HEADER_LEN..end];
+    let checksum = u32::from_le_bytes(data[end..end + 4].try_into().unwrap());
+    if crc32(payload) != checksum {
+        return Err(FileError::ChecksumMismatch);
+    }
+    Ok(payload)
+}
+
+/// Load a document from the binary container
+pub fn from_binary(data: &[u8]) -> Result<Document, FileError> {
+    let payload = binary_payload(data)?;
+    let json = std::str::from_utf8(payload).map_err(|err| FileError::Json(err.to_string()))?;
+    from_json(json)
+}
+
+/// Load a document from either the JSON or the binary representation
+pub fn load(data: &[u8]) -> Result<Document, FileError> {
+    load_with_report(data).map(|(doc, _)| doc)
+}
+
+/// Load a document from either representation, reporting applied migrations
+pub fn load_with_report(data: &[u8]) -> Result<(Document, MigrationReport), FileError> {
+    let json = if data.starts_with(BINARY_MAGIC) { binary_payload(data)? } else { data };
+    let json = std::str::from_utf8(json).map_err(|err| FileError::Json(err.to_string()))?;
+    from_json_with_report(json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::{Quat, Vec3};
+    use crate::math::Placement;
+    use crate::object::ShapeType;
+    use crate::property::Property;
+
+    fn sample_document() -> Document {
+        let mut doc = Document::new();
+        doc.units = Units::Inch;
+        let id = doc.add_object(ShapeType::Cylinder { radius: 1.0, height: 2.0 });
+        let placement = Placement::from_position_rotation(Vec3::new(1.0, 2.0, 3.0), Quat::from_rotation_x(0.5));
+        doc.set_object_property(id, "Placement".to_string(), Property::Placement(placement));
+        doc.add_object(ShapeType::Sphere { radius: 3.0 });
+        doc.recompute();
+        doc
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let doc = sample_document();
+        let json = to_json(&doc);
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["header"]["version"], FORMAT_VERSION);
+        assert_eq!(value["header"]["units"], "Inch");
+
+        let loaded = from_json(&json).unwrap();
+        assert_eq!(loaded.units, Units::Inch);
+        assert_eq!(loaded.objects.len(), 2);
+        assert_eq!(loaded.next_id(), doc.next_id());
+        assert_eq!(loaded.dependency_graph.node_count(), 2);
+        assert!(loaded.objects.values().all(|obj| obj.mesh.is_some()));
+        assert_eq!(loaded.get_object(1).unwrap().placement().position, Vec3::new(1.0, 2.0, 3.0));
+    }
+
+    #[test]
+    fn test_binary_round_trip() {
+        let doc = sample_document();
+        let data = to_binary(&doc);
+        assert_eq!(&data[..4], BINARY_MAGIC);
+
+        let loaded = load(&data).unwrap();
+        assert_eq!(loaded.units, Units::Inch);
+        assert_eq!(loaded.objects.len(), 2);
+    }
+
+    #[test]
+    fn test_binary_corruption_detected() {
+        let mut data = to_binary(&sample_document());
+        let mid = data.len() / 2;
+        data[mid] ^= 0xFF;
+        assert_eq!(from_binary(&data).unwrap_err(), FileError::ChecksumMismatch);
+        assert_eq!(from_binary(&data[..10]).unwrap_err(), FileError::Truncated);
+        // A payload length past the end of the data
+        let mut data = to_binary(&sample_document());
+        data[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(from_binary(&data).unwrap_err(), FileError::Truncated);
+        assert_eq!(from_binary(b"nope").unwrap_err(), FileError::NotADocument);
+    }
+
+    #[test]
+    fn test_loads_legacy_document_with_report() {
+        let legacy = serde_json::to_string(&sample_document()).unwrap();
+        let (doc, report) = load_with_report(legacy.as_bytes()).unwrap();
+        assert_eq!(doc.objects.len(), 2);
+        assert_eq!(doc.units, Units::Inch);
+        assert_eq!(report.from_version, 0);
+        assert_eq!(report.to_version, FORMAT_VERSION);
+        assert_eq!(report.changes.len(), 1);
+    }
+
+    #[test]
+    fn test_rejects_future_version() {
+        let json = to_json(&sample_document()).replace(
+            &format!("\"version\": {}", FORMAT_VERSION),
+            &format!("\"version\": {}", FORMAT_VERSION + 1),
+        );
+        assert_eq!(
+            from_json(&json).unwrap_err(),
+            FileError::UnsupportedVersion { found: FORMAT_VERSION + 1, supported: FORMAT_VERSION }
+        );
+    }
+
+    #[test]
+    fn test_rejects_invalid_materials() {
+        let mut doc = sample_document();
+        let mut custom = doc.material("PLA").unwrap();
+        custom.name = "PLA silk".to_string();
+        doc.add_material(custom).unwrap();
+        let mut value: serde_json::Value = serde_json::from_str(&to_json(&doc)).unwrap();
+        assert!(from_json(&value.to_string()).is_ok());
+
+        value["document"]["materials"][0]["density"] = serde_json::json!(-1.0);
+        assert_eq!(
+            from_json(&value.to_string()).unwrap_err(),
+            FileError::Material(MaterialError::NotPositive("Density".to_string()))
+        );
+        value["document"]["materials"][0]["density"] = serde_json::json!(1240.0);
+        value["document"]["materials"][0]["name"] = serde_json::json!("Steel");
+        assert_eq!(
+            from_json(&value.to_string()).unwrap_err(),
+            FileError::Material(MaterialError::BuiltIn("Steel".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_rejects_foreign_json() {
+        let json = r#"{"header":{"format":"other","version":1,"units":"Meter"},"document":{"objects":{},"next_id":1}}"#;
+        assert_eq!(from_json(json).unwrap_err(), FileError::NotADocument);
+        assert_eq!(from_json("{}").unwrap_err(), FileError::NotADocument);
+        assert!(matches!(from_json("not json"), Err(FileError::Json(_))));
+    }
+}
@@ -0,0 +1,220 @@
+//! Wavefront OBJ import
+//!
+//! Reads positions, normals and polygonal faces, split into groups by the
+//! `g` and `o` statements. Texture coordinates, materials and smoothing
+//! groups are ignored.
+
+use std::collections::HashMap;
+use glam::Vec3;
+use crate::geometry::{triangle_normal, Mesh};
+use crate::io::ParseError;
+
+/// A named group of faces
+#[derive(Debug, Clone)]
+pub struct ObjGroup {
+    pub name: String,
+    pub mesh: Mesh,
+}
+
+/// A parsed OBJ file
+#[derive(Debug, Clone, Default)]
+pub struct ObjModel {
+    pub groups: Vec<ObjGroup>,
+}
+
+impl ObjModel {
+    /// All groups merged into a single mesh
+    pub fn merged(&self) -> Mesh {
+        let mut mesh = Mesh::new();
+        for group in &self.groups {
+            mesh.append(&group.mesh);
+        }
+        mesh
+    }
+}
+
+/// Group being filled while reading faces
+struct GroupBuilder {
+    name: String,
+    mesh: Mesh,
+    /// Maps (position index, normal index) to the mesh vertex using them
+    vertex_map: HashMap<(usize, usize), u32>,
+}
+
+impl GroupBuilder {
+    fn new(name: &str) -> Self {
+        Self { name: name.to_string(), mesh: Mesh::new(), vertex_map: HashMap::new() }
+    }
+
+    fn vertex(&mut self, position: Vec3, normal: Vec3) -> u32 {
+        let index = self.mesh.vertex_count() as u32;
+        self.mesh.vertices.extend_from_slice(&position.to_array());
+        self.mesh.normals.extend_from_slice(&normal.to_array());
+        index
+    }
+
+    fn finish(self, groups: &mut Vec<ObjGroup>) {
+        if !self.mesh.indices.is_empty() {
+            groups.push(ObjGroup { name: self.name, mesh: self.mesh });
+        }
+    }
+}
+
+/// Resolve a 1-based or negative (relative) OBJ index
+fn resolve_index(token: &str, count: usize, what: &str, line: usize) -> Result<usize, ParseError> {
+    let index: i64 = token.parse()
+        .map_err(|_| ParseError::line(line, format!("invalid {} index '{}'", what, token)))?;
+    let resolved = if index > 0 { index - 1 } else { count as i64 + index };
+    if index == 0 || resolved < 0 || resolved >= count as i64 {
+        return Err(ParseError::line(line, format!("{} index {} is out of range", what, index)));
+    }
+    Ok(resolved as usize)
+}
+
+fn parse_vec3(tokens: &[&str], line: usize) -> Result<Vec3, ParseError> {
+    if tokens.len() < 3 {
+        return Err(ParseError::line(line, format!("expected 3 coordinates, found {}", tokens.len())));
+    }
+    let mut v = [0.0f32; 3];
+    for (value, token) in v.iter_mut().zip(tokens) {
+        *value = token.parse()
+            .ok()
+            .filter(|v: &f32| v.is_finite())
+            .ok_or_else(|| ParseError::line(line, format!("invalid number '{}'", token)))?;
+    }
+    Ok(Vec3::from_array(v))
+}
+
+/// Read an OBJ file
+///
+/// Polygons are triangulated as a fan. Corners that share a position and
+/// normal are shared between faces; faces without normals get flat normals.
+pub fn read(text: &str) -> Result<ObjModel, ParseError> {
+    let mut positions: Vec<Vec3> = Vec::new();
+    let mut normals: Vec<Vec3> = Vec::new();
+    let mut groups = Vec::new();
+    let mut current = GroupBuilder::new("default");
+
+    for (index, raw) in text.lines().enumerate() {
+        let line = index + 1;
+        let content = raw.split('#').next().unwrap_or("");
+        let tokens: Vec<&str> = content.split_whitespace().collect();
+        let Some((keyword, rest)) = tokens.split_first() else {
+            continue;
+        };
+        match *keyword {
+            "v" => positions.push(parse_vec3(rest, line)?),
+            "vn" => normals.push(parse_vec3(rest, line)?.normalize_or_zero()),
+            "g" | "o" => {
+                let name = if rest.is_empty() { "default".to_string() } else { rest.join(" ") };
+                if name != current.name {
+                    std::mem::replace(&mut current, GroupBuilder::new(&name)).finish(&mut groups);
+                }
+            }
+            "f" => {
+                if rest.len() < 3 {
+                    return Err(ParseError::line(line, format!("face has {} vertices", rest.len())));
+                }
+                let mut corners = Vec::with_capacity(rest.len());
+                for token in rest {
+                    let mut parts = token.split('/');
+                    let position = resolve_index(parts.next().unwrap_or(""), positions.len(), "vertex", line)?;
+                    let _texture = parts.next();
+                    let normal = match parts.next() {
+                        Some(n) if !n.is_empty() => Some(resolve_index(n, normals.len(), "normal", line)?),
+                        _ => None,
+                    };
+                    corners.push((position, normal));
+                }
+
+                let flat = {
+                    let p = |i: usize| positions[corners[i].0];
+                    triangle_normal(p(0), p(1), p(2))
+                };
+                let mut face = Vec::with_capacity(corners.len());
+                for (position, normal) in corners {
+                    let vertex = match normal {
+                        Some(n) => match current.vertex_map.get(&(position, n)) {
+                            Some(v) => *v,
+                            None => {
+                                let v = current.vertex(positions[position], normals[n]);
+                                current.vertex_map.insert((position, n), v);
+                                v
+                            }
+                        },
+                        None => current.vertex(positions[position], flat),
+                    };
+                    face.push(vertex);
+                }
+                for i in 1..face.len() - 1 {
+                    current.mesh.indices.extend_from_slice(&[face[0], face[i], face[i + 1]]);
+                }
+            }
+            // Texture coordinates, materials, smoothing groups, lines and points
+            _ => {}
+        }
+    }
+    current.finish(&mut groups);
+    Ok(ObjModel { groups })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::Location;
+
+    const QUADS: &str = "\
+# two groups
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+v 0 0 1
+vn 0 0 1
+g bottom
+f 1//1 2//1 3//1 4//1
+g side
+f -5 -4 -1
+";
+
+    #[test]
+    fn test_groups_and_normals() {
+        let model = read(QUADS).unwrap();
+        assert_eq!(model.groups.len(), 2);
+
+        let bottom = &model.groups[0];
+        assert_eq!(bottom.name, "bottom");
+        assert_eq!(bottom.mesh.vertex_count(), 4);
+        assert_eq!(bottom.mesh.indices, vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(&bottom.mesh.normals[0..3], &[0.0, 0.0, 1.0]);
+
+        let side = &model.groups[1];
+        assert_eq!(side.name, "side");
+        assert_eq!(side.mesh.triangle(0), [Vec3::ZERO, Vec3::X, Vec3::Z]);
+        // Flat normal of a face without normals
+        assert_eq!(&side.mesh.normals[0..3], &[0.0, -1.0, 0.0]);
+
+        assert_eq!(model.merged().triangle_count(), 3);
+    }
+
+    #[test]
+    fn test_texture_coordinates_are_skipped() {
+        let text = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvn 0 0 1\nf 1/1/1 2/1/1 3/1/1\n";
+        let model = read(text).unwrap();
+        assert_eq!(model.groups[0].name, "default");
+        assert_eq!(model.groups[0].mesh.triangle_count(), 1);
+    }
+
+    #[test]
+    fn test_errors_report_line() {
+        let err = read("v 0 0 0\nv 1 0 0\nf 1 2 3\n").unwrap_err();
+        assert_eq!(err.location, Location::Line(3));
+        assert_eq!(err.to_string(), "line 3: vertex index 3 is out of range");
+
+        let err = read("v 0 0\n").unwrap_err();
+        assert_eq!(err.location, Location::Line(1));
+
+        let err = read("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1//2 2 3\n").unwrap_err();
+        assert_eq!(err.to_string(), "line 4: normal index 2 is out of range");
+    }
+}
@@ -0,0 +1,201 @@
+//! OFF (Object File Format) import and export
+//!
+//! OFF is a plain list of vertices followed by polygons given as vertex
+//! counts and indices, common in geometry processing research. The header
+//! keyword can carry prefixes for per-vertex extras: `ST` texture
+//! coordinates, `C` colors and `N` normals, as in `CNOFF`. Texture
+//! coordinates and face colors are read past.
+
+use std::fmt::Write;
+use glam::Vec3;
+use crate::geometry::Mesh;
+use crate::io::{linear_to_srgb8, srgb8_to_linear, ParseError};
+
+/// Lines with content, numbered from 1, with comments removed
+fn content_lines(text: &str) -> impl Iterator<Item = (usize, Vec<&str>)> {
+    text.lines().enumerate().filter_map(|(i, line)| {
+        let line = line.split('#').next().unwrap_or("");
+        let words: Vec<&str> = line.split_whitespace().collect();
+        (!words.is_empty()).then_some((i + 1, words))
+    })
+}
+
+fn numbers<T: std::str::FromStr>(line: usize, words: &[&str]) -> Result<Vec<T>, ParseError> {
+    words
+        .iter()
+        .map(|w| w.parse().map_err(|_| ParseError::line(line, format!("invalid number '{}'", w))))
+        .collect()
+}
+
+/// Read an OFF, COFF, NOFF or CNOFF file, splitting polygons into triangle fans
+pub fn read(text: &str) -> Result<Mesh, ParseError> {
+    let mut lines = content_lines(text);
+    let (line, words) = lines.next().ok_or_else(|| ParseError::line(1, "empty file"))?;
+    let keyword = words[0];
+    let prefix = keyword.strip_suffix("OFF").ok_or_else(|| ParseError::line(line, "not an OFF file"))?;
+    let (texture, rest) = prefix.strip_prefix("ST").map_or((false, prefix), |rest| (true, rest));
+    let (colors, rest) = rest.strip_prefix('C').map_or((false, rest), |rest| (true, rest));
+    let (normals, rest) = rest.strip_prefix('N').map_or((false, rest), |rest| (true, rest));
+    if !rest.is_empty() {
+        return Err(ParseError::line(line, format!("unsupported OFF variant {}", keyword)));
+    }
+
+    // The counts may follow the keyword on the same line
+    let (mut line, counts) = if words.len() > 1 {
+        (line, numbers::<usize>(line, &words[1..])?)
+    } else {
+        let (line, words) = lines.next().ok_or_else(|| ParseError::line(line + 1, "missing vertex and face counts"))?;
+        (line, numbers::<usize>(line, &words)?)
+    };
+    let [vertex_count, face_count, ..] = counts[..] else {
+        return Err(ParseError::line(line, "missing vertex and face counts"));
+    };
+
+    let mut mesh = Mesh::new();
+    // Not preallocated, as the counts are not to be trusted
+    let mut file_normals = Vec::new();
+    for _ in 0..vertex_count {
+        let (number, words) = lines.next().ok_or_else(|| ParseError::line(line + 1, "unexpected end of file"))?;
+        line = number;
+        let values: Vec<f32> = numbers(line, &words)?;
+        if let Some(k) = values.iter().position(|v| !v.is_finite()) {
+            return Err(ParseError::line(line, format!("invalid number '{}'", words[k])));
+        }
+        let needed = 3 + if normals { 3 } else { 0 } + if colors { 3 } else { 0 } + if texture { 2 } else { 0 };
+        if values.len() < needed {
+            return Err(ParseError::line(line, format!("expected {} values per vertex", needed)));
+        }
+        mesh.vertices.extend_from_slice(&values[..3]);
+        let mut next = 3;
+        if normals {
+            file_normals.extend_from_slice(&values[3..6]);
+            next = 6;
+        }
+        if colors {
+            // Colors are 0 to 255 integers or 0 to 1 floats, with optional alpha
+            let rgb = &values[next..next + 3];
+            let integer = words[next..next + 3].iter().all(|w| !w.contains('.'));
+            let scale = if integer { 1.0 } else { 255.0 };
+            mesh.colors.extend(rgb.iter().map(|c| srgb8_to_linear((c * scale).round().clamp(0.0, 255.0) as u8)));
+        }
+    }
+
+    for _ in 0..face_count {
+        let (number, words) = lines.next().ok_or_else(|| ParseError::line(line + 1, "unexpected end of file"))?;
+        line = number;
+        let values: Vec<usize> = numbers(line, &words[..1])?;
+        let n = values[0];
+        if n >= words.len() {
+            return Err(ParseError::line(line, format!("face needs {} vertex indices", n)));
+        }
+        let polygon: Vec<u32> = numbers(line, &words[1..n + 1])?;
+        if let Some(index) = polygon.iter().find(|i| **i as usize >= vertex_count) {
+            return Err(ParseError::line(line, format!("vertex index {} is out of range", index)));
+        }
+        for k in 1..n.saturating_sub(1) {
+            mesh.indices.extend_from_slice(&[polygon[0], polygon[k], polygon[k + 1]]);
+        }
+    }
+
+    if normals && file_normals.chunks(3).all(|n| Vec3::from_slice(n) != Vec3::ZERO) {
+        mesh.normals = file_normals.chunks(3).flat_map(|n| Vec3::from_slice(n).normalize().to_array()).collect();
+    } else {
+        mesh.compute_smooth_normals();
+    }
+    Ok(mesh)
+}
+
+/// Write a mesh as OFF
+///
+/// Meshes with vertex colors are written as COFF with 8-bit RGBA colors.
+/// Others have coincident vertices merged, so that the file describes
+/// connected surfaces rather than the split vertices of sharp edges.
+pub fn write(mesh: &Mesh) -> String {
+    let mut out = String::new();
+    if mesh.colors.is_empty() {
+        let (positions, triangles) = mesh.welded();
+        let _ = writeln!(out, "OFF\n{} {} 0", positions.len(), triangles.len());
+        for p in &positions {
+            let _ = writeln!(out, "{} {} {}", p.x, p.y, p.z);
+        }
+        for [a, b, c] in &triangles {
+            let _ = writeln!(out, "3 {} {} {}", a, b, c);
+        }
+    } else {
+        let _ = writeln!(out, "COFF\n{} {} 0", mesh.vertex_count(), mesh.triangle_count());
+        for i in 0..mesh.vertex_count() {
+            let p = mesh.vertex(i);
+            let [r, g, b] = mesh.color(i).unwrap_or(Vec3::ONE).to_array().map(linear_to_srgb8);
+            let _ = writeln!(out, "{} {} {} {} {} {} 255", p.x, p.y, p.z, r, g, b);
+        }
+        for i in 0..mesh.triangle_count() {
+            let [a, b, c] = mesh.triangle_indices(i);
+            let _ = writeln!(out, "3 {} {} {}", a, b, c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::generate_box_mesh;
+
+    #[test]
+    fn test_welded_round_trip() {
+        let mesh = generate_box_mesh(1.0, 2.0, 3.0);
+        let text = write(&mesh);
+        assert!(text.starts_with("OFF\n8 12 0\n"));
+        let read_back = read(&text).unwrap();
+        assert_eq!(read_back.vertex_count(), 8);
+        assert_eq!(read_back.triangle_count(), 12);
+        assert!(read_back.colors.is_empty());
+        for i in 0..12 {
+            let [a, b, c] = read_back.triangle(i);
+            let original = mesh.triangle(i);
+            assert_eq!((a, b, c), (original[0], original[1], original[2]));
+        }
+    }
+
+    #[test]
+    fn test_colored_round_trip() {
+        let mut mesh = Mesh::new();
+        mesh.vertices = vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+        mesh.normals = [0.0, 0.0, 1.0].repeat(3);
+        mesh.indices = vec![0, 1, 2];
+        mesh.colors = vec![1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+        let text = write(&mesh);
+        assert_eq!(text, "COFF\n3 1 0\n0 0 0 255 0 0 255\n1 0 0 0 255 0 255\n0 1 0 0 0 255 255\n3 0 1 2\n");
+        let read_back = read(&text).unwrap();
+        assert_eq!(read_back.colors, mesh.colors);
+        assert_eq!(read_back.normals, mesh.normals);
+    }
+
+    #[test]
+    fn test_variants_and_polygons() {
+        // Counts on the keyword line, comments, float colors and a quad
+        let text = "CNOFF 4 1 0 # a square\n0 0 0 0 0 2 1.0 1.0 1.0\n1 0 0 0 0 2 1.0 1.0 1.0\n\
+                    1 1 0 0 0 2 1.0 1.0 1.0\n0 1 0 0 0 2 1.0 1.0 1.0\n\n# faces\n4 0 1 2 3 255 0 0\n";
+        let mesh = read(text).unwrap();
+        assert_eq!(mesh.indices, vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(mesh.normals, [0.0, 0.0, 1.0].repeat(4));
+        assert_eq!(mesh.colors, vec![1.0; 12]);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(read("ply\n").unwrap_err(), ParseError::line(1, "not an OFF file"));
+        assert_eq!(read("4OFF\n").unwrap_err(), ParseError::line(1, "unsupported OFF variant 4OFF"));
+        assert_eq!(read("OFF\n3 1 0\n0 0 0\n1 0 0\n0 1 0\n3 0 1 3\n").unwrap_err(), ParseError::line(6, "vertex index 3 is out of range"));
+        assert_eq!(read("OFF\n1 0 0\n0 0\n").unwrap_err(), ParseError::line(3, "expected 3 values per vertex"));
+        assert_eq!(read("OFF\n2 0 0\n0 0 0\n").unwrap_err(), ParseError::line(4, "unexpected end of file"));
+        assert_eq!(read("OFF\n1 0 0\n0 nan 0\n").unwrap_err(), ParseError::line(3, "invalid number 'nan'"));
+        assert_eq!(read("OFF\n1 0 0\n0 0 -inf\n").unwrap_err(), ParseError::line(3, "invalid number '-inf'"));
+        // Counts far beyond the file are not allocated or overflowed
+        assert_eq!(
+            read(&format!("OFF\n1 1 0\n0 0 0\n{} 0\n", usize::MAX)).unwrap_err(),
+            ParseError::line(4, format!("face needs {} vertex indices", usize::MAX))
+        );
+        assert_eq!(read(&format!("OFF\n{} 0 0\n0 0 0\n", usize::MAX)).unwrap_err(), ParseError::line(4, "unexpected end of file"));
+    }
+}
@@ -0,0 +1,438 @@
+//! PLY (Stanford polygon file) import and export
+//!
+//! Reads ASCII and binary little-endian files with a `vertex` element
+//! holding positions and optionally normals and colors, and a `face`
+//! element holding polygons, which are split into triangle fans. Other
+//! elements and properties are read past and ignored. Meshes without
+//! normals, such as most scans, get smooth normals.
+
+use std::fmt::Write;
+use glam::Vec3;
+use crate::geometry::Mesh;
+use crate::io::{linear_to_srgb8, srgb8_to_linear, ParseError};
+
+/// Numeric type of a property
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn from_name(name: &str) -> Option<Scalar> {
+        Some(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    fn is_integer(self) -> bool {
+        !matches!(self, Scalar::F32 | Scalar::F64)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum PropertyKind {
+    Scalar(Scalar),
+    List { count: Scalar, item: Scalar },
+}
+
+#[derive(Debug, Clone)]
+struct Property {
+    name: String,
+    kind: PropertyKind,
+}
+
+#[derive(Debug, Clone)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+/// Source of property values in the file body
+trait Values {
+    /// The next value, which is always finite
+    fn next(&mut self, scalar: Scalar) -> Result<f64, ParseError>;
+
+    /// An error at the last value read
+    fn error(&self, message: String) -> ParseError;
+}
+
+/// Whitespace separated values of an ASCII body
+struct AsciiValues<'a> {
+    tokens: Box<dyn Iterator<Item = (usize, &'a str)> + 'a>,
+    line: usize,
+}
+
+impl Values for AsciiValues<'_> {
+    fn next(&mut self, _scalar: Scalar) -> Result<f64, ParseError> {
+        let (line, token) = self.tokens.next().ok_or_else(|| ParseError::line(self.line, "unexpected end of file"))?;
+        self.line = line;
+        token
+            .parse()
+            .ok()
+            .filter(|v: &f64| v.is_finite())
+            .ok_or_else(|| ParseError::line(line, format!("invalid number '{}'", token)))
+    }
+
+    fn error(&self, message: String) -> ParseError {
+        ParseError::line(self.line, message)
+    }
+}
+
+/// Little-endian values of a binary body
+struct BinaryValues<'a> {
+    data: &'a [u8],
+    offset: usize,
+    /// Where the last value read starts
+    last: usize,
+}
+
+impl Values for BinaryValues<'_> {
+    fn next(&mut self, scalar: Scalar) -> Result<f64, ParseError> {
+        let bytes = self
+            .data
+            .get(self.offset..self.offset + scalar.size())
+            .ok_or_else(|| ParseError::byte(self.offset, "unexpected end of file"))?;
+        self.last = self.offset;
+        self.offset += scalar.size();
+        let value = match scalar {
+            Scalar::I8 => bytes[0] as i8 as f64,
+            Scalar::U8 => bytes[0] as f64,
+            Scalar::I16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            Scalar::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            Scalar::I32 => i32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            Scalar::U32 => u32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            Scalar::F32 => f32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            Scalar::F64 => f64::from_le_bytes(bytes.try_into().unwrap()),
+        };
+        if !value.is_finite() {
+            return Err(ParseError::byte(self.last, "non-finite value"));
+        }
+        Ok(value)
+    }
+
+    fn error(&self, message: String) -> ParseError {
+        ParseError::byte(self.last, message)
+    }
+}
+
+/// Vertex data gathered from the body
+#[derive(Default)]
+struct Builder {
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    colors: Vec<Vec3>,
+    triangles: Vec<[u32; 3]>,
+}
+
+/// Parse the header, returning the elements, whether the body is binary,
+/// and where the body starts as a byte offset and line number
+fn read_header(data: &[u8]) -> Result<(Vec<Element>, bool, usize, usize), ParseError> {
+    if !data.starts_with(b"ply") {
+        return Err(ParseError::line(1, "not a PLY file"));
+    }
+    let end = data
+        .windows(b"end_header".len())
+        .position(|w| w == b"end_header")
+        .ok_or_else(|| ParseError::line(1, "missing end_header"))?;
+    let body = end + data[end..].iter().position(|b| *b == b'\n').map_or(data.len() - end, |p| p + 1);
+    let header = std::str::from_utf8(&data[..end]).map_err(|err| ParseError::byte(err.valid_up_to(), "header is not text"))?;
+
+    let mut elements: Vec<Element> = Vec::new();
+    let mut binary = None;
+    let mut lines = 1;
+    for (index, line) in header.lines().enumerate().skip(1) {
+        let number = index + 1;
+        lines = number + 1;
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[..] {
+            ["format", "ascii", _] => binary = Some(false),
+            ["format", "binary_little_endian", _] => binary = Some(true),
+            ["format", format, _] => return Err(ParseError::line(number, format!("unsupported format {}", format))),
+            ["element", name, count] => {
+                let count = count.parse().map_err(|_| ParseError::line(number, format!("invalid element count '{}'", count)))?;
+                elements.push(Element { name: name.to_string(), count, properties: Vec::new() });
+            }
+            ["property", "list", count, item, name] => {
+                let (Some(count), Some(item)) = (Scalar::from_name(count), Scalar::from_name(item)) else {
+                    return Err(ParseError::line(number, "unknown property type"));
+                };
+                let element = elements.last_mut().ok_or_else(|| ParseError::line(number, "property before element"))?;
+                element.properties.push(Property { name: name.to_string(), kind: PropertyKind::List { count, item } });
+            }
+            ["property", scalar, name] => {
+                let scalar = Scalar::from_name(scalar).ok_or_else(|| ParseError::line(number, format!("unknown property type {}", scalar)))?;
+                let element = elements.last_mut().ok_or_else(|| ParseError::line(number, "property before element"))?;
+                element.properties.push(Property { name: name.to_string(), kind: PropertyKind::Scalar(scalar) });
+            }
+            [] | ["comment", ..] | ["obj_info", ..] => {}
+            _ => return Err(ParseError::line(number, format!("unexpected header line '{}'", line.trim()))),
+        }
+    }
+    let binary = binary.ok_or_else(|| ParseError::line(2, "missing format"))?;
+    Ok((elements, binary, body, lines + 1))
+}
+
+/// Read all instances of the elements, keeping vertices and faces
+fn read_body(elements: &[Element], values: &mut dyn Values, builder: &mut Builder) -> Result<(), ParseError> {
+    for element in elements {
+        let index_of = |name: &str| element.properties.iter().position(|p| p.name == name);
+        let position = ["x", "y", "z"].map(index_of);
+        let normal = ["nx", "ny", "nz"].map(index_of);
+        let color = ["red", "green", "blue"].map(index_of);
+        let has = |indices: [Option<usize>; 3]| indices.iter().all(Option::is_some);
+        let indices = index_of("vertex_indices").or_else(|| index_of("vertex_index"));
+
+        let mut row = vec![0.0; element.properties.len()];
+        for _ in 0..element.count {
+            let mut polygon = Vec::new();
+            for (i, property) in element.properties.iter().enumerate() {
+                match property.kind {
+                    PropertyKind::Scalar(scalar) => row[i] = values.next(scalar)?,
+                    PropertyKind::List { count, item } => {
+                        let n = values.next(count)? as usize;
+                        for _ in 0..n {
+                            let value = values.next(item)?;
+                            if Some(i) == indices {
+                                if value < 0.0 || value > u32::MAX as f64 || value.fract() != 0.0 {
+                                    return Err(values.error(format!("invalid vertex index {}", value)));
+                                }
+                                polygon.push(value as u32);
+                            }
+                        }
+                    }
+                }
+            }
+            let vec3 = |indices: [Option<usize>; 3]| Vec3::from_array(indices.map(|i| row[i.unwrap()] as f32));
+            if element.name == "vertex" && has(position) {
+                builder.positions.push(vec3(position));
+                if has(normal) {
+                    builder.normals.push(vec3(normal));
+                }
+                if has(color) {
+                    // Integer colors are 0 to 255, floating point ones 0 to 1
+                    let scale = match &element.properties[color[0].unwrap()].kind {
+                        PropertyKind::Scalar(s) if s.is_integer() => 1.0,
+                        _ => 255.0,
+                    };
+                    builder.colors.push(Vec3::from_array(color.map(|i| srgb8_to_linear((row[i.unwrap()] * scale).round() as u8))));
+                }
+            } else if element.name == "face" {
+                for k in 1..polygon.len().saturating_sub(1) {
+                    builder.triangles.push([polygon[0], polygon[k], polygon[k + 1]]);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Read an ASCII or binary little-endian PLY file
+pub fn read(data: &[u8]) -> Result<Mesh, ParseError> {
+    let (elements, binary, body, first_line) = read_header(data)?;
+    let mut builder = Builder::default();
+    if binary {
+        read_body(&elements, &mut BinaryValues { data, offset: body, last: body }, &mut builder)?;
+    } else {
+        let text = std::str::from_utf8(&data[body..]).map_err(|err| ParseError::byte(body + err.valid_up_to(), "ASCII PLY is not valid UTF-8"))?;
+        let tokens = text
+            .lines()
+            .enumerate()
+            .flat_map(move |(i, line)| line.split_whitespace().map(move |token| (first_line + i, token)));
+        read_body(&elements, &mut AsciiValues { tokens: Box::new(tokens), line: first_line }, &mut builder)?;
+    }
+
+    let count = builder.positions.len() as u32;
+    if let Some(index) = builder.triangles.iter().flatten().find(|i| **i >= count) {
+        return Err(ParseError::line(first_line, format!("face vertex index {} is out of range", index)));
+    }
+    let mut mesh = Mesh::new();
+    mesh.vertices = builder.positions.iter().flat_map(|p| p.to_array()).collect();
+    mesh.indices = builder.triangles.into_iter().flatten().collect();
+    if builder.colors.len() == builder.positions.len() {
+        mesh.colors = builder.colors.iter().flat_map(|c| c.to_array()).collect();
+    }
+    if builder.normals.len() == builder.positions.len() && builder.normals.iter().all(|n| *n != Vec3::ZERO) {
+        mesh.normals = builder.normals.iter().flat_map(|n| n.normalize().to_array()).collect();
+    } else {
+        mesh.compute_smooth_normals();
+    }
+    Ok(mesh)
+}
+
+fn header(mesh: &Mesh, format: &str, comment: &str) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "ply\nformat {} 1.0", format);
+    for line in comment.lines() {
+        let _ = writeln!(out, "comment {}", line);
+    }
+    let _ = writeln!(out, "element vertex {}", mesh.vertex_count());
+    for name in ["x", "y", "z", "nx", "ny", "nz"] {
+        let _ = writeln!(out, "property float {}", name);
+    }
+    if !mesh.colors.is_empty() {
+        for name in ["red", "green", "blue"] {
+            let _ = writeln!(out, "property uchar {}", name);
+        }
+    }
+    let _ = writeln!(out, "element face {}", mesh.triangle_count());
+    out.push_str("property list uchar int vertex_indices\nend_header\n");
+    out
+}
+
+/// 8-bit sRGB color of a vertex
+fn color8(mesh: &Mesh, i: usize) -> Option<[u8; 3]> {
+    mesh.color(i).map(|c| c.to_array().map(linear_to_srgb8))
+}
+
+/// Write a mesh as ASCII PLY, with vertex colors if it has any
+pub fn write_ascii(mesh: &Mesh, comment: &str) -> String {
+    let mut out = header(mesh, "ascii", comment);
+    for i in 0..mesh.vertex_count() {
+        let (p, n) = (mesh.vertex(i), Vec3::from_slice(&mesh.normals[i * 3..i * 3 + 3]));
+        let _ = write!(out, "{} {} {} {} {} {}", p.x, p.y, p.z, n.x, n.y, n.z);
+        if let Some([r, g, b]) = color8(mesh, i) {
+            let _ = write!(out, " {} {} {}", r, g, b);
+        }
+        out.push('\n');
+    }
+    for i in 0..mesh.triangle_count() {
+        let [a, b, c] = mesh.triangle_indices(i);
+        let _ = writeln!(out, "3 {} {} {}", a, b, c);
+    }
+    out
+}
+
+/// Write a mesh as binary little-endian PLY, with vertex colors if it has any
+pub fn write_binary(mesh: &Mesh, comment: &str) -> Vec<u8> {
+    let mut out = header(mesh, "binary_little_endian", comment).into_bytes();
+    for i in 0..mesh.vertex_count() {
+        for value in mesh.vertices[i * 3..i * 3 + 3].iter().chain(&mesh.normals[i * 3..i * 3 + 3]) {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        if let Some(color) = color8(mesh, i) {
+            out.extend_from_slice(&color);
+        }
+    }
+    for i in 0..mesh.triangle_count() {
+        out.push(3);
+        for index in mesh.triangle_indices(i) {
+            out.extend_from_slice(&(index as i32).to_le_bytes());
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::generate_box_mesh;
+
+    fn colored_triangle() -> Mesh {
+        let mut mesh = Mesh::new();
+        mesh.vertices = vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+        mesh.normals = [0.0, 0.0, 1.0].repeat(3);
+        mesh.indices = vec![0, 1, 2];
+        mesh.colors = vec![1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+        mesh
+    }
+
+    #[test]
+    fn test_ascii_round_trip() {
+        let mesh = colored_triangle();
+        let text = write_ascii(&mesh, "scan 1");
+        assert!(text.starts_with("ply\nformat ascii 1.0\ncomment scan 1\nelement vertex 3\n"));
+        assert!(text.contains("property uchar red\n"));
+        assert!(text.ends_with("end_header\n0 0 0 0 0 1 255 0 0\n1 0 0 0 0 1 0 255 0\n0 1 0 0 0 1 0 0 255\n3 0 1 2\n"));
+
+        let read_back = read(text.as_bytes()).unwrap();
+        assert_eq!(read_back.vertices, mesh.vertices);
+        assert_eq!(read_back.normals, mesh.normals);
+        assert_eq!(read_back.indices, mesh.indices);
+        assert_eq!(read_back.colors, mesh.colors);
+    }
+
+    #[test]
+    fn test_binary_round_trip() {
+        let mesh = generate_box_mesh(1.0, 2.0, 3.0);
+        let data = write_binary(&mesh, "");
+        let read_back = read(&data).unwrap();
+        assert_eq!(read_back.vertices, mesh.vertices);
+        assert_eq!(read_back.normals, mesh.normals);
+        assert_eq!(read_back.indices, mesh.indices);
+        assert!(read_back.colors.is_empty());
+
+        let colored = read(&write_binary(&colored_triangle(), "")).unwrap();
+        assert_eq!(colored.colors, colored_triangle().colors);
+    }
+
+    #[test]
+    fn test_scan_without_normals() {
+        // A quad face, an extra element, float colors and no normals
+        let text = "ply\nformat ascii 1.0\ncomment from a scanner\nelement vertex 4\nproperty double x\nproperty double y\n\
+                    property double z\nproperty float red\nproperty float green\nproperty float blue\n\
+                    element face 1\nproperty list uchar uint vertex_index\nelement edge 1\nproperty int vertex1\n\
+                    property int vertex2\nend_header\n0 0 0 1 1 1\n1 0 0 1 1 1\n1 1 0 1 1 1\n0 1 0 1 1 1\n4 0 1 2 3\n0 1\n";
+        let mesh = read(text.as_bytes()).unwrap();
+        assert_eq!(mesh.triangle_count(), 2);
+        assert_eq!(mesh.normals, [0.0, 0.0, 1.0].repeat(4));
+        assert_eq!(mesh.colors, vec![1.0; 12]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = read(b"ply\nformat binary_big_endian 1.0\nend_header\n").unwrap_err();
+        assert_eq!(err, ParseError::line(2, "unsupported format binary_big_endian"));
+        let text = "ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nproperty float y\nproperty float z\nend_header\n0 0 zero\n";
+        assert_eq!(read(text.as_bytes()).unwrap_err(), ParseError::line(8, "invalid number 'zero'"));
+        let mut data = write_binary(&colored_triangle(), "");
+        data.truncate(data.len() - 2);
+        assert!(matches!(read(&data).unwrap_err().location, crate::io::Location::Byte(_)));
+        assert_eq!(read(b"solid x").unwrap_err(), ParseError::line(1, "not a PLY file"));
+    }
+
+    #[test]
+    fn test_non_finite_values_and_bad_indices() {
+        let header = "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\n\
+                      element face 1\nproperty list uchar int vertex_indices\nend_header\n";
+        let vertices = "0 0 0\n1 0 0\n0 1 0\n";
+        let nan = format!("{}0 0 0\n1 nan 0\n0 1 0\n3 0 1 2\n", header);
+        assert_eq!(read(nan.as_bytes()).unwrap_err(), ParseError::line(11, "invalid number 'nan'"));
+        let inf = format!("{}0 0 0\n1 0 -inf\n0 1 0\n3 0 1 2\n", header);
+        assert_eq!(read(inf.as_bytes()).unwrap_err(), ParseError::line(11, "invalid number '-inf'"));
+        let negative = format!("{}{}3 0 -1 2\n", header, vertices);
+        assert_eq!(read(negative.as_bytes()).unwrap_err(), ParseError::line(13, "invalid vertex index -1"));
+        let fractional = format!("{}{}3 0 1 2.7\n", header, vertices);
+        assert_eq!(read(fractional.as_bytes()).unwrap_err(), ParseError::line(13, "invalid vertex index 2.7"));
+
+        // A NaN coordinate in a binary body is reported at its offset
+        let mut data = write_binary(&colored_triangle(), "");
+        let body = data.windows(11).position(|w| w == b"end_header\n").unwrap() + 11;
+        data[body..body + 4].copy_from_slice(&f32::NAN.to_le_bytes());
+        assert_eq!(read(&data).unwrap_err(), ParseError::byte(body, "non-finite value"));
+    }
+}
@@ -0,0 +1,824 @@
+//! Rebuilding document objects from the solids of a STEP file
+
+use std::collections::BTreeMap;
+use glam::{Mat3, Quat, Vec3};
+use serde::Serialize;
+use crate::geometry::{generate_cone_mesh, triangle_normal, Mesh};
+use crate::io::ParseError;
+use crate::io::step::parser::{parse, Param, StepFile};
+use crate::math::Placement;
+use crate::object::ShapeType;
+use crate::units::Units;
+
+/// What happened to the contents of an imported STEP file
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ImportReport {
+    /// Number of solids turned into objects
+    pub imported: usize,
+    /// Solids and representation items that were skipped, with the reason
+    pub skipped: Vec<String>,
+    /// Unsupported entity types that caused something to be skipped, with counts
+    pub unsupported: BTreeMap<String, usize>,
+}
+
+/// A solid rebuilt from a STEP file
+#[derive(Debug, Clone)]
+pub struct ImportedPart {
+    pub name: String,
+    pub shape_type: ShapeType,
+    pub placement: Placement,
+}
+
+/// Solids rebuilt from a STEP file and the report of what was skipped
+#[derive(Debug, Clone)]
+pub struct StepImport {
+    pub parts: Vec<ImportedPart>,
+    pub report: ImportReport,
+}
+
+/// Right-handed coordinate system of an `AXIS2_PLACEMENT_3D`
+#[derive(Debug, Clone, Copy)]
+struct Frame {
+    origin: Vec3,
+    x: Vec3,
+    y: Vec3,
+    z: Vec3,
+}
+
+impl Frame {
+    fn new(origin: Vec3, z: Vec3, x: Vec3) -> Self {
+        let z = z.normalize();
+        let x = (x - z * x.dot(z)).try_normalize().unwrap_or_else(|| z.any_orthonormal_vector());
+        Self { origin, x, y: z.cross(x), z }
+    }
+
+    /// Placement taking local coordinates into this frame
+    fn placement(&self) -> Placement {
+        Placement::from_position_rotation(self.origin, Quat::from_mat3(&Mat3::from_cols(self.x, self.y, self.z)))
+    }
+}
+
+/// Surface of a face
+enum Surface {
+    Plane(Frame),
+    Cylinder(Frame, f32),
+    Sphere(Frame, f32),
+    /// Frame, radius at the frame origin and semi-angle in radians
+    Cone(Frame, f32, f32),
+}
+
+struct Face {
+    surface: Option<Surface>,
+    /// Outward normal flip of the surface
+    same_sense: bool,
+    /// Corner loops; the first is the outer bound
+    loops: Vec<Vec<Vec3>>,
+}
+
+const TOLERANCE: f32 = 1e-4;
+
+fn parallel(a: Vec3, b: Vec3) -> bool {
+    a.cross(b).length() < TOLERANCE
+}
+
+/// Link between two shape representations
+struct Relationship {
+    first: usize,
+    second: usize,
+    /// `ITEM_DEFINED_TRANSFORMATION` placing `first` in `second`
+    transformation: Option<usize>,
+}
+
+/// All representation relationships, simple or combined with a transformation
+fn relationships(file: &StepFile) -> Vec<Relationship> {
+    let mut found: Vec<(usize, &[Param])> = file.find("REPRESENTATION_RELATIONSHIP");
+    found.extend(file.find("SHAPE_REPRESENTATION_RELATIONSHIP").into_iter().filter(|(_, p)| p.len() >= 4));
+    found.sort_by_key(|(id, _)| *id);
+    found
+        .into_iter()
+        .filter_map(|(id, params)| {
+            Some(Relationship {
+                first: params.get(2)?.reference()?,
+                second: params.get(3)?.reference()?,
+                transformation: file
+                    .record(id, "REPRESENTATION_RELATIONSHIP_WITH_TRANSFORMATION")
+                    .and_then(|p| p.first()?.reference()),
+            })
+        })
+        .collect()
+}
+
+/// Rebuilds document objects from the entity graph of a STEP file
+struct Importer<'a> {
+    file: &'a StepFile,
+    relationships: Vec<Relationship>,
+    /// Document units per file length unit
+    length: f32,
+    /// Radians per file angle unit
+    angle: f32,
+    report: ImportReport,
+}
+
+impl<'a> Importer<'a> {
+    fn params(&self, id: usize, name: &str) -> Result<&'a [Param], String> {
+        let file = self.file;
+        file.record(id, name).ok_or_else(|| match file.type_name(id) {
+            Some(found) => format!("#{} is {}, expected {}", id, found, name),
+            None => format!("#{} is missing", id),
+        })
+    }
+
+    fn unsupported(&mut self, entity: &str) {
+        *self.report.unsupported.entry(entity.to_string()).or_insert(0) += 1;
+    }
+
+    /// Size of a unit in meters (length) or radians (angle)
+    fn unit_factor(&self, id: usize, depth: usize) -> Option<f64> {
+        if let Some(params) = self.file.record(id, "SI_UNIT") {
+            let prefix = match params.first()? {
+                Param::Enum(prefix) => prefix.as_str(),
+                _ => "",
+            };
+            return Some(match prefix {
+                "KILO" => 1e3,
+                "CENTI" => 1e-2,
+                "MILLI" => 1e-3,
+                "MICRO" => 1e-6,
+                "NANO" => 1e-9,
+                _ => 1.0,
+            });
+        }
+        let params = self.file.record(id, "CONVERSION_BASED_UNIT")?;
+        let measure = params.get(1)?.reference()?;
+        let measure = self.file.instances.get(&measure)?.first()?;
+        let value = measure.params.first()?.real()?;
+        let unit = measure.params.get(1)?.reference()?;
+        if depth > 4 {
+            return None;
+        }
+        Some(value * self.unit_factor(unit, depth + 1)?)
+    }
+
+    /// Find the length and angle units assigned to the geometry
+    fn detect_units(&mut self, target: Units) {
+        let mut length_meters = Units::Millimeter.to_meters();
+        let mut angle = 1.0;
+        let assigned = self.file.find("GLOBAL_UNIT_ASSIGNED_CONTEXT");
+        let units: Vec<usize> = match assigned.first() {
+            Some((_, params)) if !params.is_empty() => params[0].references(),
+            found => {
+                if let Some((id, _)) = found {
+                    self.report.skipped.push(format!("Skipped units #{}: GLOBAL_UNIT_ASSIGNED_CONTEXT has no units, assuming millimeters", id));
+                }
+                let mut all: Vec<usize> = self.file.find("LENGTH_UNIT").into_iter().map(|(id, _)| id).collect();
+                all.extend(self.file.find("PLANE_ANGLE_UNIT").into_iter().map(|(id, _)| id));
+                all
+            }
+        };
+        for unit in units {
+            if self.file.record(unit, "LENGTH_UNIT").is_some() {
+                length_meters = self.unit_factor(unit, 0).unwrap_or(length_meters);
+            } else if self.file.record(unit, "PLANE_ANGLE_UNIT").is_some() {
+                angle = self.unit_factor(unit, 0).unwrap_or(angle);
+            }
+        }
+        self.length = (length_meters / target.to_meters()) as f32;
+        self.angle = angle as f32;
+    }
+
+    fn length(&self, param: Option<&Param>) -> Result<f32, String> {
+        param.and_then(Param::real).map(|v| v as f32 * self.length).ok_or_else(|| "missing length".to_string())
+    }
+
+    fn coordinates(&self, id: usize, name: &str) -> Result<Vec3, String> {
+        let params = self.params(id, name)?;
+        let values: Vec<f32> = params.get(1)
+            .and_then(Param::list)
+            .ok_or_else(|| format!("#{} has no coordinates", id))?
+            .iter()
+            .map(|v| v.real().map(|v| v as f32))
+            .collect::<Option<_>>()
+            .ok_or_else(|| format!("#{} has invalid coordinates", id))?;
+        Ok(Vec3::new(
+            values.first().copied().unwrap_or(0.0),
+            values.get(1).copied().unwrap_or(0.0),
+            values.get(2).copied().unwrap_or(0.0),
+        ))
+    }
+
+    fn point(&self, id: usize) -> Result<Vec3, String> {
+        Ok(self.coordinates(id, "CARTESIAN_POINT")? * self.length)
+    }
+
+    fn direction(&self, param: Option<&Param>, default: Vec3) -> Result<Vec3, String> {
+        match param.and_then(Param::reference) {
+            Some(id) => self.coordinates(id, "DIRECTION")?
+                .try_normalize()
+                .ok_or_else(|| format!("#{} is a zero direction", id)),
+            None => Ok(default),
+        }
+    }
+
+    fn frame(&self, param: Option<&Param>) -> Result<Frame, String> {
+        let id = param.and_then(Param::reference).ok_or("missing placement")?;
+        let params = self.params(id, "AXIS2_PLACEMENT_3D")?;
+        let origin = self.point(params.get(1).and_then(Param::reference).ok_or("missing location")?)?;
+        let z = self.direction(params.get(2), Vec3::Z)?;
+        let x = self.direction(params.get(3), Vec3::X)?;
+        Ok(Frame::new(origin, z, x))
+    }
+
+    fn vertex(&self, param: Option<&Param>) -> Result<Vec3, String> {
+        let id = param.and_then(Param::reference).ok_or("missing vertex")?;
+        let params = self.params(id, "VERTEX_POINT")?;
+        self.point(params.get(1).and_then(Param::reference).ok_or("missing vertex point")?)
+    }
+
+    /// Start points of the edges of a loop, in loop order
+    fn loop_points(&mut self, id: usize) -> Result<Vec<Vec3>, String> {
+        if let Some(params) = self.file.record(id, "POLY_LOOP") {
+            return params.get(1).map(Param::references).unwrap_or_default().into_iter().map(|p| self.point(p)).collect();
+        }
+        if let Some(params) = self.file.record(id, "VERTEX_LOOP") {
+            return Ok(vec![self.vertex(params.get(1))?]);
+        }
+        let params = self.params(id, "EDGE_LOOP")?;
+        let mut points = Vec::new();
+        for oriented in params.get(1).map(Param::references).unwrap_or_default() {
+            let oriented = self.params(oriented, "ORIENTED_EDGE")?;
+            let edge = oriented.get(3).and_then(Param::reference).ok_or("missing edge")?;
+            let edge = self.params(edge, "EDGE_CURVE")?;
+            let curve = edge.get(3).and_then(Param::reference).ok_or("missing edge curve")?;
+            match self.file.type_name(curve) {
+                Some("LINE" | "POLYLINE" | "CIRCLE") => {}
+                Some(other) => {
+                    let other = other.to_string();
+                    self.unsupported(&other);
+                    return Err(format!("unsupported edge curve {}", other));
+                }
+                None => return Err(format!("#{} is missing", curve)),
+            }
+            let forward = oriented.get(4).and_then(Param::boolean).unwrap_or(true);
+            points.push(self.vertex(edge.get(if forward { 1 } else { 2 }))?);
+        }
+        Ok(points)
+    }
+
+    fn face(&mut self, id: usize) -> Result<Face, String> {
+        let file = self.file;
+        let records = file.instances.get(&id).ok_or_else(|| format!("#{} is missing", id))?;
+        let record = records.iter()
+            .find(|r| matches!(r.name.as_str(), "ADVANCED_FACE" | "FACE_SURFACE" | "FACE"))
+            .ok_or_else(|| format!("#{} is not a face", id))?;
+        let params = &record.params;
+
+        let mut loops = Vec::new();
+        for bound in params.get(1).map(Param::references).unwrap_or_default() {
+            let bound_params = file.record(bound, "FACE_OUTER_BOUND")
+                .or_else(|| file.record(bound, "FACE_BOUND"))
+                .ok_or_else(|| format!("#{} is not a face bound", bound))?;
+            let edge_loop = bound_params.get(1).and_then(Param::reference).ok_or("missing loop")?;
+            let mut points = self.loop_points(edge_loop)?;
+            if bound_params.get(2).and_then(Param::boolean) == Some(false) {
+                points.reverse();
+            }
+            // The outer bound goes first
+            if file.record(bound, "FACE_OUTER_BOUND").is_some() {
+                loops.insert(0, points);
+            } else {
+                loops.push(points);
+            }
+        }
+
+        let surface = match params.get(2).and_then(Param::reference) {
+            None => None,
+            Some(surface) => {
+                let surface_params = file.instances.get(&surface).and_then(|r| r.first()).map(|r| &r.params);
+                let surface_params = surface_params.ok_or_else(|| format!("#{} is missing", surface))?;
+                Some(match file.type_name(surface).unwrap_or("") {
+                    "PLANE" => Surface::Plane(self.frame(surface_params.get(1))?),
+                    "CYLINDRICAL_SURFACE" => {
+                        Surface::Cylinder(self.frame(surface_params.get(1))?, self.length(surface_params.get(2))?)
+                    }
+                    "SPHERICAL_SURFACE" => {
+                        Surface::Sphere(self.frame(surface_params.get(1))?, self.length(surface_params.get(2))?)
+                    }
+                    "CONICAL_SURFACE" => {
+                        let angle = surface_params.get(3).and_then(Param::real).ok_or("missing cone angle")?;
+                        Surface::Cone(self.frame(surface_params.get(1))?, self.length(surface_params.get(2))?, angle as f32 * self.angle)
+                    }
+                    other => {
+                        let other = other.to_string();
+                        self.unsupported(&other);
+                        return Err(format!("unsupported surface {}", other));
+                    }
+                })
+            }
+        };
+        let same_sense = params.get(3).and_then(Param::boolean).unwrap_or(true);
+        Ok(Face { surface, same_sense, loops })
+    }
+
+    /// Rebuild a solid as a shape with a placement in its representation
+    fn solid(&mut self, id: usize) -> Result<(ShapeType, Placement), String> {
+        let file = self.file;
+        let shell = if let Some(params) = file.record(id, "BREP_WITH_VOIDS") {
+            if params.get(2).map(Param::references).is_some_and(|voids| !voids.is_empty()) {
+                self.unsupported("BREP_WITH_VOIDS");
+                return Err("solids with voids are not supported".to_string());
+            }
+            params.get(1)
+        } else {
+            file.record(id, "MANIFOLD_SOLID_BREP").or_else(|| file.record(id, "FACETED_BREP")).and_then(|p| p.get(1))
+        };
+        let shell = shell.and_then(Param::reference).ok_or("missing shell")?;
+        let shell = file.record(shell, "CLOSED_SHELL")
+            .or_else(|| file.record(shell, "OPEN_SHELL"))
+            .ok_or_else(|| format!("#{} is not a shell", shell))?;
+        let faces = shell.get(1)
+            .map(Param::references)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|face| self.face(face))
+            .collect::<Result<Vec<_>, _>>()?;
+        if faces.is_empty() {
+            return Err("shell has no faces".to_string());
+        }
+        if faces.iter().any(|f| f.surface.is_some() && f.loops.len() > 1) {
+            return Err("faces with holes are not supported".to_string());
+        }
+
+        let (shape_type, placement) = recognize_sphere(&faces)
+            .or_else(|| recognize_cylinder(&faces))
+            .or_else(|| recognize_cone(&faces))
+            .or_else(|| recognize_box(&faces))
+            .or_else(|| polyhedron(&faces).map(|mesh| (ShapeType::ImportedMesh { mesh }, Placement::new())))
+            .ok_or_else(|| "combination of faces is not supported".to_string())?;
+        shape_type.validate().map_err(|err| err.to_string())?;
+        Ok((shape_type, placement))
+    }
+
+    /// World placements of every occurrence of a representation
+    ///
+    /// Assemblies relate a part's representation to their own through a
+    /// transformation; representations without a parent are at the origin.
+    fn occurrences(&self, representation: usize, visited: &mut Vec<usize>) -> Vec<Placement> {
+        if visited.contains(&representation) {
+            return Vec::new();
+        }
+        visited.push(representation);
+        let mut placements = Vec::new();
+        let mut has_parent = false;
+        for relationship in &self.relationships {
+            let (first, second) = (relationship.first, relationship.second);
+            let transformation = relationship.transformation.and_then(|t| self.file.record(t, "ITEM_DEFINED_TRANSFORMATION"));
+            match transformation {
+                Some(transformation) if first == representation => {
+                    let (Ok(from), Ok(to)) = (self.frame(transformation.get(2)), self.frame(transformation.get(3))) else {
+                        continue;
+                    };
+                    has_parent = true;
+                    let local = to.placement().multiply(&from.placement().inverse());
+                    for parent in self.occurrences(second, visited) {
+                        placements.push(parent.multiply(&local));
+                    }
+                }
+                // Plain relationships tie two representations of the same shape
+                None if first == representation || second == representation => {
+                    let other = if first == representation { second } else { first };
+                    let inherited = self.occurrences(other, visited);
+                    if !inherited.is_empty() {
+                        has_parent = true;
+                        placements.extend(inherited);
+                    }
+                }
+                _ => {}
+            }
+        }
+        visited.pop();
+        if !has_parent {
+            placements.push(Placement::new());
+        }
+        placements
+    }
+
+    /// Name of the product a representation describes, if any
+    fn product_name(&self, representation: usize) -> Option<String> {
+        let mut representations = vec![representation];
+        for relationship in self.relationships.iter().filter(|r| r.transformation.is_none()) {
+            if relationship.second == representation {
+                representations.push(relationship.first);
+            } else if relationship.first == representation {
+                representations.push(relationship.second);
+            }
+        }
+        for (_, params) in self.file.find("SHAPE_DEFINITION_REPRESENTATION") {
+            if !params.get(1).and_then(Param::reference).is_some_and(|r| representations.contains(&r)) {
+                continue;
+            }
+            let shape = self.file.record(params[0].reference()?, "PRODUCT_DEFINITION_SHAPE")?;
+            let definition = self.file.record(shape.get(2)?.reference()?, "PRODUCT_DEFINITION")?;
+            let formation = self.file.instances.get(&definition.get(2)?.reference()?)?.first()?;
+            let product = self.file.record(formation.params.get(2)?.reference()?, "PRODUCT")?;
+            return product.get(1).or(product.first())?.string().map(str::to_string);
+        }
+        None
+    }
+
+    fn import(mut self) -> StepImport {
+        let mut parts = Vec::new();
+        let mut representations: Vec<(usize, Vec<usize>)> = self.file.instances
+            .iter()
+            .filter(|(_, records)| records.iter().any(|r| r.name.ends_with("SHAPE_REPRESENTATION")))
+            .map(|(id, records)| {
+                let record = records.iter().find(|r| r.name.ends_with("SHAPE_REPRESENTATION")).unwrap();
+                (*id, record.params.get(1).map(Param::references).unwrap_or_default())
+            })
+            .collect();
+        representations.sort_by_key(|(id, _)| *id);
+
+        for (representation, items) in representations {
+            for item in items {
+                let type_name = self.file.type_name(item).unwrap_or("").to_string();
+                match type_name.as_str() {
+                    "AXIS2_PLACEMENT_3D" => continue,
+                    "MANIFOLD_SOLID_BREP" | "FACETED_BREP" | "BREP_WITH_VOIDS" => {}
+                    _ => {
+                        self.unsupported(&type_name);
+                        self.report.skipped.push(format!("Skipped #{}: {} is not supported", item, type_name));
+                        continue;
+                    }
+                }
+                let solid_name = self.file.instances[&item][0].params.first().and_then(Param::string).unwrap_or("").to_string();
+                let (shape_type, local) = match self.solid(item) {
+                    Ok(solid) => solid,
+                    Err(reason) => {
+                        self.report.skipped.push(format!("Skipped solid #{}: {}", item, reason));
+                        continue;
+                    }
+                };
+                let name = self.product_name(representation)
+                    .filter(|n| !n.is_empty())
+                    .or(Some(solid_name).filter(|n| !n.is_empty()))
+                    .unwrap_or_else(|| format!("Solid {}", item));
+                for occurrence in self.occurrences(representation, &mut Vec::new()) {
+                    parts.push(ImportedPart {
+                        name: name.clone(),
+                        shape_type: shape_type.clone(),
+                        placement: occurrence.multiply(&local),
+                    });
+                    self.report.imported += 1;
+                }
+            }
+        }
+        StepImport { parts, report: self.report }
+    }
+}
+
+/// A single spherical face
+fn recognize_sphere(faces: &[Face]) -> Option<(ShapeType, Placement)> {
+    let [Face { surface: Some(Surface::Sphere(frame, radius)), .. }] = faces else { return None };
+    Some((ShapeType::Sphere { radius: *radius }, Placement::from_position_rotation(frame.origin, Quat::IDENTITY)))
+}
+
+/// Heights along `frame`'s axis of the planes capping a surface of revolution
+fn cap_heights(faces: &[Face], frame: &Frame) -> Option<Vec<f32>> {
+    let mut heights = Vec::new();
+    for face in faces {
+        match &face.surface {
+            Some(Surface::Plane(plane)) if parallel(plane.z, frame.z) => {
+                heights.push((plane.origin - frame.origin).dot(frame.z));
+            }
+            Some(Surface::Cylinder(other, _)) | Some(Surface::Cone(other, _, _)) => {
+                let offset = other.origin - frame.origin;
+                if !parallel(other.z, frame.z) || offset.cross(frame.z).length() > TOLERANCE {
+                    return None;
+                }
+            }
+            _ => return None,
+        }
+    }
+    heights.sort_by(|a, b| a.total_cmp(b));
+    Some(heights)
+}
+
+/// Placement of a Y-axis primitive between two heights along `frame`'s axis
+fn axial_placement(frame: &Frame, bottom: f32, top: f32) -> Placement {
+    let center = frame.origin + frame.z * (bottom + top) / 2.0;
+    let rotation = Quat::from_mat3(&Mat3::from_cols(frame.x, frame.z, frame.x.cross(frame.z)));
+    Placement::from_position_rotation(center, rotation)
+}
+
+/// Coaxial cylindrical faces of one radius between two perpendicular planes
+fn recognize_cylinder(faces: &[Face]) -> Option<(ShapeType, Placement)> {
+    let (frame, radius) = faces.iter().find_map(|f| match &f.surface {
+        Some(Surface::Cylinder(frame, radius)) => Some((*frame, *radius)),
+        _ => None,
+    })?;
+    if faces.iter().any(|f| matches!(f.surface, Some(Surface::Cylinder(_, r)) if (r - radius).abs() > TOLERANCE)) {
+        return None;
+    }
+    let heights = cap_heights(faces, &frame)?;
+    let [bottom, top] = heights[..] else { return None };
+    Some((ShapeType::Cylinder { radius, height: top - bottom }, axial_placement(&frame, bottom, top)))
+}
+
+/// Coaxial conical faces capped by one or two planes, tessellated
+fn recognize_cone(faces: &[Face]) -> Option<(ShapeType, Placement)> {
+    let (frame, radius, angle) = faces.iter().find_map(|f| match &f.surface {
+        Some(Surface::Cone(frame, radius, angle)) => Some((*frame, *radius, *angle)),
+        _ => None,
+    })?;
+    let slope = angle.tan();
+    let radius_at = |height: f32| radius + height * slope;
+    let heights = cap_heights(faces, &frame)?;
+    let (bottom, top) = match heights[..] {
+        [bottom, top] => (bottom, top),
+        // The other end is the apex
+        [cap] if slope.abs() > TOLERANCE => {
+            let apex = -radius / slope;
+            (cap.min(apex), cap.max(apex))
+        }
+        _ => return None,
+    };
+    let mesh = generate_cone_mesh(radius_at(bottom).max(0.0), radius_at(top).max(0.0), top - bottom);
+    Some((ShapeType::ImportedMesh { mesh }, axial_placement(&frame, bottom, top)))
+}
+
+/// Six planes in three perpendicular pairs of opposite faces
+fn recognize_box(faces: &[Face]) -> Option<(ShapeType, Placement)> {
+    let planes: Vec<Frame> = faces.iter()
+        .map(|f| match &f.surface {
+            Some(Surface::Plane(frame)) => Some(*frame),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+    if planes.len() != 6 {
+        return None;
+    }
+
+    // Pair each plane with its opposite: (axis, size, center along the axis)
+    let mut pairs = Vec::new();
+    let mut used = [false; 6];
+    for i in 0..6 {
+        if used[i] {
+            continue;
+        }
+        let j = (i + 1..6).find(|j| !used[*j] && parallel(planes[i].z, planes[*j].z))?;
+        used[i] = true;
+        used[j] = true;
+        let axis = planes[i].z;
+        let (a, b) = (planes[i].origin.dot(axis), planes[j].origin.dot(axis));
+        pairs.push((axis, (a - b).abs(), (a + b) / 2.0));
+    }
+    let [p0, p1, p2] = pairs[..] else { return None };
+    if p0.0.dot(p1.0).abs() > TOLERANCE || p0.0.dot(p2.0).abs() > TOLERANCE || p1.0.dot(p2.0).abs() > TOLERANCE {
+        return None;
+    }
+    let center = p0.0 * p0.2 + p1.0 * p1.2 + p2.0 * p2.2;
+
+    // Of the equivalent orientations, pick the one closest to no rotation
+    let mut best: Option<(f32, Mat3, [f32; 3])> = None;
+    for [a, b, c] in [[0, 1, 2], [0, 2, 1], [1, 0, 2], [1, 2, 0], [2, 0, 1], [2, 1, 0]] {
+        for (sa, sb) in [(1.0, 1.0), (1.0, -1.0), (-1.0, 1.0), (-1.0, -1.0)] {
+            let x = pairs[a].0 * sa;
+            let y = pairs[b].0 * sb;
+            let matrix = Mat3::from_cols(x, y, x.cross(y));
+            let trace = matrix.x_axis.x + matrix.y_axis.y + matrix.z_axis.z;
+            if best.as_ref().is_none_or(|(t, _, _)| trace > *t + TOLERANCE) {
+                best = Some((trace, matrix, [pairs[a].1, pairs[b].1, pairs[c].1]));
+            }
+        }
+    }
+    let (_, matrix, [length, width, height]) = best?;
+    Some((
+        ShapeType::Box { length, width, height },
+        Placement::from_position_rotation(center, Quat::from_mat3(&matrix)),
+    ))
+}
+
+/// Mesh of a solid bounded by planar polygons, triangulated as fans
+fn polyhedron(faces: &[Face]) -> Option<Mesh> {
+    let mut mesh = Mesh::new();
+    for face in faces {
+        let mut corners = face.loops.first()?.clone();
+        if corners.len() < 3 {
+            return None;
+        }
+        match &face.surface {
+            Some(Surface::Plane(plane)) => {
+                // Wind the polygon counter-clockwise around the outward normal
+                let normal = if face.same_sense { plane.z } else { -plane.z };
+                let winding: Vec3 = (1..corners.len() - 1)
+                    .map(|i| (corners[i] - corners[0]).cross(corners[i + 1] - corners[0]))
+                    .sum();
+                if winding.dot(normal) < 0.0 {
+                    corners.reverse();
+                }
+            }
+            // Faceted faces follow their loop order
+            None => {}
+            Some(_) => return None,
+        }
+        let normal = triangle_normal(corners[0], corners[1], corners[2]);
+        let start = mesh.vertex_count() as u32;
+        for corner in &corners {
+            mesh.vertices.extend_from_slice(&corner.to_array());
+            mesh.normals.extend_from_slice(&normal.to_array());
+        }
+        for i in 1..corners.len() as u32 - 1 {
+            mesh.indices.extend_from_slice(&[start, start + i, start + i + 1]);
+        }
+    }
+    Some(mesh)
+}
+
+/// Read a STEP file and rebuild its solids in the given document units
+///
+/// Only syntax errors fail the import. Solids and representation items that
+/// cannot be rebuilt are listed in the report instead.
+pub fn import(text: &str, units: Units) -> Result<StepImport, ParseError> {
+    let file = parse(text)?;
+    let mut importer = Importer {
+        file: &file,
+        relationships: relationships(&file),
+        length: 1.0,
+        angle: 1.0,
+        report: ImportReport::default(),
+    };
+    importer.detect_units(units);
+    Ok(importer.import())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::Document;
+    use crate::geometry::generate_box_mesh;
+    use crate::io::step::export;
+    use crate::property::Property;
+
+    const CONE_FILE: &str = r"ISO-10303-21;
+HEADER;
+FILE_SCHEMA(('AUTOMOTIVE_DESIGN'));
+ENDSEC;
+DATA;
+/* a cone with its apex below the origin */
+#1=CARTESIAN_POINT('',(0.,0.,0.));
+#2=DIRECTION('',(0.,0.,1.));
+#3=DIRECTION('',(1.,0.,0.));
+#4=AXIS2_PLACEMENT_3D('',#1,#2,#3);
+#5=CONICAL_SURFACE('',#4,2.,0.785398163397);
+#6=ADVANCED_FACE('',(),#5,.T.);
+#7=PLANE('',#4);
+#8=ADVANCED_FACE('',(),#7,.T.);
+#9=CLOSED_SHELL('',(#6,#8));
+#10=MANIFOLD_SOLID_BREP('\X2\00D8\X0\4 cone',#9);
+#11=B_SPLINE_SURFACE_WITH_KNOTS('',1,1,(),.UNSPECIFIED.,.F.,.F.,.F.,(),(),(),(),.UNSPECIFIED.);
+#12=ADVANCED_FACE('',(),#11,.T.);
+#13=CLOSED_SHELL('',(#12));
+#14=MANIFOLD_SOLID_BREP('freeform',#13);
+#15=SHELL_BASED_SURFACE_MODEL('',());
+#16=ADVANCED_BREP_SHAPE_REPRESENTATION('',(#4,#10,#14,#15),#99);
+ENDSEC;
+END-ISO-10303-21;
+";
+
+    fn world_corners(part: &ImportedPart) -> Vec<Vec3> {
+        let mut doc = Document::new();
+        let id = doc.add_object(part.shape_type.clone());
+        doc.set_object_property(id, "Placement".to_string(), Property::Placement(part.placement));
+        doc.recompute();
+        let (positions, _) = doc.world_mesh(id).unwrap().welded();
+        positions
+    }
+
+    fn same_points(a: &[Vec3], b: &[Vec3]) -> bool {
+        a.len() == b.len() && a.iter().all(|p| b.iter().any(|q| p.distance(*q) < 1e-4))
+    }
+
+    #[test]
+    fn test_round_trip_primitives() {
+        let mut doc = Document::new();
+        let a = doc.add_object(ShapeType::Box { length: 1.0, width: 2.0, height: 3.0 });
+        let placement = Placement::from_position_rotation(Vec3::new(5.0, 0.0, 0.0), Quat::from_rotation_x(0.4));
+        doc.set_object_property(a, "Placement".to_string(), Property::Placement(placement));
+        let b = doc.add_object(ShapeType::Cylinder { radius: 1.5, height: 4.0 });
+        let placement = Placement::from_position_rotation(Vec3::new(0.0, 5.0, 1.0), Quat::from_rotation_z(1.0));
+        doc.set_object_property(b, "Placement".to_string(), Property::Placement(placement));
+        doc.add_object(ShapeType::Sphere { radius: 2.5 });
+        doc.add_object(ShapeType::ImportedMesh { mesh: generate_box_mesh(1.0, 1.0, 1.0) });
+        doc.recompute();
+
+        let imported = import(&export(&doc, &[], "parts", ""), Units::Millimeter).unwrap();
+        assert_eq!(imported.report.imported, 4);
+        assert!(imported.report.skipped.is_empty(), "{:?}", imported.report);
+        let parts = &imported.parts;
+        assert_eq!(parts.iter().map(|p| p.name.as_str()).collect::<Vec<_>>(), vec!["Box 1", "Cylinder 2", "Sphere 3", "ImportedMesh 4"]);
+
+        assert!(matches!(parts[1].shape_type, ShapeType::Cylinder { radius, height } if radius == 1.5 && (height - 4.0).abs() < 1e-5));
+        assert!(matches!(parts[2].shape_type, ShapeType::Sphere { radius } if radius == 2.5));
+        assert!(matches!(&parts[3].shape_type, ShapeType::ImportedMesh { mesh } if mesh.triangle_count() == 12));
+        for (id, part) in [1, 2, 3, 4].into_iter().zip(parts) {
+            let (original, _) = doc.world_mesh(id).unwrap().welded();
+            assert!(same_points(&world_corners(part), &original), "{}", part.name);
+        }
+    }
+
+    #[test]
+    fn test_box_orientation_prefers_no_rotation() {
+        // A 1 x 2 x 3 box turned a quarter around Z, with geometry in world coordinates
+        let rotation = Quat::from_rotation_z(std::f32::consts::FRAC_PI_2);
+        let faces: Vec<Face> = [(Vec3::X, 0.5), (Vec3::Y, 1.0), (Vec3::Z, 1.5)]
+            .into_iter()
+            .flat_map(|(axis, half)| [axis, -axis].map(|normal| (rotation * normal, half)))
+            .map(|(normal, half)| Face {
+                surface: Some(Surface::Plane(Frame::new(normal * half, normal, normal.any_orthonormal_vector()))),
+                same_sense: true,
+                loops: Vec::new(),
+            })
+            .collect();
+
+        let (shape, placement) = recognize_box(&faces).unwrap();
+        let ShapeType::Box { length, width, height } = shape else { panic!("not a box") };
+        assert!((Vec3::new(length, width, height) - Vec3::new(2.0, 1.0, 3.0)).length() < 1e-5);
+        assert!(placement.rotation.angle_between(Quat::IDENTITY) < 1e-4);
+    }
+
+    #[test]
+    fn test_import_converts_units() {
+        let mut doc = Document::new();
+        doc.units = Units::Inch;
+        doc.add_object(ShapeType::Sphere { radius: 1.0 });
+        doc.recompute();
+        let imported = import(&export(&doc, &[], "parts", ""), Units::Millimeter).unwrap();
+        assert!(matches!(imported.parts[0].shape_type, ShapeType::Sphere { radius } if (radius - 25.4).abs() < 1e-4));
+    }
+
+    #[test]
+    fn test_units_with_empty_parameters() {
+        let file = |units: &str| {
+            format!(
+                "ISO-10303-21;\nHEADER;\nENDSEC;\nDATA;\n{}\n#9=(GEOMETRIC_REPRESENTATION_CONTEXT(3)GLOBAL_UNIT_ASSIGNED_CONTEXT((#1))REPRESENTATION_CONTEXT('',''));\nENDSEC;\nEND-ISO-10303-21;\n",
+                units
+            )
+        };
+        // A unit without its prefix and name falls back to millimeters
+        let imported = import(&file("#1=(LENGTH_UNIT()NAMED_UNIT(*)SI_UNIT());"), Units::Millimeter).unwrap();
+        assert!(imported.report.skipped.is_empty());
+
+        let empty = "ISO-10303-21;\nHEADER;\nENDSEC;\nDATA;\n#1=(LENGTH_UNIT()NAMED_UNIT(*)SI_UNIT(.MILLI.,.METRE.));\n#9=(GLOBAL_UNIT_ASSIGNED_CONTEXT()REPRESENTATION_CONTEXT('',''));\nENDSEC;\nEND-ISO-10303-21;\n";
+        let imported = import(empty, Units::Millimeter).unwrap();
+        assert_eq!(imported.report.skipped, vec!["Skipped units #9: GLOBAL_UNIT_ASSIGNED_CONTEXT has no units, assuming millimeters".to_string()]);
+    }
+
+    #[test]
+    fn test_cone_and_unsupported_entities() {
+        let imported = import(CONE_FILE, Units::Millimeter).unwrap();
+        assert_eq!(imported.parts.len(), 1);
+        let cone = &imported.parts[0];
+        assert_eq!(cone.name, "Ø4 cone");
+        assert!((cone.placement.position - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-5);
+        let ShapeType::ImportedMesh { mesh } = &cone.shape_type else { panic!("cone is not a mesh") };
+        // The base of radius 2 faces up at z = 0, which is local +Y
+        let top = mesh.vertices.chunks(3).map(|v| v[1]).fold(f32::MIN, f32::max);
+        assert!((top - 1.0).abs() < 1e-5);
+
+        let report = &imported.report;
+        assert_eq!(report.imported, 1);
+        assert_eq!(report.skipped.len(), 2);
+        assert_eq!(report.unsupported.get("B_SPLINE_SURFACE_WITH_KNOTS"), Some(&1));
+        assert_eq!(report.unsupported.get("SHELL_BASED_SURFACE_MODEL"), Some(&1));
+    }
+
+    #[test]
+    fn test_repeated_occurrences() {
+        let mut doc = Document::new();
+        doc.add_object(ShapeType::Sphere { radius: 1.0 });
+        doc.recompute();
+        let step = export(&doc, &[], "parts", "");
+
+        // Place the same part a second time, 10 units along X
+        let last = step.lines().filter(|l| l.starts_with('#')).count();
+        let file = parse(&step).unwrap();
+        let (usage, params) = file.find("NEXT_ASSEMBLY_USAGE_OCCURRENCE")[0];
+        let relationship = file.find("REPRESENTATION_RELATIONSHIP")[0].1;
+        let (assembly, part) = (params[3].reference().unwrap(), params[4].reference().unwrap());
+        let (child, parent) = (relationship[2].reference().unwrap(), relationship[3].reference().unwrap());
+        let transform = file.find("ITEM_DEFINED_TRANSFORMATION")[0].1;
+        let origin = transform[2].reference().unwrap();
+        let extra = format!(
+            "#{a}=CARTESIAN_POINT('',(10.,0.,0.));\n#{b}=AXIS2_PLACEMENT_3D('',#{a},$,$);\n\
+             #{c}=ITEM_DEFINED_TRANSFORMATION('','',#{origin},#{b});\n\
+             #{d}=(REPRESENTATION_RELATIONSHIP('','',#{child},#{parent})REPRESENTATION_RELATIONSHIP_WITH_TRANSFORMATION(#{c})SHAPE_REPRESENTATION_RELATIONSHIP());\n\
+             #{e}=NEXT_ASSEMBLY_USAGE_OCCURRENCE('2','','',#{assembly},#{part},$);\nENDSEC;",
+            a = last + 1, b = last + 2, c = last + 3, d = last + 4, e = last + 5,
+        );
+        let step = step.replacen("ENDSEC;\nEND-ISO", &format!("{}\nEND-ISO", extra), 1);
+        assert!(usage > 0);
+
+        let imported = import(&step, Units::Millimeter).unwrap();
+        assert_eq!(imported.parts.len(), 2);
+        assert_eq!(imported.parts[1].placement.position, Vec3::new(10.0, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,25 @@
+//! STEP (ISO 10303-21) import and export
+//!
+//! Export uses the AP214 schema. Every object becomes a part of an assembly
+//! named after the document, with its placement stored as the transformation
+//! of the assembly occurrence. Boxes, cylinders and spheres are written as
+//! exact B-rep solids bounded by planes, cylindrical and spherical surfaces.
+//! Imported meshes are written as faceted B-rep, one planar face per triangle.
+//!
+//! Part geometry is in the object's local coordinates: boxes and spheres are
+//! centered on the origin and cylinders run along the Y axis, like their
+//! meshes.
+//!
+//! Import parses the entity graph and rebuilds B-rep solids from their face
+//! surfaces: a sphere, a cylinder between two planes, or six planes forming
+//! a box become the matching primitive; cones and other planar solids are
+//! tessellated into meshes. Assembly transformations are applied, so each
+//! occurrence of a part becomes one object with its placement.
+
+pub mod writer;
+pub mod parser;
+pub mod importer;
+
+pub use writer::{export, SCHEMA};
+pub use parser::{parse, Param, Record, StepFile};
+pub use importer::{import, ImportReport, ImportedPart, StepImport};
@@ -0,0 +1,484 @@
+//! Parsing the Part 21 exchange structure into entity instances
+
+use std::collections::HashMap;
+use crate::io::ParseError;
+
+/// A parameter of an entity instance
+#[derive(Debug, Clone, PartialEq)]
+pub enum Param {
+    Ref(usize),
+    Integer(i64),
+    Real(f64),
+    String(String),
+    Enum(String),
+    List(Vec<Param>),
+    /// Typed value such as `LENGTH_MEASURE(1.)`
+    Typed(String, Vec<Param>),
+    /// `$`, an omitted optional value
+    Unset,
+    /// `*`, a value derived from other attributes
+    Derived,
+}
+
+impl Param {
+    pub fn reference(&self) -> Option<usize> {
+        match self {
+            Param::Ref(id) => Some(*id),
+            _ => None,
+        }
+    }
+
+    /// Numeric value, looking through typed values
+    pub fn real(&self) -> Option<f64> {
+        match self {
+            Param::Real(v) => Some(*v),
+            Param::Integer(v) => Some(*v as f64),
+            Param::Typed(_, params) if params.len() == 1 => params[0].real(),
+            _ => None,
+        }
+    }
+
+    pub fn string(&self) -> Option<&str> {
+        match self {
+            Param::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn list(&self) -> Option<&[Param]> {
+        match self {
+            Param::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn boolean(&self) -> Option<bool> {
+        match self {
+            Param::Enum(e) if e == "T" => Some(true),
+            Param::Enum(e) if e == "F" => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Entity ids of a list of references
+    pub fn references(&self) -> Vec<usize> {
+        self.list().unwrap_or(&[]).iter().filter_map(Param::reference).collect()
+    }
+}
+
+/// One entity of an instance: its type name and parameters
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub name: String,
+    pub params: Vec<Param>,
+}
+
+/// A parsed STEP Part 21 file
+///
+/// Complex instances such as units hold several records; simple instances
+/// hold one.
+#[derive(Debug, Clone, Default)]
+pub struct StepFile {
+    pub schemas: Vec<String>,
+    pub instances: HashMap<usize, Vec<Record>>,
+}
+
+impl StepFile {
+    /// Parameters of the record named `name` in instance `id`
+    pub fn record(&self, id: usize, name: &str) -> Option<&[Param]> {
+        self.instances.get(&id)?.iter().find(|r| r.name == name).map(|r| r.params.as_slice())
+    }
+
+    /// Type name of a simple instance, or the first record of a complex one
+    pub fn type_name(&self, id: usize) -> Option<&str> {
+        self.instances.get(&id)?.first().map(|r| r.name.as_str())
+    }
+
+    /// Instances containing a record named `name`, in id order
+    pub fn find(&self, name: &str) -> Vec<(usize, &[Param])> {
+        let mut found: Vec<_> = self.instances
+            .iter()
+            .filter_map(|(id, records)| records.iter().find(|r| r.name == name).map(|r| (*id, r.params.as_slice())))
+            .collect();
+        found.sort_by_key(|(id, _)| *id);
+        found
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Keyword(String),
+    Ref(usize),
+    Integer(i64),
+    Real(f64),
+    String(String),
+    Enum(String),
+    Open,
+    Close,
+    Comma,
+    Equals,
+    Semicolon,
+    Unset,
+    Derived,
+}
+
+struct Lexer<'a> {
+    text: &'a str,
+    pos: usize,
+    line: usize,
+    peeked: Option<(Token, usize)>,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(text: &'a str) -> Self {
+        Self { text, pos: 0, line: 1, peeked: None }
+    }
+
+    fn error(&self, message: impl Into<String>) -> ParseError {
+        ParseError::line(self.line, message)
+    }
+
+    fn byte(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn take_while(&mut self, accept: impl Fn(u8) -> bool) -> &'a str {
+        let start = self.pos;
+        while self.byte().is_some_and(&accept) {
+            self.pos += 1;
+        }
+        &self.text[start..self.pos]
+    }
+
+    fn skip_space(&mut self) -> Result<(), ParseError> {
+        loop {
+            match self.byte() {
+                Some(b'\n') => {
+                    self.line += 1;
+                    self.pos += 1;
+                }
+                Some(b) if b.is_ascii_whitespace() => self.pos += 1,
+                Some(b'/') if self.text[self.pos..].starts_with("/*") => {
+                    let end = self.text[self.pos..].find("*/").ok_or_else(|| self.error("unterminated comment"))?;
+                    self.line += self.text[self.pos..self.pos + end].matches('\n').count();
+                    self.pos += end + 2;
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn peek(&mut self) -> Result<Option<&Token>, ParseError> {
+        if self.peeked.is_none() {
+            self.peeked = self.read()?;
+        }
+        Ok(self.peeked.as_ref().map(|(token, _)| token))
+    }
+
+    fn next(&mut self) -> Result<Token, ParseError> {
+        match self.peeked.take() {
+            Some((token, line)) => {
+                self.line = line;
+                Ok(token)
+            }
+            None => self.read()?.map(|(token, _)| token).ok_or_else(|| self.error("unexpected end of file")),
+        }
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), ParseError> {
+        let token = self.next()?;
+        if token != expected {
+            return Err(self.error(format!("expected {:?}, found {:?}", expected, token)));
+        }
+        Ok(())
+    }
+
+    fn read(&mut self) -> Result<Option<(Token, usize)>, ParseError> {
+        self.skip_space()?;
+        let line = self.line;
+        let Some(byte) = self.byte() else { return Ok(None) };
+        let token = match byte {
+            b'(' | b')' | b',' | b'=' | b';' | b'$' | b'*' => {
+                self.pos += 1;
+                match byte {
+                    b'(' => Token::Open,
+                    b')' => Token::Close,
+                    b',' => Token::Comma,
+                    b'=' => Token::Equals,
+                    b';' => Token::Semicolon,
+                    b'$' => Token::Unset,
+                    _ => Token::Derived,
+                }
+            }
+            b'#' => {
+                self.pos += 1;
+                let digits = self.take_while(|b| b.is_ascii_digit());
+                Token::Ref(digits.parse().map_err(|_| self.error("invalid entity reference"))?)
+            }
+            b'.' => {
+                self.pos += 1;
+                let value = self.take_while(|b| b.is_ascii_alphanumeric() || b == b'_');
+                if self.byte() != Some(b'.') {
+                    return Err(self.error(format!("unterminated enumeration '.{}'", value)));
+                }
+                self.pos += 1;
+                Token::Enum(value.to_string())
+            }
+            b'\'' => Token::String(self.string()?),
+            b'"' => {
+                self.pos += 1;
+                let value = self.take_while(|b| b != b'"').to_string();
+                self.pos += 1;
+                Token::String(value)
+            }
+            b'0'..=b'9' | b'+' | b'-' => {
+                let number = self.take_while(|b| b.is_ascii_digit() || matches!(b, b'+' | b'-' | b'.' | b'E' | b'e'));
+                if number.contains(['.', 'E', 'e']) {
+                    Token::Real(number.parse().map_err(|_| self.error(format!("invalid number '{}'", number)))?)
+                } else {
+                    Token::Integer(number.parse().map_err(|_| self.error(format!("invalid number '{}'", number)))?)
+                }
+            }
+            b if b.is_ascii_alphabetic() || b == b'_' || b == b'!' => {
+                let word = self.take_while(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'-' | b'!'));
+                Token::Keyword(word.to_ascii_uppercase())
+            }
+            _ => {
+                let c = self.text[self.pos..].chars().next().unwrap_or('?');
+                return Err(self.error(format!("unexpected character '{}'", c)));
+            }
+        };
+        Ok(Some((token, line)))
+    }
+
+    /// Read a quoted string, decoding escapes and control directives
+    fn string(&mut self) -> Result<String, ParseError> {
+        self.pos += 1;
+        let mut raw = String::new();
+        loop {
+            let rest = &self.text[self.pos..];
+            let end = rest.find('\'').ok_or_else(|| self.error("unterminated string"))?;
+            raw.push_str(&rest[..end]);
+            self.line += rest[..end].matches('\n').count();
+            self.pos += end + 1;
+            if self.byte() == Some(b'\'') {
+                raw.push('\'');
+                self.pos += 1;
+            } else {
+                return Ok(decode_string(&raw));
+            }
+        }
+    }
+}
+
+/// Decode the `\\`, `\X\`, `\X2\` and `\X4\` directives of a STEP string
+fn decode_string(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(start) = rest.find('\\') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let hex_units = |body: &str, width: usize| -> Option<Vec<u32>> {
+            (0..body.len() / width).map(|i| u32::from_str_radix(body.get(i * width..(i + 1) * width)?, 16).ok()).collect()
+        };
+        if let Some(tail) = rest.strip_prefix("\\\\") {
+            out.push('\\');
+            rest = tail;
+        } else if let Some(tail) = rest.strip_prefix("\\X2\\").or_else(|| rest.strip_prefix("\\X4\\")) {
+            let width = if rest.starts_with("\\X2\\") { 4 } else { 8 };
+            let end = tail.find("\\X0\\").unwrap_or(tail.len());
+            let units = hex_units(&tail[..end], width).unwrap_or_default();
+            if width == 4 {
+                let units: Vec<u16> = units.into_iter().map(|u| u as u16).collect();
+                out.push_str(&String::from_utf16_lossy(&units));
+            } else {
+                out.extend(units.into_iter().filter_map(char::from_u32));
+            }
+            rest = tail.get(end + 4..).unwrap_or("");
+        } else if let Some(tail) = rest.strip_prefix("\\X\\") {
+            match tail.get(..2).and_then(|h| u32::from_str_radix(h, 16).ok()).and_then(char::from_u32) {
+                Some(c) => {
+                    out.push(c);
+                    rest = &tail[2..];
+                }
+                None => {
+                    out.push('\\');
+                    rest = &rest[1..];
+                }
+            }
+        } else {
+            out.push('\\');
+            rest = &rest[1..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn parse_params(lexer: &mut Lexer) -> Result<Vec<Param>, ParseError> {
+    lexer.expect(Token::Open)?;
+    parse_list(lexer)
+}
+
+/// Parameters after an opening parenthesis, up to the matching closing one
+fn parse_list(lexer: &mut Lexer) -> Result<Vec<Param>, ParseError> {
+    let mut params = Vec::new();
+    if lexer.peek()? == Some(&Token::Close) {
+        lexer.next()?;
+        return Ok(params);
+    }
+    loop {
+        let param = match lexer.next()? {
+            Token::Ref(id) => Param::Ref(id),
+            Token::Integer(v) => Param::Integer(v),
+            Token::Real(v) => Param::Real(v),
+            Token::String(s) => Param::String(s),
+            Token::Enum(e) => Param::Enum(e),
+            Token::Unset => Param::Unset,
+            Token::Derived => Param::Derived,
+            Token::Open => Param::List(parse_list(lexer)?),
+            Token::Keyword(name) => Param::Typed(name, parse_params(lexer)?),
+            token => return Err(lexer.error(format!("unexpected {:?} in parameter list", token))),
+        };
+        params.push(param);
+        match lexer.next()? {
+            Token::Comma => continue,
+            Token::Close => return Ok(params),
+            token => return Err(lexer.error(format!("expected ',' or ')', found {:?}", token))),
+        }
+    }
+}
+
+fn parse_record(lexer: &mut Lexer) -> Result<Record, ParseError> {
+    match lexer.next()? {
+        Token::Keyword(name) => Ok(Record { name, params: parse_params(lexer)? }),
+        token => Err(lexer.error(format!("expected an entity name, found {:?}", token))),
+    }
+}
+
+fn expect_keyword(lexer: &mut Lexer, keyword: &str) -> Result<(), ParseError> {
+    lexer.expect(Token::Keyword(keyword.to_string()))
+}
+
+/// Parse a STEP Part 21 file into its entity instances
+pub fn parse(text: &str) -> Result<StepFile, ParseError> {
+    let mut lexer = Lexer::new(text);
+    let mut file = StepFile::default();
+
+    expect_keyword(&mut lexer, "ISO-10303-21")?;
+    lexer.expect(Token::Semicolon)?;
+    expect_keyword(&mut lexer, "HEADER")?;
+    lexer.expect(Token::Semicolon)?;
+    loop {
+        if lexer.peek()? == Some(&Token::Keyword("ENDSEC".to_string())) {
+            lexer.next()?;
+            lexer.expect(Token::Semicolon)?;
+            break;
+        }
+        let record = parse_record(&mut lexer)?;
+        lexer.expect(Token::Semicolon)?;
+        if record.name == "FILE_SCHEMA" {
+            if let Some(schemas) = record.params.first().and_then(Param::list) {
+                file.schemas = schemas.iter().filter_map(|s| s.string().map(str::to_string)).collect();
+            }
+        }
+    }
+
+    // Files may have several data sections, optionally with parameters
+    while lexer.peek()? == Some(&Token::Keyword("DATA".to_string())) {
+        lexer.next()?;
+        if lexer.peek()? == Some(&Token::Open) {
+            parse_params(&mut lexer)?;
+        }
+        lexer.expect(Token::Semicolon)?;
+        loop {
+            let id = match lexer.next()? {
+                Token::Ref(id) => id,
+                Token::Keyword(k) if k == "ENDSEC" => break,
+                token => return Err(lexer.error(format!("expected an entity instance, found {:?}", token))),
+            };
+            lexer.expect(Token::Equals)?;
+            let records = if lexer.peek()? == Some(&Token::Open) {
+                lexer.next()?;
+                let mut records = Vec::new();
+                while lexer.peek()? != Some(&Token::Close) {
+                    records.push(parse_record(&mut lexer)?);
+                }
+                lexer.next()?;
+                records
+            } else {
+                vec![parse_record(&mut lexer)?]
+            };
+            lexer.expect(Token::Semicolon)?;
+            if file.instances.insert(id, records).is_some() {
+                return Err(lexer.error(format!("duplicate entity #{}", id)));
+            }
+        }
+        lexer.expect(Token::Semicolon)?;
+    }
+    expect_keyword(&mut lexer, "END-ISO-10303-21")?;
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r"ISO-10303-21;
+HEADER;
+FILE_SCHEMA(('AUTOMOTIVE_DESIGN'));
+ENDSEC;
+DATA;
+/* points */
+#1=CARTESIAN_POINT('',(0.,-1.5,2.E-3));
+#2=PRODUCT('\X2\00D8\X0\4 pin','it''s',$,(#3));
+#3=(LENGTH_UNIT()NAMED_UNIT(*)SI_UNIT(.MILLI.,.METRE.));
+#4=UNCERTAINTY_MEASURE_WITH_UNIT(LENGTH_MEASURE(1.E-06),#3,'a','b');
+ENDSEC;
+END-ISO-10303-21;
+";
+
+    #[test]
+    fn test_parse_instances() {
+        let file = parse(SAMPLE).unwrap();
+        assert_eq!(file.schemas, vec!["AUTOMOTIVE_DESIGN".to_string()]);
+        assert_eq!(file.instances.len(), 4);
+        assert_eq!(file.type_name(1), Some("CARTESIAN_POINT"));
+        let coordinates: Vec<f64> = file.record(1, "CARTESIAN_POINT").unwrap()[1]
+            .list()
+            .unwrap()
+            .iter()
+            .filter_map(Param::real)
+            .collect();
+        assert_eq!(coordinates, vec![0.0, -1.5, 0.002]);
+
+        let product = file.record(2, "PRODUCT").unwrap();
+        assert_eq!(product[0].string(), Some("Ø4 pin"));
+        assert_eq!(product[1].string(), Some("it's"));
+        assert_eq!(product[2], Param::Unset);
+        assert_eq!(product[3].references(), vec![3]);
+
+        // Complex instances keep each of their records
+        assert_eq!(file.instances[&3].len(), 3);
+        assert_eq!(file.record(3, "SI_UNIT").unwrap()[1], Param::Enum("METRE".to_string()));
+        assert_eq!(file.find("NAMED_UNIT")[0].1, &[Param::Derived]);
+        let uncertainty = file.record(4, "UNCERTAINTY_MEASURE_WITH_UNIT").unwrap();
+        assert_eq!(uncertainty[0], Param::Typed("LENGTH_MEASURE".to_string(), vec![Param::Real(1e-6)]));
+        assert_eq!(uncertainty[0].real(), Some(1e-6));
+    }
+
+    #[test]
+    fn test_parse_errors_report_line() {
+        let err = parse("ISO-10303-21;\nHEADER;\nENDSEC;\nDATA;\n#1=POINT('',(1.,2.);\n").unwrap_err();
+        assert_eq!(err.location, crate::io::Location::Line(5));
+        assert!(parse("ISO-10303-21;\nHEADER;\nENDSEC;\nDATA;\n#1=A();\n#1=B();\nENDSEC;\nEND-ISO-10303-21;\n").is_err());
+        assert!(parse("solid cube\n").is_err());
+    }
+
+    #[test]
+    fn test_decode_string_with_non_ascii_escape() {
+        // Hex digits are ASCII; anything else in an escape is dropped
+        assert_eq!(decode_string("\\X2\\000Ø\\X0\\ pin"), " pin");
+        assert_eq!(decode_string("\\X4\\0000ØØ\\X0\\"), "");
+    }
+}
@@ -0,0 +1,508 @@
+//! Writing documents as STEP AP214 assemblies
+
+use std::collections::HashMap;
+use std::fmt::{Display, Write};
+use glam::Vec3;
+use crate::document::Document;
+use crate::geometry::triangle_normal;
+use crate::object::{Object, ShapeType};
+use crate::units::Units;
+
+/// Application protocol written into the file schema
+pub const SCHEMA: &str = "AUTOMOTIVE_DESIGN { 1 0 10303 214 1 1 1 1 }";
+
+/// Format a STEP real, which always needs a decimal point
+fn real(value: impl Display) -> String {
+    let mut text = value.to_string();
+    if !text.contains('.') {
+        text.push('.');
+    }
+    text
+}
+
+/// Format a STEP string literal
+///
+/// Quotes and backslashes are doubled; characters outside printable ASCII
+/// use the `\X2\` (UTF-16) and `\X4\` (UTF-32) control directives.
+fn string(text: &str) -> String {
+    let mut out = String::from("'");
+    for c in text.chars() {
+        match c {
+            '\'' => out.push_str("''"),
+            '\\' => out.push_str("\\\\"),
+            ' '..='~' => out.push(c),
+            c if (c as u32) < 0x1_0000 => {
+                let _ = write!(out, "\\X2\\{:04X}\\X0\\", c as u32);
+            }
+            c => {
+                let _ = write!(out, "\\X4\\{:08X}\\X0\\", c as u32);
+            }
+        }
+    }
+    out.push('\'');
+    out
+}
+
+fn list(ids: &[usize]) -> String {
+    let refs: Vec<String> = ids.iter().map(|id| format!("#{}", id)).collect();
+    format!("({})", refs.join(","))
+}
+
+fn triple(v: Vec3) -> String {
+    format!("({},{},{})", real(v.x), real(v.y), real(v.z))
+}
+
+/// Collects numbered entity instances of the data section
+#[derive(Default)]
+struct StepWriter {
+    entities: Vec<String>,
+}
+
+impl StepWriter {
+    /// Add an entity instance and return its id
+    fn add(&mut self, entity: impl Into<String>) -> usize {
+        self.entities.push(entity.into());
+        self.entities.len()
+    }
+
+    fn point(&mut self, p: Vec3) -> usize {
+        self.add(format!("CARTESIAN_POINT('',{})", triple(p)))
+    }
+
+    fn direction(&mut self, d: Vec3) -> usize {
+        self.add(format!("DIRECTION('',{})", triple(d)))
+    }
+
+    /// Coordinate system at `origin` with the given Z and X axes
+    fn axis(&mut self, origin: Vec3, z: Vec3, x: Vec3) -> usize {
+        let origin = self.point(origin);
+        let z = self.direction(z);
+        let x = self.direction(x);
+        self.add(format!("AXIS2_PLACEMENT_3D('',#{},#{},#{})", origin, z, x))
+    }
+
+    fn vertex(&mut self, p: Vec3) -> usize {
+        let point = self.point(p);
+        self.add(format!("VERTEX_POINT('',#{})", point))
+    }
+
+    fn edge(&mut self, start: usize, end: usize, curve: usize) -> usize {
+        self.add(format!("EDGE_CURVE('',#{},#{},#{},.T.)", start, end, curve))
+    }
+
+    fn line(&mut self, start: Vec3, end: Vec3) -> usize {
+        let point = self.point(start);
+        let direction = self.direction((end - start).normalize());
+        let vector = self.add(format!("VECTOR('',#{},{})", direction, real(start.distance(end))));
+        self.add(format!("LINE('',#{},#{})", point, vector))
+    }
+
+    fn circle(&mut self, center: Vec3, axis: Vec3, x: Vec3, radius: f32) -> usize {
+        let placement = self.axis(center, axis, x);
+        self.add(format!("CIRCLE('',#{},{})", placement, real(radius)))
+    }
+
+    /// Face bounded by a single loop of (edge, same sense) pairs
+    fn face(&mut self, edges: &[(usize, bool)], surface: usize) -> usize {
+        let oriented: Vec<usize> = edges
+            .iter()
+            .map(|(edge, sense)| self.add(format!("ORIENTED_EDGE('',*,*,#{},{})", edge, flag(*sense))))
+            .collect();
+        let edge_loop = self.add(format!("EDGE_LOOP('',{})", list(&oriented)));
+        let bound = self.add(format!("FACE_OUTER_BOUND('',#{},.T.)", edge_loop));
+        self.add(format!("ADVANCED_FACE('',(#{}),#{},.T.)", bound, surface))
+    }
+
+    fn solid(&mut self, name: &str, faces: &[usize]) -> usize {
+        let shell = self.add(format!("CLOSED_SHELL('',{})", list(faces)));
+        self.add(format!("MANIFOLD_SOLID_BREP({},#{})", string(name), shell))
+    }
+
+    fn finish(self, name: &str, timestamp: &str) -> String {
+        let mut out = String::new();
+        out.push_str("ISO-10303-21;\nHEADER;\n");
+        let _ = writeln!(out, "FILE_DESCRIPTION(('RustyCAD model'),'2;1');");
+        let _ = writeln!(
+            out,
+            "FILE_NAME({},{},(''),(''),{},'rustycad','');",
+            string(name),
+            string(timestamp),
+            string(&format!("rustycad {}", env!("CARGO_PKG_VERSION")))
+        );
+        let _ = writeln!(out, "FILE_SCHEMA(('{}'));", SCHEMA);
+        out.push_str("ENDSEC;\nDATA;\n");
+        for (index, entity) in self.entities.iter().enumerate() {
+            let _ = writeln!(out, "#{}={};", index + 1, entity);
+        }
+        out.push_str("ENDSEC;\nEND-ISO-10303-21;\n");
+        out
+    }
+}
+
+fn flag(value: bool) -> &'static str {
+    if value { ".T." } else { ".F." }
+}
+
+/// Shared contexts that every product and representation refers to
+struct Contexts {
+    product: usize,
+    definition: usize,
+    geometry: usize,
+}
+
+/// Length unit entity of the document's unit system
+fn length_unit(w: &mut StepWriter, units: Units) -> usize {
+    let si = |prefix: &str| format!("(LENGTH_UNIT()NAMED_UNIT(*)SI_UNIT({},.METRE.))", prefix);
+    match units {
+        Units::Millimeter => w.add(si(".MILLI.")),
+        Units::Centimeter => w.add(si(".CENTI.")),
+        Units::Meter => w.add(si("$")),
+        Units::Inch | Units::Foot => {
+            let metre = w.add(si("$"));
+            let measure = w.add(format!("LENGTH_MEASURE_WITH_UNIT(LENGTH_MEASURE({}),#{})", real(units.to_meters()), metre));
+            let exponents = w.add("DIMENSIONAL_EXPONENTS(1.,0.,0.,0.,0.,0.,0.)");
+            let name = if units == Units::Inch { "INCH" } else { "FOOT" };
+            w.add(format!("(CONVERSION_BASED_UNIT('{}',#{})LENGTH_UNIT()NAMED_UNIT(#{}))", name, measure, exponents))
+        }
+    }
+}
+
+fn contexts(w: &mut StepWriter, units: Units) -> Contexts {
+    let application = w.add("APPLICATION_CONTEXT('core data for automotive mechanical design processes')");
+    w.add(format!("APPLICATION_PROTOCOL_DEFINITION('international standard','automotive_design',2000,#{})", application));
+    let product = w.add(format!("PRODUCT_CONTEXT('',#{},'mechanical')", application));
+    let definition = w.add(format!("PRODUCT_DEFINITION_CONTEXT('part definition',#{},'design')", application));
+
+    let length = length_unit(w, units);
+    let angle = w.add("(NAMED_UNIT(*)PLANE_ANGLE_UNIT()SI_UNIT($,.RADIAN.))");
+    let solid_angle = w.add("(NAMED_UNIT(*)SI_UNIT($,.STERADIAN.)SOLID_ANGLE_UNIT())");
+    let uncertainty = w.add(format!(
+        "UNCERTAINTY_MEASURE_WITH_UNIT(LENGTH_MEASURE(1.E-06),#{},'distance_accuracy_value','confusion accuracy')",
+        length
+    ));
+    let geometry = w.add(format!(
+        "(GEOMETRIC_REPRESENTATION_CONTEXT(3)GLOBAL_UNCERTAINTY_ASSIGNED_CONTEXT((#{}))\
+         GLOBAL_UNIT_ASSIGNED_CONTEXT((#{},#{},#{}))REPRESENTATION_CONTEXT('Context3D','3D Context with UNIT and UNCERTAINTY'))",
+        uncertainty, length, angle, solid_angle
+    ));
+    Contexts { product, definition, geometry }
+}
+
+/// Product with its definition; returns the product definition and its shape
+fn product(w: &mut StepWriter, ctx: &Contexts, name: &str) -> (usize, usize) {
+    let product = w.add(format!("PRODUCT({},{},'',(#{}))", string(name), string(name), ctx.product));
+    w.add(format!("PRODUCT_RELATED_PRODUCT_CATEGORY('part',$,(#{}))", product));
+    let formation = w.add(format!("PRODUCT_DEFINITION_FORMATION('','',#{})", product));
+    let definition = w.add(format!("PRODUCT_DEFINITION('design','',#{},#{})", formation, ctx.definition));
+    let shape = w.add(format!("PRODUCT_DEFINITION_SHAPE('','',#{})", definition));
+    (definition, shape)
+}
+
+/// Solid bounded by planar faces, given as counter-clockwise corner loops
+fn planar_solid(w: &mut StepWriter, name: &str, corners: &[Vec3], faces: &[Vec<usize>]) -> usize {
+    let vertices: Vec<usize> = corners.iter().map(|p| w.vertex(*p)).collect();
+    let mut edges: HashMap<(usize, usize), usize> = HashMap::new();
+    let mut face_ids = Vec::with_capacity(faces.len());
+    for face in faces {
+        let mut bound = Vec::with_capacity(face.len());
+        for i in 0..face.len() {
+            let (a, b) = (face[i], face[(i + 1) % face.len()]);
+            // Each edge is shared by two faces that run along it in opposite directions
+            let (edge, sense) = match edges.get(&(b, a)) {
+                Some(edge) => (*edge, false),
+                None => {
+                    let curve = w.line(corners[a], corners[b]);
+                    let edge = w.edge(vertices[a], vertices[b], curve);
+                    edges.insert((a, b), edge);
+                    (edge, true)
+                }
+            };
+            bound.push((edge, sense));
+        }
+        let [p0, p1, p2] = [0, 1, 2].map(|i| corners[face[i]]);
+        let normal = triangle_normal(p0, p1, p2);
+        let center = face.iter().map(|i| corners[*i]).sum::<Vec3>() / face.len() as f32;
+        let placement = w.axis(center, normal, (p1 - p0).normalize());
+        let plane = w.add(format!("PLANE('',#{})", placement));
+        face_ids.push(w.face(&bound, plane));
+    }
+    w.solid(name, &face_ids)
+}
+
+fn box_solid(w: &mut StepWriter, name: &str, length: f32, width: f32, height: f32) -> usize {
+    let half = Vec3::new(length, width, height) / 2.0;
+    // Corner i has the sign of bit 0, 1 and 2 of i along X, Y and Z
+    let corners: Vec<Vec3> = (0..8)
+        .map(|i| {
+            let sign = |bit: u32| if i & (1 << bit) != 0 { 1.0 } else { -1.0 };
+            half * Vec3::new(sign(0), sign(1), sign(2))
+        })
+        .collect();
+    let faces = [
+        vec![0, 4, 6, 2],
+        vec![1, 3, 7, 5],
+        vec![0, 1, 5, 4],
+        vec![2, 6, 7, 3],
+        vec![0, 2, 3, 1],
+        vec![4, 5, 7, 6],
+    ];
+    planar_solid(w, name, &corners, &faces)
+}
+
+fn cylinder_solid(w: &mut StepWriter, name: &str, radius: f32, height: f32) -> usize {
+    let h = height / 2.0;
+    let top_center = Vec3::Y * h;
+    let bottom_center = -Vec3::Y * h;
+    let top = w.vertex(top_center + Vec3::X * radius);
+    let bottom = w.vertex(bottom_center + Vec3::X * radius);
+
+    let top_curve = w.circle(top_center, Vec3::Y, Vec3::X, radius);
+    let top_edge = w.edge(top, top, top_curve);
+    let bottom_curve = w.circle(bottom_center, Vec3::Y, Vec3::X, radius);
+    let bottom_edge = w.edge(bottom, bottom, bottom_curve);
+    let seam_curve = w.line(bottom_center + Vec3::X * radius, top_center + Vec3::X * radius);
+    let seam = w.edge(bottom, top, seam_curve);
+
+    let top_placement = w.axis(top_center, Vec3::Y, Vec3::X);
+    let top_plane = w.add(format!("PLANE('',#{})", top_placement));
+    let top_face = w.face(&[(top_edge, true)], top_plane);
+
+    let bottom_placement = w.axis(bottom_center, -Vec3::Y, Vec3::X);
+    let bottom_plane = w.add(format!("PLANE('',#{})", bottom_placement));
+    let bottom_face = w.face(&[(bottom_edge, false)], bottom_plane);
+
+    // Counter-clockwise around the unrolled side, whose normal points outwards
+    let side_placement = w.axis(bottom_center, Vec3::Y, Vec3::X);
+    let side_surface = w.add(format!("CYLINDRICAL_SURFACE('',#{},{})", side_placement, real(radius)));
+    let side_face = w.face(&[(bottom_edge, true), (seam, true), (top_edge, false), (seam, false)], side_surface);
+
+    w.solid(name, &[top_face, bottom_face, side_face])
+}
+
+fn sphere_solid(w: &mut StepWriter, name: &str, radius: f32) -> usize {
+    let south = w.vertex(-Vec3::Z * radius);
+    let north = w.vertex(Vec3::Z * radius);
+    // Half circle from the south to the north pole through +X
+    let seam_curve = w.circle(Vec3::ZERO, -Vec3::Y, Vec3::X, radius);
+    let seam = w.edge(south, north, seam_curve);
+    let placement = w.axis(Vec3::ZERO, Vec3::Z, Vec3::X);
+    let surface = w.add(format!("SPHERICAL_SURFACE('',#{},{})", placement, real(radius)));
+    let face = w.face(&[(seam, true), (seam, false)], surface);
+    w.solid(name, &[face])
+}
+
+/// Faceted B-rep of a mesh, one planar face per triangle
+fn faceted_solid(w: &mut StepWriter, name: &str, object: &Object) -> Option<usize> {
+    let mesh = object.mesh.as_ref()?;
+    let (positions, triangles) = mesh.welded();
+    let points: Vec<usize> = positions.iter().map(|p| w.point(*p)).collect();
+    let mut faces = Vec::with_capacity(triangles.len());
+    for triangle in &triangles {
+        let [a, b, c] = triangle.map(|i| positions[i as usize]);
+        let normal = triangle_normal(a, b, c);
+        if normal == Vec3::ZERO {
+            continue;
+        }
+        let poly_loop = w.add(format!("POLY_LOOP('',{})", list(&triangle.map(|i| points[i as usize]))));
+        let bound = w.add(format!("FACE_OUTER_BOUND('',#{},.T.)", poly_loop));
+        let placement = w.axis(a, normal, (b - a).normalize());
+        let plane = w.add(format!("PLANE('',#{})", placement));
+        faces.push(w.add(format!("FACE_SURFACE('',(#{}),#{},.T.)", bound, plane)));
+    }
+    let shell = w.add(format!("CLOSED_SHELL('',{})", list(&faces)));
+    Some(w.add(format!("FACETED_BREP({},#{})", string(name), shell)))
+}
+
+/// Write the given objects (all if `ids` is empty) as a STEP file
+///
+/// `name` names the file and the assembly holding the objects; `timestamp`
+/// is written into the header as is and should be an ISO 8601 date-time.
+/// Objects with invalid parameters are skipped.
+pub fn export(doc: &Document, ids: &[u32], name: &str, timestamp: &str) -> String {
+    let mut w = StepWriter::default();
+    let ctx = contexts(&mut w, doc.units);
+
+    let (assembly, assembly_shape) = product(&mut w, &ctx, name);
+    let assembly_origin = w.axis(Vec3::ZERO, Vec3::Z, Vec3::X);
+    let mut assembly_items = vec![assembly_origin];
+    let mut occurrences = Vec::new();
+
+    for id in doc.resolve_ids(ids) {
+        let Some(object) = doc.get_object(id) else { continue };
+        if object.validate().is_err() {
+            continue;
+        }
+        let part_name = object.name();
+        let (solid, representation) = match &object.shape_type {
+            ShapeType::Box { length, width, height } => {
+                (box_solid(&mut w, &part_name, *length, *width, *height), "ADVANCED_BREP_SHAPE_REPRESENTATION")
+            }
+            ShapeType::Cylinder { radius, height } => {
+                (cylinder_solid(&mut w, &part_name, *radius, *height), "ADVANCED_BREP_SHAPE_REPRESENTATION")
+            }
+            ShapeType::Sphere { radius } => {
+                (sphere_solid(&mut w, &part_name, *radius), "ADVANCED_BREP_SHAPE_REPRESENTATION")
+            }
+            ShapeType::ImportedMesh { .. } => match faceted_solid(&mut w, &part_name, object) {
+                Some(solid) => (solid, "FACETED_BREP_SHAPE_REPRESENTATION"),
+                None => continue,
+            },
+        };
+
+        let (definition, shape) = product(&mut w, &ctx, &part_name);
+        let origin = w.axis(Vec3::ZERO, Vec3::Z, Vec3::X);
+        let part_representation = w.add(format!(
+            "{}({},(#{},#{}),#{})",
+            representation,
+            string(&part_name),
+            origin,
+            solid,
+            ctx.geometry
+        ));
+        w.add(format!("SHAPE_DEFINITION_REPRESENTATION(#{},#{})", shape, part_representation));
+
+        let placement = object.placement();
+        let placed = w.axis(placement.position, placement.rotation * Vec3::Z, placement.rotation * Vec3::X);
+        assembly_items.push(placed);
+        occurrences.push((id, part_name, definition, part_representation, origin, placed));
+    }
+
+    let assembly_representation = w.add(format!("SHAPE_REPRESENTATION({},{},#{})", string(name), list(&assembly_items), ctx.geometry));
+    w.add(format!("SHAPE_DEFINITION_REPRESENTATION(#{},#{})", assembly_shape, assembly_representation));
+
+    for (id, part_name, definition, part_representation, origin, placed) in occurrences {
+        let usage = w.add(format!(
+            "NEXT_ASSEMBLY_USAGE_OCCURRENCE('{}',{},'',#{},#{},$)",
+            id,
+            string(&part_name),
+            assembly,
+            definition
+        ));
+        let usage_shape = w.add(format!("PRODUCT_DEFINITION_SHAPE('Placement','',#{})", usage));
+        let transformation = w.add(format!("ITEM_DEFINED_TRANSFORMATION('','',#{},#{})", origin, placed));
+        let relationship = w.add(format!(
+            "(REPRESENTATION_RELATIONSHIP('','',#{},#{})REPRESENTATION_RELATIONSHIP_WITH_TRANSFORMATION(#{})\
+             SHAPE_REPRESENTATION_RELATIONSHIP())",
+            part_representation, assembly_representation, transformation
+        ));
+        w.add(format!("CONTEXT_DEPENDENT_SHAPE_REPRESENTATION(#{},#{})", relationship, usage_shape));
+    }
+
+    w.finish(name, timestamp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Quat;
+    use crate::geometry::generate_box_mesh;
+    use crate::math::Placement;
+    use crate::property::Property;
+
+    fn export_shape(shape: ShapeType) -> String {
+        let mut doc = Document::new();
+        doc.add_object(shape);
+        doc.recompute();
+        export(&doc, &[], "part", "2024-01-01T00:00:00")
+    }
+
+    fn count(step: &str, entity: &str) -> usize {
+        step.matches(&format!("={}(", entity)).count()
+    }
+
+    /// References of every oriented edge, which must each appear twice in a closed shell
+    fn oriented_edge_uses(step: &str) -> HashMap<String, usize> {
+        let mut uses = HashMap::new();
+        for line in step.lines().filter(|l| l.contains("=ORIENTED_EDGE(")) {
+            let edge = line.split(',').nth(3).unwrap().to_string();
+            *uses.entry(edge).or_insert(0) += 1;
+        }
+        uses
+    }
+
+    #[test]
+    fn test_file_structure() {
+        let step = export_shape(ShapeType::Box { length: 1.0, width: 2.0, height: 3.0 });
+        assert!(step.starts_with("ISO-10303-21;\nHEADER;\n"));
+        assert!(step.contains("FILE_NAME('part','2024-01-01T00:00:00'"));
+        assert!(step.contains(SCHEMA));
+        assert!(step.ends_with("ENDSEC;\nEND-ISO-10303-21;\n"));
+
+        // Instances are numbered in order and every reference resolves
+        let data: Vec<&str> = step.lines().filter(|l| l.starts_with('#')).collect();
+        for (index, line) in data.iter().enumerate() {
+            assert!(line.starts_with(&format!("#{}=", index + 1)));
+            for reference in line.split('#').skip(2) {
+                let id: usize = reference.chars().take_while(char::is_ascii_digit).collect::<String>().parse().unwrap();
+                assert!(id >= 1 && id <= data.len(), "{}", line);
+            }
+        }
+        assert!(step.contains("(LENGTH_UNIT()NAMED_UNIT(*)SI_UNIT(.MILLI.,.METRE.))"));
+    }
+
+    #[test]
+    fn test_box_is_closed_planar_solid() {
+        let step = export_shape(ShapeType::Box { length: 1.0, width: 2.0, height: 3.0 });
+        assert_eq!(count(&step, "ADVANCED_FACE"), 6);
+        assert_eq!(count(&step, "PLANE"), 6);
+        assert_eq!(count(&step, "EDGE_CURVE"), 12);
+        assert_eq!(count(&step, "VERTEX_POINT"), 8);
+        assert!(step.contains("CARTESIAN_POINT('',(0.5,1.,1.5))"));
+        assert!(oriented_edge_uses(&step).values().all(|n| *n == 2));
+        assert!(step.contains("=ADVANCED_BREP_SHAPE_REPRESENTATION('Box 1',"));
+    }
+
+    #[test]
+    fn test_cylinder_and_sphere_surfaces() {
+        let step = export_shape(ShapeType::Cylinder { radius: 2.0, height: 4.0 });
+        assert_eq!(count(&step, "ADVANCED_FACE"), 3);
+        assert_eq!(count(&step, "CIRCLE"), 2);
+        assert!(step.contains("CYLINDRICAL_SURFACE('',"));
+        assert!(step.contains(",2.)"));
+        assert!(oriented_edge_uses(&step).values().all(|n| *n == 2));
+
+        let step = export_shape(ShapeType::Sphere { radius: 1.5 });
+        assert_eq!(count(&step, "ADVANCED_FACE"), 1);
+        assert_eq!(count(&step, "SPHERICAL_SURFACE"), 1);
+        assert!(step.contains("CARTESIAN_POINT('',(0.,0.,1.5))"));
+    }
+
+    #[test]
+    fn test_mesh_exports_faceted_brep() {
+        let step = export_shape(ShapeType::ImportedMesh { mesh: generate_box_mesh(1.0, 1.0, 1.0) });
+        assert_eq!(count(&step, "FACETED_BREP"), 1);
+        assert_eq!(count(&step, "FACE_SURFACE"), 12);
+        assert_eq!(count(&step, "POLY_LOOP"), 12);
+        assert!(step.contains("=FACETED_BREP_SHAPE_REPRESENTATION("));
+    }
+
+    #[test]
+    fn test_assembly_and_placements() {
+        let mut doc = Document::new();
+        doc.units = Units::Inch;
+        let a = doc.add_object(ShapeType::Box { length: 1.0, width: 1.0, height: 1.0 });
+        let placement = Placement::from_position_rotation(Vec3::new(10.0, 20.0, 30.0), Quat::from_rotation_z(std::f32::consts::FRAC_PI_2));
+        doc.set_object_property(a, "Placement".to_string(), Property::Placement(placement));
+        doc.set_object_property(a, "Name".to_string(), Property::String("Jack's block".to_string()));
+        doc.add_object(ShapeType::Sphere { radius: 1.0 });
+        doc.add_object(ShapeType::Sphere { radius: -1.0 });
+        doc.recompute();
+
+        let step = export(&doc, &[], "assembly", "");
+        assert_eq!(count(&step, "PRODUCT"), 3);
+        assert_eq!(count(&step, "NEXT_ASSEMBLY_USAGE_OCCURRENCE"), 2);
+        assert_eq!(count(&step, "CONTEXT_DEPENDENT_SHAPE_REPRESENTATION"), 2);
+        assert!(step.contains("PRODUCT('Jack''s block','Jack''s block'"));
+        assert!(step.contains("CARTESIAN_POINT('',(10.,20.,30.))"));
+        assert!(step.contains("CONVERSION_BASED_UNIT('INCH',"));
+        assert!(step.contains("LENGTH_MEASURE(0.0254)"));
+    }
+
+    #[test]
+    fn test_string_encoding() {
+        assert_eq!(string("a'b\\c"), "'a''b\\\\c'");
+        assert_eq!(string("Ø10"), "'\\X2\\00D8\\X0\\10'");
+        assert_eq!(real(1.0f32), "1.");
+        assert_eq!(real(-0.25f32), "-0.25");
+    }
+}
@@ -0,0 +1,315 @@
+//! STL (stereolithography) import and export
+//!
+//! STL stores unconnected triangles with one facet normal each. Facet normals
+//! are computed from the triangle corners rather than taken from the mesh's
+//! vertex normals, which are smoothed on curved surfaces.
+
+use std::fmt::Write;
+use glam::Vec3;
+use crate::geometry::{triangle_normal, Mesh};
+use crate::io::ParseError;
+
+/// Size of the free-form header of a binary STL file
+const BINARY_HEADER_LEN: usize = 80;
+
+/// Write a mesh as binary STL
+///
+/// The header text is truncated to 80 bytes. It never starts with "solid",
+/// so readers do not mistake the file for ASCII STL.
+pub fn write_binary(mesh: &Mesh, header: &str) -> Vec<u8> {
+    let count = mesh.triangle_count();
+    let mut out = Vec::with_capacity(BINARY_HEADER_LEN + 4 + count * 50);
+
+    let mut header_bytes = [0u8; BINARY_HEADER_LEN];
+    let text = header.trim_start_matches("solid").as_bytes();
+    let len = text.len().min(BINARY_HEADER_LEN);
+    header_bytes[..len].copy_from_slice(&text[..len]);
+    out.extend_from_slice(&header_bytes);
+    out.extend_from_slice(&(count as u32).to_le_bytes());
+
+    for [a, b, c] in mesh.triangles() {
+        let normal = triangle_normal(a, b, c);
+        for v in [normal, a, b, c] {
+            for component in v.to_array() {
+                out.extend_from_slice(&component.to_le_bytes());
+            }
+        }
+        // Attribute byte count, unused
+        out.extend_from_slice(&0u16.to_le_bytes());
+    }
+    out
+}
+
+/// Write a mesh as ASCII STL with the given solid name
+pub fn write_ascii(mesh: &Mesh, name: &str) -> String {
+    let name: String = name.chars().map(|c| if c.is_whitespace() { '_' } else { c }).collect();
+    let mut out = String::new();
+    writeln!(out, "solid {}", name).unwrap();
+    for [a, b, c] in mesh.triangles() {
+        let n = triangle_normal(a, b, c);
+        writeln!(out, "  facet normal {:e} {:e} {:e}", n.x, n.y, n.z).unwrap();
+        writeln!(out, "    outer loop").unwrap();
+        for v in [a, b, c] {
+            writeln!(out, "      vertex {:e} {:e} {:e}", v.x, v.y, v.z).unwrap();
+        }
+        writeln!(out, "    endloop").unwrap();
+        writeln!(out, "  endfacet").unwrap();
+    }
+    writeln!(out, "endsolid {}", name).unwrap();
+    out
+}
+
+/// A parsed STL file
+#[derive(Debug, Clone)]
+pub struct StlModel {
+    /// Solid name of an ASCII file, empty for binary files
+    pub name: String,
+    pub mesh: Mesh,
+}
+
+/// Read binary or ASCII STL
+///
+/// Every facet gets its own three vertices carrying the facet normal. A
+/// missing or zero facet normal is recomputed from the corners.
+pub fn read(data: &[u8]) -> Result<StlModel, ParseError> {
+    if is_binary(data) {
+        read_binary(data)
+    } else {
+        let text = std::str::from_utf8(data)
+            .map_err(|err| ParseError::byte(err.valid_up_to(), "ASCII STL is not valid UTF-8"))?;
+        read_ascii(text)
+    }
+}
+
+/// Decide between binary and ASCII STL
+///
+/// Some binary exporters start their header with "solid" too, so a matching
+/// binary size takes precedence over the keyword.
+fn is_binary(data: &[u8]) -> bool {
+    if data.len() >= BINARY_HEADER_LEN + 4 {
+        let count = u32::from_le_bytes(data[80..84].try_into().unwrap()) as usize;
+        if count.checked_mul(50).and_then(|n| n.checked_add(84)) == Some(data.len()) {
+            return true;
+        }
+    }
+    let start = data.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(data.len());
+    !data[start..].starts_with(b"solid")
+}
+
+fn push_facet(mesh: &mut Mesh, normal: Vec3, corners: [Vec3; 3]) {
+    let [a, b, c] = corners;
+    let normal = if normal.length_squared() > 0.0 { normal.normalize() } else { triangle_normal(a, b, c) };
+    let base = mesh.vertex_count() as u32;
+    for v in corners {
+        mesh.vertices.extend_from_slice(&v.to_array());
+        mesh.normals.extend_from_slice(&normal.to_array());
+    }
+    mesh.indices.extend_from_slice(&[base, base + 1, base + 2]);
+}
+
+/// Read binary STL
+pub fn read_binary(data: &[u8]) -> Result<StlModel, ParseError> {
+    if data.len() < BINARY_HEADER_LEN + 4 {
+        return Err(ParseError::byte(data.len(), "binary STL header is truncated"));
+    }
+    let count = u32::from_le_bytes(data[80..84].try_into().unwrap()) as usize;
+    let mut mesh = Mesh::new();
+    let read_vec = |offset: usize| {
+        let f = |o: usize| f32::from_le_bytes(data[o..o + 4].try_into().unwrap());
+        Vec3::new(f(offset), f(offset + 4), f(offset + 8))
+    };
+    for i in 0..count {
+        let offset = BINARY_HEADER_LEN + 4 + i * 50;
+        if offset + 50 > data.len() {
+            return Err(ParseError::byte(offset, format!("facet {} of {} is truncated", i + 1, count)));
+        }
+        let corners = [read_vec(offset + 12), read_vec(offset + 24), read_vec(offset + 36)];
+        if corners.iter().any(|v| !v.is_finite()) {
+            return Err(ParseError::byte(offset + 12, "vertex coordinate is not finite"));
+        }
+        push_facet(&mut mesh, read_vec(offset), corners);
+    }
+    Ok(StlModel { name: String::new(), mesh })
+}
+
+/// Parse the `N` numbers following a keyword on an ASCII STL line
+fn parse_floats<const N: usize>(tokens: &[&str], line: usize) -> Result<[f32; N], ParseError> {
+    if tokens.len() != N {
+        return Err(ParseError::line(line, format!("expected {} numbers, found {}", N, tokens.len())));
+    }
+    let mut values = [0.0; N];
+    for (value, token) in values.iter_mut().zip(tokens) {
+        *value = token.parse()
+            .ok()
+            .filter(|v: &f32| v.is_finite())
+            .ok_or_else(|| ParseError::line(line, format!("invalid number '{}'", token)))?;
+    }
+    Ok(values)
+}
+
+/// Read ASCII STL
+///
+/// Facets with more than three vertices are triangulated as a fan.
+pub fn read_ascii(text: &str) -> Result<StlModel, ParseError> {
+    let mut name = String::new();
+    let mut mesh = Mesh::new();
+    let mut normal = Vec3::ZERO;
+    let mut corners: Vec<Vec3> = Vec::new();
+    let mut in_facet = false;
+    let mut seen_solid = false;
+    let mut facet_line = 0;
+
+    for (index, raw) in text.lines().enumerate() {
+        let line = index + 1;
+        let tokens: Vec<&str> = raw.split_whitespace().collect();
+        let Some((keyword, rest)) = tokens.split_first() else {
+            continue;
+        };
+        match *keyword {
+            "solid" if !seen_solid => {
+                seen_solid = true;
+                if name.is_empty() {
+                    name = rest.join(" ");
+                }
+            }
+            "facet" => {
+                if in_facet {
+                    return Err(ParseError::line(line, "facet started before the previous endfacet"));
+                }
+                if rest.first() != Some(&"normal") {
+                    return Err(ParseError::line(line, "expected 'facet normal'"));
+                }
+                normal = Vec3::from_array(parse_floats::<3>(&rest[1..], line)?);
+                corners.clear();
+                in_facet = true;
+                facet_line = line;
+            }
+            "vertex" => {
+                if !in_facet {
+                    return Err(ParseError::line(line, "vertex outside of a facet"));
+                }
+                corners.push(Vec3::from_array(parse_floats::<3>(rest, line)?));
+            }
+            "endfacet" => {
+                if !in_facet {
+                    return Err(ParseError::line(line, "endfacet without facet"));
+                }
+                if corners.len() < 3 {
+                    return Err(ParseError::line(line, format!("facet has {} vertices", corners.len())));
+                }
+                for i in 1..corners.len() - 1 {
+                    push_facet(&mut mesh, normal, [corners[0], corners[i], corners[i + 1]]);
+                }
+                in_facet = false;
+            }
+            "outer" | "endloop" => {}
+            "endsolid" => {
+                if in_facet {
+                    return Err(ParseError::line(line, "endsolid inside a facet"));
+                }
+                // Files may contain several solids; they are merged
+                seen_solid = false;
+            }
+            "solid" => return Err(ParseError::line(line, "solid started before the previous endsolid")),
+            other => return Err(ParseError::line(line, format!("unexpected keyword '{}'", other))),
+        }
+    }
+    if in_facet {
+        return Err(ParseError::line(facet_line, "facet is not closed"));
+    }
+    Ok(StlModel { name, mesh })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::generate_box_mesh;
+
+    fn read_f32(data: &[u8], offset: usize) -> f32 {
+        f32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_binary_layout() {
+        let mesh = generate_box_mesh(2.0, 2.0, 2.0);
+        let data = write_binary(&mesh, "solid box from rustycad");
+        assert_eq!(data.len(), 80 + 4 + 12 * 50);
+        assert!(!data.starts_with(b"solid"));
+        assert_eq!(u32::from_le_bytes(data[80..84].try_into().unwrap()), 12);
+
+        // First facet is on the front (+Z) face
+        let normal = Vec3::new(read_f32(&data, 84), read_f32(&data, 88), read_f32(&data, 92));
+        assert_eq!(normal, Vec3::Z);
+        let first_vertex = Vec3::new(read_f32(&data, 96), read_f32(&data, 100), read_f32(&data, 104));
+        assert_eq!(first_vertex, Vec3::new(-1.0, -1.0, 1.0));
+    }
+
+    #[test]
+    fn test_facet_normals_follow_winding() {
+        let mesh = Mesh {
+            vertices: vec![0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0],
+            // Deliberately wrong vertex normals
+            normals: vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0],
+            indices: vec![0, 1, 2],
+            colors: Vec::new(),
+        };
+        let text = write_ascii(&mesh, "tri");
+        assert!(text.contains("facet normal 0e0 0e0 -1e0"));
+    }
+
+    #[test]
+    fn test_ascii_structure() {
+        let mesh = generate_box_mesh(1.0, 1.0, 1.0);
+        let text = write_ascii(&mesh, "my part");
+        assert!(text.starts_with("solid my_part\n"));
+        assert!(text.ends_with("endsolid my_part\n"));
+        assert_eq!(text.matches("endfacet").count(), 12);
+        assert_eq!(text.matches("vertex").count(), 36);
+    }
+
+    #[test]
+    fn test_binary_round_trip() {
+        let mesh = generate_box_mesh(1.0, 2.0, 3.0);
+        let model = read(&write_binary(&mesh, "box")).unwrap();
+        assert_eq!(model.mesh.triangle_count(), 12);
+        assert_eq!(model.mesh.vertex_count(), 36);
+        assert_eq!(model.mesh.triangle(0), mesh.triangle(0));
+        assert_eq!(&model.mesh.normals[0..3], &[0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_binary_starting_with_solid() {
+        let mesh = generate_box_mesh(1.0, 1.0, 1.0);
+        let mut data = write_binary(&mesh, "");
+        data[..5].copy_from_slice(b"solid");
+        assert_eq!(read(&data).unwrap().mesh.triangle_count(), 12);
+    }
+
+    #[test]
+    fn test_ascii_round_trip() {
+        let mesh = generate_box_mesh(1.0, 2.0, 3.0);
+        let model = read(write_ascii(&mesh, "part").as_bytes()).unwrap();
+        assert_eq!(model.name, "part");
+        assert_eq!(model.mesh.triangle_count(), 12);
+        assert_eq!(model.mesh.triangle(5), mesh.triangle(5));
+    }
+
+    #[test]
+    fn test_ascii_error_reports_line() {
+        let text = "solid t\n facet normal 0 0 1\n  outer loop\n   vertex 0 0 zero\n";
+        let err = read(text.as_bytes()).unwrap_err();
+        assert_eq!(err.location, crate::io::Location::Line(4));
+        assert_eq!(err.to_string(), "line 4: invalid number 'zero'");
+
+        let err = read(b"solid t\n facet normal 0 0 1\n outer loop\n").unwrap_err();
+        assert_eq!(err.location, crate::io::Location::Line(2));
+    }
+
+    #[test]
+    fn test_truncated_binary_reports_offset() {
+        let mesh = generate_box_mesh(1.0, 1.0, 1.0);
+        let data = write_binary(&mesh, "box");
+        let err = read_binary(&data[..84 + 50 * 3 + 10]).unwrap_err();
+        assert_eq!(err.location, crate::io::Location::Byte(84 + 50 * 3));
+    }
+}
@@ -0,0 +1,283 @@
+//! SVG output of technical drawings
+//!
+//! The sheet is drawn one to one in millimeters: visible edges as solid
+//! lines, hidden edges as thinner dashed lines, thin dimensions with filled
+//! arrowheads, and a border with the title block. SVG has Y pointing down,
+//! so sheet coordinates are flipped. Cross-sections are drawn on their own,
+//! also one to one, as filled outlines.
+
+use std::fmt::Write;
+use glam::Vec2;
+use crate::dimension::DraftingStandard;
+use crate::drawing::{Drawing, MARGIN, TITLE_BLOCK_SIZE};
+use crate::io::escape_xml;
+use crate::section::ObjectSection;
+use crate::units::Units;
+
+/// Line widths in millimeters, per ISO 128 for an A3 sheet
+const VISIBLE_WIDTH: f32 = 0.5;
+const HIDDEN_WIDTH: f32 = 0.25;
+const THIN_WIDTH: f32 = 0.25;
+/// Dash and gap lengths of hidden lines
+const HIDDEN_DASHES: &str = "3 1.5";
+/// Space around a rendered section, in millimeters
+const SECTION_MARGIN: f32 = 5.0;
+
+/// Writes SVG elements in sheet coordinates
+struct SvgWriter {
+    out: String,
+    height: f32,
+}
+
+impl SvgWriter {
+    fn point(&self, p: Vec2) -> String {
+        format!("{:.2} {:.2}", p.x, self.height - p.y)
+    }
+
+    /// One path holding all the segments, with the given attributes
+    fn segments(&mut self, segments: &[[Vec2; 2]], attributes: &str) {
+        if segments.is_empty() {
+            return;
+        }
+        let d: Vec<String> = segments.iter().map(|[a, b]| format!("M{}L{}", self.point(*a), self.point(*b))).collect();
+        let _ = writeln!(self.out, "  <path {} d=\"{}\"/>", attributes, d.join(""));
+    }
+
+    fn rect(&mut self, min: Vec2, max: Vec2, stroke_width: f32) {
+        let _ = writeln!(
+            self.out,
+            "  <rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" stroke-width=\"{}\"/>",
+            min.x,
+            self.height - max.y,
+            max.x - min.x,
+            max.y - min.y,
+            stroke_width
+        );
+    }
+
+    /// Text centered on `p`, turned counter-clockwise by `angle` degrees
+    fn centered_text(&mut self, p: Vec2, size: f32, angle: f32, text: &str) {
+        let (x, y) = (p.x, self.height - p.y);
+        let rotate = if angle == 0.0 { String::new() } else { format!(" transform=\"rotate({:.2} {:.2} {:.2})\"", -angle, x, y) };
+        let _ = writeln!(
+            self.out,
+            "  <text x=\"{:.2}\" y=\"{:.2}\" font-size=\"{}\" text-anchor=\"middle\" dominant-baseline=\"central\"{}>{}</text>",
+            x,
+            y,
+            size,
+            rotate,
+            escape_xml(text)
+        );
+    }
+
+    fn text(&mut self, p: Vec2, size: f32, anchor: &str, text: &str) {
+        let _ = writeln!(
+            self.out,
+            "  <text x=\"{:.2}\" y=\"{:.2}\" font-size=\"{}\" text-anchor=\"{}\">{}</text>",
+            p.x,
+            self.height - p.y,
+            size,
+            anchor,
+            escape_xml(text)
+        );
+    }
+}
+
+/// Render a drawing as an SVG document
+pub fn render(drawing: &Drawing) -> String {
+    let size = drawing.options.sheet.size();
+    let mut svg = SvgWriter { out: String::new(), height: size.y };
+    let _ = writeln!(svg.out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
+    let _ = writeln!(
+        svg.out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}mm\" height=\"{h}mm\" viewBox=\"0 0 {w} {h}\">",
+        w = size.x,
+        h = size.y
+    );
+    let _ = writeln!(svg.out, " <title>{}</title>", escape_xml(&drawing.options.title));
+    svg.out.push_str(" <rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n");
+
+    for view in &drawing.views {
+        let _ = writeln!(
+            svg.out,
+            " <g id=\"view-{}\" fill=\"none\" stroke=\"black\" stroke-linecap=\"round\">",
+            view.view.name()
+        );
+        let hidden = format!("class=\"hidden\" stroke-width=\"{}\" stroke-dasharray=\"{}\"", HIDDEN_WIDTH, HIDDEN_DASHES);
+        svg.segments(&view.edges.hidden, &hidden);
+        svg.segments(&view.edges.visible, &format!("class=\"visible\" stroke-width=\"{}\"", VISIBLE_WIDTH));
+        svg.out.push_str(" </g>\n");
+    }
+
+    if !drawing.annotations.is_empty() {
+        svg.out.push_str(" <g id=\"dimensions\" stroke=\"black\" fill=\"black\">\n");
+        for annotation in &drawing.annotations {
+            svg.segments(&annotation.lines, &format!("class=\"dimension\" fill=\"none\" stroke-width=\"{}\"", THIN_WIDTH));
+            for [a, b, c] in &annotation.arrows {
+                let _ = writeln!(svg.out, "  <path stroke=\"none\" d=\"M{}L{}L{}Z\"/>", svg.point(*a), svg.point(*b), svg.point(*c));
+            }
+        }
+        svg.out.push_str(" </g>\n");
+    }
+
+    svg.out.push_str(" <g fill=\"none\" stroke=\"black\">\n");
+    svg.rect(Vec2::splat(MARGIN), size - MARGIN, VISIBLE_WIDTH);
+    let block_min = Vec2::new(size.x - MARGIN - TITLE_BLOCK_SIZE.x, MARGIN);
+    let block_max = block_min + TITLE_BLOCK_SIZE;
+    svg.rect(block_min, block_max, VISIBLE_WIDTH);
+    // A title row over two rows of three fields
+    let row = TITLE_BLOCK_SIZE.y / 4.0;
+    let column = TITLE_BLOCK_SIZE.x / 3.0;
+    let mut rules = vec![
+        [block_min + Vec2::new(0.0, row), block_min + Vec2::new(TITLE_BLOCK_SIZE.x, row)],
+        [block_min + Vec2::new(0.0, 2.0 * row), block_min + Vec2::new(TITLE_BLOCK_SIZE.x, 2.0 * row)],
+    ];
+    for i in 1..3 {
+        let x = block_min.x + column * i as f32;
+        rules.push([Vec2::new(x, block_min.y), Vec2::new(x, block_min.y + 2.0 * row)]);
+    }
+    svg.segments(&rules, &format!("stroke-width=\"{}\"", THIN_WIDTH));
+    svg.out.push_str(" </g>\n");
+
+    svg.out.push_str(" <g font-family=\"sans-serif\" fill=\"black\">\n");
+    for annotation in &drawing.annotations {
+        svg.centered_text(annotation.text_position, annotation.text_height, annotation.text_angle, &annotation.text);
+    }
+    for view in &drawing.views {
+        let label = view.view.name().to_uppercase();
+        svg.text(Vec2::new((view.min.x + view.max.x) / 2.0, view.min.y - 7.0), 3.5, "middle", &label);
+    }
+    svg.text(block_min + Vec2::new(3.0, 2.0 * row + 4.0), 7.0, "start", &drawing.options.title);
+    let projection = match drawing.standard {
+        DraftingStandard::Iso => "ISO first angle",
+        DraftingStandard::Ansi => "ANSI third angle",
+    };
+    let fields = [
+        ("SCALE", drawing.scale_label.clone(), 1),
+        ("UNITS", drawing.units.symbol().to_string(), 1),
+        ("SHEET", drawing.options.sheet.name().to_string(), 1),
+        ("DATE", drawing.options.date.clone(), 0),
+        ("PROJECTION", projection.to_string(), 0),
+        ("SOFTWARE", format!("rustycad {}", env!("CARGO_PKG_VERSION")), 0),
+    ];
+    for (i, (label, value, row_index)) in fields.iter().enumerate() {
+        let corner = block_min + Vec2::new(column * (i % 3) as f32, row * (*row_index as f32 + 1.0));
+        svg.text(corner + Vec2::new(1.5, -2.5), 2.0, "start", label);
+        svg.text(corner + Vec2::new(1.5, -row + 1.5), 3.5, "start", value);
+    }
+    svg.out.push_str(" </g>\n</svg>\n");
+    svg.out
+}
+
+/// Render cross-sections one to one in millimeters, one filled path per
+/// object with holes left open by the even-odd rule
+pub fn render_section(sections: &[ObjectSection], units: Units) -> String {
+    let scale = (units.to_meters() * 1000.0) as f32;
+    let contours = |s: &ObjectSection| {
+        s.regions.iter().flat_map(|r| std::iter::once(&r.outer).chain(&r.holes)).map(|c| c.points.clone()).collect::<Vec<_>>()
+    };
+    let all = sections.iter().flat_map(|s| contours(s).into_iter().chain(s.open.iter().cloned())).flatten();
+    let (min, max) = all.fold((Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)), |(min, max), p| (min.min(p * scale), max.max(p * scale)));
+    let (min, size) = if min.x <= max.x { (min - SECTION_MARGIN, max - min + 2.0 * SECTION_MARGIN) } else { (Vec2::ZERO, Vec2::ZERO) };
+
+    let mut svg = SvgWriter { out: String::new(), height: size.y };
+    let _ = writeln!(svg.out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
+    let _ = writeln!(
+        svg.out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w:.2}mm\" height=\"{h:.2}mm\" viewBox=\"0 0 {w:.2} {h:.2}\">",
+        w = size.x,
+        h = size.y
+    );
+    let _ = writeln!(svg.out, " <g stroke=\"black\" stroke-width=\"{}\" stroke-linejoin=\"round\">", VISIBLE_WIDTH);
+    for section in sections {
+        let path = |points: &[Vec2], close: bool| {
+            let d: Vec<String> = points.iter().map(|p| svg.point(*p * scale - min)).collect();
+            format!("M{}{}", d.join("L"), if close { "Z" } else { "" })
+        };
+        let loops: String = contours(section).iter().map(|c| path(c, true)).collect();
+        let open: String = section.open.iter().map(|c| path(c, false)).collect();
+        let _ = writeln!(svg.out, "  <path id=\"object-{}\" fill=\"#d0d0d0\" fill-rule=\"evenodd\" d=\"{}\"/>", section.object, loops);
+        if !open.is_empty() {
+            let _ = writeln!(svg.out, "  <path class=\"open\" fill=\"none\" d=\"{}\"/>", open);
+        }
+    }
+    svg.out.push_str(" </g>\n</svg>\n");
+    svg.out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::Document;
+    use crate::drawing::{layout, DrawingOptions, Sheet};
+    use crate::object::ShapeType;
+
+    fn sample_svg() -> String {
+        let mut doc = Document::new();
+        doc.add_object(ShapeType::Box { length: 40.0, width: 30.0, height: 20.0 });
+        doc.recompute();
+        let options = DrawingOptions {
+            title: "Bracket <A>".to_string(),
+            date: "2024-05-01".to_string(),
+            sheet: Sheet::A4,
+            dimensions: true,
+        };
+        render(&layout(&doc, &[], options))
+    }
+
+    #[test]
+    fn test_sheet_and_title_block() {
+        let svg = sample_svg();
+        assert!(svg.contains("width=\"297mm\" height=\"210mm\" viewBox=\"0 0 297 210\""));
+        assert!(svg.contains(">Bracket &lt;A&gt;</text>"));
+        assert!(svg.contains(">2024-05-01</text>"));
+        assert!(svg.contains(">1:1</text>"));
+        assert!(svg.contains(">ISO first angle</text>"));
+        for label in ["FRONT", "TOP", "RIGHT", "ISOMETRIC"] {
+            assert!(svg.contains(&format!(">{}</text>", label)), "{}", label);
+        }
+    }
+
+    #[test]
+    fn test_visible_and_hidden_edges() {
+        let svg = sample_svg();
+        assert_eq!(svg.matches("<g id=\"view-").count(), 4);
+        // Only the isometric view of a box has hidden edges
+        assert_eq!(svg.matches("class=\"hidden\"").count(), 1);
+        assert_eq!(svg.matches("class=\"visible\"").count(), 4);
+        let isometric = &svg[svg.find("view-isometric").unwrap()..];
+        let hidden = &isometric[isometric.find("class=\"hidden\"").unwrap()..];
+        let d = &hidden[hidden.find(" d=\"").unwrap()..hidden.find("/>").unwrap()];
+        assert_eq!(d.matches('M').count(), 3);
+        assert!(hidden.contains("stroke-dasharray"));
+    }
+
+    #[test]
+    fn test_dimensions() {
+        let svg = sample_svg();
+        let dimensions = &svg[svg.find("<g id=\"dimensions\"").unwrap()..];
+        let dimensions = &dimensions[..dimensions.find(" </g>").unwrap()];
+        // Three dimensions with two arrowheads each
+        assert_eq!(dimensions.matches("class=\"dimension\"").count(), 3);
+        assert_eq!(dimensions.matches("Z\"/>").count(), 6);
+        assert!(svg.contains("dominant-baseline=\"central\" transform=\"rotate(-90.00"));
+        for value in ["40", "30", "20"] {
+            assert!(svg.contains(&format!(">{}</text>", value)), "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_section() {
+        use crate::section::{section, Plane};
+        use crate::projection::View;
+        let mut doc = Document::new();
+        let tube = doc.add_object(ShapeType::Cylinder { radius: 10.0, height: 40.0 });
+        doc.recompute();
+
+        let svg = render_section(&section(&doc, &[], &Plane::from_view(View::Front, 0.0)), Units::Centimeter);
+        // A 20 x 40 cm cut with a 5 mm margin
+        assert!(svg.contains("width=\"210.00mm\" height=\"410.00mm\""), "{}", svg);
+        assert!(svg.contains(&format!("<path id=\"object-{}\" fill=\"#d0d0d0\" fill-rule=\"evenodd\" d=\"M", tube)));
+        assert!(render_section(&[], Units::Millimeter).contains("viewBox=\"0 0 0.00 0.00\""));
+    }
+}
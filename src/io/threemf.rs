@@ -0,0 +1,210 @@
+//! 3MF export for additive manufacturing
+//!
+//! A 3MF file is a ZIP package holding a content types list, a relationship
+//! to the model part, and the model XML itself. Each object becomes a mesh
+//! resource in local coordinates with a build item carrying its placement,
+//! so slicers see separate parts in the document's units.
+
+use std::fmt::Write;
+use glam::Vec3;
+use crate::document::Document;
+use crate::io::{escape_xml, linear_to_srgb8};
+use crate::io::zip::ZipWriter;
+use crate::units::Units;
+
+/// Path of the model part inside the package
+pub const MODEL_PATH: &str = "3D/3dmodel.model";
+
+const CORE_NAMESPACE: &str = "http://schemas.microsoft.com/3dmanufacturing/core/2015/02";
+
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">
+ <Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>
+ <Default Extension="model" ContentType="application/vnd.ms-package.3dmanufacturing-3dmodel+xml"/>
+</Types>
+"#;
+
+const RELATIONSHIPS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+ <Relationship Target="/3D/3dmodel.model" Id="rel0" Type="http://schemas.microsoft.com/3dmanufacturing/2013/01/3dmodel"/>
+</Relationships>
+"#;
+
+/// Value of the model's `unit` attribute
+fn unit_name(units: Units) -> &'static str {
+    match units {
+        Units::Millimeter => "millimeter",
+        Units::Centimeter => "centimeter",
+        Units::Meter => "meter",
+        Units::Inch => "inch",
+        Units::Foot => "foot",
+    }
+}
+
+/// `#RRGGBB` display color of a linear RGB color
+fn display_color(color: Vec3) -> String {
+    let [r, g, b] = color.to_array().map(linear_to_srgb8);
+    format!("#{:02X}{:02X}{:02X}", r, g, b)
+}
+
+/// Build the model XML for the given objects (all if `ids` is empty)
+///
+/// Objects without a mesh, e.g. because of invalid parameters, are skipped.
+pub fn model_xml(doc: &Document, ids: &[u32]) -> String {
+    let objects: Vec<_> = doc.resolve_ids(ids)
+        .into_iter()
+        .filter_map(|id| doc.get_object(id))
+        .filter(|object| object.mesh.is_some())
+        .collect();
+
+    let mut colors: Vec<String> = Vec::new();
+    let mut resources = String::new();
+    let mut build = String::new();
+    for (index, object) in objects.iter().enumerate() {
+        let mesh = object.mesh.as_ref().unwrap();
+        // Resource id 1 is the material group
+        let resource_id = index + 2;
+
+        let color = display_color(doc.object_color(object.id));
+        let color_index = match colors.iter().position(|c| *c == color) {
+            Some(i) => i,
+            None => {
+                colors.push(color);
+                colors.len() - 1
+            }
+        };
+
+        let (positions, triangles) = mesh.welded();
+        let _ = writeln!(
+            resources,
+            "  <object id=\"{}\" type=\"model\" name=\"{}\" pid=\"1\" pindex=\"{}\">",
+            resource_id,
+            escape_xml(&object.name()),
+            color_index
+        );
+        resources.push_str("   <mesh>\n    <vertices>\n");
+        for p in &positions {
+            let _ = writeln!(resources, "     <vertex x=\"{}\" y=\"{}\" z=\"{}\"/>", p.x, p.y, p.z);
+        }
+        resources.push_str("    </vertices>\n    <triangles>\n");
+        for [a, b, c] in &triangles {
+            let _ = writeln!(resources, "     <triangle v1=\"{}\" v2=\"{}\" v3=\"{}\"/>", a, b, c);
+        }
+        resources.push_str("    </triangles>\n   </mesh>\n  </object>\n");
+
+        // 3MF transforms are row-major 4x3 matrices applied to row vectors,
+        // so the rows are the columns of the placement matrix
+        let matrix = object.placement().to_matrix4();
+        let transform: Vec<String> = (0..4)
+            .flat_map(|col| matrix.col(col).truncate().to_array())
+            .map(|v| v.to_string())
+            .collect();
+        let _ = writeln!(build, "  <item objectid=\"{}\" transform=\"{}\"/>", resource_id, transform.join(" "));
+    }
+
+    let mut xml = String::new();
+    let _ = writeln!(xml, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
+    let _ = writeln!(xml, "<model unit=\"{}\" xml:lang=\"en-US\" xmlns=\"{}\">", unit_name(doc.units), CORE_NAMESPACE);
+    let _ = writeln!(
+        xml,
+        " <metadata name=\"Application\">rustycad {}</metadata>",
+        env!("CARGO_PKG_VERSION")
+    );
+    xml.push_str(" <resources>\n");
+    if !colors.is_empty() {
+        xml.push_str("  <basematerials id=\"1\">\n");
+        for (index, color) in colors.iter().enumerate() {
+            let _ = writeln!(xml, "   <base name=\"Color {}\" displaycolor=\"{}\"/>", index + 1, color);
+        }
+        xml.push_str("  </basematerials>\n");
+    }
+    xml.push_str(&resources);
+    xml.push_str(" </resources>\n <build>\n");
+    xml.push_str(&build);
+    xml.push_str(" </build>\n</model>\n");
+    xml
+}
+
+/// Write the given objects (all if `ids` is empty) as a 3MF package
+pub fn export(doc: &Document, ids: &[u32]) -> Vec<u8> {
+    let mut zip = ZipWriter::new();
+    zip.add_file("[Content_Types].xml", CONTENT_TYPES.as_bytes());
+    zip.add_file("_rels/.rels", RELATIONSHIPS.as_bytes());
+    zip.add_file(MODEL_PATH, model_xml(doc, ids).as_bytes());
+    zip.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Quat;
+    use crate::io::zip::read_stored;
+    use crate::math::Placement;
+    use crate::object::ShapeType;
+    use crate::property::Property;
+
+    fn sample_document() -> Document {
+        let mut doc = Document::new();
+        doc.units = Units::Inch;
+        let a = doc.add_object(ShapeType::Box { length: 1.0, width: 2.0, height: 3.0 });
+        let placement = Placement::from_position_rotation(Vec3::new(5.0, 6.0, 7.0), Quat::from_rotation_z(std::f32::consts::FRAC_PI_2));
+        doc.set_object_property(a, "Placement".to_string(), Property::Placement(placement));
+        doc.set_object_property(a, "Name".to_string(), Property::String("Base & plate".to_string()));
+        doc.set_object_property(a, "Color".to_string(), Property::Vector(Vec3::new(1.0, 0.0, 0.0)));
+        doc.add_object(ShapeType::Sphere { radius: 1.0 });
+        doc.recompute();
+        doc
+    }
+
+    #[test]
+    fn test_package_parts() {
+        let data = export(&sample_document(), &[]);
+        let entries = read_stored(&data).unwrap();
+        let names: Vec<&str> = entries.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["[Content_Types].xml", "_rels/.rels", MODEL_PATH]);
+        let rels = String::from_utf8(entries[1].1.clone()).unwrap();
+        assert!(rels.contains("Target=\"/3D/3dmodel.model\""));
+    }
+
+    #[test]
+    fn test_model_xml() {
+        let xml = model_xml(&sample_document(), &[]);
+        assert!(xml.contains("<model unit=\"inch\""));
+        assert!(xml.contains("name=\"Base &amp; plate\" pid=\"1\" pindex=\"0\""));
+        assert!(xml.contains("<base name=\"Color 1\" displaycolor=\"#FF0000\"/>"));
+        assert!(xml.contains("<base name=\"Color 2\" displaycolor=\"#B1B1B8\"/>"));
+        assert!(xml.contains("name=\"Sphere 2\" pid=\"1\" pindex=\"1\""));
+
+        // Box vertices are welded to its 8 corners
+        let box_part = &xml[xml.find("<object id=\"2\"").unwrap()..xml.find("<object id=\"3\"").unwrap()];
+        assert_eq!(box_part.matches("<vertex ").count(), 8);
+        assert_eq!(box_part.matches("<triangle ").count(), 12);
+    }
+
+    #[test]
+    fn test_build_item_transform() {
+        let xml = model_xml(&sample_document(), &[1]);
+        let start = xml.find("transform=\"").unwrap() + "transform=\"".len();
+        let values: Vec<f32> = xml[start..]
+            .split('"')
+            .next()
+            .unwrap()
+            .split(' ')
+            .map(|v| v.parse().unwrap())
+            .collect();
+        let expected = [0.0, 1.0, 0.0, -1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 5.0, 6.0, 7.0];
+        assert_eq!(values.len(), 12);
+        for (value, expected) in values.iter().zip(expected) {
+            assert!((value - expected).abs() < 1e-6, "{:?}", values);
+        }
+        assert!(!xml.contains("<object id=\"3\""));
+    }
+
+    #[test]
+    fn test_empty_document() {
+        let xml = model_xml(&Document::new(), &[]);
+        assert!(xml.contains("<model unit=\"millimeter\""));
+        assert!(!xml.contains("basematerials"));
+        assert!(xml.contains("<build>\n </build>"));
+    }
+}
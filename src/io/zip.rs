@@ -0,0 +1,170 @@
+//! Minimal ZIP archive writer
+//!
+//! Entries are stored without compression, which every ZIP reader accepts.
+//! That is enough for package formats such as 3MF that use ZIP purely as a
+//! container.
+
+use crate::io::crc32;
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4B50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4B50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4B50;
+/// Version 2.0, the minimum for stored entries in directories
+const VERSION: u16 = 20;
+/// General purpose flag marking entry names as UTF-8
+const FLAG_UTF8: u16 = 1 << 11;
+/// MS-DOS date of 1980-01-01, the earliest representable, so output is reproducible
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+struct Entry {
+    name: String,
+    crc: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// Builds a ZIP archive in memory
+#[derive(Default)]
+pub struct ZipWriter {
+    data: Vec<u8>,
+    entries: Vec<Entry>,
+}
+
+fn put_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+impl ZipWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a file stored under `name`, using `/` as the path separator
+    pub fn add_file(&mut self, name: &str, contents: &[u8]) {
+        let entry = Entry {
+            name: name.to_string(),
+            crc: crc32(contents),
+            size: contents.len() as u32,
+            offset: self.data.len() as u32,
+        };
+
+        let out = &mut self.data;
+        put_u32(out, LOCAL_HEADER_SIGNATURE);
+        put_u16(out, VERSION);
+        put_u16(out, FLAG_UTF8);
+        put_u16(out, 0); // stored
+        put_u16(out, 0); // time
+        put_u16(out, DOS_DATE);
+        put_u32(out, entry.crc);
+        put_u32(out, entry.size);
+        put_u32(out, entry.size);
+        put_u16(out, entry.name.len() as u16);
+        put_u16(out, 0); // extra field length
+        out.extend_from_slice(entry.name.as_bytes());
+        out.extend_from_slice(contents);
+
+        self.entries.push(entry);
+    }
+
+    /// Write the central directory and return the archive
+    pub fn finish(mut self) -> Vec<u8> {
+        let directory_offset = self.data.len() as u32;
+        let out = &mut self.data;
+        for entry in &self.entries {
+            put_u32(out, CENTRAL_HEADER_SIGNATURE);
+            put_u16(out, VERSION); // made by
+            put_u16(out, VERSION); // needed to extract
+            put_u16(out, FLAG_UTF8);
+            put_u16(out, 0); // stored
+            put_u16(out, 0); // time
+            put_u16(out, DOS_DATE);
+            put_u32(out, entry.crc);
+            put_u32(out, entry.size);
+            put_u32(out, entry.size);
+            put_u16(out, entry.name.len() as u16);
+            put_u16(out, 0); // extra field length
+            put_u16(out, 0); // comment length
+            put_u16(out, 0); // disk number
+            put_u16(out, 0); // internal attributes
+            put_u32(out, 0); // external attributes
+            put_u32(out, entry.offset);
+            out.extend_from_slice(entry.name.as_bytes());
+        }
+        let directory_size = out.len() as u32 - directory_offset;
+
+        put_u32(out, END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+        put_u16(out, 0); // this disk
+        put_u16(out, 0); // disk with the central directory
+        put_u16(out, self.entries.len() as u16);
+        put_u16(out, self.entries.len() as u16);
+        put_u32(out, directory_size);
+        put_u32(out, directory_offset);
+        put_u16(out, 0); // comment length
+        self.data
+    }
+}
+
+/// Read the entries of a stored (uncompressed) ZIP archive
+///
+/// Only meant for checking archives written by `ZipWriter`: compressed
+/// entries and data descriptors are not supported.
+pub fn read_stored(data: &[u8]) -> Option<Vec<(String, Vec<u8>)>> {
+    let u16_at = |offset: usize| data.get(offset..offset + 2).map(|b| u16::from_le_bytes([b[0], b[1]]));
+    let u32_at = |offset: usize| data.get(offset..offset + 4).map(|b| u32::from_le_bytes(b.try_into().unwrap()));
+
+    let mut entries = Vec::new();
+    let mut offset = 0;
+    while u32_at(offset)? == LOCAL_HEADER_SIGNATURE {
+        if u16_at(offset + 8)? != 0 {
+            return None;
+        }
+        let crc = u32_at(offset + 14)?;
+        let size = u32_at(offset + 18)? as usize;
+        let name_len = u16_at(offset + 26)? as usize;
+        let extra_len = u16_at(offset + 28)? as usize;
+        let name_start = offset + 30;
+        let contents_start = name_start + name_len + extra_len;
+        let name = std::str::from_utf8(data.get(name_start..name_start + name_len)?).ok()?;
+        let contents = data.get(contents_start..contents_start + size)?;
+        if crc32(contents) != crc {
+            return None;
+        }
+        entries.push((name.to_string(), contents.to_vec()));
+        offset = contents_start + size;
+    }
+    Some(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut zip = ZipWriter::new();
+        zip.add_file("a.txt", b"hello");
+        zip.add_file("dir/b.bin", &[0, 1, 2, 3]);
+        let data = zip.finish();
+
+        let entries = read_stored(&data).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0], ("a.txt".to_string(), b"hello".to_vec()));
+        assert_eq!(entries[1].0, "dir/b.bin");
+
+        // End of central directory record: 22 bytes, entry count at offset 10
+        let eocd = data.len() - 22;
+        assert_eq!(u32::from_le_bytes(data[eocd..eocd + 4].try_into().unwrap()), END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+        assert_eq!(u16::from_le_bytes([data[eocd + 10], data[eocd + 11]]), 2);
+    }
+
+    #[test]
+    fn test_empty_archive() {
+        let data = ZipWriter::new().finish();
+        assert_eq!(data.len(), 22);
+        assert_eq!(read_stored(&data).unwrap().len(), 0);
+    }
+}
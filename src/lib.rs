@@ -1,15 +1,34 @@
-mod math;
-mod property;
-mod geometry;
-mod object;
-mod document;
+pub mod math;
+pub mod property;
+pub mod geometry;
+pub mod object;
+pub mod history;
+pub mod document;
+pub mod units;
+pub mod profile;
+pub mod projection;
+pub mod drawing;
+pub mod dimension;
+pub mod mass;
+pub mod material;
+pub mod bounds;
+pub mod bvh;
+pub mod measure;
+pub mod clash;
+pub mod section;
+pub mod validation;
+pub mod repair;
+pub mod decimate;
+pub mod io;
 
 use wasm_bindgen::prelude::*;
 use document::Document;
-use object::ShapeType;
+use object::{ObjectInfo, ShapeError, ShapeType};
 use property::Property;
 use math::Placement;
-use glam::{Vec3, Quat};
+use units::Units;
+use glam::{Vec3, Vec4, Quat};
+use std::cell::RefCell;
 use std::sync::Mutex;
 use std::collections::HashMap;
 
@@ -17,12 +36,54 @@ use std::collections::HashMap;
 static DOCUMENTS: Mutex<Option<HashMap<u32, Document>>> = Mutex::new(None);
 static mut NEXT_DOC_ID: u32 = 1;
 
+thread_local! {
+    // Message describing why the last failing call returned 0 or false
+    static LAST_ERROR: RefCell<Option<String>> = const { RefCell::new(None) };
+    // JSON report of the last load or import, e.g. applied migrations
+    static LAST_REPORT: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
 #[wasm_bindgen]
 extern "C" {
     #[wasm_bindgen(js_namespace = console)]
     fn log(s: &str);
 }
 
+/// Run a closure against a document, if it exists
+fn with_document<R>(doc_id: u32, f: impl FnOnce(&Document) -> R) -> Option<R> {
+    let docs = DOCUMENTS.lock().unwrap();
+    docs.as_ref()?.get(&doc_id).map(f)
+}
+
+/// Run a closure against a mutable document, if it exists
+fn with_document_mut<R>(doc_id: u32, f: impl FnOnce(&mut Document) -> R) -> Option<R> {
+    let mut docs = DOCUMENTS.lock().unwrap();
+    docs.as_mut()?.get_mut(&doc_id).map(f)
+}
+
+/// Remember an error message for `take_last_error`
+fn set_last_error(message: impl Into<String>) {
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message.into()));
+}
+
+/// Take the message of the last error, clearing it
+#[wasm_bindgen]
+pub fn take_last_error() -> Option<String> {
+    LAST_ERROR.with(|e| e.borrow_mut().take())
+}
+
+/// Remember a report for `take_last_report`
+fn set_last_report(report: &impl serde::Serialize) {
+    let json = serde_json::to_string(report).ok();
+    LAST_REPORT.with(|r| *r.borrow_mut() = json);
+}
+
+/// Take the JSON report of the last load or import, clearing it
+#[wasm_bindgen]
+pub fn take_last_report() -> Option<String> {
+    LAST_REPORT.with(|r| r.borrow_mut().take())
+}
+
 /// Initialize the WASM module
 #[wasm_bindgen]
 pub fn init() {
@@ -35,9 +96,8 @@ pub fn init() {
     }
 }
 
-/// Create a new document and return its ID
-#[wasm_bindgen]
-pub fn create_document() -> u32 {
+/// Store a document and return its new ID
+fn insert_document(doc: Document) -> u32 {
     let mut docs = DOCUMENTS.lock().unwrap();
     if docs.is_none() {
         *docs = Some(HashMap::new());
@@ -49,237 +109,1907 @@ pub fn create_document() -> u32 {
         id
     };
     
-    let doc = Document::new();
     docs.as_mut().unwrap().insert(doc_id, doc);
     
     doc_id
 }
 
+/// Create a new document and return its ID
+#[wasm_bindgen]
+pub fn create_document() -> u32 {
+    insert_document(Document::new())
+}
+
+/// Save a document as native JSON
+///
+/// Returns an empty string if the document does not exist.
+#[wasm_bindgen]
+pub fn save_document(doc_id: u32) -> String {
+    with_document(doc_id, io::native::to_json).unwrap_or_default()
+}
+
+/// Save a document in the compact binary container
+#[wasm_bindgen]
+pub fn save_document_binary(doc_id: u32) -> Vec<u8> {
+    with_document(doc_id, io::native::to_binary).unwrap_or_default()
+}
+
+/// Load a native document (JSON or binary) into a new document and return its ID
+///
+/// Older format versions are upgraded first; `take_last_report` returns the
+/// migration report as `{ from_version, to_version, changes }`.
+/// Returns 0 if the data cannot be loaded; see `take_last_error`.
+#[wasm_bindgen]
+pub fn load_document(data: &[u8]) -> u32 {
+    match io::native::load_with_report(data) {
+        Ok((doc, report)) => {
+            set_last_report(&report);
+            insert_document(doc)
+        }
+        Err(err) => {
+            set_last_error(err.to_string());
+            0
+        }
+    }
+}
+
+/// Get the unit symbol of a document, e.g. "mm"
+#[wasm_bindgen]
+pub fn get_document_units(doc_id: u32) -> Option<String> {
+    with_document(doc_id, |doc| doc.units.symbol().to_string())
+}
+
+/// Set the unit system of a document from its symbol ("mm", "cm", "m", "in", "ft")
+#[wasm_bindgen]
+pub fn set_document_units(doc_id: u32, symbol: &str) -> bool {
+    let Some(units) = Units::from_symbol(symbol) else {
+        set_last_error(format!("Unknown units {}", symbol));
+        return false;
+    };
+    with_document_mut(doc_id, |doc| doc.units = units).is_some()
+}
+
+/// Get the drafting standard of a document's drawings, "ISO" or "ANSI"
+#[wasm_bindgen]
+pub fn get_drafting_standard(doc_id: u32) -> Option<String> {
+    with_document(doc_id, |doc| doc.drafting_standard.name().to_string())
+}
+
+/// Set the drafting standard of a document's drawings, "ISO" or "ANSI", as
+/// one undoable step
+#[wasm_bindgen]
+pub fn set_drafting_standard(doc_id: u32, name: &str) -> bool {
+    let Some(standard) = dimension::DraftingStandard::from_name(name) else {
+        set_last_error(format!("Unknown drafting standard {}", name));
+        return false;
+    };
+    with_document_mut(doc_id, |doc| {
+        doc.open_transaction("Set Drafting Standard");
+        doc.set_drafting_standard(standard);
+        doc.commit_transaction();
+    }).is_some()
+}
+
+/// Add a dimension, given as JSON, to the document's drawings, as one
+/// undoable step
+///
+/// Returns the dimension's index, or -1 if the JSON is not a valid
+/// dimension; see `take_last_error`.
+#[wasm_bindgen]
+pub fn add_dimension(doc_id: u32, dimension_json: &str) -> i32 {
+    let dimension: dimension::Dimension = match serde_json::from_str(dimension_json) {
+        Ok(dimension) => dimension,
+        Err(err) => {
+            set_last_error(format!("Invalid dimension JSON: {}", err));
+            return -1;
+        }
+    };
+    if let Err(err) = dimension.validate() {
+        set_last_error(err);
+        return -1;
+    }
+    with_document_mut(doc_id, |doc| {
+        doc.open_transaction("Add Dimension");
+        let index = doc.add_dimension(dimension);
+        doc.commit_transaction();
+        index as i32
+    }).unwrap_or(-1)
+}
+
+/// Remove the dimension at `index`, as one undoable step
+#[wasm_bindgen]
+pub fn remove_dimension(doc_id: u32, index: usize) -> bool {
+    with_document_mut(doc_id, |doc| {
+        doc.open_transaction("Remove Dimension");
+        let removed = doc.remove_dimension(index);
+        doc.commit_transaction();
+        removed
+    }).unwrap_or(false)
+}
+
+/// Get the document's dimensions as a JSON array
+#[wasm_bindgen]
+pub fn get_dimensions(doc_id: u32) -> Option<String> {
+    with_document(doc_id, |doc| serde_json::to_string(&doc.dimensions).ok()).flatten()
+}
+
+/// List the built-in and user-defined materials as a JSON array
+///
+/// Each entry has a `builtin` flag. Densities are in kg/m³, colors in
+/// linear RGB and mechanical properties in pascals.
+#[wasm_bindgen]
+pub fn list_materials(doc_id: u32) -> Option<String> {
+    with_document(doc_id, |doc| {
+        let builtin = material::library().into_iter().map(|m| (m, true));
+        let user = doc.materials.iter().cloned().map(|m| (m, false));
+        let entries: Vec<serde_json::Value> = builtin
+            .chain(user)
+            .filter_map(|(material, builtin)| {
+                let mut value = serde_json::to_value(material).ok()?;
+                value["builtin"] = builtin.into();
+                Some(value)
+            })
+            .collect();
+        serde_json::to_string(&entries).ok()
+    }).flatten()
+}
+
+/// Add a user-defined material, given as JSON, or replace one with the same
+/// name, as one undoable step
+///
+/// Returns false if the JSON is not a valid material or names a built-in
+/// one; see `take_last_error`.
+#[wasm_bindgen]
+pub fn add_material(doc_id: u32, material_json: &str) -> bool {
+    let material: material::Material = match serde_json::from_str(material_json) {
+        Ok(material) => material,
+        Err(err) => {
+            set_last_error(format!("Invalid material JSON: {}", err));
+            return false;
+        }
+    };
+    with_document_mut(doc_id, |doc| {
+        doc.open_transaction("Add Material");
+        let result = doc.add_material(material);
+        doc.commit_transaction();
+        match result {
+            Ok(()) => true,
+            Err(err) => {
+                set_last_error(err.to_string());
+                false
+            }
+        }
+    }).unwrap_or(false)
+}
+
+/// Remove a user-defined material, as one undoable step
+#[wasm_bindgen]
+pub fn remove_material(doc_id: u32, name: &str) -> bool {
+    with_document_mut(doc_id, |doc| {
+        doc.open_transaction("Remove Material");
+        let removed = doc.remove_material(name);
+        doc.commit_transaction();
+        removed
+    }).unwrap_or(false)
+}
+
+/// Assign a material to an object by name, as one undoable step
+///
+/// An empty name clears the assignment. Returns false if the material is
+/// unknown; see `take_last_error`.
+#[wasm_bindgen]
+pub fn set_object_material(doc_id: u32, obj_id: u32, name: &str) -> bool {
+    with_document_mut(doc_id, |doc| {
+        if doc.get_object(obj_id).is_none() {
+            return false;
+        }
+        let name = match doc.material(name) {
+            Some(material) => material.name,
+            None if name.is_empty() => String::new(),
+            None => {
+                set_last_error(material::MaterialError::Unknown(name.to_string()).to_string());
+                return false;
+            }
+        };
+        doc.open_transaction("Set Material");
+        doc.set_object_property(obj_id, "Material".to_string(), Property::String(name));
+        doc.commit_transaction()
+    }).unwrap_or(false)
+}
+
+/// Get how an object is displayed as JSON: `color` as an sRGB hex string,
+/// and `roughness` and `metalness` from its material
+#[wasm_bindgen]
+pub fn get_object_appearance(doc_id: u32, obj_id: u32) -> Option<String> {
+    with_document(doc_id, |doc| {
+        doc.get_object(obj_id)?;
+        let [r, g, b] = doc.object_color(obj_id).to_array().map(io::linear_to_srgb8);
+        let (metalness, roughness) = doc.object_material(obj_id).map_or((0.0, 0.5), |m| (m.metalness, m.roughness));
+        Some(serde_json::json!({
+            "color": format!("#{:02x}{:02x}{:02x}", r, g, b),
+            "roughness": roughness,
+            "metalness": metalness,
+        }).to_string())
+    }).flatten()
+}
+
 /// Add a box to the document
+///
+/// Returns 0 if the dimensions are invalid; see `take_last_error`.
 #[wasm_bindgen]
 pub fn add_box(doc_id: u32, length: f64, width: f64, height: f64) -> u32 {
-    let mut docs = DOCUMENTS.lock().unwrap();
-    if let Some(docs_map) = docs.as_mut() {
-        if let Some(doc) = docs_map.get_mut(&doc_id) {
-            let shape = ShapeType::Box { length: length as f32, width: width as f32, height: height as f32 };
-            let obj_id = doc.add_object(shape);
-            doc.set_object_property(obj_id, "Length".to_string(), Property::Float(length));
-            doc.set_object_property(obj_id, "Width".to_string(), Property::Float(width));
-            doc.set_object_property(obj_id, "Height".to_string(), Property::Float(height));
-            doc.set_object_property(obj_id, "Placement".to_string(), Property::Placement(Placement::new()));
-            doc.recompute();
-            return obj_id;
-        }
+    let shape = ShapeType::Box { length: length as f32, width: width as f32, height: height as f32 };
+    if let Err(err) = shape.validate() {
+        set_last_error(err.to_string());
+        return 0;
     }
-    0
+    with_document_mut(doc_id, |doc| {
+        doc.open_transaction("Add Box");
+        let obj_id = doc.add_object(shape);
+        doc.set_object_property(obj_id, "Length".to_string(), Property::Float(length));
+        doc.set_object_property(obj_id, "Width".to_string(), Property::Float(width));
+        doc.set_object_property(obj_id, "Height".to_string(), Property::Float(height));
+        doc.set_object_property(obj_id, "Placement".to_string(), Property::Placement(Placement::new()));
+        doc.commit_transaction();
+        doc.recompute();
+        obj_id
+    }).unwrap_or(0)
 }
 
 /// Add a cylinder to the document
+///
+/// Returns 0 if the dimensions are invalid; see `take_last_error`.
 #[wasm_bindgen]
 pub fn add_cylinder(doc_id: u32, radius: f64, height: f64) -> u32 {
-    let mut docs = DOCUMENTS.lock().unwrap();
-    if let Some(docs_map) = docs.as_mut() {
-        if let Some(doc) = docs_map.get_mut(&doc_id) {
-            let shape = ShapeType::Cylinder { radius: radius as f32, height: height as f32 };
-            let obj_id = doc.add_object(shape);
-            doc.set_object_property(obj_id, "Radius".to_string(), Property::Float(radius));
-            doc.set_object_property(obj_id, "Height".to_string(), Property::Float(height));
-            doc.set_object_property(obj_id, "Placement".to_string(), Property::Placement(Placement::new()));
-            doc.recompute();
-            return obj_id;
-        }
+    let shape = ShapeType::Cylinder { radius: radius as f32, height: height as f32 };
+    if let Err(err) = shape.validate() {
+        set_last_error(err.to_string());
+        return 0;
     }
-    0
+    with_document_mut(doc_id, |doc| {
+        doc.open_transaction("Add Cylinder");
+        let obj_id = doc.add_object(shape);
+        doc.set_object_property(obj_id, "Radius".to_string(), Property::Float(radius));
+        doc.set_object_property(obj_id, "Height".to_string(), Property::Float(height));
+        doc.set_object_property(obj_id, "Placement".to_string(), Property::Placement(Placement::new()));
+        doc.commit_transaction();
+        doc.recompute();
+        obj_id
+    }).unwrap_or(0)
 }
 
 /// Add a sphere to the document
+///
+/// Returns 0 if the radius is invalid; see `take_last_error`.
 #[wasm_bindgen]
 pub fn add_sphere(doc_id: u32, radius: f64) -> u32 {
-    let mut docs = DOCUMENTS.lock().unwrap();
-    if let Some(docs_map) = docs.as_mut() {
-        if let Some(doc) = docs_map.get_mut(&doc_id) {
-            let shape = ShapeType::Sphere { radius: radius as f32 };
-            let obj_id = doc.add_object(shape);
-            doc.set_object_property(obj_id, "Radius".to_string(), Property::Float(radius));
-            doc.set_object_property(obj_id, "Placement".to_string(), Property::Placement(Placement::new()));
-            doc.recompute();
-            return obj_id;
-        }
+    let shape = ShapeType::Sphere { radius: radius as f32 };
+    if let Err(err) = shape.validate() {
+        set_last_error(err.to_string());
+        return 0;
     }
-    0
+    with_document_mut(doc_id, |doc| {
+        doc.open_transaction("Add Sphere");
+        let obj_id = doc.add_object(shape);
+        doc.set_object_property(obj_id, "Radius".to_string(), Property::Float(radius));
+        doc.set_object_property(obj_id, "Placement".to_string(), Property::Placement(Placement::new()));
+        doc.commit_transaction();
+        doc.recompute();
+        obj_id
+    }).unwrap_or(0)
 }
 
 /// Restore a box to the document
+///
+/// Invalid dimensions are kept so the object shows up in an error state.
 #[wasm_bindgen]
 pub fn restore_box(doc_id: u32, id: u32, length: f64, width: f64, height: f64) {
-    let mut docs = DOCUMENTS.lock().unwrap();
-    if let Some(docs_map) = docs.as_mut() {
-        if let Some(doc) = docs_map.get_mut(&doc_id) {
-            let shape = ShapeType::Box { length: length as f32, width: width as f32, height: height as f32 };
-            doc.restore_object(id, shape);
-            doc.set_object_property(id, "Length".to_string(), Property::Float(length));
-            doc.set_object_property(id, "Width".to_string(), Property::Float(width));
-            doc.set_object_property(id, "Height".to_string(), Property::Float(height));
-            doc.set_object_property(id, "Placement".to_string(), Property::Placement(Placement::new()));
-            doc.recompute();
-        }
-    }
+    with_document_mut(doc_id, |doc| {
+        let shape = ShapeType::Box { length: length as f32, width: width as f32, height: height as f32 };
+        doc.open_transaction("Restore Object");
+        doc.restore_object(id, shape);
+        doc.set_object_property(id, "Length".to_string(), Property::Float(length));
+        doc.set_object_property(id, "Width".to_string(), Property::Float(width));
+        doc.set_object_property(id, "Height".to_string(), Property::Float(height));
+        doc.set_object_property(id, "Placement".to_string(), Property::Placement(Placement::new()));
+        doc.commit_transaction();
+        doc.recompute();
+    });
 }
 
 /// Restore a cylinder to the document
 #[wasm_bindgen]
 pub fn restore_cylinder(doc_id: u32, id: u32, radius: f64, height: f64) {
-    let mut docs = DOCUMENTS.lock().unwrap();
-    if let Some(docs_map) = docs.as_mut() {
-        if let Some(doc) = docs_map.get_mut(&doc_id) {
-            let shape = ShapeType::Cylinder { radius: radius as f32, height: height as f32 };
-            doc.restore_object(id, shape);
-            doc.set_object_property(id, "Radius".to_string(), Property::Float(radius));
-            doc.set_object_property(id, "Height".to_string(), Property::Float(height));
-            doc.set_object_property(id, "Placement".to_string(), Property::Placement(Placement::new()));
-            doc.recompute();
-        }
-    }
+    with_document_mut(doc_id, |doc| {
+        let shape = ShapeType::Cylinder { radius: radius as f32, height: height as f32 };
+        doc.open_transaction("Restore Object");
+        doc.restore_object(id, shape);
+        doc.set_object_property(id, "Radius".to_string(), Property::Float(radius));
+        doc.set_object_property(id, "Height".to_string(), Property::Float(height));
+        doc.set_object_property(id, "Placement".to_string(), Property::Placement(Placement::new()));
+        doc.commit_transaction();
+        doc.recompute();
+    });
 }
 
 /// Restore a sphere to the document
 #[wasm_bindgen]
 pub fn restore_sphere(doc_id: u32, id: u32, radius: f64) {
-    let mut docs = DOCUMENTS.lock().unwrap();
-    if let Some(docs_map) = docs.as_mut() {
-        if let Some(doc) = docs_map.get_mut(&doc_id) {
-            let shape = ShapeType::Sphere { radius: radius as f32 };
-            doc.restore_object(id, shape);
-            doc.set_object_property(id, "Radius".to_string(), Property::Float(radius));
-            doc.set_object_property(id, "Placement".to_string(), Property::Placement(Placement::new()));
-            doc.recompute();
+    with_document_mut(doc_id, |doc| {
+        let shape = ShapeType::Sphere { radius: radius as f32 };
+        doc.open_transaction("Restore Object");
+        doc.restore_object(id, shape);
+        doc.set_object_property(id, "Radius".to_string(), Property::Float(radius));
+        doc.set_object_property(id, "Placement".to_string(), Property::Placement(Placement::new()));
+        doc.commit_transaction();
+        doc.recompute();
+    });
+}
+
+/// Restore an object from the JSON description produced by `list_objects`
+///
+/// Shape, properties and placement are restored in one step.
+#[wasm_bindgen]
+pub fn restore_object(doc_id: u32, info_json: &str) -> bool {
+    let info: ObjectInfo = match serde_json::from_str(info_json) {
+        Ok(info) => info,
+        Err(err) => {
+            set_last_error(err.to_string());
+            return false;
         }
-    }
+    };
+    with_document_mut(doc_id, |doc| {
+        if !doc.restore_from_info(&info) {
+            set_last_error(format!("Unknown shape type {}", info.type_name));
+            return false;
+        }
+        doc.recompute();
+        true
+    }).unwrap_or(false)
+}
+
+/// List all objects as a JSON array of `{ id, type, params, placement, properties, error }`
+#[wasm_bindgen]
+pub fn list_objects(doc_id: u32) -> String {
+    let infos = with_document(doc_id, |doc| doc.list_objects()).unwrap_or_default();
+    serde_json::to_string(&infos).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// Describe a single object as JSON, in the same shape as `list_objects` entries
+#[wasm_bindgen]
+pub fn get_object_info(doc_id: u32, obj_id: u32) -> Option<String> {
+    with_document(doc_id, |doc| doc.get_object(obj_id).map(|obj| obj.info()))
+        .flatten()
+        .and_then(|info| serde_json::to_string(&info).ok())
 }
 
 /// Update object placement
+///
+/// The rotation quaternion is normalized; zero-length or non-finite ones are
+/// rejected, see `take_last_error`.
 #[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
 pub fn update_placement(doc_id: u32, obj_id: u32, px: f64, py: f64, pz: f64, qx: f64, qy: f64, qz: f64, qw: f64) -> bool {
-    let mut docs = DOCUMENTS.lock().unwrap();
-    if let Some(docs_map) = docs.as_mut() {
-        if let Some(doc) = docs_map.get_mut(&doc_id) {
-            let position = Vec3::new(px as f32, py as f32, pz as f32);
-            let rotation = Quat::from_xyzw(qx as f32, qy as f32, qz as f32, qw as f32);
-            let placement = Placement::from_position_rotation(position, rotation);
-
-            if doc.set_object_property(obj_id, "Placement".to_string(), Property::Placement(placement)) {
-                doc.recompute();
-                return true;
-            }
-        }
+    let position = Vec3::new(px as f32, py as f32, pz as f32);
+    let Some(rotation) = Vec4::new(qx as f32, qy as f32, qz as f32, qw as f32).try_normalize().map(Quat::from_vec4) else {
+        set_last_error("Rotation must be a finite, non-zero quaternion");
+        return false;
+    };
+    if !position.is_finite() {
+        set_last_error("Position must be finite");
+        return false;
     }
-    false
+    with_document_mut(doc_id, |doc| {
+        let placement = Placement::from_position_rotation(position, rotation);
+
+        if doc.set_object_property(obj_id, "Placement".to_string(), Property::Placement(placement)) {
+            doc.recompute();
+            true
+        } else {
+            false
+        }
+    }).unwrap_or(false)
 }
 
 /// Update shape parameters
+///
+/// Invalid parameters are rejected and leave the object unchanged;
+/// see `take_last_error`.
 #[wasm_bindgen]
 pub fn update_shape_params(doc_id: u32, obj_id: u32, p1: f64, p2: f64, p3: f64) -> bool {
-    let mut docs = DOCUMENTS.lock().unwrap();
-    if let Some(docs_map) = docs.as_mut() {
-        if let Some(doc) = docs_map.get_mut(&doc_id) {
-             if let Some(obj) = doc.objects.get_mut(&obj_id) {
-                 match &mut obj.shape_type {
-                    ShapeType::Box { length, width, height } => {
-                        *length = p1 as f32;
-                        *width = p2 as f32;
-                        *height = p3 as f32;
-                        obj.set_property("Length".to_string(), Property::Float(p1));
-                        obj.set_property("Width".to_string(), Property::Float(p2));
-                        obj.set_property("Height".to_string(), Property::Float(p3));
-                    }
-                    ShapeType::Cylinder { radius, height } => {
-                        *radius = p1 as f32;
-                        *height = p2 as f32;
-                        obj.set_property("Radius".to_string(), Property::Float(p1));
-                        obj.set_property("Height".to_string(), Property::Float(p2));
-                    }
-                    ShapeType::Sphere { radius } => {
-                        *radius = p1 as f32;
-                        obj.set_property("Radius".to_string(), Property::Float(p1));
-                    }
-                }
-            } else {
+    with_document_mut(doc_id, |doc| {
+        let Some(obj) = doc.get_object(obj_id) else {
+            return false;
+        };
+        let shape = match obj.shape_type {
+            ShapeType::Box { .. } => ShapeType::Box { length: p1 as f32, width: p2 as f32, height: p3 as f32 },
+            ShapeType::Cylinder { .. } => ShapeType::Cylinder { radius: p1 as f32, height: p2 as f32 },
+            ShapeType::Sphere { .. } => ShapeType::Sphere { radius: p1 as f32 },
+            ShapeType::ImportedMesh { .. } => {
+                set_last_error("Imported meshes have no shape parameters");
                 return false;
             }
-            doc.recompute();
-            return true;
+        };
+        if let Err(err) = shape.validate() {
+            set_last_error(err.to_string());
+            return false;
         }
-    }
-    false
+
+        doc.open_transaction("Edit Parameters");
+        let params: &[(&str, f64)] = match shape {
+            ShapeType::Box { .. } => &[("Length", p1), ("Width", p2), ("Height", p3)],
+            ShapeType::Cylinder { .. } => &[("Radius", p1), ("Height", p2)],
+            ShapeType::Sphere { .. } => &[("Radius", p1)],
+            ShapeType::ImportedMesh { .. } => &[],
+        };
+        for (name, value) in params {
+            doc.set_object_property(obj_id, name.to_string(), Property::Float(*value));
+        }
+        doc.set_object_shape(obj_id, shape);
+        doc.commit_transaction();
+        doc.recompute();
+        true
+    }).unwrap_or(false)
+}
+
+/// Get the error message of an object, if its parameters are invalid
+#[wasm_bindgen]
+pub fn get_object_error(doc_id: u32, obj_id: u32) -> Option<String> {
+    with_document(doc_id, |doc| {
+        doc.get_object(obj_id)
+            .and_then(|obj| obj.error.as_ref())
+            .map(|err| err.to_string())
+    }).flatten()
+}
+
+/// Get all object errors in the document as a JSON array of `{ id, error }`
+#[wasm_bindgen]
+pub fn get_document_errors(doc_id: u32) -> String {
+    let errors = with_document(doc_id, |doc| {
+        doc.errors()
+            .into_iter()
+            .map(|(id, err)| serde_json::json!({ "id": id, "error": err.to_string() }))
+            .collect::<Vec<_>>()
+    }).unwrap_or_default();
+    serde_json::Value::Array(errors).to_string()
 }
 
 /// Get mesh data for an object (interleaved position and normal data)
 #[wasm_bindgen]
 pub fn get_mesh_data(doc_id: u32, obj_id: u32) -> Vec<f32> {
-    let docs = DOCUMENTS.lock().unwrap();
-    if let Some(docs_map) = docs.as_ref() {
-        if let Some(doc) = docs_map.get(&doc_id) {
-            if let Some(buffer) = doc.get_mesh_buffer(obj_id) {
-                return buffer;
-            }
-        }
-    }
-    Vec::new()
+    with_document(doc_id, |doc| doc.get_mesh_buffer(obj_id))
+        .flatten()
+        .unwrap_or_default()
 }
 
 /// Get mesh indices for an object
 #[wasm_bindgen]
 pub fn get_mesh_indices(doc_id: u32, obj_id: u32) -> Vec<u32> {
-    let docs = DOCUMENTS.lock().unwrap();
-    if let Some(docs_map) = docs.as_ref() {
-        if let Some(doc) = docs_map.get(&doc_id) {
-            if let Some(indices) = doc.get_mesh_indices(obj_id) {
-                return indices;
-            }
+    with_document(doc_id, |doc| doc.get_mesh_indices(obj_id))
+        .flatten()
+        .unwrap_or_default()
+}
+
+/// Find the object nearest along a world space ray
+///
+/// `ray_origin` and `ray_dir` have three components each. Returns JSON with
+/// the `object_id`, the hit `distance`, `point` and unit `normal` in world
+/// space, and the index of the `triangle` hit in the object's mesh, or
+/// `None` if nothing is hit.
+#[wasm_bindgen]
+pub fn pick(doc_id: u32, ray_origin: &[f32], ray_dir: &[f32]) -> Option<String> {
+    let ray = match (ray_origin, ray_dir) {
+        (&[ox, oy, oz], &[dx, dy, dz]) => bvh::Ray::new(Vec3::new(ox, oy, oz), Vec3::new(dx, dy, dz)),
+        _ => None,
+    };
+    let Some(ray) = ray else {
+        set_last_error("Ray needs a 3 component origin and a non-zero 3 component direction");
+        return None;
+    };
+    with_document(doc_id, |doc| {
+        let (object_id, hit) = doc.pick(&ray)?;
+        let mut value = serde_json::to_value(hit).ok()?;
+        value["object_id"] = object_id.into();
+        Some(value.to_string())
+    }).flatten()
+}
+
+/// Parse a measurement entity given as JSON
+fn parse_entity(json: &str) -> Option<measure::Entity> {
+    serde_json::from_str(json).map_err(|err| set_last_error(format!("Invalid entity JSON: {}", err))).ok()
+}
+
+/// Serialize a measurement, or record why it could not be taken
+fn measurement_json<T: serde::Serialize>(result: Result<T, measure::MeasureError>) -> Option<String> {
+    match result {
+        Ok(value) => serde_json::to_string(&value).ok(),
+        Err(err) => {
+            set_last_error(err.to_string());
+            None
         }
     }
-    Vec::new()
 }
 
-/// Delete an object from the document
+/// Measure the minimum distance between two entities
+///
+/// Entities are JSON objects with a `type` of `point`, `vertex`, `edge`,
+/// `face` or `object`, e.g. `{"type":"face","object":1,"triangle":4}` with
+/// the triangle returned by `pick`, or `{"type":"edge","object":1,"point":[x,y,z]}`
+/// for the edge nearest a world space point. Returns JSON with the
+/// `distance` and the closest `points` on each entity, or `None`; see
+/// `take_last_error`.
 #[wasm_bindgen]
-pub fn delete_object(doc_id: u32, obj_id: u32) -> bool {
-    let mut docs = DOCUMENTS.lock().unwrap();
-    if let Some(docs_map) = docs.as_mut() {
-        if let Some(doc) = docs_map.get_mut(&doc_id) {
-            return doc.objects.remove(&obj_id).is_some();
+pub fn measure_distance(doc_id: u32, first_json: &str, second_json: &str) -> Option<String> {
+    let (first, second) = (parse_entity(first_json)?, parse_entity(second_json)?);
+    with_document(doc_id, |doc| measurement_json(measure::distance(doc, &first, &second))).flatten()
+}
+
+/// Measure the angle in degrees between two faces, edges or axes
+///
+/// Takes entities as `measure_distance` does. Returns JSON with the angle in
+/// `degrees`, the `directions` compared and `points` on each entity to draw
+/// leaders from, or `None`; see `take_last_error`.
+#[wasm_bindgen]
+pub fn measure_angle(doc_id: u32, first_json: &str, second_json: &str) -> Option<String> {
+    let (first, second) = (parse_entity(first_json)?, parse_entity(second_json)?);
+    with_document(doc_id, |doc| measurement_json(measure::angle(doc, &first, &second))).flatten()
+}
+
+/// Measure the radius and diameter of a circular edge
+///
+/// Returns JSON with the `center`, `axis`, `radius`, `diameter` and a
+/// `point` on the edge, or `None`; see `take_last_error`.
+#[wasm_bindgen]
+pub fn measure_circle(doc_id: u32, entity_json: &str) -> Option<String> {
+    let entity = parse_entity(entity_json)?;
+    with_document(doc_id, |doc| measurement_json(measure::circle(doc, &entity))).flatten()
+}
+
+/// Find pairs of objects whose solids overlap, touch or come within
+/// `clearance` of each other
+///
+/// Checks the given objects, or the whole document if `obj_ids` is empty.
+/// Returns a JSON array of clashes, each with the two `objects`, a `kind` of
+/// `interference`, `contact` or `clearance`, the `distance` between their
+/// surfaces with the nearest `points`, and the estimated shared `volume`.
+/// Returns `None` if the clearance is negative; see `take_last_error`.
+#[wasm_bindgen]
+pub fn check_clashes(doc_id: u32, obj_ids: Vec<u32>, clearance: f64) -> Option<String> {
+    if !(clearance >= 0.0 && clearance.is_finite()) {
+        set_last_error("Clearance must be a number >= 0");
+        return None;
+    }
+    with_document(doc_id, |doc| serde_json::to_string(&clash::find_clashes(doc, &obj_ids, clearance as f32)).ok()).flatten()
+}
+
+/// Get an object's axis-aligned bounding box as `[min x, y, z, max x, y, z]`
+///
+/// In the object's local frame, or in world space if `world` is set.
+/// Returns an empty array if the object does not exist or has no mesh.
+#[wasm_bindgen]
+pub fn get_bounding_box(doc_id: u32, obj_id: u32, world: bool) -> Vec<f32> {
+    with_document(doc_id, |doc| {
+        let obj = doc.get_object(obj_id)?;
+        if world { obj.world_aabb() } else { obj.local_bounds().map(|(aabb, _)| aabb) }
+    })
+    .flatten()
+    .map(|aabb| [aabb.min.to_array(), aabb.max.to_array()].concat())
+    .unwrap_or_default()
+}
+
+/// Get the world space bounding box of objects as `[min x, y, z, max x, y, z]`
+///
+/// Covers the given objects, or the whole document if `obj_ids` is empty,
+/// e.g. for zoom to fit. Returns an empty array if there is nothing to bound.
+#[wasm_bindgen]
+pub fn get_document_bounding_box(doc_id: u32, obj_ids: Vec<u32>) -> Vec<f32> {
+    with_document(doc_id, |doc| doc.bounding_box(&obj_ids))
+        .filter(|aabb| !aabb.is_empty())
+        .map(|aabb| [aabb.min.to_array(), aabb.max.to_array()].concat())
+        .unwrap_or_default()
+}
+
+/// Get an object's oriented bounding box as JSON, e.g. for stock sizes
+///
+/// Has the box's `center`, its `rotation` quaternion and its edge lengths
+/// as `size`, in document units, in the local frame or in world space if
+/// `world` is set. Returns `None` if the object has no mesh.
+#[wasm_bindgen]
+pub fn get_oriented_bounding_box(doc_id: u32, obj_id: u32, world: bool) -> Option<String> {
+    with_document(doc_id, |doc| {
+        let obj = doc.get_object(obj_id)?;
+        let obb = if world { obj.world_obb() } else { obj.local_bounds().map(|(_, obb)| obb) }?;
+        serde_json::to_string(&obb).ok()
+    }).flatten()
+}
+
+/// Check whether an object's mesh bounds a solid, e.g. before printing
+///
+/// Returns JSON with `watertight` and `valid` flags and what spoils them:
+/// `open_edges` and `non_manifold_edges` as pairs of points,
+/// `non_manifold_vertices` as points, `flipped_triangles` and
+/// `degenerate_triangles` as triangle indices, whether the surface is
+/// `inside_out`, and
+/// `self_intersections` as pairs of triangle indices. Returns `None` if the
+/// object has no mesh.
+#[wasm_bindgen]
+pub fn validate_mesh(doc_id: u32, obj_id: u32) -> Option<String> {
+    with_document(doc_id, |doc| {
+        let report = doc.get_object(obj_id)?.mesh.as_ref()?.validate();
+        let mut value = serde_json::to_value(&report).ok()?;
+        value["watertight"] = report.is_watertight().into();
+        value["valid"] = report.is_valid().into();
+        Some(value.to_string())
+    }).flatten()
+}
+
+/// Repair an imported mesh so that it bounds a solid, as one undoable step
+///
+/// Welds vertices within `tolerance`, removes degenerate and duplicate
+/// triangles, fills holes with up to `max_hole_edges` edges, winds the
+/// triangles outwards, and recomputes normals split at faces meeting at
+/// more than `crease_angle` degrees. Returns the JSON report of what
+/// changed, as counts of `welded_vertices`, `degenerate_triangles`,
+/// `duplicate_triangles`, `filled_holes` and `flipped_triangles`, or `None`
+/// if the object is not an imported mesh; see `take_last_error`.
+#[wasm_bindgen]
+pub fn repair_mesh(doc_id: u32, obj_id: u32, tolerance: f32, max_hole_edges: u32, crease_angle: f32) -> Option<String> {
+    with_document_mut(doc_id, |doc| {
+        let Some(ShapeType::ImportedMesh { mesh }) = doc.get_object(obj_id).map(|obj| &obj.shape_type) else {
+            set_last_error("Only imported meshes can be repaired");
+            return None;
+        };
+        let mut mesh = mesh.clone();
+        let options = repair::RepairOptions {
+            tolerance: tolerance.max(0.0),
+            max_hole_edges: max_hole_edges as usize,
+            crease_angle: crease_angle.to_radians(),
+        };
+        let report = mesh.repair(&options);
+        doc.open_transaction("Repair Mesh");
+        doc.set_object_shape(obj_id, ShapeType::ImportedMesh { mesh });
+        doc.commit_transaction();
+        doc.recompute();
+        serde_json::to_string(&report).ok()
+    }).flatten()
+}
+
+/// Simplify an imported mesh, as one undoable step
+///
+/// Collapses edges until no more than `target_triangles` are left or the
+/// next collapse would move the surface further than `max_error` document
+/// units, keeping open boundaries and edges where faces meet at more than
+/// `crease_angle` degrees. Returns the JSON report with `triangles_before`,
+/// `triangles_after` and the `error` reached, or `None` if the object is not
+/// an imported mesh; see `take_last_error`.
+#[wasm_bindgen]
+pub fn decimate_mesh(doc_id: u32, obj_id: u32, target_triangles: u32, max_error: f32, crease_angle: f32) -> Option<String> {
+    with_document_mut(doc_id, |doc| {
+        let Some(ShapeType::ImportedMesh { mesh }) = doc.get_object(obj_id).map(|obj| &obj.shape_type) else {
+            set_last_error("Only imported meshes can be decimated");
+            return None;
+        };
+        let mut mesh = mesh.clone();
+        let options = decimate::DecimateOptions {
+            target_triangles: target_triangles as usize,
+            max_error: max_error.max(0.0),
+            crease_angle: crease_angle.to_radians(),
+        };
+        let report = mesh.decimate(&options);
+        doc.open_transaction("Decimate Mesh");
+        doc.set_object_shape(obj_id, ShapeType::ImportedMesh { mesh });
+        doc.commit_transaction();
+        doc.recompute();
+        serde_json::to_string(&report).ok()
+    }).flatten()
+}
+
+/// Get the combined mass properties of objects as JSON
+///
+/// Measures the given objects, or the whole document if `obj_ids` is empty,
+/// at the density of their "Density" property or material in kg/m³, else
+/// that of water. Lengths are in the document's units, given as `units`,
+/// and masses in kilograms. Returns `None` if an object has no closed mesh;
+/// see `take_last_error`.
+#[wasm_bindgen]
+pub fn get_mass_properties(doc_id: u32, obj_ids: Vec<u32>) -> Option<String> {
+    with_document(doc_id, |doc| match doc.mass_properties(&obj_ids) {
+        Ok(props) => {
+            let mut value = serde_json::to_value(props).ok()?;
+            value["units"] = doc.units.symbol().into();
+            Some(value.to_string())
+        }
+        Err((id, err)) => {
+            set_last_error(format!("Object {}: {}", id, err));
+            None
+        }
+    }).flatten()
+}
+
+/// Add an imported mesh object named `name`, as one undoable step
+fn add_imported_mesh(doc: &mut Document, mesh: geometry::Mesh, name: &str) -> u32 {
+    doc.open_transaction("Import Mesh");
+    let obj_id = doc.add_object(ShapeType::ImportedMesh { mesh });
+    doc.set_object_property(obj_id, "Name".to_string(), Property::String(name.to_string()));
+    doc.set_object_property(obj_id, "Placement".to_string(), Property::Placement(Placement::new()));
+    doc.commit_transaction();
+    obj_id
+}
+
+/// Import a binary or ASCII STL file as a mesh object
+///
+/// Returns 0 if the file cannot be parsed; see `take_last_error`.
+#[wasm_bindgen]
+pub fn import_stl(doc_id: u32, data: &[u8]) -> u32 {
+    let model = match io::stl::read(data) {
+        Ok(model) => model,
+        Err(err) => {
+            set_last_error(format!("STL {}", err));
+            return 0;
+        }
+    };
+    if let Err(err) = (ShapeType::ImportedMesh { mesh: model.mesh.clone() }).validate() {
+        set_last_error(err.to_string());
+        return 0;
+    }
+    with_document_mut(doc_id, |doc| {
+        let name = if model.name.is_empty() { "STL" } else { &model.name };
+        let obj_id = add_imported_mesh(doc, model.mesh, name);
+        doc.recompute();
+        obj_id
+    }).unwrap_or(0)
+}
+
+/// Import a Wavefront OBJ file, creating one mesh object per group
+///
+/// Returns the new object IDs, or an empty list if the file cannot be
+/// parsed; see `take_last_error`.
+#[wasm_bindgen]
+pub fn import_obj(doc_id: u32, text: &str) -> Vec<u32> {
+    let model = match io::obj::read(text) {
+        Ok(model) => model,
+        Err(err) => {
+            set_last_error(format!("OBJ {}", err));
+            return Vec::new();
         }
+    };
+    if model.groups.is_empty() {
+        set_last_error(ShapeError::EmptyMesh.to_string());
+        return Vec::new();
     }
-    false
+    with_document_mut(doc_id, |doc| {
+        doc.open_transaction("Import OBJ");
+        let ids = model.groups
+            .into_iter()
+            .map(|group| add_imported_mesh(doc, group.mesh, &group.name))
+            .collect();
+        doc.commit_transaction();
+        doc.recompute();
+        ids
+    }).unwrap_or_default()
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Import an ASCII or binary PLY file, such as a scan, as a mesh object
+///
+/// Vertex colors are kept. Returns 0 if the file cannot be parsed; see
+/// `take_last_error`.
+#[wasm_bindgen]
+pub fn import_ply(doc_id: u32, data: &[u8]) -> u32 {
+    let mesh = match io::ply::read(data) {
+        Ok(mesh) => mesh,
+        Err(err) => {
+            set_last_error(format!("PLY {}", err));
+            return 0;
+        }
+    };
+    import_single_mesh(doc_id, mesh, "PLY")
+}
 
-    #[test]
-    fn test_document_lifecycle() {
-        init();
-        let doc_id = create_document();
-        assert!(doc_id > 0);
-        
-        let obj_id = add_box(doc_id, 2.0, 3.0, 4.0);
-        assert!(obj_id > 0);
-        
-        let mesh_data = get_mesh_data(doc_id, obj_id);
-        assert!(!mesh_data.is_empty());
-        
-        let indices = get_mesh_indices(doc_id, obj_id);
-        assert_eq!(indices.len(), 36);
+/// Import an OFF file as a mesh object
+///
+/// Returns 0 if the file cannot be parsed; see `take_last_error`.
+#[wasm_bindgen]
+pub fn import_off(doc_id: u32, text: &str) -> u32 {
+    let mesh = match io::off::read(text) {
+        Ok(mesh) => mesh,
+        Err(err) => {
+            set_last_error(format!("OFF {}", err));
+            return 0;
+        }
+    };
+    import_single_mesh(doc_id, mesh, "OFF")
+}
+
+/// Validate a parsed mesh and add it to the document
+fn import_single_mesh(doc_id: u32, mesh: geometry::Mesh, name: &str) -> u32 {
+    if let Err(err) = (ShapeType::ImportedMesh { mesh: mesh.clone() }).validate() {
+        set_last_error(err.to_string());
+        return 0;
     }
+    with_document_mut(doc_id, |doc| {
+        let obj_id = add_imported_mesh(doc, mesh, name);
+        doc.recompute();
+        obj_id
+    }).unwrap_or(0)
+}
 
-    #[test]
-    fn test_new_shapes() {
-        init();
-        let doc_id = create_document();
-        let cyl_id = add_cylinder(doc_id, 1.0, 2.0);
-        assert!(cyl_id > 0);
-        let sph_id = add_sphere(doc_id, 1.0);
-        assert!(sph_id > 0);
+/// Import the solids of a STEP file as objects
+///
+/// Returns the new object IDs, or an empty list if the file cannot be
+/// parsed; see `take_last_error`. Solids that could not be rebuilt and
+/// unsupported entities are listed in the report from `take_last_report`.
+#[wasm_bindgen]
+pub fn import_step(doc_id: u32, text: &str) -> Vec<u32> {
+    with_document_mut(doc_id, |doc| {
+        let imported = match io::step::import(text, doc.units) {
+            Ok(imported) => imported,
+            Err(err) => {
+                set_last_error(format!("STEP {}", err));
+                return Vec::new();
+            }
+        };
+        set_last_report(&imported.report);
+        doc.open_transaction("Import STEP");
+        let ids = imported.parts
+            .into_iter()
+            .map(|part| {
+                let obj_id = doc.add_object(part.shape_type);
+                doc.set_object_property(obj_id, "Name".to_string(), Property::String(part.name));
+                doc.set_object_property(obj_id, "Placement".to_string(), Property::Placement(part.placement));
+                obj_id
+            })
+            .collect();
+        doc.commit_transaction();
+        doc.recompute();
+        ids
+    }).unwrap_or_default()
+}
+
+/// Read a DXF profile and sweep it into a mesh object named `name`
+///
+/// Returns 0 if the file cannot be parsed or the profile cannot be swept;
+/// see `take_last_error`.
+fn add_dxf_solid(
+    doc_id: u32,
+    text: &str,
+    name: &str,
+    sweep: impl FnOnce(&profile::Profile) -> Result<geometry::Mesh, profile::ProfileError>,
+) -> u32 {
+    let model = match io::dxf::read(text) {
+        Ok(model) => model,
+        Err(err) => {
+            set_last_error(format!("DXF {}", err));
+            return 0;
+        }
+    };
+    with_document_mut(doc_id, |doc| {
+        let (profile, report) = model.to_profile(doc.units);
+        set_last_report(&report);
+        let mesh = match sweep(&profile) {
+            Ok(mesh) => mesh,
+            Err(err) => {
+                set_last_error(err.to_string());
+                return 0;
+            }
+        };
+        let obj_id = add_imported_mesh(doc, mesh, name);
+        doc.recompute();
+        obj_id
+    }).unwrap_or(0)
+}
+
+/// Extrude the closed contours of a DXF drawing along +Z by `height`
+///
+/// Contours inside others become holes. Returns the new object ID, or 0 on
+/// failure; see `take_last_error`. Open chains and skipped entities are
+/// listed in the report from `take_last_report`.
+#[wasm_bindgen]
+pub fn extrude_dxf(doc_id: u32, text: &str, height: f32) -> u32 {
+    add_dxf_solid(doc_id, text, "Extrusion", |profile| profile.extrude(height))
+}
+
+/// Revolve the closed contours of a DXF drawing around the Y axis
+///
+/// Returns the new object ID, or 0 on failure; see `take_last_error`.
+/// Open chains and skipped entities are listed in the report from
+/// `take_last_report`.
+#[wasm_bindgen]
+pub fn revolve_dxf(doc_id: u32, text: &str, angle_degrees: f32) -> u32 {
+    add_dxf_solid(doc_id, text, "Revolution", |profile| profile.revolve(angle_degrees.to_radians()))
+}
+
+/// Export objects as STL in world space
+///
+/// Exports the given objects, or the whole document if `obj_ids` is empty,
+/// merged into one solid. Returns an empty buffer if the document does not
+/// exist or an object ID is unknown; see `take_last_error`.
+#[wasm_bindgen]
+pub fn export_stl(doc_id: u32, obj_ids: Vec<u32>, binary: bool) -> Vec<u8> {
+    with_document(doc_id, |doc| {
+        let missing: Vec<String> = obj_ids.iter().filter(|id| doc.get_object(**id).is_none()).map(u32::to_string).collect();
+        if !missing.is_empty() {
+            set_last_error(format!("Unknown object IDs: {}", missing.join(", ")));
+            return Vec::new();
+        }
+        let mesh = doc.combined_world_mesh(&obj_ids);
+        if binary {
+            io::stl::write_binary(&mesh, "Exported by RustyCAD")
+        } else {
+            io::stl::write_ascii(&mesh, "rustycad").into_bytes()
+        }
+    }).unwrap_or_default()
+}
+
+/// Export objects as PLY in world space
+///
+/// Exports the given objects, or the whole document if `obj_ids` is empty,
+/// merged into one mesh. With `colors`, vertices carry their imported
+/// colors or their object's color. Returns an empty buffer if the document
+/// does not exist.
+#[wasm_bindgen]
+pub fn export_ply(doc_id: u32, obj_ids: Vec<u32>, binary: bool, colors: bool) -> Vec<u8> {
+    with_document(doc_id, |doc| {
+        let mut mesh = doc.combined_colored_mesh(&obj_ids);
+        if !colors {
+            mesh.colors.clear();
+        }
+        if binary {
+            io::ply::write_binary(&mesh, "Exported by RustyCAD")
+        } else {
+            io::ply::write_ascii(&mesh, "Exported by RustyCAD").into_bytes()
+        }
+    }).unwrap_or_default()
+}
+
+/// Export objects as OFF in world space
+///
+/// Exports the given objects, or the whole document if `obj_ids` is empty,
+/// merged into one mesh. Returns an empty string if the document does not exist.
+#[wasm_bindgen]
+pub fn export_off(doc_id: u32, obj_ids: Vec<u32>) -> String {
+    with_document(doc_id, |doc| io::off::write(&doc.combined_world_mesh(&obj_ids))).unwrap_or_default()
+}
+
+/// Export objects as a 3MF package for slicers
+///
+/// Exports the given objects, or the whole document if `obj_ids` is empty,
+/// as separate parts in the document's units. Returns an empty buffer if the
+/// document does not exist.
+#[wasm_bindgen]
+pub fn export_3mf(doc_id: u32, obj_ids: Vec<u32>) -> Vec<u8> {
+    with_document(doc_id, |doc| io::threemf::export(doc, &obj_ids)).unwrap_or_default()
+}
+
+/// Export objects as a STEP AP214 assembly
+///
+/// Exports the given objects, or the whole document if `obj_ids` is empty.
+/// `timestamp` is the ISO 8601 date-time written into the file header.
+/// Returns an empty string if the document does not exist.
+#[wasm_bindgen]
+pub fn export_step(doc_id: u32, obj_ids: Vec<u32>, timestamp: &str) -> String {
+    with_document(doc_id, |doc| io::step::export(doc, &obj_ids, "rustycad", timestamp)).unwrap_or_default()
+}
+
+/// Export the cross-section of objects on a plane facing `view` as DXF
+///
+/// The plane lies `offset` from the origin towards the viewer of `view`,
+/// e.g. "front" or "top". Exports the whole document if `obj_ids` is empty.
+/// Returns an empty string if the document does not exist or the view is
+/// unknown; see `take_last_error`.
+#[wasm_bindgen]
+pub fn export_dxf_section(doc_id: u32, obj_ids: Vec<u32>, view: &str, offset: f32) -> String {
+    let Some(view) = projection::View::from_name(view) else {
+        set_last_error(format!("Unknown view '{}'", view));
+        return String::new();
+    };
+    with_document(doc_id, |doc| io::dxf::export_section(doc, &obj_ids, &section::Plane::from_view(view, offset))).unwrap_or_default()
+}
+
+/// Parse a cutting plane from an origin and a normal of three components each
+fn parse_plane(origin: &[f32], normal: &[f32]) -> Option<section::Plane> {
+    let plane = match (origin, normal) {
+        (&[ox, oy, oz], &[nx, ny, nz]) => section::Plane::new(Vec3::new(ox, oy, oz), Vec3::new(nx, ny, nz)),
+        _ => None,
+    };
+    if plane.is_none() {
+        set_last_error("Plane needs a 3 component origin and a non-zero 3 component normal");
+    }
+    plane
+}
+
+/// Cut objects by a plane into closed contours
+///
+/// Cuts the given objects, or the whole document if `obj_ids` is empty.
+/// Returns JSON with the `plane`, its `x_axis` and `y_axis` in world space,
+/// and the `objects` it cuts. Each has its `object` id, the cut `area`, and
+/// `regions` of an `outer` loop with its `holes`, as arrays of 2D points in
+/// the plane's axes; outer loops run counter-clockwise seen from the side the
+/// normal points to. Chains that do not close, where a mesh is open, are
+/// listed in `open`. Returns `None` if the plane is invalid; see
+/// `take_last_error`.
+#[wasm_bindgen]
+pub fn section(doc_id: u32, obj_ids: Vec<u32>, origin: &[f32], normal: &[f32]) -> Option<String> {
+    let plane = parse_plane(origin, normal)?;
+    with_document(doc_id, |doc| {
+        let (x_axis, y_axis) = plane.axes();
+        let json = serde_json::json!({
+            "plane": plane,
+            "x_axis": x_axis,
+            "y_axis": y_axis,
+            "objects": section::section(doc, &obj_ids, &plane),
+        });
+        json.to_string()
+    })
+}
+
+/// Export the cross-section of objects on any plane as DXF
+///
+/// Drawn in the plane's axes as `section` returns them. Exports the whole
+/// document if `obj_ids` is empty. Returns an empty string if the document
+/// does not exist or the plane is invalid; see `take_last_error`.
+#[wasm_bindgen]
+pub fn export_dxf_plane_section(doc_id: u32, obj_ids: Vec<u32>, origin: &[f32], normal: &[f32]) -> String {
+    let Some(plane) = parse_plane(origin, normal) else {
+        return String::new();
+    };
+    with_document(doc_id, |doc| io::dxf::export_section(doc, &obj_ids, &plane)).unwrap_or_default()
+}
+
+/// Export the cross-section of objects on a plane as SVG, one to one in
+/// millimeters
+///
+/// Returns an empty string if the document does not exist or the plane is
+/// invalid; see `take_last_error`.
+#[wasm_bindgen]
+pub fn export_svg_section(doc_id: u32, obj_ids: Vec<u32>, origin: &[f32], normal: &[f32]) -> String {
+    let Some(plane) = parse_plane(origin, normal) else {
+        return String::new();
+    };
+    with_document(doc_id, |doc| io::svg::render_section(&section::section(doc, &obj_ids, &plane), doc.units)).unwrap_or_default()
+}
+
+/// Extrude the cross-section of objects on a plane along its normal
+///
+/// The new mesh object starts on the plane and reaches `height` along the
+/// normal. Returns its ID, or 0 if the plane is invalid or cuts nothing; see
+/// `take_last_error`.
+#[wasm_bindgen]
+pub fn extrude_section(doc_id: u32, obj_ids: Vec<u32>, origin: &[f32], normal: &[f32], height: f32) -> u32 {
+    let Some(plane) = parse_plane(origin, normal) else {
+        return 0;
+    };
+    with_document_mut(doc_id, |doc| {
+        let contours = section::section(doc, &obj_ids, &plane).iter().flat_map(|s| s.profile().contours).collect();
+        let mesh = match profile::Profile::new(contours).extrude(height) {
+            Ok(mesh) => mesh.transformed(&plane.placement()),
+            Err(err) => {
+                set_last_error(err.to_string());
+                return 0;
+            }
+        };
+        let obj_id = add_imported_mesh(doc, mesh, "Section");
+        doc.recompute();
+        obj_id
+    }).unwrap_or(0)
+}
+
+/// Export the outline of objects projected onto `view` as DXF
+///
+/// Exports the whole document if `obj_ids` is empty. Returns an empty
+/// string if the document does not exist or the view is unknown; see
+/// `take_last_error`.
+#[wasm_bindgen]
+pub fn export_dxf_outline(doc_id: u32, obj_ids: Vec<u32>, view: &str) -> String {
+    let Some(view) = projection::View::from_name(view) else {
+        set_last_error(format!("Unknown view '{}'", view));
+        return String::new();
+    };
+    with_document(doc_id, |doc| io::dxf::export_outline(doc, &obj_ids, view)).unwrap_or_default()
+}
+
+/// Export a technical drawing of objects as SVG
+///
+/// Shows the front, top, right and isometric views of the given objects,
+/// or the whole document if `obj_ids` is empty, on a `sheet` such as "A3",
+/// with `title` and `date` in the title block. With `dimensions`, object
+/// parameters are dimensioned along with the document's own dimensions.
+/// Returns an empty string if the document does not exist or the sheet is
+/// unknown; see `take_last_error`.
+#[wasm_bindgen]
+pub fn export_drawing_svg(
+    doc_id: u32,
+    obj_ids: Vec<u32>,
+    title: &str,
+    sheet: &str,
+    date: &str,
+    dimensions: bool,
+) -> String {
+    let Some(sheet) = drawing::Sheet::from_name(sheet) else {
+        set_last_error(format!("Unknown sheet '{}'", sheet));
+        return String::new();
+    };
+    let options = drawing::DrawingOptions { title: title.to_string(), date: date.to_string(), sheet, dimensions };
+    with_document(doc_id, |doc| io::svg::render(&drawing::layout(doc, &obj_ids, options))).unwrap_or_default()
+}
+
+/// Export objects as glTF 2.0 JSON referencing the buffer at `bin_uri`
+///
+/// The buffer itself comes from `export_gltf_bin` with the same objects.
+/// Exports the whole document if `obj_ids` is empty.
+#[wasm_bindgen]
+pub fn export_gltf(doc_id: u32, obj_ids: Vec<u32>, bin_uri: &str) -> String {
+    with_document(doc_id, |doc| io::gltf::to_gltf_json(&io::gltf::export(doc, &obj_ids), bin_uri))
+        .unwrap_or_default()
+}
+
+/// Binary buffer belonging to `export_gltf`
+#[wasm_bindgen]
+pub fn export_gltf_bin(doc_id: u32, obj_ids: Vec<u32>) -> Vec<u8> {
+    with_document(doc_id, |doc| io::gltf::export(doc, &obj_ids).bin).unwrap_or_default()
+}
+
+/// Export objects as a single binary GLB file
+///
+/// Exports the whole document if `obj_ids` is empty.
+#[wasm_bindgen]
+pub fn export_glb(doc_id: u32, obj_ids: Vec<u32>) -> Vec<u8> {
+    with_document(doc_id, |doc| io::gltf::to_glb(&io::gltf::export(doc, &obj_ids))).unwrap_or_default()
+}
+
+/// Delete an object from the document
+#[wasm_bindgen]
+pub fn delete_object(doc_id: u32, obj_id: u32) -> bool {
+    with_document_mut(doc_id, |doc| doc.remove_object(obj_id))
+        .unwrap_or(false)
+}
+
+/// Open a named transaction; changes until `commit_transaction` undo as one step
+#[wasm_bindgen]
+pub fn open_transaction(doc_id: u32, name: &str) -> bool {
+    with_document_mut(doc_id, |doc| doc.open_transaction(name)).is_some()
+}
+
+/// Commit the open transaction
+#[wasm_bindgen]
+pub fn commit_transaction(doc_id: u32) -> bool {
+    with_document_mut(doc_id, |doc| doc.commit_transaction()).unwrap_or(false)
+}
+
+/// Revert the open transaction and return the affected object IDs
+#[wasm_bindgen]
+pub fn abort_transaction(doc_id: u32) -> Vec<u32> {
+    with_document_mut(doc_id, |doc| doc.abort_transaction()).unwrap_or_default()
+}
+
+/// Undo the last transaction and return the affected object IDs
+///
+/// Returns an empty list if there was nothing to undo, or if a transaction
+/// is open; see `take_last_error`.
+#[wasm_bindgen]
+pub fn undo(doc_id: u32) -> Vec<u32> {
+    with_document_mut(doc_id, |doc| {
+        if doc.is_transaction_open() {
+            set_last_error("Cannot undo while a transaction is open");
+            return None;
+        }
+        doc.undo()
+    }).flatten().unwrap_or_default()
+}
+
+/// Redo the last undone transaction and return the affected object IDs
+///
+/// Returns an empty list if there was nothing to redo, or if a transaction
+/// is open; see `take_last_error`.
+#[wasm_bindgen]
+pub fn redo(doc_id: u32) -> Vec<u32> {
+    with_document_mut(doc_id, |doc| {
+        if doc.is_transaction_open() {
+            set_last_error("Cannot redo while a transaction is open");
+            return None;
+        }
+        doc.redo()
+    }).flatten().unwrap_or_default()
+}
+
+/// Name of the transaction that would be undone next
+#[wasm_bindgen]
+pub fn get_undo_name(doc_id: u32) -> Option<String> {
+    with_document(doc_id, |doc| doc.undo_name().map(str::to_string)).flatten()
+}
+
+/// Name of the transaction that would be redone next
+#[wasm_bindgen]
+pub fn get_redo_name(doc_id: u32) -> Option<String> {
+    with_document(doc_id, |doc| doc.redo_name().map(str::to_string)).flatten()
+}
+
+/// Forget the undo and redo history, e.g. after restoring a session
+#[wasm_bindgen]
+pub fn clear_history(doc_id: u32) -> bool {
+    with_document_mut(doc_id, |doc| doc.clear_history()).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_lifecycle() {
+        init();
+        let doc_id = create_document();
+        assert!(doc_id > 0);
+        
+        let obj_id = add_box(doc_id, 2.0, 3.0, 4.0);
+        assert!(obj_id > 0);
+        
+        let mesh_data = get_mesh_data(doc_id, obj_id);
+        assert!(!mesh_data.is_empty());
+        
+        let indices = get_mesh_indices(doc_id, obj_id);
+        assert_eq!(indices.len(), 36);
+    }
+
+    #[test]
+    fn test_new_shapes() {
+        init();
+        let doc_id = create_document();
+        let cyl_id = add_cylinder(doc_id, 1.0, 2.0);
+        assert!(cyl_id > 0);
+        let sph_id = add_sphere(doc_id, 1.0);
+        assert!(sph_id > 0);
+    }
+
+    #[test]
+    fn test_invalid_params_rejected() {
+        init();
+        let doc_id = create_document();
+        assert_eq!(add_sphere(doc_id, 0.0), 0);
+        assert_eq!(take_last_error().as_deref(), Some("Radius must be > 0"));
+        assert_eq!(add_box(doc_id, 1.0, f64::NAN, 1.0), 0);
+        assert_eq!(take_last_error().as_deref(), Some("Width is NaN"));
+
+        let cyl_id = add_cylinder(doc_id, 1.0, 2.0);
+        assert!(!update_shape_params(doc_id, cyl_id, 1.0, f64::INFINITY, 0.0));
+        assert_eq!(take_last_error().as_deref(), Some("Height is infinite"));
+        // The object keeps its previous, valid parameters
+        assert!(!get_mesh_data(doc_id, cyl_id).is_empty());
+        assert!(get_object_error(doc_id, cyl_id).is_none());
+    }
+
+    #[test]
+    fn test_restored_invalid_object_reports_error() {
+        init();
+        let doc_id = create_document();
+        restore_sphere(doc_id, 5, -1.0);
+        let box_id = add_box(doc_id, 1.0, 1.0, 1.0);
+
+        assert_eq!(get_object_error(doc_id, 5).as_deref(), Some("Radius must be > 0"));
+        assert!(get_mesh_data(doc_id, 5).is_empty());
+        assert!(!get_mesh_data(doc_id, box_id).is_empty());
+        assert_eq!(get_document_errors(doc_id), r#"[{"error":"Radius must be > 0","id":5}]"#);
+    }
+
+    #[test]
+    fn test_undo_redo_over_wasm() {
+        init();
+        let doc_id = create_document();
+        let box_id = add_box(doc_id, 1.0, 2.0, 3.0);
+        assert!(update_shape_params(doc_id, box_id, 4.0, 5.0, 6.0));
+        assert!(delete_object(doc_id, box_id));
+        assert!(get_mesh_data(doc_id, box_id).is_empty());
+
+        assert_eq!(get_undo_name(doc_id).as_deref(), Some("Delete Object"));
+        assert_eq!(undo(doc_id), vec![box_id]);
+        assert!(!get_mesh_data(doc_id, box_id).is_empty());
+
+        assert_eq!(get_undo_name(doc_id).as_deref(), Some("Edit Parameters"));
+        assert_eq!(undo(doc_id), vec![box_id]);
+        assert_eq!(get_undo_name(doc_id).as_deref(), Some("Add Box"));
+        assert_eq!(undo(doc_id), vec![box_id]);
+        assert!(undo(doc_id).is_empty());
+        assert!(take_last_error().is_none());
+
+        assert_eq!(redo(doc_id), vec![box_id]);
+        assert_eq!(get_redo_name(doc_id).as_deref(), Some("Edit Parameters"));
+
+        // Open transactions refuse undo and redo with an error
+        assert!(open_transaction(doc_id, "Move"));
+        assert!(undo(doc_id).is_empty());
+        assert_eq!(take_last_error().as_deref(), Some("Cannot undo while a transaction is open"));
+        assert!(redo(doc_id).is_empty());
+        assert_eq!(take_last_error().as_deref(), Some("Cannot redo while a transaction is open"));
+        assert!(commit_transaction(doc_id));
+        assert_eq!(redo(doc_id), vec![box_id]);
+    }
+
+    #[test]
+    fn test_list_and_restore_over_wasm() {
+        init();
+        let doc_id = create_document();
+        let sph_id = add_sphere(doc_id, 2.0);
+        assert!(update_placement(doc_id, sph_id, 1.0, 2.0, 3.0, 0.0, 0.0, 0.0, 1.0));
+
+        let list: serde_json::Value = serde_json::from_str(&list_objects(doc_id)).unwrap();
+        let entry = &list[0];
+        assert_eq!(entry["id"], sph_id);
+        assert_eq!(entry["type"], "Sphere");
+        assert_eq!(entry["params"]["Radius"], 2.0);
+        assert_eq!(entry["placement"]["position"], serde_json::json!([1.0, 2.0, 3.0]));
+
+        let other_id = create_document();
+        assert!(restore_object(other_id, &entry.to_string()));
+        let restored: serde_json::Value = serde_json::from_str(&get_object_info(other_id, sph_id).unwrap()).unwrap();
+        assert_eq!(restored["placement"], entry["placement"]);
+        assert!(!get_mesh_data(other_id, sph_id).is_empty());
+
+        assert!(!restore_object(other_id, "{}"));
+        assert!(take_last_error().is_some());
+    }
+
+    #[test]
+    fn test_save_and_load_over_wasm() {
+        init();
+        let doc_id = create_document();
+        assert!(set_document_units(doc_id, "cm"));
+        let box_id = add_box(doc_id, 1.0, 2.0, 3.0);
+        assert!(update_placement(doc_id, box_id, 4.0, 5.0, 6.0, 0.0, 0.0, 0.0, 1.0));
+
+        let loaded_id = load_document(save_document(doc_id).as_bytes());
+        assert!(loaded_id > 0 && loaded_id != doc_id);
+        assert_eq!(get_document_units(loaded_id).as_deref(), Some("cm"));
+        assert_eq!(get_mesh_indices(loaded_id, box_id).len(), 36);
+        assert_eq!(get_object_info(loaded_id, box_id), get_object_info(doc_id, box_id));
+
+        let binary_id = load_document(&save_document_binary(doc_id));
+        assert!(binary_id > 0);
+        assert_eq!(list_objects(binary_id), list_objects(doc_id));
+
+        let report: serde_json::Value = serde_json::from_str(&take_last_report().unwrap()).unwrap();
+        assert_eq!(report["from_version"], report["to_version"]);
+
+        assert_eq!(load_document(b"garbage"), 0);
+        assert!(take_last_error().is_some());
+    }
+
+    #[test]
+    fn test_export_stl_over_wasm() {
+        init();
+        let doc_id = create_document();
+        let box_id = add_box(doc_id, 1.0, 1.0, 1.0);
+        add_sphere(doc_id, 1.0);
+        assert!(update_placement(doc_id, box_id, 5.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0));
+
+        let single = export_stl(doc_id, vec![box_id], true);
+        assert_eq!(single.len(), 84 + 12 * 50);
+        // First vertex of the first facet is moved by the placement
+        assert_eq!(f32::from_le_bytes(single[96..100].try_into().unwrap()), 4.5);
+
+        let whole = export_stl(doc_id, Vec::new(), true);
+        assert!(whole.len() > single.len());
+
+        let ascii = String::from_utf8(export_stl(doc_id, vec![box_id], false)).unwrap();
+        assert!(ascii.starts_with("solid rustycad"));
+        assert!(export_stl(9999, Vec::new(), true).is_empty());
+        assert!(export_stl(doc_id, vec![box_id, 9998, 9999], true).is_empty());
+        assert_eq!(take_last_error().as_deref(), Some("Unknown object IDs: 9998, 9999"));
+    }
+
+    #[test]
+    fn test_pick_over_wasm() {
+        init();
+        let doc_id = create_document();
+        let box_id = add_box(doc_id, 2.0, 2.0, 2.0);
+        assert!(update_placement(doc_id, box_id, 0.0, 0.0, 5.0, 0.0, 0.0, 0.0, 1.0));
+
+        let hit: serde_json::Value = serde_json::from_str(&pick(doc_id, &[0.5, 0.5, 20.0], &[0.0, 0.0, -2.0]).unwrap()).unwrap();
+        assert_eq!(hit["object_id"], box_id);
+        assert_eq!(hit["distance"], 14.0);
+        assert_eq!(hit["point"], serde_json::json!([0.5, 0.5, 6.0]));
+        assert_eq!(hit["normal"], serde_json::json!([0.0, 0.0, 1.0]));
+        assert!(hit["triangle"].as_u64().unwrap() < 12);
+
+        assert!(pick(doc_id, &[0.5, 0.5, 20.0], &[0.0, 0.0, 1.0]).is_none());
+        assert!(take_last_error().is_none());
+        assert!(pick(doc_id, &[0.0, 0.0], &[0.0, 0.0, 1.0]).is_none());
+        assert!(take_last_error().is_some());
+    }
+
+    #[test]
+    fn test_measurements_over_wasm() {
+        init();
+        let doc_id = create_document();
+        let box_id = add_box(doc_id, 2.0, 2.0, 2.0);
+        let cylinder_id = add_cylinder(doc_id, 1.5, 2.0);
+        assert!(update_placement(doc_id, cylinder_id, 5.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0));
+
+        let object = |id: u32| format!(r#"{{"type":"object","object":{}}}"#, id);
+        let result: serde_json::Value = serde_json::from_str(&measure_distance(doc_id, &object(box_id), &object(cylinder_id)).unwrap()).unwrap();
+        assert!((result["distance"].as_f64().unwrap() - 2.5).abs() < 1e-5);
+        assert_eq!(result["points"].as_array().unwrap().len(), 2);
+
+        let hit: serde_json::Value = serde_json::from_str(&pick(doc_id, &[0.0, 0.0, 5.0], &[0.0, 0.0, -1.0]).unwrap()).unwrap();
+        let top = format!(r#"{{"type":"face","object":{},"triangle":{}}}"#, box_id, hit["triangle"]);
+        let rim = format!(r#"{{"type":"edge","object":{},"point":[6.5,1,0]}}"#, cylinder_id);
+        let result: serde_json::Value = serde_json::from_str(&measure_angle(doc_id, &top, &rim).unwrap()).unwrap();
+        assert!((result["degrees"].as_f64().unwrap() - 90.0).abs() < 1e-3);
+        let result: serde_json::Value = serde_json::from_str(&measure_circle(doc_id, &rim).unwrap()).unwrap();
+        assert!((result["diameter"].as_f64().unwrap() - 3.0).abs() < 1e-4);
+
+        assert!(measure_circle(doc_id, &top).is_none());
+        assert_eq!(take_last_error().unwrap(), "Entity is not a circular edge");
+        assert!(measure_distance(doc_id, "{}", &top).is_none());
+        assert!(take_last_error().unwrap().starts_with("Invalid entity JSON"));
+    }
+
+    #[test]
+    fn test_clashes_over_wasm() {
+        init();
+        let doc_id = create_document();
+        let first = add_box(doc_id, 2.0, 2.0, 2.0);
+        let second = add_box(doc_id, 2.0, 2.0, 2.0);
+        assert!(update_placement(doc_id, second, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0));
+
+        let clashes: serde_json::Value = serde_json::from_str(&check_clashes(doc_id, vec![], 0.0).unwrap()).unwrap();
+        assert_eq!(clashes.as_array().unwrap().len(), 1);
+        assert_eq!(clashes[0]["objects"], serde_json::json!([first, second]));
+        assert_eq!(clashes[0]["kind"], "interference");
+        assert!((clashes[0]["volume"].as_f64().unwrap() - 4.0).abs() < 1e-4);
+
+        assert!(update_placement(doc_id, second, 5.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0));
+        assert_eq!(check_clashes(doc_id, vec![], 1.0).unwrap(), "[]");
+        assert!(check_clashes(doc_id, vec![], -1.0).is_none());
+        assert!(take_last_error().is_some());
+    }
+
+    #[test]
+    fn test_validate_mesh_over_wasm() {
+        init();
+        let doc_id = create_document();
+        let obj_id = add_cylinder(doc_id, 1.0, 2.0);
+
+        let json: serde_json::Value = serde_json::from_str(&validate_mesh(doc_id, obj_id).unwrap()).unwrap();
+        assert_eq!(json["watertight"], true);
+        assert_eq!(json["valid"], true);
+        assert_eq!(json["open_edges"], serde_json::json!([]));
+        assert_eq!(json["inside_out"], false);
+        assert!(validate_mesh(doc_id, 999).is_none());
+    }
+
+    #[test]
+    fn test_repair_mesh_over_wasm() {
+        init();
+        let doc_id = create_document();
+        let mut open = geometry::generate_box_mesh(2.0, 2.0, 2.0);
+        open.indices.truncate(30);
+        let obj_id = import_stl(doc_id, &io::stl::write_binary(&open, ""));
+        let validation = |doc_id, obj_id| serde_json::from_str::<serde_json::Value>(&validate_mesh(doc_id, obj_id).unwrap()).unwrap();
+        assert_eq!(validation(doc_id, obj_id)["watertight"], false);
+
+        let report: serde_json::Value = serde_json::from_str(&repair_mesh(doc_id, obj_id, 0.0, 16, 30.0).unwrap()).unwrap();
+        assert_eq!(report["filled_holes"], 1);
+        assert_eq!(report["welded_vertices"], 30 - 20);
+        assert_eq!(validation(doc_id, obj_id)["valid"], true);
+
+        // One step to undo
+        undo(doc_id);
+        assert_eq!(validation(doc_id, obj_id)["watertight"], false);
+
+        let box_id = add_box(doc_id, 1.0, 1.0, 1.0);
+        assert!(repair_mesh(doc_id, box_id, 0.0, 16, 30.0).is_none());
+        assert_eq!(take_last_error().unwrap(), "Only imported meshes can be repaired");
+    }
+
+    #[test]
+    fn test_decimate_mesh_over_wasm() {
+        init();
+        let doc_id = create_document();
+        let sphere = geometry::generate_sphere_mesh(10.0);
+        let obj_id = import_stl(doc_id, &io::stl::write_binary(&sphere, ""));
+
+        let report: serde_json::Value = serde_json::from_str(&decimate_mesh(doc_id, obj_id, 100, 5.0, 30.0).unwrap()).unwrap();
+        assert_eq!(report["triangles_before"], sphere.triangle_count());
+        assert!(report["triangles_after"].as_u64().unwrap() <= 100);
+        assert!(report["error"].as_f64().unwrap() <= 5.0);
+        assert_eq!(get_mesh_indices(doc_id, obj_id).len(), 3 * report["triangles_after"].as_u64().unwrap() as usize);
+
+        undo(doc_id);
+        assert_eq!(get_mesh_indices(doc_id, obj_id).len(), 3 * sphere.triangle_count());
+
+        let box_id = add_box(doc_id, 1.0, 1.0, 1.0);
+        assert!(decimate_mesh(doc_id, box_id, 0, 1.0, 30.0).is_none());
+        assert_eq!(take_last_error().unwrap(), "Only imported meshes can be decimated");
+    }
+
+    #[test]
+    fn test_sections_over_wasm() {
+        init();
+        let doc_id = create_document();
+        let box_id = add_box(doc_id, 4.0, 2.0, 6.0);
+
+        let json: serde_json::Value = serde_json::from_str(&section(doc_id, vec![], &[0.0, 0.0, 1.0], &[0.0, 0.0, 2.0]).unwrap()).unwrap();
+        assert_eq!(json["plane"]["normal"], serde_json::json!([0.0, 0.0, 1.0]));
+        assert_eq!(json["x_axis"], serde_json::json!([1.0, 0.0, 0.0]));
+        let objects = json["objects"].as_array().unwrap();
+        assert_eq!(objects.len(), 1);
+        assert_eq!(objects[0]["object"], box_id);
+        assert!((objects[0]["area"].as_f64().unwrap() - 8.0).abs() < 1e-4);
+        assert_eq!(objects[0]["regions"][0]["outer"].as_array().unwrap().len(), 4);
+        assert_eq!(objects[0]["regions"][0]["holes"], serde_json::json!([]));
+
+        let dxf = export_dxf_plane_section(doc_id, vec![], &[0.0; 3], &[1.0, 0.0, 0.0]);
+        assert!(dxf.contains("POLYLINE"));
+        let svg = export_svg_section(doc_id, vec![], &[0.0; 3], &[0.0, 1.0, 0.0]);
+        assert!(svg.contains(&format!("id=\"object-{}\"", box_id)));
+
+        // The 4 x 2 cut extruded by 3 from z = 1 is a 24 mm3 solid above the plane
+        let obj_id = extrude_section(doc_id, vec![box_id], &[0.0, 0.0, 1.0], &[0.0, 0.0, 1.0], 3.0);
+        assert_ne!(obj_id, 0);
+        let bounds = get_bounding_box(doc_id, obj_id, true);
+        assert_eq!(bounds[2], 1.0);
+        assert_eq!(bounds[5], 4.0);
+
+        assert!(section(doc_id, vec![], &[0.0; 3], &[0.0; 3]).is_none());
+        assert!(take_last_error().is_some());
+        assert_eq!(extrude_section(doc_id, vec![box_id], &[0.0, 0.0, 10.0], &[0.0, 0.0, 1.0], 3.0), 0);
+        assert_eq!(take_last_error().unwrap(), "Profile has no closed contours");
+    }
+
+    #[test]
+    fn test_placement_rotation_is_normalized() {
+        init();
+        let doc_id = create_document();
+        let box_id = add_box(doc_id, 4.0, 2.0, 1.0);
+        // A quarter turn about Z, scaled up
+        assert!(update_placement(doc_id, box_id, 0.0, 0.0, 0.0, 0.0, 0.0, 2.0, 2.0));
+        let world = get_bounding_box(doc_id, box_id, true);
+        assert!((world[0] + 1.0).abs() < 1e-5 && (world[4] - 2.0).abs() < 1e-5 && (world[5] - 0.5).abs() < 1e-5);
+
+        assert!(!update_placement(doc_id, box_id, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0));
+        assert_eq!(take_last_error().unwrap(), "Rotation must be a finite, non-zero quaternion");
+        assert!(!update_placement(doc_id, box_id, 0.0, 0.0, 0.0, f64::NAN, 0.0, 0.0, 1.0));
+        assert!(take_last_error().is_some());
+        assert!(!update_placement(doc_id, box_id, f64::INFINITY, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0));
+        assert_eq!(take_last_error().unwrap(), "Position must be finite");
+        assert_eq!(get_bounding_box(doc_id, box_id, true), world);
+    }
+
+    #[test]
+    fn test_bounding_boxes_over_wasm() {
+        init();
+        let doc_id = create_document();
+        let box_id = add_box(doc_id, 4.0, 2.0, 1.0);
+        assert_eq!(get_bounding_box(doc_id, box_id, false), vec![-2.0, -1.0, -0.5, 2.0, 1.0, 0.5]);
+        let rotation = Quat::from_rotation_z(std::f32::consts::FRAC_PI_4);
+        assert!(update_placement(doc_id, box_id, 10.0, 0.0, 0.0, rotation.x as f64, rotation.y as f64, rotation.z as f64, rotation.w as f64));
+
+        let world = get_bounding_box(doc_id, box_id, true);
+        let half = 3.0 / 2f32.sqrt();
+        assert!((world[0] - (10.0 - half)).abs() < 1e-4 && (world[3] - (10.0 + half)).abs() < 1e-4);
+        let obb: serde_json::Value = serde_json::from_str(&get_oriented_bounding_box(doc_id, box_id, true).unwrap()).unwrap();
+        let mut size: Vec<f64> = obb["size"].as_array().unwrap().iter().map(|v| v.as_f64().unwrap()).collect();
+        size.sort_by(f64::total_cmp);
+        assert!((size[0] - 1.0).abs() < 1e-4 && (size[1] - 2.0).abs() < 1e-4 && (size[2] - 4.0).abs() < 1e-4);
+        assert!((obb["center"][0].as_f64().unwrap() - 10.0).abs() < 1e-4);
+
+        add_sphere(doc_id, 1.0);
+        let all = get_document_bounding_box(doc_id, Vec::new());
+        assert_eq!(all.len(), 6);
+        assert!((all[0] + 1.0).abs() < 1e-5);
+        assert!(get_document_bounding_box(create_document(), Vec::new()).is_empty());
+        assert!(get_bounding_box(doc_id, 999, true).is_empty());
+    }
+
+    #[test]
+    fn test_mass_properties_over_wasm() {
+        init();
+        let doc_id = create_document();
+        let box_id = add_box(doc_id, 100.0, 100.0, 100.0);
+        with_document_mut(doc_id, |doc| {
+            doc.set_object_property(box_id, "Density".to_string(), Property::Float(7850.0));
+        });
+        assert!(update_placement(doc_id, box_id, 0.0, 0.0, 50.0, 0.0, 0.0, 0.0, 1.0));
+
+        let props: serde_json::Value = serde_json::from_str(&get_mass_properties(doc_id, vec![box_id]).unwrap()).unwrap();
+        assert_eq!(props["units"], "mm");
+        assert!((props["mass"].as_f64().unwrap() - 7.85).abs() < 1e-6);
+        assert!((props["volume"].as_f64().unwrap() - 1e6).abs() < 1e-3);
+        assert!((props["centroid"][2].as_f64().unwrap() - 50.0).abs() < 1e-4);
+        // m (w² + h²) / 12 in kg·mm²
+        let ixx = 7.85 * 20000.0 / 12.0;
+        assert!((props["inertia"][0][0].as_f64().unwrap() - ixx).abs() < 1e-3);
+        assert!((props["principal_moments"][0].as_f64().unwrap() - ixx).abs() < 1e-3);
+
+        // A water-density sphere joins the whole-document total
+        add_sphere(doc_id, 10.0);
+        let total: serde_json::Value = serde_json::from_str(&get_mass_properties(doc_id, Vec::new()).unwrap()).unwrap();
+        assert!(total["mass"].as_f64().unwrap() > 7.85);
+
+        let broken = add_box(doc_id, 1.0, 1.0, 1.0);
+        with_document_mut(doc_id, |doc| {
+            doc.set_object_shape(broken, ShapeType::Box { length: -1.0, width: 1.0, height: 1.0 });
+            doc.recompute();
+        });
+        assert!(get_mass_properties(doc_id, vec![broken]).is_none());
+        assert_eq!(take_last_error(), Some(format!("Object {}: Object has no mesh", broken)));
+    }
+
+    #[test]
+    fn test_materials_over_wasm() {
+        init();
+        let doc_id = create_document();
+        let box_id = add_box(doc_id, 100.0, 100.0, 100.0);
+
+        let materials: serde_json::Value = serde_json::from_str(&list_materials(doc_id).unwrap()).unwrap();
+        let steel = materials.as_array().unwrap().iter().find(|m| m["name"] == "Steel").unwrap();
+        assert_eq!(steel["builtin"], true);
+        assert_eq!(steel["density"], 7850.0);
+
+        assert!(set_object_material(doc_id, box_id, "steel"));
+        assert_eq!(get_undo_name(doc_id).as_deref(), Some("Set Material"));
+        let props: serde_json::Value = serde_json::from_str(&get_mass_properties(doc_id, vec![box_id]).unwrap()).unwrap();
+        assert!((props["mass"].as_f64().unwrap() - 7.85).abs() < 1e-6);
+        let appearance: serde_json::Value = serde_json::from_str(&get_object_appearance(doc_id, box_id).unwrap()).unwrap();
+        assert_eq!(appearance["color"], "#8a8d91");
+        assert_eq!(appearance["metalness"], 1.0);
+
+        let resin = r#"{"name":"Resin","density":1100,"color":[0.2,0.4,0.9],"roughness":0.3,"metalness":0}"#;
+        assert!(add_material(doc_id, resin));
+        assert_eq!(get_undo_name(doc_id).as_deref(), Some("Add Material"));
+        assert!(set_object_material(doc_id, box_id, "Resin"));
+        let props: serde_json::Value = serde_json::from_str(&get_mass_properties(doc_id, vec![box_id]).unwrap()).unwrap();
+        assert!((props["mass"].as_f64().unwrap() - 1.1).abs() < 1e-6);
+
+        // User materials are saved with the document
+        let reloaded = load_document(save_document(doc_id).as_bytes());
+        let materials: serde_json::Value = serde_json::from_str(&list_materials(reloaded).unwrap()).unwrap();
+        assert!(materials.as_array().unwrap().iter().any(|m| m["name"] == "Resin" && m["builtin"] == false));
+
+        assert!(!set_object_material(doc_id, box_id, "Mithril"));
+        assert_eq!(take_last_error().as_deref(), Some("Unknown material 'Mithril'"));
+        assert!(!add_material(doc_id, &resin.replace("Resin", "PLA")));
+        assert_eq!(take_last_error().as_deref(), Some("Material 'PLA' is built in"));
+        assert!(!add_material(doc_id, &resin.replace("1100", "-1")));
+        assert_eq!(take_last_error().as_deref(), Some("Density must be > 0"));
+        assert!(remove_material(doc_id, "resin"));
+        assert_eq!(get_undo_name(doc_id).as_deref(), Some("Remove Material"));
+        undo(doc_id);
+        assert!(list_materials(doc_id).unwrap().contains("\"Resin\""));
+        redo(doc_id);
+        assert!(!list_materials(doc_id).unwrap().contains("\"Resin\""));
+        assert!(!remove_material(doc_id, "Steel"));
+        assert!(set_object_material(doc_id, box_id, ""));
+    }
+
+    #[test]
+    fn test_ply_and_off_over_wasm() {
+        init();
+        let doc_id = create_document();
+        let box_id = add_box(doc_id, 1.0, 2.0, 3.0);
+        with_document_mut(doc_id, |doc| {
+            doc.set_object_property(box_id, "Color".to_string(), Property::Vector(Vec3::new(1.0, 0.0, 0.0)));
+        });
+
+        let ply = export_ply(doc_id, vec![box_id], true, true);
+        let mesh_id = import_ply(doc_id, &ply);
+        assert!(mesh_id > 0);
+        assert_eq!(get_mesh_indices(doc_id, mesh_id).len(), 36);
+        let mesh = with_document(doc_id, |doc| doc.objects[&mesh_id].mesh.clone().unwrap()).unwrap();
+        assert_eq!(mesh.color(0), Some(Vec3::new(1.0, 0.0, 0.0)));
+        let info: serde_json::Value = serde_json::from_str(&get_object_info(doc_id, mesh_id).unwrap()).unwrap();
+        assert_eq!(info["properties"]["Name"]["String"], "PLY");
+        // Imported colors survive a second export
+        let again = String::from_utf8(export_ply(doc_id, vec![mesh_id], false, true)).unwrap();
+        assert!(again.contains(" 255 0 0\n"));
+        assert!(!String::from_utf8(export_ply(doc_id, vec![box_id], false, false)).unwrap().contains("red"));
+
+        let off = export_off(doc_id, vec![box_id]);
+        assert!(off.starts_with("OFF\n8 12 0\n"));
+        let off_id = import_off(doc_id, &off);
+        assert_eq!(get_mesh_indices(doc_id, off_id).len(), 36);
+
+        assert_eq!(import_ply(doc_id, b"ply\nformat binary_big_endian 1.0\nend_header\n"), 0);
+        assert_eq!(take_last_error().as_deref(), Some("PLY line 2: unsupported format binary_big_endian"));
+        assert_eq!(import_off(doc_id, "OFF\n0 0 0\n"), 0);
+        assert!(take_last_error().is_some());
+    }
+
+    #[test]
+    fn test_import_stl_and_obj_over_wasm() {
+        init();
+        let doc_id = create_document();
+        let box_id = add_box(doc_id, 1.0, 2.0, 3.0);
+        let stl = export_stl(doc_id, vec![box_id], false);
+
+        let mesh_id = import_stl(doc_id, &stl);
+        assert!(mesh_id > 0);
+        assert_eq!(get_mesh_indices(doc_id, mesh_id).len(), 36);
+        let info: serde_json::Value = serde_json::from_str(&get_object_info(doc_id, mesh_id).unwrap()).unwrap();
+        assert_eq!(info["type"], "ImportedMesh");
+        assert_eq!(info["properties"]["Name"]["String"], "rustycad");
+        assert!(!update_shape_params(doc_id, mesh_id, 1.0, 1.0, 1.0));
+
+        // Imported meshes are saved with the document
+        let loaded_id = load_document(save_document(doc_id).as_bytes());
+        assert_eq!(get_mesh_indices(loaded_id, mesh_id).len(), 36);
+
+        let obj = "v 0 0 0\nv 1 0 0\nv 0 1 0\ng a\nf 1 2 3\ng b\nf 3 2 1\n";
+        let ids = import_obj(doc_id, obj);
+        assert_eq!(ids.len(), 2);
+        assert_eq!(get_undo_name(doc_id).as_deref(), Some("Import OBJ"));
+
+        assert_eq!(import_stl(doc_id, b"solid x\n facet normal 0 0 1\n"), 0);
+        assert_eq!(take_last_error().as_deref(), Some("STL line 2: facet is not closed"));
+        assert!(import_obj(doc_id, "f 1 2 3\n").is_empty());
+        assert_eq!(take_last_error().as_deref(), Some("OBJ line 1: vertex index 1 is out of range"));
+    }
+
+    #[test]
+    fn test_export_gltf_over_wasm() {
+        init();
+        let doc_id = create_document();
+        let box_id = add_box(doc_id, 1.0, 1.0, 1.0);
+        add_sphere(doc_id, 1.0);
+
+        let json: serde_json::Value = serde_json::from_str(&export_gltf(doc_id, vec![box_id], "box.bin")).unwrap();
+        assert_eq!(json["buffers"][0]["uri"], "box.bin");
+        assert_eq!(json["buffers"][0]["byteLength"], export_gltf_bin(doc_id, vec![box_id]).len());
+
+        let glb = export_glb(doc_id, Vec::new());
+        assert_eq!(&glb[0..4], b"glTF");
+        assert!(export_glb(9999, Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn test_export_3mf_over_wasm() {
+        init();
+        let doc_id = create_document();
+        let box_id = add_box(doc_id, 1.0, 1.0, 1.0);
+        add_sphere(doc_id, 1.0);
+
+        let entries = io::zip::read_stored(&export_3mf(doc_id, vec![box_id])).unwrap();
+        let model = String::from_utf8(entries[2].1.clone()).unwrap();
+        assert_eq!(model.matches("<item ").count(), 1);
+
+        let entries = io::zip::read_stored(&export_3mf(doc_id, Vec::new())).unwrap();
+        let model = String::from_utf8(entries[2].1.clone()).unwrap();
+        assert_eq!(model.matches("<item ").count(), 2);
+        assert!(export_3mf(9999, Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn test_export_step_over_wasm() {
+        init();
+        let doc_id = create_document();
+        let box_id = add_box(doc_id, 1.0, 1.0, 1.0);
+        add_cylinder(doc_id, 1.0, 2.0);
+
+        let step = export_step(doc_id, vec![box_id], "2024-01-01T00:00:00");
+        assert!(step.starts_with("ISO-10303-21;"));
+        assert!(step.contains("'2024-01-01T00:00:00'"));
+        assert_eq!(step.matches("NEXT_ASSEMBLY_USAGE_OCCURRENCE(").count(), 1);
+
+        let step = export_step(doc_id, Vec::new(), "");
+        assert_eq!(step.matches("NEXT_ASSEMBLY_USAGE_OCCURRENCE(").count(), 2);
+        assert!(export_step(9999, Vec::new(), "").is_empty());
+    }
+
+    #[test]
+    fn test_import_step_over_wasm() {
+        init();
+        let doc_id = create_document();
+        let box_id = add_box(doc_id, 1.0, 2.0, 3.0);
+        add_sphere(doc_id, 1.0);
+        let step = export_step(doc_id, Vec::new(), "");
+
+        let target = create_document();
+        let ids = import_step(target, &step);
+        assert_eq!(ids.len(), 2);
+        let info: serde_json::Value = serde_json::from_str(&get_object_info(target, ids[0]).unwrap()).unwrap();
+        assert_eq!(info["type"], "Box");
+        assert_eq!(info["properties"]["Name"]["String"], format!("Box {}", box_id));
+        let report: serde_json::Value = serde_json::from_str(&take_last_report().unwrap()).unwrap();
+        assert_eq!(report["imported"], 2);
+
+        // One undo removes the whole import
+        assert_eq!(undo(target).len(), 2);
+        assert!(import_step(target, "not a step file").is_empty());
+        assert!(take_last_error().unwrap().starts_with("STEP line 1"));
+    }
+
+    #[test]
+    fn test_dxf_round_trip_over_wasm() {
+        init();
+        let doc_id = create_document();
+        add_box(doc_id, 4.0, 2.0, 6.0);
+
+        let section = export_dxf_section(doc_id, Vec::new(), "front", 0.0);
+        assert!(section.contains("SECTION\n"));
+        assert!(!export_dxf_outline(doc_id, Vec::new(), "top").is_empty());
+        assert!(export_dxf_outline(doc_id, Vec::new(), "sideways").is_empty());
+        assert_eq!(take_last_error().unwrap(), "Unknown view 'sideways'");
+
+        // The 4 x 2 section extruded by 3 is a 24 mm3 solid
+        let target = create_document();
+        let obj_id = extrude_dxf(target, &section, 3.0);
+        assert_ne!(obj_id, 0);
+        let info: serde_json::Value = serde_json::from_str(&get_object_info(target, obj_id).unwrap()).unwrap();
+        assert_eq!(info["properties"]["Name"]["String"], "Extrusion");
+        let report: serde_json::Value = serde_json::from_str(&take_last_report().unwrap()).unwrap();
+        assert_eq!(report["contours"], 1);
+
+        // The section is centered on the axis, so it cannot be revolved
+        assert_eq!(revolve_dxf(target, &section, 360.0), 0);
+        assert_eq!(take_last_error().unwrap(), "Profile crosses the axis of revolution");
+        assert_eq!(extrude_dxf(target, &section, -1.0), 0);
+        assert_eq!(extrude_dxf(target, "0\nLINE\n10", 1.0), 0);
+        assert!(take_last_error().unwrap().starts_with("DXF line"));
+    }
+
+    #[test]
+    fn test_export_drawing_over_wasm() {
+        init();
+        let doc_id = create_document();
+        add_box(doc_id, 10.0, 20.0, 30.0);
+
+        let svg = export_drawing_svg(doc_id, Vec::new(), "Block", "a4", "2024-01-01", false);
+        assert!(svg.starts_with("<?xml"));
+        assert!(svg.contains("<title>Block</title>"));
+        assert!(export_drawing_svg(doc_id, Vec::new(), "Block", "B7", "", false).is_empty());
+        assert_eq!(take_last_error().unwrap(), "Unknown sheet 'B7'");
+        assert!(export_drawing_svg(9999, Vec::new(), "", "A3", "", false).is_empty());
+    }
+
+    #[test]
+    fn test_dimensions_over_wasm() {
+        init();
+        let doc_id = create_document();
+        add_box(doc_id, 10.0, 20.0, 30.0);
+        assert_eq!(get_drafting_standard(doc_id).as_deref(), Some("ISO"));
+        assert!(set_drafting_standard(doc_id, "ansi"));
+        assert!(!set_drafting_standard(doc_id, "DIN"));
+        assert_eq!(take_last_error().unwrap(), "Unknown drafting standard DIN");
+
+        let json = r#"{"Linear":{"start":[0,0,0],"end":[5,0,0]}}"#;
+        assert_eq!(add_dimension(doc_id, json), 0);
+        assert_eq!(add_dimension(doc_id, r#"{"Linear":{"start":[0,0,0],"end":[0,0,0]}}"#), -1);
+        assert_eq!(add_dimension(doc_id, "{}"), -1);
+        assert!(take_last_error().unwrap().starts_with("Invalid dimension JSON"));
+        let svg = export_drawing_svg(doc_id, Vec::new(), "", "A4", "", true);
+        for text in [">5</text>", ">10</text>", ">20</text>", ">30</text>"] {
+            assert!(svg.contains(text), "{}", text);
+        }
+
+        // Dimensions and the standard are saved with the document
+        let loaded_id = load_document(save_document(doc_id).as_bytes());
+        assert_eq!(get_drafting_standard(loaded_id).as_deref(), Some("ANSI"));
+        let dimensions: serde_json::Value = serde_json::from_str(&get_dimensions(loaded_id).unwrap()).unwrap();
+        assert_eq!(dimensions[0]["Linear"]["end"], serde_json::json!([5.0, 0.0, 0.0]));
+
+        assert!(remove_dimension(doc_id, 0));
+        assert!(!remove_dimension(doc_id, 0));
+        assert_eq!(get_dimensions(doc_id).as_deref(), Some("[]"));
+    }
+
+    #[test]
+    fn test_drawing_edits_are_undoable() {
+        init();
+        let doc_id = create_document();
+        assert!(set_drafting_standard(doc_id, "ANSI"));
+        assert_eq!(get_undo_name(doc_id).as_deref(), Some("Set Drafting Standard"));
+        let json = r#"{"Linear":{"start":[0,0,0],"end":[5,0,0]}}"#;
+        assert_eq!(add_dimension(doc_id, json), 0);
+        assert_eq!(get_undo_name(doc_id).as_deref(), Some("Add Dimension"));
+        assert!(remove_dimension(doc_id, 0));
+        assert_eq!(get_undo_name(doc_id).as_deref(), Some("Remove Dimension"));
+
+        undo(doc_id);
+        let dimensions: serde_json::Value = serde_json::from_str(&get_dimensions(doc_id).unwrap()).unwrap();
+        assert_eq!(dimensions.as_array().unwrap().len(), 1);
+        undo(doc_id);
+        assert_eq!(get_dimensions(doc_id).as_deref(), Some("[]"));
+        undo(doc_id);
+        assert_eq!(get_drafting_standard(doc_id).as_deref(), Some("ISO"));
+        redo(doc_id);
+        assert_eq!(get_drafting_standard(doc_id).as_deref(), Some("ANSI"));
     }
 }
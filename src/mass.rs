@@ -0,0 +1,244 @@
+//! Mass properties of solids
+//!
+//! Volume, center of mass and inertia are integrated exactly over the
+//! triangles of a closed mesh, by summing signed tetrahedra from the origin
+//! to each triangle. Curved primitives are measured on their tessellation.
+//! Lengths are in document units and masses in kilograms; densities are
+//! given in kilograms per cubic meter and converted.
+
+use std::fmt;
+use glam::{DMat3, DVec3};
+use serde::Serialize;
+use crate::geometry::{mesh_edges, Mesh};
+use crate::math::symmetric_eigen;
+use crate::units::Units;
+
+/// Density of objects without a "Density" property, that of water, in kg/m³
+pub const DEFAULT_DENSITY: f64 = 1000.0;
+
+/// Why mass properties cannot be computed
+#[derive(Debug, Clone, PartialEq)]
+pub enum MassError {
+    /// The object has no mesh, e.g. because its parameters are invalid
+    NoMesh,
+    /// The mesh has this many edges not shared by exactly two triangles
+    NotClosed(usize),
+    /// The density, in kg/m³, is not a finite number above zero
+    InvalidDensity(f64),
+}
+
+impl fmt::Display for MassError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MassError::NoMesh => write!(f, "Object has no mesh"),
+            MassError::NotClosed(count) => write!(f, "Mesh is not closed: {} open or non-manifold edges", count),
+            MassError::InvalidDensity(density) => write!(f, "Density must be a finite number > 0, not {}", density),
+        }
+    }
+}
+
+impl std::error::Error for MassError {}
+
+/// Mass properties of one or more solids
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct MassProperties {
+    /// Cubic document units
+    pub volume: f64,
+    /// Square document units
+    pub area: f64,
+    /// Kilograms
+    pub mass: f64,
+    /// Center of mass
+    pub centroid: DVec3,
+    /// Inertia tensor about the center of mass in kg·unit², by rows
+    pub inertia: [[f64; 3]; 3],
+    /// Principal moments of inertia, smallest first
+    pub principal_moments: DVec3,
+    /// Unit principal axes matching the moments, forming a right-handed frame
+    pub principal_axes: [DVec3; 3],
+}
+
+impl MassProperties {
+    fn new(volume: f64, area: f64, mass: f64, centroid: DVec3, inertia: DMat3) -> Self {
+        let (principal_moments, axes) = symmetric_eigen(inertia);
+        Self {
+            volume,
+            area,
+            mass,
+            centroid,
+            inertia: inertia.to_cols_array_2d(),
+            principal_moments,
+            principal_axes: [axes.x_axis, axes.y_axis, axes.z_axis],
+        }
+    }
+
+    /// Integrate over a closed mesh of uniform density, in kg per cubic unit
+    ///
+    /// Meshes with inward facing triangles are measured as if they were
+    /// turned outward.
+    pub fn from_mesh(mesh: &Mesh, density: f64) -> Result<Self, MassError> {
+        let (positions, triangles) = mesh.welded();
+        let open = mesh_edges(&triangles).iter().filter(|e| e.triangles.len() != 2).count();
+        if triangles.is_empty() || open > 0 {
+            return Err(MassError::NotClosed(open));
+        }
+
+        let mut volume = 0.0;
+        let mut area = 0.0;
+        let mut moment = DVec3::ZERO;
+        // Second moments ∫ x xᵀ dV about the origin
+        let mut second = DMat3::ZERO;
+        for triangle in &triangles {
+            let [a, b, c] = triangle.map(|i| positions[i as usize].as_dvec3());
+            let det = a.dot(b.cross(c));
+            let sum = a + b + c;
+            volume += det / 6.0;
+            area += (b - a).cross(c - a).length() / 2.0;
+            moment += det / 24.0 * sum;
+            let outer = |v: DVec3| DMat3::from_cols(v * v.x, v * v.y, v * v.z);
+            second += (outer(a) + outer(b) + outer(c) + outer(sum)) * (det / 120.0);
+        }
+        if volume < 0.0 {
+            volume = -volume;
+            moment = -moment;
+            second = -second;
+        }
+
+        let centroid = if volume > 0.0 { moment / volume } else { DVec3::ZERO };
+        let about_centroid = second - DMat3::from_cols(centroid * centroid.x, centroid * centroid.y, centroid * centroid.z) * volume;
+        let trace = about_centroid.x_axis.x + about_centroid.y_axis.y + about_centroid.z_axis.z;
+        let inertia = (DMat3::from_diagonal(DVec3::splat(trace)) - about_centroid) * density;
+        Ok(Self::new(volume, area, volume * density, centroid, inertia))
+    }
+
+    /// Mass properties of an object's mesh, with a density in kg/m³
+    pub fn from_mesh_in_units(mesh: &Mesh, density: f64, units: Units) -> Result<Self, MassError> {
+        Self::from_mesh(mesh, density * units.to_meters().powi(3))
+    }
+
+    /// Mass properties of several solids together, by the parallel axis theorem
+    ///
+    /// The solids are assumed not to overlap.
+    pub fn combine(parts: &[MassProperties]) -> Self {
+        let volume: f64 = parts.iter().map(|p| p.volume).sum();
+        let mass: f64 = parts.iter().map(|p| p.mass).sum();
+        // Weight by volume when nothing has mass, so the centroid stays meaningful
+        let weight = |p: &MassProperties| if mass > 0.0 { p.mass / mass } else if volume > 0.0 { p.volume / volume } else { 0.0 };
+        let centroid = parts.iter().map(|p| p.centroid * weight(p)).sum();
+        let mut inertia = DMat3::ZERO;
+        for part in parts {
+            let d: DVec3 = part.centroid - centroid;
+            let shift = DMat3::from_diagonal(DVec3::splat(d.length_squared())) - DMat3::from_cols(d * d.x, d * d.y, d * d.z);
+            inertia += DMat3::from_cols_array_2d(&part.inertia) + shift * part.mass;
+        }
+        Self::new(volume, parts.iter().map(|p| p.area).sum(), mass, centroid, inertia)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+    use glam::{Quat, Vec3};
+    use crate::geometry::{generate_box_mesh, generate_cylinder_mesh, generate_sphere_mesh};
+    use crate::math::Placement;
+
+    fn close(a: f64, b: f64, relative: f64) -> bool {
+        (a - b).abs() <= relative * b.abs().max(1e-12)
+    }
+
+    #[test]
+    fn test_box_matches_closed_form() {
+        let (l, w, h) = (4.0, 2.0, 1.0);
+        let props = MassProperties::from_mesh(&generate_box_mesh(l, w, h), 2.0).unwrap();
+        let m = 2.0 * l * w * h;
+        assert!(close(props.volume, l * w * h, 1e-6));
+        assert!(close(props.area, 2.0 * (l * w + w * h + h * l), 1e-6));
+        assert!(close(props.mass, m, 1e-6));
+        assert!(props.centroid.length() < 1e-6);
+        let expected = [m * (w * w + h * h) / 12.0, m * (l * l + h * h) / 12.0, m * (l * l + w * w) / 12.0];
+        for (i, row) in props.inertia.iter().enumerate() {
+            for (j, value) in row.iter().enumerate() {
+                assert!(if i == j { close(*value, expected[i], 1e-6) } else { value.abs() < 1e-5 });
+            }
+        }
+        // The long side is the axis of least inertia
+        assert!(close(props.principal_moments.x, expected[0], 1e-6));
+        assert!(props.principal_axes[0].x.abs() > 0.999999);
+    }
+
+    #[test]
+    fn test_cylinder_and_sphere_match_closed_form() {
+        // Within the error of the 32-sided tessellation
+        let (r, h) = (2.0, 5.0);
+        let props = MassProperties::from_mesh(&generate_cylinder_mesh(r, h), 1.0).unwrap();
+        let m = PI * r * r * h;
+        assert!(close(props.volume, m, 0.01));
+        assert!(close(props.area, 2.0 * PI * r * (r + h), 0.01));
+        let axial = m * r * r / 2.0;
+        let transverse = m * (3.0 * r * r + h * h) / 12.0;
+        let moments = props.principal_moments.to_array();
+        assert!(moments.iter().filter(|i| close(**i, transverse, 0.02)).count() == 2, "{:?}", moments);
+        assert!(moments.iter().any(|i| close(*i, axial, 0.02)), "{:?}", moments);
+
+        let props = MassProperties::from_mesh(&generate_sphere_mesh(3.0), 1.0).unwrap();
+        let m = 4.0 / 3.0 * PI * 27.0;
+        assert!(close(props.volume, m, 0.02));
+        assert!(close(props.area, 4.0 * PI * 9.0, 0.02));
+        assert!(props.centroid.length() < 1e-5);
+        for moment in props.principal_moments.to_array() {
+            assert!(close(moment, 0.4 * m * 9.0, 0.03));
+        }
+    }
+
+    #[test]
+    fn test_placement_moves_and_turns_the_tensor() {
+        let mesh = generate_box_mesh(4.0, 2.0, 1.0);
+        let local = MassProperties::from_mesh(&mesh, 1.0).unwrap();
+        let placement = Placement::from_position_rotation(Vec3::new(10.0, -5.0, 2.0), Quat::from_rotation_z(std::f32::consts::FRAC_PI_2));
+        let world = MassProperties::from_mesh(&mesh.transformed(&placement), 1.0).unwrap();
+        assert!((world.centroid - DVec3::new(10.0, -5.0, 2.0)).length() < 1e-5);
+        // Turning the box a quarter turn about Z swaps the X and Y moments
+        assert!(close(world.inertia[0][0], local.inertia[1][1], 1e-5));
+        assert!(close(world.inertia[1][1], local.inertia[0][0], 1e-5));
+        assert!(world.principal_axes[0].y.abs() > 0.99999);
+        assert!(close(world.principal_moments.x, local.principal_moments.x, 1e-5));
+    }
+
+    #[test]
+    fn test_combine_two_halves() {
+        let whole = MassProperties::from_mesh(&generate_box_mesh(4.0, 2.0, 1.0), 1.0).unwrap();
+        let half = generate_box_mesh(2.0, 2.0, 1.0);
+        let left = MassProperties::from_mesh(&half.transformed(&Placement::from_position_rotation(Vec3::new(-1.0, 0.0, 0.0), Quat::IDENTITY)), 1.0).unwrap();
+        let right = MassProperties::from_mesh(&half.transformed(&Placement::from_position_rotation(Vec3::new(1.0, 0.0, 0.0), Quat::IDENTITY)), 1.0).unwrap();
+        let combined = MassProperties::combine(&[left, right]);
+        assert!(close(combined.mass, whole.mass, 1e-6));
+        assert!(combined.centroid.length() < 1e-6);
+        for i in 0..3 {
+            assert!(close(combined.inertia[i][i], whole.inertia[i][i], 1e-5));
+        }
+        assert_eq!(MassProperties::combine(&[]), MassProperties::new(0.0, 0.0, 0.0, DVec3::ZERO, DMat3::ZERO));
+    }
+
+    #[test]
+    fn test_inverted_and_open_meshes() {
+        let mut mesh = generate_box_mesh(1.0, 1.0, 1.0);
+        for triangle in mesh.indices.chunks_mut(3) {
+            triangle.swap(1, 2);
+        }
+        assert!(close(MassProperties::from_mesh(&mesh, 1.0).unwrap().volume, 1.0, 1e-6));
+
+        mesh.indices.truncate(mesh.indices.len() - 3);
+        assert_eq!(MassProperties::from_mesh(&mesh, 1.0).unwrap_err(), MassError::NotClosed(3));
+        assert_eq!(MassError::NotClosed(3).to_string(), "Mesh is not closed: 3 open or non-manifold edges");
+    }
+
+    #[test]
+    fn test_density_units() {
+        // A 100 mm steel cube weighs 7.85 kg
+        let mesh = generate_box_mesh(100.0, 100.0, 100.0);
+        let props = MassProperties::from_mesh_in_units(&mesh, 7850.0, Units::Millimeter).unwrap();
+        assert!(close(props.mass, 7.85, 1e-6));
+        assert!(close(props.volume, 1e6, 1e-6));
+    }
+}
@@ -0,0 +1,193 @@
+//! Materials with physical and visual properties
+//!
+//! A built-in library covers common engineering metals, printing plastics
+//! and construction materials; documents add their own entries. Objects
+//! refer to a material by name through their "Material" property, which
+//! gives them a density for mass properties and a color, roughness and
+//! metalness for display and export. Mechanical properties are in SI units.
+
+use std::fmt;
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+use crate::io::srgb8_to_linear;
+
+/// A named material
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Material {
+    pub name: String,
+    /// Kilograms per cubic meter
+    pub density: f64,
+    /// Linear RGB base color
+    pub color: Vec3,
+    /// PBR roughness from 0 (mirror) to 1 (matte)
+    pub roughness: f32,
+    /// PBR metalness from 0 (dielectric) to 1 (metal)
+    pub metalness: f32,
+    /// Young's modulus in pascals
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub youngs_modulus: Option<f64>,
+    /// Yield strength in pascals
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub yield_strength: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poisson_ratio: Option<f64>,
+}
+
+/// Error describing an invalid or unknown material
+#[derive(Debug, Clone, PartialEq)]
+pub enum MaterialError {
+    EmptyName,
+    /// User materials cannot replace library ones
+    BuiltIn(String),
+    NotPositive(String),
+    OutOfRange { name: String, min: f64, max: f64 },
+    Unknown(String),
+}
+
+impl fmt::Display for MaterialError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MaterialError::EmptyName => write!(f, "Material name is empty"),
+            MaterialError::BuiltIn(name) => write!(f, "Material '{}' is built in", name),
+            MaterialError::NotPositive(name) => write!(f, "{} must be > 0", name),
+            MaterialError::OutOfRange { name, min, max } => write!(f, "{} must be between {} and {}", name, min, max),
+            MaterialError::Unknown(name) => write!(f, "Unknown material '{}'", name),
+        }
+    }
+}
+
+impl std::error::Error for MaterialError {}
+
+fn check_positive(name: &str, value: Option<f64>) -> Result<(), MaterialError> {
+    match value {
+        Some(v) if !(v.is_finite() && v > 0.0) => Err(MaterialError::NotPositive(name.to_string())),
+        _ => Ok(()),
+    }
+}
+
+fn check_range(name: &str, value: f64, min: f64, max: f64) -> Result<(), MaterialError> {
+    if (min..=max).contains(&value) {
+        Ok(())
+    } else {
+        Err(MaterialError::OutOfRange { name: name.to_string(), min, max })
+    }
+}
+
+impl Material {
+    /// Check that the name is set and every property is physically sensible
+    pub fn validate(&self) -> Result<(), MaterialError> {
+        if self.name.trim().is_empty() {
+            return Err(MaterialError::EmptyName);
+        }
+        check_positive("Density", Some(self.density))?;
+        for channel in self.color.to_array() {
+            check_range("Color", channel as f64, 0.0, 1.0)?;
+        }
+        check_range("Roughness", self.roughness as f64, 0.0, 1.0)?;
+        check_range("Metalness", self.metalness as f64, 0.0, 1.0)?;
+        check_positive("Young's modulus", self.youngs_modulus)?;
+        check_positive("Yield strength", self.yield_strength)?;
+        if let Some(ratio) = self.poisson_ratio {
+            check_range("Poisson ratio", ratio, -1.0, 0.5)?;
+        }
+        Ok(())
+    }
+}
+
+/// Library entry: name, density, sRGB color, roughness, metalness, and
+/// Young's modulus, yield strength and Poisson ratio where they apply
+type Entry = (&'static str, f64, u32, f32, f32, Option<f64>, Option<f64>, Option<f64>);
+
+const LIBRARY: [Entry; 13] = [
+    ("Steel", 7850.0, 0x8A8D91, 0.4, 1.0, Some(200e9), Some(250e6), Some(0.30)),
+    ("Stainless steel 304", 8000.0, 0xB4B7BA, 0.3, 1.0, Some(193e9), Some(215e6), Some(0.29)),
+    ("Aluminium 6061-T6", 2700.0, 0xC8CACC, 0.35, 1.0, Some(68.9e9), Some(276e6), Some(0.33)),
+    ("Titanium Ti-6Al-4V", 4430.0, 0x9C9A96, 0.4, 1.0, Some(113.8e9), Some(880e6), Some(0.342)),
+    ("Brass C360", 8500.0, 0xC9A94F, 0.3, 1.0, Some(97e9), Some(310e6), Some(0.31)),
+    ("Copper", 8960.0, 0xB87333, 0.3, 1.0, Some(117e9), Some(70e6), Some(0.34)),
+    ("ABS", 1040.0, 0xE8E4D8, 0.6, 0.0, Some(2.3e9), Some(40e6), Some(0.35)),
+    ("PLA", 1240.0, 0xF2F2F2, 0.5, 0.0, Some(3.5e9), Some(60e6), Some(0.36)),
+    ("PETG", 1270.0, 0xD8E6EC, 0.4, 0.0, Some(2.1e9), Some(50e6), Some(0.38)),
+    ("Nylon PA12", 1010.0, 0xEFEEE6, 0.7, 0.0, Some(1.7e9), Some(48e6), Some(0.40)),
+    // Along the grain; wood has no single yield strength or Poisson ratio
+    ("Wood (oak)", 750.0, 0xA67B50, 0.8, 0.0, Some(11e9), None, None),
+    ("Glass", 2500.0, 0xDFEAEA, 0.05, 0.0, Some(70e9), None, Some(0.22)),
+    ("Concrete", 2400.0, 0xA3A3A0, 0.9, 0.0, Some(30e9), None, Some(0.20)),
+];
+
+/// The built-in materials
+pub fn library() -> Vec<Material> {
+    LIBRARY
+        .iter()
+        .map(|&(name, density, srgb, roughness, metalness, youngs_modulus, yield_strength, poisson_ratio)| {
+            let [_, r, g, b] = srgb.to_be_bytes();
+            Material {
+                name: name.to_string(),
+                density,
+                color: Vec3::new(srgb8_to_linear(r), srgb8_to_linear(g), srgb8_to_linear(b)),
+                roughness,
+                metalness,
+                youngs_modulus,
+                yield_strength,
+                poisson_ratio,
+            }
+        })
+        .collect()
+}
+
+/// The built-in material with the given name, ignoring case
+pub fn builtin(name: &str) -> Option<Material> {
+    library().into_iter().find(|m| m.name.eq_ignore_ascii_case(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_library_is_valid() {
+        let materials = library();
+        assert_eq!(materials.len(), LIBRARY.len());
+        for material in &materials {
+            assert_eq!(material.validate(), Ok(()), "{}", material.name);
+        }
+        let steel = builtin("steel").unwrap();
+        assert_eq!(steel.density, 7850.0);
+        assert!((steel.color.x - srgb8_to_linear(0x8A)).abs() < 1e-6);
+        assert!(builtin("ABS").is_some() && builtin("PLA").is_some() && builtin("Wood (oak)").is_some());
+        assert!(builtin("Unobtainium").is_none());
+    }
+
+    #[test]
+    fn test_validation() {
+        let mut material = builtin("PLA").unwrap();
+        material.name = "  ".to_string();
+        assert_eq!(material.validate(), Err(MaterialError::EmptyName));
+
+        let mut material = builtin("PLA").unwrap();
+        material.density = 0.0;
+        assert_eq!(material.validate().unwrap_err().to_string(), "Density must be > 0");
+
+        let mut material = builtin("PLA").unwrap();
+        material.roughness = 1.5;
+        assert_eq!(material.validate().unwrap_err().to_string(), "Roughness must be between 0 and 1");
+
+        let mut material = builtin("PLA").unwrap();
+        material.poisson_ratio = Some(0.7);
+        assert_eq!(material.validate().unwrap_err().to_string(), "Poisson ratio must be between -1 and 0.5");
+
+        let mut material = builtin("PLA").unwrap();
+        material.yield_strength = Some(f64::NAN);
+        assert_eq!(material.validate(), Err(MaterialError::NotPositive("Yield strength".to_string())));
+    }
+
+    #[test]
+    fn test_json_omits_missing_mechanical_properties() {
+        let wood = builtin("Wood (oak)").unwrap();
+        let json = serde_json::to_value(&wood).unwrap();
+        assert!(json.get("yield_strength").is_none());
+        assert_eq!(json["youngs_modulus"], 11e9);
+        let parsed: Material = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, wood);
+    }
+}
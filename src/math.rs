@@ -1,4 +1,4 @@
-use glam::{Quat, Vec3, Mat4};
+use glam::{DMat3, DVec3, Quat, Vec3, Mat4};
 use serde::{Deserialize, Serialize};
 
 /// Represents a 3D placement in space with position and rotation
@@ -27,8 +27,22 @@ impl Placement {
         self.rotation * p + self.position
     }
 
+    /// Placement that applies `other` first and then this one
+    pub fn multiply(&self, other: &Placement) -> Placement {
+        Self {
+            position: self.transform_point(other.position),
+            rotation: (self.rotation * other.rotation).normalize(),
+        }
+    }
+
+    /// Placement that undoes this one
+    pub fn inverse(&self) -> Placement {
+        let rotation = self.rotation.inverse();
+        Self { position: rotation * -self.position, rotation }
+    }
+
     /// Convert this placement to a 4x4 transformation matrix
-    pub fn to_matrix4(&self) -> Mat4 {
+    pub fn to_matrix4(self) -> Mat4 {
         Mat4::from_rotation_translation(self.rotation, self.position)
     }
 }
@@ -39,6 +53,52 @@ impl Default for Placement {
     }
 }
 
+/// Eigenvalues and eigenvectors of a symmetric matrix, by Jacobi rotations
+///
+/// Eigenvalues are in ascending order, with the matching unit eigenvectors
+/// as the columns of a rotation matrix.
+pub fn symmetric_eigen(m: DMat3) -> (DVec3, DMat3) {
+    let mut a = m.transpose().to_cols_array_2d();
+    let mut v = DMat3::IDENTITY.to_cols_array_2d();
+    let scale = a.iter().flatten().fold(0.0f64, |s, x| s.max(x.abs()));
+    for _ in 0..50 {
+        let (p, q) = [(0, 1), (0, 2), (1, 2)]
+            .into_iter()
+            .max_by(|x, y| a[x.0][x.1].abs().total_cmp(&a[y.0][y.1].abs()))
+            .unwrap();
+        if a[p][q].abs() <= scale * 1e-15 {
+            break;
+        }
+        // Rotate in the (p, q) plane so that a[p][q] becomes zero
+        let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+        let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+        let c = 1.0 / (t * t + 1.0).sqrt();
+        let s = t * c;
+        for row in a.iter_mut() {
+            let (kp, kq) = (row[p], row[q]);
+            row[p] = c * kp - s * kq;
+            row[q] = s * kp + c * kq;
+        }
+        let (row_p, row_q) = (a[p], a[q]);
+        a[p] = std::array::from_fn(|k| c * row_p[k] - s * row_q[k]);
+        a[q] = std::array::from_fn(|k| s * row_p[k] + c * row_q[k]);
+        for row in v.iter_mut() {
+            let (kp, kq) = (row[p], row[q]);
+            row[p] = c * kp - s * kq;
+            row[q] = s * kp + c * kq;
+        }
+    }
+
+    // Columns of v are the eigenvectors; sort them by eigenvalue
+    let vectors = DMat3::from_cols_array_2d(&v).transpose();
+    let mut order = [0, 1, 2];
+    order.sort_by(|i, j| a[*i][*i].total_cmp(&a[*j][*j]));
+    let values = DVec3::from_array(order.map(|i| a[i][i]));
+    let x = vectors.col(order[0]);
+    let y = vectors.col(order[1]);
+    (values, DMat3::from_cols(x, y, x.cross(y)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -71,4 +131,34 @@ mod tests {
         let expected = Mat4::from_translation(Vec3::new(1.0, 2.0, 3.0));
         assert_eq!(matrix, expected);
     }
+
+    #[test]
+    fn test_multiply_and_inverse() {
+        let a = Placement::from_position_rotation(Vec3::new(1.0, 0.0, 0.0), Quat::from_rotation_z(std::f32::consts::FRAC_PI_2));
+        let b = Placement::from_position_rotation(Vec3::new(0.0, 2.0, 0.0), Quat::IDENTITY);
+        let p = Vec3::new(1.0, 1.0, 1.0);
+        let combined = a.multiply(&b);
+        assert!((combined.transform_point(p) - a.transform_point(b.transform_point(p))).length() < 1e-5);
+
+        let back = a.inverse().transform_point(a.transform_point(p));
+        assert!((back - p).length() < 1e-5);
+    }
+
+    #[test]
+    fn test_symmetric_eigen() {
+        let m = DMat3::from_cols(DVec3::new(4.0, 1.0, 2.0), DVec3::new(1.0, 3.0, 0.5), DVec3::new(2.0, 0.5, 5.0));
+        let (values, vectors) = symmetric_eigen(m);
+        assert!(values.x <= values.y && values.y <= values.z);
+        assert!((values.dot(DVec3::ONE) - 12.0).abs() < 1e-12);
+        for i in 0..3 {
+            let v = vectors.col(i);
+            assert!((m * v - values[i] * v).length() < 1e-12);
+            assert!((v.length() - 1.0).abs() < 1e-12);
+        }
+        assert!((vectors.determinant() - 1.0).abs() < 1e-12);
+
+        let (values, vectors) = symmetric_eigen(DMat3::from_diagonal(DVec3::new(3.0, 1.0, 2.0)));
+        assert_eq!(values, DVec3::new(1.0, 2.0, 3.0));
+        assert_eq!(vectors.col(0), DVec3::Y);
+    }
 }
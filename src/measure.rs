@@ -0,0 +1,670 @@
+//! Measurements between objects and their vertices, edges and faces
+//!
+//! Entities are picked on world space meshes. A face is the smooth region
+//! around a picked triangle, bounded by creases sharper than the drawing
+//! crease angle, so a box has six faces and a cylinder three. An edge is the
+//! chain of crease or boundary edges nearest a picked point, running until
+//! it branches or turns a corner; an edge whose points lie on a circle has a
+//! center and radius. Results carry the points they were measured between
+//! so that leader lines can be drawn to them.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use glam::{DMat3, Vec3};
+use serde::{Deserialize, Serialize};
+use crate::bounds::Aabb;
+use crate::bvh::{intersect_triangle, Bvh, Ray};
+use crate::document::Document;
+use crate::geometry::{mesh_edges, triangle_normal, Mesh, MeshEdge};
+use crate::math::{symmetric_eigen, Placement};
+use crate::projection::DEFAULT_CREASE_ANGLE;
+
+/// Something to measure, in world space
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Entity {
+    Point { point: Vec3 },
+    /// The mesh vertex of an object nearest to a point
+    Vertex { object: u32, point: Vec3 },
+    /// The crease or boundary edge of an object nearest to a point
+    Edge { object: u32, point: Vec3 },
+    /// The face of an object containing a triangle, as returned by picking
+    Face { object: u32, triangle: usize },
+    Object { object: u32 },
+}
+
+/// Why a measurement cannot be taken
+#[derive(Debug, Clone, PartialEq)]
+pub enum MeasureError {
+    NoMesh(u32),
+    NoTriangle { object: u32, triangle: usize },
+    NoEdges(u32),
+    /// Angles need flat faces, faces around an axis, or straight or circular edges
+    NoDirection,
+    NotCircular,
+}
+
+impl fmt::Display for MeasureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeasureError::NoMesh(id) => write!(f, "Object {} has no mesh", id),
+            MeasureError::NoTriangle { object, triangle } => write!(f, "Object {} has no triangle {}", object, triangle),
+            MeasureError::NoEdges(id) => write!(f, "Object {} has no edges", id),
+            MeasureError::NoDirection => write!(f, "Entity has no direction to measure an angle from"),
+            MeasureError::NotCircular => write!(f, "Entity is not a circular edge"),
+        }
+    }
+}
+
+impl std::error::Error for MeasureError {}
+
+/// Minimum distance between two entities
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Distance {
+    pub distance: f32,
+    /// Closest points on the first and second entity
+    pub points: [Vec3; 2],
+}
+
+/// Angle between the directions of two entities
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Angle {
+    /// Between 0 and 180 for two flat faces, whose normals point outwards,
+    /// and at most 90 when either direction is a line or axis
+    pub degrees: f32,
+    pub directions: [Vec3; 2],
+    /// Points on the first and second entity to draw leaders from
+    pub points: [Vec3; 2],
+}
+
+/// A circular edge
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Circle {
+    pub center: Vec3,
+    /// Unit normal of the circle's plane
+    pub axis: Vec3,
+    pub radius: f32,
+    pub diameter: f32,
+    /// A point on the edge to draw a leader to
+    pub point: Vec3,
+}
+
+/// Point of segment `a`-`b` nearest to `p`
+pub fn closest_on_segment(p: Vec3, a: Vec3, b: Vec3) -> Vec3 {
+    let ab = b - a;
+    let length_squared = ab.length_squared();
+    if length_squared == 0.0 {
+        return a;
+    }
+    a + ab * ((p - a).dot(ab) / length_squared).clamp(0.0, 1.0)
+}
+
+/// Point of a triangle nearest to `p`, by the regions of its corners and sides
+pub fn closest_on_triangle(p: Vec3, [a, b, c]: [Vec3; 3]) -> Vec3 {
+    let (ab, ac, ap) = (b - a, c - a, p - a);
+    let (d1, d2) = (ab.dot(ap), ac.dot(ap));
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a;
+    }
+    let bp = p - b;
+    let (d3, d4) = (ab.dot(bp), ac.dot(bp));
+    if d3 >= 0.0 && d4 <= d3 {
+        return b;
+    }
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+    let cp = p - c;
+    let (d5, d6) = (ab.dot(cp), ac.dot(cp));
+    if d6 >= 0.0 && d5 <= d6 {
+        return c;
+    }
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && d4 >= d3 && d5 >= d6 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+    let sum = va + vb + vc;
+    if sum <= 0.0 {
+        // Degenerate triangles are as near as their nearest side
+        return [(a, b), (b, c), (c, a)]
+            .map(|(u, v)| closest_on_segment(p, u, v))
+            .into_iter()
+            .min_by(|x, y| x.distance_squared(p).total_cmp(&y.distance_squared(p)))
+            .unwrap_or(a);
+    }
+    a + ab * (vb / sum) + ac * (vc / sum)
+}
+
+/// Nearest points of two segments
+pub fn closest_between_segments([p1, q1]: [Vec3; 2], [p2, q2]: [Vec3; 2]) -> (Vec3, Vec3) {
+    let (d1, d2, r) = (q1 - p1, q2 - p2, p1 - p2);
+    let (a, e, f) = (d1.length_squared(), d2.length_squared(), d2.dot(r));
+    let (s, t) = if a == 0.0 && e == 0.0 {
+        (0.0, 0.0)
+    } else if a == 0.0 {
+        (0.0, (f / e).clamp(0.0, 1.0))
+    } else {
+        let c = d1.dot(r);
+        if e == 0.0 {
+            ((-c / a).clamp(0.0, 1.0), 0.0)
+        } else {
+            let b = d1.dot(d2);
+            let denom = a * e - b * b;
+            // Parallel segments have no single nearest pair; start from p1
+            let s = if denom > f32::EPSILON * a * e { ((b * f - c * e) / denom).clamp(0.0, 1.0) } else { 0.0 };
+            let t = (b * s + f) / e;
+            if t < 0.0 {
+                ((-c / a).clamp(0.0, 1.0), 0.0)
+            } else if t > 1.0 {
+                (((b - c) / a).clamp(0.0, 1.0), 1.0)
+            } else {
+                (s, t)
+            }
+        }
+    };
+    (p1 + d1 * s, p2 + d2 * t)
+}
+
+fn nearest_pair(pairs: impl IntoIterator<Item = (Vec3, Vec3)>) -> Option<(Vec3, Vec3)> {
+    pairs.into_iter().min_by(|x, y| x.0.distance_squared(x.1).total_cmp(&y.0.distance_squared(y.1)))
+}
+
+fn sides([a, b, c]: [Vec3; 3]) -> [[Vec3; 2]; 3] {
+    [[a, b], [b, c], [c, a]]
+}
+
+/// Nearest points of a segment and a triangle, the same point where they cross
+pub fn closest_between_segment_and_triangle(segment: [Vec3; 2], triangle: [Vec3; 3]) -> (Vec3, Vec3) {
+    let [p, q] = segment;
+    if let Some(ray) = Ray::new(p, q - p) {
+        if let Some(t) = intersect_triangle(&ray, triangle).filter(|t| *t <= p.distance(q)) {
+            let crossing = ray.at(t);
+            return (crossing, crossing);
+        }
+    }
+    let ends = [p, q].map(|e| (e, closest_on_triangle(e, triangle)));
+    let sides = sides(triangle).map(|side| closest_between_segments(segment, side));
+    nearest_pair(ends.into_iter().chain(sides)).unwrap_or((p, triangle[0]))
+}
+
+/// Nearest points of two triangles, a shared point if they intersect
+///
+/// Unless the triangles intersect, a side of one of them holds a nearest
+/// point, so comparing each side against the other triangle suffices.
+pub fn closest_between_triangles(first: [Vec3; 3], second: [Vec3; 3]) -> (Vec3, Vec3) {
+    let forward = sides(first).map(|side| closest_between_segment_and_triangle(side, second));
+    let backward = sides(second).map(|side| {
+        let (q, p) = closest_between_segment_and_triangle(side, first);
+        (p, q)
+    });
+    nearest_pair(forward.into_iter().chain(backward)).unwrap_or((first[0], second[0]))
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Primitive {
+    Point(Vec3),
+    Segment([Vec3; 2]),
+    Triangle([Vec3; 3]),
+}
+
+impl Primitive {
+    fn aabb(&self) -> Aabb {
+        match self {
+            Primitive::Point(p) => Aabb::from_points([*p]),
+            Primitive::Segment(points) => Aabb::from_points(*points),
+            Primitive::Triangle(points) => Aabb::from_points(*points),
+        }
+    }
+
+    fn closest(&self, other: &Primitive) -> (Vec3, Vec3) {
+        match (*self, *other) {
+            (Primitive::Point(p), Primitive::Point(q)) => (p, q),
+            (Primitive::Point(p), Primitive::Segment([a, b])) => (p, closest_on_segment(p, a, b)),
+            (Primitive::Point(p), Primitive::Triangle(t)) => (p, closest_on_triangle(p, t)),
+            (Primitive::Segment(s), Primitive::Segment(t)) => closest_between_segments(s, t),
+            (Primitive::Segment(s), Primitive::Triangle(t)) => closest_between_segment_and_triangle(s, t),
+            (Primitive::Triangle(s), Primitive::Triangle(t)) => closest_between_triangles(s, t),
+            _ => {
+                let (q, p) = other.closest(self);
+                (p, q)
+            }
+        }
+    }
+}
+
+/// Hierarchy over the boxes of primitives, for entities without a cached one
+fn hierarchy(primitives: &[Primitive]) -> Bvh {
+    Bvh::from_boxes(&primitives.iter().map(Primitive::aabb).collect::<Vec<_>>())
+}
+
+/// Welded connectivity of a mesh, keeping the mesh's triangle order
+struct Topology {
+    positions: Vec<Vec3>,
+    triangles: Vec<[u32; 3]>,
+    normals: Vec<Vec3>,
+    /// Edges of the non-degenerate triangles
+    edges: Vec<MeshEdge>,
+}
+
+impl Topology {
+    fn new(mesh: &Mesh) -> Self {
+        let (positions, remap) = mesh.weld_map();
+        let triangles: Vec<[u32; 3]> = (0..mesh.triangle_count())
+            .map(|i| mesh.triangle_indices(i).map(|v| remap[v as usize]))
+            .collect();
+        let normals: Vec<Vec3> = triangles
+            .iter()
+            .map(|t| {
+                let [a, b, c] = t.map(|i| positions[i as usize]);
+                triangle_normal(a, b, c)
+            })
+            .collect();
+        let kept: Vec<usize> = (0..triangles.len()).filter(|i| normals[*i] != Vec3::ZERO).collect();
+        let edges = mesh_edges(&kept.iter().map(|i| triangles[*i]).collect::<Vec<_>>())
+            .into_iter()
+            .map(|mut edge| {
+                edge.triangles.iter_mut().for_each(|t| *t = kept[*t]);
+                edge
+            })
+            .collect();
+        Self { positions, triangles, normals, edges }
+    }
+
+    /// Whether an edge lies inside a face rather than bounding it
+    fn is_smooth(&self, edge: &MeshEdge) -> bool {
+        match edge.triangles[..] {
+            [a, b] => self.normals[a].dot(self.normals[b]) >= DEFAULT_CREASE_ANGLE.cos(),
+            _ => false,
+        }
+    }
+
+    /// Triangles of the face containing `triangle`
+    fn face(&self, triangle: usize) -> Vec<usize> {
+        let mut neighbours: HashMap<usize, Vec<usize>> = HashMap::new();
+        for edge in self.edges.iter().filter(|e| self.is_smooth(e)) {
+            let [a, b] = [edge.triangles[0], edge.triangles[1]];
+            neighbours.entry(a).or_default().push(b);
+            neighbours.entry(b).or_default().push(a);
+        }
+        let mut face = vec![triangle];
+        let mut seen = vec![false; self.triangles.len()];
+        seen[triangle] = true;
+        let mut next = 0;
+        while next < face.len() {
+            for &t in neighbours.get(&face[next]).into_iter().flatten() {
+                if !seen[t] {
+                    seen[t] = true;
+                    face.push(t);
+                }
+            }
+            next += 1;
+        }
+        face
+    }
+
+    /// Points of the edge nearest to `point`, the first repeated at the end
+    /// if the edge is closed
+    fn edge(&self, point: Vec3) -> Option<Vec<Vec3>> {
+        let features: Vec<[u32; 2]> = self.edges.iter().filter(|e| !self.is_smooth(e)).map(|e| e.vertices).collect();
+        let position = |v: u32| self.positions[v as usize];
+        let start = (0..features.len()).min_by(|a, b| {
+            let distance = |e: usize| {
+                let [u, v] = features[e];
+                closest_on_segment(point, position(u), position(v)).distance_squared(point)
+            };
+            distance(*a).total_cmp(&distance(*b))
+        })?;
+
+        let mut incident: HashMap<u32, Vec<usize>> = HashMap::new();
+        for (index, [u, v]) in features.iter().enumerate() {
+            incident.entry(*u).or_default().push(index);
+            incident.entry(*v).or_default().push(index);
+        }
+        let other_end = |e: usize, v: u32| if features[e][0] == v { features[e][1] } else { features[e][0] };
+        // The edge continuing `e` through its end `v`, unless `v` is a corner or branch
+        let continuation = |e: usize, v: u32| -> Option<usize> {
+            let &[a, b] = &incident[&v][..] else { return None };
+            let next = if a == e { b } else { a };
+            let (before, at, after) = (position(other_end(e, v)), position(v), position(other_end(next, v)));
+            ((at - before).normalize().dot((after - at).normalize()) >= DEFAULT_CREASE_ANGLE.cos()).then_some(next)
+        };
+
+        let [first, second] = features[start];
+        let mut forward = vec![position(first), position(second)];
+        let (mut edge, mut vertex) = (start, second);
+        while let Some(next) = continuation(edge, vertex) {
+            if next == start {
+                return Some(forward);
+            }
+            (edge, vertex) = (next, other_end(next, vertex));
+            forward.push(position(vertex));
+        }
+        let mut backward = Vec::new();
+        let (mut edge, mut vertex) = (start, first);
+        while let Some(next) = continuation(edge, vertex) {
+            (edge, vertex) = (next, other_end(next, vertex));
+            backward.push(position(vertex));
+        }
+        backward.reverse();
+        backward.extend(forward);
+        Some(backward)
+    }
+}
+
+/// Circle through the points of an edge, as center, unit axis and radius,
+/// if they all lie on one
+fn fit_circle(points: &[Vec3]) -> Option<(Vec3, Vec3, f32)> {
+    let closed = points.len() > 2 && points.first() == points.last();
+    let points = if closed { &points[..points.len() - 1] } else { points };
+    let n = points.len();
+    if n < 4 {
+        return None;
+    }
+    let (a, b, c) = if closed { (points[0], points[n / 3], points[2 * n / 3]) } else { (points[0], points[n / 2], points[n - 1]) };
+    let (ab, ac) = (b - a, c - a);
+    let normal = ab.cross(ac);
+    let length_squared = normal.length_squared();
+    if length_squared <= f32::EPSILON * ab.length_squared() * ac.length_squared() {
+        return None;
+    }
+    let center = a + (normal.cross(ab) * ac.length_squared() + ac.cross(normal) * ab.length_squared()) / (2.0 * length_squared);
+    let axis = normal.normalize();
+    let radius = a.distance(center);
+    let tolerance = radius * 1e-3;
+    points
+        .iter()
+        .all(|p| (p.distance(center) - radius).abs() <= tolerance && (*p - center).dot(axis).abs() <= tolerance)
+        .then_some((center, axis, radius))
+}
+
+/// What an entity stands for on its object's world space mesh
+enum Geometry {
+    Point(Vec3),
+    Edge(Vec<Vec3>),
+    Surface(Vec<[Vec3; 3]>),
+}
+
+impl Geometry {
+    fn resolve(doc: &Document, entity: &Entity) -> Result<Geometry, MeasureError> {
+        let mesh = |object: u32| doc.world_mesh(object).filter(|m| m.triangle_count() > 0).ok_or(MeasureError::NoMesh(object));
+        match *entity {
+            Entity::Point { point } => Ok(Geometry::Point(point)),
+            Entity::Vertex { object, point } => {
+                let mesh = mesh(object)?;
+                let nearest = (0..mesh.vertex_count())
+                    .map(|i| mesh.vertex(i))
+                    .min_by(|a, b| a.distance_squared(point).total_cmp(&b.distance_squared(point)));
+                nearest.map(Geometry::Point).ok_or(MeasureError::NoMesh(object))
+            }
+            Entity::Edge { object, point } => {
+                Topology::new(&mesh(object)?).edge(point).map(Geometry::Edge).ok_or(MeasureError::NoEdges(object))
+            }
+            Entity::Face { object, triangle } => {
+                let mesh = mesh(object)?;
+                if triangle >= mesh.triangle_count() {
+                    return Err(MeasureError::NoTriangle { object, triangle });
+                }
+                let face = Topology::new(&mesh).face(triangle);
+                Ok(Geometry::Surface(face.into_iter().map(|t| mesh.triangle(t)).collect()))
+            }
+            Entity::Object { object } => Ok(Geometry::Surface(mesh(object)?.triangles().collect())),
+        }
+    }
+
+    fn primitives(&self) -> Vec<Primitive> {
+        match self {
+            Geometry::Point(p) => vec![Primitive::Point(*p)],
+            Geometry::Edge(points) => points.windows(2).map(|w| Primitive::Segment([w[0], w[1]])).collect(),
+            Geometry::Surface(triangles) => triangles.iter().map(|t| Primitive::Triangle(*t)).collect(),
+        }
+    }
+
+    /// A representative point: the middle of an edge or the centroid of a surface
+    fn anchor(&self) -> Vec3 {
+        match self {
+            Geometry::Point(p) => *p,
+            Geometry::Edge(points) => match fit_circle(points) {
+                Some((center, ..)) => center,
+                None => points[points.len() / 2],
+            },
+            Geometry::Surface(triangles) => {
+                let (mut sum, mut area) = (Vec3::ZERO, 0.0);
+                for [a, b, c] in triangles {
+                    let weight = (*b - *a).cross(*c - *a).length();
+                    sum += (*a + *b + *c) / 3.0 * weight;
+                    area += weight;
+                }
+                if area > 0.0 { sum / area } else { triangles[0][0] }
+            }
+        }
+    }
+
+    /// Unit direction, and whether its sign is meaningful
+    ///
+    /// Flat faces have their outward normal. Lines, circles and faces swept
+    /// around an axis, such as cylinders, have an axis without a sign.
+    fn direction(&self) -> Option<(Vec3, bool)> {
+        match self {
+            Geometry::Point(_) => None,
+            Geometry::Edge(points) => {
+                if let Some((_, axis, _)) = fit_circle(points) {
+                    return Some((axis, false));
+                }
+                let (first, last) = (points[0], points[points.len() - 1]);
+                let direction = (last - first).try_normalize()?;
+                let tolerance = first.distance(last) * 1e-4;
+                let straight = points.iter().all(|p| (*p - first).reject_from_normalized(direction).length() <= tolerance);
+                straight.then_some((direction, false))
+            }
+            Geometry::Surface(triangles) => {
+                let weighted: Vec<Vec3> = triangles.iter().map(|[a, b, c]| (*b - *a).cross(*c - *a)).collect();
+                let normal = weighted.iter().sum::<Vec3>().normalize_or_zero();
+                if weighted.iter().all(|n| n.normalize_or_zero().dot(normal) >= 1.0 - 1e-5) {
+                    return Some((normal, true));
+                }
+                // Normals of a surface around an axis are all perpendicular to it
+                let mut scatter = DMat3::ZERO;
+                for n in &weighted {
+                    let n = n.as_dvec3();
+                    let area = n.length();
+                    if area > 0.0 {
+                        scatter += DMat3::from_cols(n * n.x, n * n.y, n * n.z) * (1.0 / area);
+                    }
+                }
+                let (values, vectors) = symmetric_eigen(scatter);
+                (values.x <= values.z * 1e-6).then(|| (vectors.x_axis.as_vec3().normalize(), false))
+            }
+        }
+    }
+}
+
+/// Minimum distance between two entities, and where it is reached
+pub fn distance(doc: &Document, first: &Entity, second: &Entity) -> Result<Distance, MeasureError> {
+    let (a, b) = (Geometry::resolve(doc, first)?, Geometry::resolve(doc, second)?);
+    let (primitives_a, primitives_b) = (a.primitives(), b.primitives());
+    // Whole objects reuse their cached hierarchies; their world space
+    // triangles keep the order of the local mesh
+    let cached = |entity: &Entity| match *entity {
+        Entity::Object { object } => doc.get_object(object).and_then(|o| Some((Cow::Borrowed(o.bvh()?), o.placement()))),
+        _ => None,
+    };
+    let [(bvh_a, placement_a), (bvh_b, placement_b)] = [(first, &primitives_a), (second, &primitives_b)]
+        .map(|(entity, primitives)| cached(entity).unwrap_or_else(|| (Cow::Owned(hierarchy(primitives)), Placement::new())));
+    let nearest = bvh_a.nearest(&placement_a, &bvh_b, &placement_b, f32::INFINITY, |i, j| primitives_a[i].closest(&primitives_b[j]));
+    // Resolved entities always have a primitive
+    let (p, q) = nearest.unwrap_or((a.anchor(), b.anchor()));
+    Ok(Distance { distance: p.distance(q), points: [p, q] })
+}
+
+/// Angle between the normals, axes or directions of two entities
+pub fn angle(doc: &Document, first: &Entity, second: &Entity) -> Result<Angle, MeasureError> {
+    let (a, b) = (Geometry::resolve(doc, first)?, Geometry::resolve(doc, second)?);
+    let (direction_a, oriented_a) = a.direction().ok_or(MeasureError::NoDirection)?;
+    let (mut direction_b, oriented_b) = b.direction().ok_or(MeasureError::NoDirection)?;
+    if !(oriented_a && oriented_b) && direction_a.dot(direction_b) < 0.0 {
+        direction_b = -direction_b;
+    }
+    Ok(Angle {
+        degrees: direction_a.angle_between(direction_b).to_degrees(),
+        directions: [direction_a, direction_b],
+        points: [a.anchor(), b.anchor()],
+    })
+}
+
+/// Center, axis and radius of a circular edge
+pub fn circle(doc: &Document, entity: &Entity) -> Result<Circle, MeasureError> {
+    let Geometry::Edge(points) = Geometry::resolve(doc, entity)? else {
+        return Err(MeasureError::NotCircular);
+    };
+    let (center, axis, radius) = fit_circle(&points).ok_or(MeasureError::NotCircular)?;
+    Ok(Circle { center, axis, radius, diameter: 2.0 * radius, point: points[0] })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Quat;
+    use crate::math::Placement;
+    use crate::object::ShapeType;
+    use crate::property::Property;
+
+    fn close(a: Vec3, b: Vec3) -> bool {
+        (a - b).length() < 1e-4
+    }
+
+    fn place(doc: &mut Document, id: u32, position: Vec3, rotation: Quat) {
+        let placement = Placement::from_position_rotation(position, rotation);
+        doc.set_object_property(id, "Placement".to_string(), Property::Placement(placement));
+        doc.recompute();
+    }
+
+    /// Face hit by a ray, as the UI would pick it
+    fn face(doc: &Document, origin: Vec3, direction: Vec3) -> Entity {
+        let (object, hit) = doc.pick(&Ray::new(origin, direction).unwrap()).unwrap();
+        Entity::Face { object, triangle: hit.triangle }
+    }
+
+    #[test]
+    fn test_closest_points() {
+        let triangle = [Vec3::ZERO, Vec3::X, Vec3::Y];
+        assert!(close(closest_on_triangle(Vec3::new(0.2, 0.2, 3.0), triangle), Vec3::new(0.2, 0.2, 0.0)));
+        assert_eq!(closest_on_triangle(Vec3::new(-1.0, -1.0, 0.0), triangle), Vec3::ZERO);
+        assert!(close(closest_on_triangle(Vec3::new(1.0, 1.0, 0.0), triangle), Vec3::new(0.5, 0.5, 0.0)));
+        assert_eq!(closest_on_triangle(Vec3::new(0.5, -2.0, 1.0), triangle), Vec3::new(0.5, 0.0, 0.0));
+
+        let (p, q) = closest_between_segments([Vec3::ZERO, Vec3::X * 2.0], [Vec3::new(1.0, -1.0, 1.0), Vec3::new(1.0, 1.0, 1.0)]);
+        assert_eq!((p, q), (Vec3::X, Vec3::new(1.0, 0.0, 1.0)));
+        // Parallel segments
+        let (p, q) = closest_between_segments([Vec3::ZERO, Vec3::X], [Vec3::new(3.0, 1.0, 0.0), Vec3::new(2.0, 1.0, 0.0)]);
+        assert_eq!((p, q), (Vec3::X, Vec3::new(2.0, 1.0, 0.0)));
+
+        let (p, q) = closest_between_segment_and_triangle([Vec3::new(0.25, 0.25, -1.0), Vec3::new(0.25, 0.25, 1.0)], triangle);
+        assert_eq!(p, q);
+        assert!(close(p, Vec3::new(0.25, 0.25, 0.0)));
+
+        // Crossed triangles touch, stacked ones are their separation apart
+        let crossing = [Vec3::new(0.2, 0.2, -1.0), Vec3::new(0.2, 0.2, 1.0), Vec3::new(-1.0, 0.2, 0.0)];
+        let (p, q) = closest_between_triangles(triangle, crossing);
+        assert!(p.distance(q) < 1e-6);
+        let lifted = triangle.map(|v| v + Vec3::new(0.1, 0.1, 2.0));
+        let (p, q) = closest_between_triangles(triangle, lifted);
+        assert!((p.distance(q) - 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_distances_between_objects_and_features() {
+        let mut doc = Document::new();
+        let left = doc.add_object(ShapeType::Box { length: 2.0, width: 2.0, height: 2.0 });
+        let right = doc.add_object(ShapeType::Box { length: 2.0, width: 2.0, height: 2.0 });
+        place(&mut doc, right, Vec3::new(5.0, 0.5, 0.0), Quat::IDENTITY);
+
+        let result = distance(&doc, &Entity::Object { object: left }, &Entity::Object { object: right }).unwrap();
+        assert!((result.distance - 3.0).abs() < 1e-5);
+        assert!((result.points[0].x - 1.0).abs() < 1e-5 && (result.points[1].x - 4.0).abs() < 1e-5);
+
+        // The far face of the right box
+        let far = face(&doc, Vec3::new(10.0, 0.5, 0.0), -Vec3::X);
+        let result = distance(&doc, &Entity::Point { point: Vec3::ZERO }, &far).unwrap();
+        assert!((result.distance - 6.0).abs() < 1e-5);
+        assert!(close(result.points[1], Vec3::new(6.0, 0.0, 0.0)));
+
+        // A corner snaps to the mesh vertex; an edge runs the box's whole side
+        let corner = Entity::Vertex { object: left, point: Vec3::new(0.9, 1.2, 1.1) };
+        let edge = Entity::Edge { object: right, point: Vec3::new(4.0, 1.5, 0.3) };
+        let result = distance(&doc, &corner, &edge).unwrap();
+        assert!(close(result.points[0], Vec3::ONE));
+        assert!(close(result.points[1], Vec3::new(4.0, 1.5, 1.0)));
+        assert!((result.distance - 3.0f32.hypot(0.5)).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_angles() {
+        let mut doc = Document::new();
+        let cube = doc.add_object(ShapeType::Box { length: 2.0, width: 2.0, height: 2.0 });
+        let tilted = doc.add_object(ShapeType::Box { length: 2.0, width: 2.0, height: 2.0 });
+        let cylinder = doc.add_object(ShapeType::Cylinder { radius: 1.0, height: 4.0 });
+        place(&mut doc, tilted, Vec3::new(10.0, 0.0, 0.0), Quat::from_rotation_x(30f32.to_radians()));
+        place(&mut doc, cylinder, Vec3::new(0.0, 10.0, 0.0), Quat::from_rotation_z(std::f32::consts::FRAC_PI_2));
+
+        let top = face(&doc, Vec3::new(0.0, 0.0, 5.0), -Vec3::Z);
+        let side = face(&doc, Vec3::new(5.0, 0.0, 0.0), -Vec3::X);
+        let bottom = face(&doc, Vec3::new(0.0, 0.0, -5.0), Vec3::Z);
+        let result = angle(&doc, &top, &side).unwrap();
+        assert!((result.degrees - 90.0).abs() < 1e-3);
+        assert!(close(result.points[0], Vec3::Z) && close(result.points[1], Vec3::X));
+        assert!((angle(&doc, &top, &bottom).unwrap().degrees - 180.0).abs() < 1e-3);
+
+        let tilted_top = face(&doc, Vec3::new(10.0, 0.0, 5.0), -Vec3::Z);
+        assert!((angle(&doc, &top, &tilted_top).unwrap().degrees - 30.0).abs() < 1e-3);
+
+        // The cylinder lies along X, so its side is parallel to the cube's side normal
+        let mantle = face(&doc, Vec3::new(0.3, 10.0, 5.0), -Vec3::Z);
+        let result = angle(&doc, &side, &mantle).unwrap();
+        assert!(result.degrees.abs() < 1e-2, "{}", result.degrees);
+        assert!(close(result.points[1], Vec3::new(0.0, 10.0, 0.0)));
+
+        // A straight edge along Y against the top face normal
+        let edge = Entity::Edge { object: cube, point: Vec3::new(1.0, 0.2, 1.0) };
+        assert!((angle(&doc, &edge, &top).unwrap().degrees - 90.0).abs() < 1e-3);
+        assert_eq!(angle(&doc, &Entity::Point { point: Vec3::ZERO }, &top).unwrap_err(), MeasureError::NoDirection);
+    }
+
+    #[test]
+    fn test_circles() {
+        let mut doc = Document::new();
+        let cylinder = doc.add_object(ShapeType::Cylinder { radius: 2.0, height: 6.0 });
+        let cube = doc.add_object(ShapeType::Box { length: 2.0, width: 2.0, height: 2.0 });
+        place(&mut doc, cylinder, Vec3::new(1.0, 2.0, 3.0), Quat::IDENTITY);
+
+        let rim = Entity::Edge { object: cylinder, point: Vec3::new(3.1, 5.0, 3.0) };
+        let result = circle(&doc, &rim).unwrap();
+        assert!((result.radius - 2.0).abs() < 1e-4 && (result.diameter - 4.0).abs() < 1e-4);
+        assert!(close(result.center, Vec3::new(1.0, 5.0, 3.0)));
+        assert!((result.axis.y.abs() - 1.0).abs() < 1e-5);
+        assert!((result.point.distance(result.center) - 2.0).abs() < 1e-4);
+
+        let edge = Entity::Edge { object: cube, point: Vec3::new(1.0, 0.0, 1.0) };
+        assert_eq!(circle(&doc, &edge).unwrap_err(), MeasureError::NotCircular);
+        assert_eq!(circle(&doc, &Entity::Object { object: cube }).unwrap_err(), MeasureError::NotCircular);
+    }
+
+    #[test]
+    fn test_errors_and_json() {
+        let mut doc = Document::new();
+        let cube = doc.add_object(ShapeType::Box { length: 2.0, width: 2.0, height: 2.0 });
+        doc.recompute();
+        let face = Entity::Face { object: cube, triangle: 12 };
+        let error = distance(&doc, &face, &Entity::Object { object: 7 }).unwrap_err();
+        assert_eq!(error.to_string(), "Object 1 has no triangle 12");
+        assert_eq!(distance(&doc, &Entity::Object { object: cube }, &Entity::Object { object: 7 }).unwrap_err(), MeasureError::NoMesh(7));
+
+        let entity: Entity = serde_json::from_str(r#"{"type":"edge","object":1,"point":[1,0,1]}"#).unwrap();
+        assert_eq!(entity, Entity::Edge { object: 1, point: Vec3::new(1.0, 0.0, 1.0) });
+    }
+}
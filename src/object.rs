@@ -1,8 +1,15 @@
-use std::collections::HashMap;
-use serde::{Deserialize, Serialize};
+use std::cell::OnceCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use glam::Vec3;
+use serde::{Deserialize, Serialize};
+use crate::bounds::{Aabb, Obb};
+use crate::bvh::{Bvh, Hit, Ray};
+use crate::mass::{MassError, MassProperties};
+use crate::math::Placement;
 use crate::property::Property;
 use crate::geometry::{Mesh, generate_box_mesh, generate_cylinder_mesh, generate_sphere_mesh};
+use crate::units::Units;
 
 /// Types of shapes with their parameters
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -10,6 +17,141 @@ pub enum ShapeType {
     Box { length: f32, width: f32, height: f32 },
     Cylinder { radius: f32, height: f32 },
     Sphere { radius: f32 },
+    /// Mesh read from an external file, stored with the document
+    ImportedMesh { mesh: Mesh },
+}
+
+/// Color of objects without a "Color" property
+pub const DEFAULT_COLOR: Vec3 = Vec3::new(0.44, 0.44, 0.48);
+
+/// Error describing an invalid shape parameter
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ShapeError {
+    NotPositive(String),
+    NotANumber(String),
+    Infinite(String),
+    EmptyMesh,
+    InvalidMesh(String),
+}
+
+impl fmt::Display for ShapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShapeError::NotPositive(name) => write!(f, "{} must be > 0", name),
+            ShapeError::NotANumber(name) => write!(f, "{} is NaN", name),
+            ShapeError::Infinite(name) => write!(f, "{} is infinite", name),
+            ShapeError::EmptyMesh => write!(f, "Mesh has no triangles"),
+            ShapeError::InvalidMesh(reason) => write!(f, "Mesh is invalid: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ShapeError {}
+
+/// Check that a dimension is a finite, strictly positive number
+fn check_dimension(name: &str, value: f32) -> Result<(), ShapeError> {
+    if value.is_nan() {
+        Err(ShapeError::NotANumber(name.to_string()))
+    } else if value.is_infinite() {
+        Err(ShapeError::Infinite(name.to_string()))
+    } else if value <= 0.0 {
+        Err(ShapeError::NotPositive(name.to_string()))
+    } else {
+        Ok(())
+    }
+}
+
+/// Check that a mesh is non-empty and its buffers are consistent
+fn check_mesh(mesh: &Mesh) -> Result<(), ShapeError> {
+    if mesh.indices.is_empty() {
+        return Err(ShapeError::EmptyMesh);
+    }
+    if !mesh.vertices.len().is_multiple_of(3) || !mesh.indices.len().is_multiple_of(3) {
+        return Err(ShapeError::InvalidMesh("buffer length is not a multiple of 3".to_string()));
+    }
+    if mesh.normals.len() != mesh.vertices.len() {
+        return Err(ShapeError::InvalidMesh("normal count does not match vertex count".to_string()));
+    }
+    if !mesh.colors.is_empty() && mesh.colors.len() != mesh.vertices.len() {
+        return Err(ShapeError::InvalidMesh("color count does not match vertex count".to_string()));
+    }
+    if mesh.vertices.iter().any(|v| !v.is_finite()) {
+        return Err(ShapeError::InvalidMesh("vertex coordinate is not finite".to_string()));
+    }
+    let count = mesh.vertex_count() as u32;
+    if let Some(index) = mesh.indices.iter().find(|i| **i >= count) {
+        return Err(ShapeError::InvalidMesh(format!("index {} is out of range", index)));
+    }
+    Ok(())
+}
+
+impl ShapeType {
+    /// Validate the shape parameters
+    pub fn validate(&self) -> Result<(), ShapeError> {
+        match self {
+            ShapeType::Box { length, width, height } => {
+                check_dimension("Length", *length)?;
+                check_dimension("Width", *width)?;
+                check_dimension("Height", *height)
+            }
+            ShapeType::Cylinder { radius, height } => {
+                check_dimension("Radius", *radius)?;
+                check_dimension("Height", *height)
+            }
+            ShapeType::Sphere { radius } => check_dimension("Radius", *radius),
+            ShapeType::ImportedMesh { mesh } => check_mesh(mesh),
+        }
+    }
+
+    /// Name of the shape type, e.g. "Box"
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ShapeType::Box { .. } => "Box",
+            ShapeType::Cylinder { .. } => "Cylinder",
+            ShapeType::Sphere { .. } => "Sphere",
+            ShapeType::ImportedMesh { .. } => "ImportedMesh",
+        }
+    }
+
+    /// Named parameters of the shape, using the same names as the object properties
+    pub fn params(&self) -> Vec<(&'static str, f32)> {
+        match self {
+            ShapeType::Box { length, width, height } => {
+                vec![("Length", *length), ("Width", *width), ("Height", *height)]
+            }
+            ShapeType::Cylinder { radius, height } => vec![("Radius", *radius), ("Height", *height)],
+            ShapeType::Sphere { radius } => vec![("Radius", *radius)],
+            ShapeType::ImportedMesh { .. } => Vec::new(),
+        }
+    }
+
+    /// Build a shape from its type name and named parameters
+    ///
+    /// Returns `None` for an unknown type, a missing parameter, or an imported
+    /// mesh, which cannot be described by parameters alone.
+    pub fn from_params(type_name: &str, params: &BTreeMap<String, f64>) -> Option<Self> {
+        let get = |name: &str| params.get(name).map(|v| *v as f32);
+        match type_name {
+            "Box" => Some(ShapeType::Box { length: get("Length")?, width: get("Width")?, height: get("Height")? }),
+            "Cylinder" => Some(ShapeType::Cylinder { radius: get("Radius")?, height: get("Height")? }),
+            "Sphere" => Some(ShapeType::Sphere { radius: get("Radius")? }),
+            _ => None,
+        }
+    }
+}
+
+/// Serializable description of an object, as listed to the front end
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectInfo {
+    pub id: u32,
+    #[serde(rename = "type")]
+    pub type_name: String,
+    pub params: BTreeMap<String, f64>,
+    pub placement: Placement,
+    #[serde(default, serialize_with = "crate::io::serialize_sorted")]
+    pub properties: HashMap<String, Property>,
+    #[serde(default)]
+    pub error: Option<String>,
 }
 
 /// An object in the CAD document
@@ -17,9 +159,23 @@ pub enum ShapeType {
 pub struct Object {
     pub id: u32,
     pub shape_type: ShapeType,
+    #[serde(serialize_with = "crate::io::serialize_sorted")]
     pub properties: HashMap<String, Property>,
     #[serde(skip)]
     pub mesh: Option<Mesh>,
+    /// Error from the last execution, if the parameters were invalid
+    #[serde(skip)]
+    pub error: Option<ShapeError>,
+    /// Local bounds of the mesh, computed on first use after each execution
+    #[serde(skip)]
+    bounds: OnceCell<(Aabb, Obb)>,
+    /// World bounds of the placed mesh, computed on first use after each
+    /// execution or change of placement
+    #[serde(skip)]
+    world_aabb: OnceCell<Aabb>,
+    /// Ray casting hierarchy of the mesh, built on first use after each execution
+    #[serde(skip)]
+    bvh: OnceCell<Bvh>,
 }
 
 impl Object {
@@ -29,11 +185,32 @@ impl Object {
             shape_type,
             properties: HashMap::new(),
             mesh: None,
+            error: None,
+            bounds: OnceCell::new(),
+            world_aabb: OnceCell::new(),
+            bvh: OnceCell::new(),
         }
     }
 
+    /// Validate the object's shape parameters
+    pub fn validate(&self) -> Result<(), ShapeError> {
+        self.shape_type.validate()
+    }
+
     /// Execute the object to generate its mesh
+    ///
+    /// Invalid parameters leave the object without a mesh and record the error.
     pub fn execute(&mut self) {
+        self.bounds = OnceCell::new();
+        self.world_aabb = OnceCell::new();
+        self.bvh = OnceCell::new();
+        if let Err(err) = self.validate() {
+            self.mesh = None;
+            self.error = Some(err);
+            return;
+        }
+        self.error = None;
+
         match &self.shape_type {
             ShapeType::Box { length, width, height } => {
                 self.mesh = Some(generate_box_mesh(*length as f64, *width as f64, *height as f64));
@@ -44,12 +221,17 @@ impl Object {
             ShapeType::Sphere { radius } => {
                 self.mesh = Some(generate_sphere_mesh(*radius as f64));
             }
+            ShapeType::ImportedMesh { mesh } => {
+                self.mesh = Some(mesh.clone());
+            }
         }
-
     }
 
     /// Set a property value
     pub fn set_property(&mut self, name: String, value: Property) {
+        if name == "Placement" {
+            self.world_aabb = OnceCell::new();
+        }
         self.properties.insert(name, value);
     }
 
@@ -57,11 +239,125 @@ impl Object {
     pub fn get_property(&self, name: &str) -> Option<&Property> {
         self.properties.get(name)
     }
+
+    /// Get the object's placement, defaulting to the origin
+    pub fn placement(&self) -> Placement {
+        self.get_property("Placement")
+            .and_then(|p| p.as_placement())
+            .unwrap_or_default()
+    }
+
+    /// Display name from the "Name" property, or the type and ID
+    pub fn name(&self) -> String {
+        match self.get_property("Name").and_then(|p| p.as_string()) {
+            Some(name) => name.to_string(),
+            None => format!("{} {}", self.shape_type.type_name(), self.id),
+        }
+    }
+
+    /// Linear RGB color from the "Color" property, defaulting to neutral grey
+    ///
+    /// Ignores the object's material; see `Document::object_color`.
+    pub fn color(&self) -> Vec3 {
+        self.get_property("Color")
+            .and_then(|p| p.as_vector())
+            .unwrap_or(DEFAULT_COLOR)
+    }
+
+    /// Density in kg/m³ from the "Density" property, which overrides the
+    /// material's, or an error if it is not a finite number above zero
+    pub fn density(&self) -> Result<Option<f64>, MassError> {
+        match self.get_property("Density").and_then(|p| p.as_float()) {
+            Some(density) if !(density.is_finite() && density > 0.0) => Err(MassError::InvalidDensity(density)),
+            density => Ok(density),
+        }
+    }
+
+    /// Name of the material from the "Material" property
+    pub fn material_name(&self) -> Option<&str> {
+        self.get_property("Material").and_then(|p| p.as_string())
+    }
+
+    /// Mass properties of the object's world space mesh, with a density in
+    /// kg/m³ and lengths in `units`
+    pub fn mass_properties(&self, density: f64, units: Units) -> Result<MassProperties, MassError> {
+        let mesh = self.world_mesh().ok_or(MassError::NoMesh)?;
+        MassProperties::from_mesh_in_units(&mesh, density, units)
+    }
+
+    /// The object's mesh moved into world space by its placement
+    pub fn world_mesh(&self) -> Option<Mesh> {
+        self.mesh.as_ref().map(|mesh| mesh.transformed(&self.placement()))
+    }
+
+    /// Axis-aligned and oriented bounding boxes of the mesh in local space
+    pub fn local_bounds(&self) -> Option<(Aabb, Obb)> {
+        let mesh = self.mesh.as_ref()?;
+        Some(*self.bounds.get_or_init(|| {
+            (Aabb::from_points((0..mesh.vertex_count()).map(|i| mesh.vertex(i))), Obb::fit(mesh))
+        }))
+    }
+
+    /// Axis-aligned bounding box of the mesh in world space
+    ///
+    /// Fitted to the placed vertices, so it is tight for any rotation.
+    pub fn world_aabb(&self) -> Option<Aabb> {
+        let mesh = self.mesh.as_ref()?;
+        Some(*self.world_aabb.get_or_init(|| {
+            let placement = self.placement();
+            Aabb::from_points((0..mesh.vertex_count()).map(|i| placement.transform_point(mesh.vertex(i))))
+        }))
+    }
+
+    /// Oriented bounding box of the mesh in world space
+    pub fn world_obb(&self) -> Option<Obb> {
+        self.local_bounds().map(|(_, obb)| obb.transformed(&self.placement()))
+    }
+
+    /// Hierarchy over the triangles of the mesh in local space
+    pub fn bvh(&self) -> Option<&Bvh> {
+        let mesh = self.mesh.as_ref()?;
+        Some(self.bvh.get_or_init(|| Bvh::build(mesh)))
+    }
+
+    /// Nearest hit of a world space ray on the object's placed mesh
+    pub fn pick(&self, ray: &Ray) -> Option<Hit> {
+        let (mesh, bvh) = (self.mesh.as_ref()?, self.bvh()?);
+        let placement = self.placement();
+        let local = ray.transformed(&placement.inverse());
+        let hit = bvh.intersect(mesh, &local)?;
+        Some(hit.transformed(&placement))
+    }
+
+    /// A shape parameter as entered: the property it was set from while that
+    /// still matches the shape, otherwise the shortest decimal of the `f32`
+    fn param_value(&self, name: &str, value: f32) -> f64 {
+        match self.get_property(name).and_then(Property::as_float) {
+            Some(entered) if entered as f32 == value => entered,
+            _ => value.to_string().parse().unwrap_or(value as f64),
+        }
+    }
+
+    /// Describe the object's type, parameters and placement
+    pub fn info(&self) -> ObjectInfo {
+        ObjectInfo {
+            id: self.id,
+            type_name: self.shape_type.type_name().to_string(),
+            params: self.shape_type.params()
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), self.param_value(name, value)))
+                .collect(),
+            placement: self.placement(),
+            properties: self.properties.clone(),
+            error: self.error.as_ref().map(|err| err.to_string()),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::geometry::generate_box_mesh;
 
     #[test]
     fn test_object_creation() {
@@ -91,4 +387,102 @@ mod tests {
         // Just check vertices count (24 vertices * 3 floats = 72)
         assert_eq!(mesh.vertices.len(), 72);
     }
+
+    #[test]
+    fn test_validate_rejects_bad_dimensions() {
+        let shape = ShapeType::Sphere { radius: 0.0 };
+        assert_eq!(shape.validate(), Err(ShapeError::NotPositive("Radius".to_string())));
+        assert_eq!(shape.validate().unwrap_err().to_string(), "Radius must be > 0");
+
+        let shape = ShapeType::Cylinder { radius: 1.0, height: f32::NAN };
+        assert_eq!(shape.validate().unwrap_err().to_string(), "Height is NaN");
+
+        let shape = ShapeType::Box { length: f32::INFINITY, width: 1.0, height: 1.0 };
+        assert_eq!(shape.validate(), Err(ShapeError::Infinite("Length".to_string())));
+
+        let shape = ShapeType::Box { length: 1.0, width: -2.0, height: 1.0 };
+        assert_eq!(shape.validate(), Err(ShapeError::NotPositive("Width".to_string())));
+    }
+
+    #[test]
+    fn test_object_info_round_trip() {
+        let mut obj = Object::new(3, ShapeType::Cylinder { radius: 1.5, height: 4.0 });
+        let placement = Placement::from_position_rotation(glam::Vec3::new(1.0, 2.0, 3.0), glam::Quat::IDENTITY);
+        obj.set_property("Placement".to_string(), Property::Placement(placement));
+
+        let info = obj.info();
+        assert_eq!(info.type_name, "Cylinder");
+        assert_eq!(info.params.get("Radius"), Some(&1.5));
+        assert_eq!(info.placement.position, placement.position);
+
+        let json = serde_json::to_string(&info).unwrap();
+        let parsed: ObjectInfo = serde_json::from_str(&json).unwrap();
+        let shape = ShapeType::from_params(&parsed.type_name, &parsed.params).unwrap();
+        assert!(matches!(shape, ShapeType::Cylinder { radius, height } if radius == 1.5 && height == 4.0));
+        assert!(ShapeType::from_params("Cone", &parsed.params).is_none());
+    }
+
+    #[test]
+    fn test_info_reports_params_as_entered() {
+        let mut obj = Object::new(1, ShapeType::Box { length: 0.1, width: 0.3, height: 2.7 });
+        obj.set_property("Length".to_string(), Property::Float(0.1));
+        // A property that no longer matches the shape is not reported
+        obj.set_property("Width".to_string(), Property::Float(5.0));
+        let info = obj.info();
+        assert_eq!(info.params["Length"], 0.1);
+        assert_eq!(info.params["Width"], 0.3);
+        assert_eq!(info.params["Height"], 2.7);
+        assert_eq!(serde_json::to_value(&info).unwrap()["params"]["Height"], serde_json::json!(2.7));
+    }
+
+    #[test]
+    fn test_imported_mesh() {
+        let mesh = generate_box_mesh(1.0, 1.0, 1.0);
+        let mut obj = Object::new(1, ShapeType::ImportedMesh { mesh: mesh.clone() });
+        obj.execute();
+        assert_eq!(obj.mesh.as_ref().unwrap().indices, mesh.indices);
+        assert!(obj.info().params.is_empty());
+
+        let mut broken = mesh.clone();
+        broken.indices[0] = 1000;
+        let shape = ShapeType::ImportedMesh { mesh: broken };
+        assert_eq!(shape.validate().unwrap_err().to_string(), "Mesh is invalid: index 1000 is out of range");
+        assert_eq!(ShapeType::ImportedMesh { mesh: Mesh::new() }.validate(), Err(ShapeError::EmptyMesh));
+    }
+
+    #[test]
+    fn test_execute_records_error() {
+        let mut obj = Object::new(1, ShapeType::Sphere { radius: -1.0 });
+        obj.execute();
+        assert!(obj.mesh.is_none());
+        assert_eq!(obj.error, Some(ShapeError::NotPositive("Radius".to_string())));
+
+        obj.shape_type = ShapeType::Sphere { radius: 1.0 };
+        obj.execute();
+        assert!(obj.mesh.is_some());
+        assert!(obj.error.is_none());
+    }
+
+    #[test]
+    fn test_bounds_follow_execution_and_placement() {
+        let mut obj = Object::new(1, ShapeType::Box { length: 4.0, width: 2.0, height: 1.0 });
+        assert!(obj.local_bounds().is_none());
+        obj.execute();
+        let (aabb, obb) = obj.local_bounds().unwrap();
+        assert_eq!(aabb.size(), Vec3::new(4.0, 2.0, 1.0));
+        assert!((obb.volume() - 8.0).abs() < 1e-4);
+
+        let placement = Placement::from_position_rotation(Vec3::new(10.0, 0.0, 0.0), glam::Quat::from_rotation_z(std::f32::consts::FRAC_PI_2));
+        obj.set_property("Placement".to_string(), Property::Placement(placement));
+        let world = obj.world_aabb().unwrap();
+        assert!((world.size() - Vec3::new(2.0, 4.0, 1.0)).length() < 1e-5);
+        obj.set_property("Placement".to_string(), Property::Placement(Placement::default()));
+        assert_eq!(obj.world_aabb().unwrap().size(), Vec3::new(4.0, 2.0, 1.0));
+        obj.set_property("Placement".to_string(), Property::Placement(placement));
+        assert!((obj.world_obb().unwrap().center - Vec3::new(10.0, 0.0, 0.0)).length() < 1e-5);
+
+        obj.shape_type = ShapeType::Box { length: 6.0, width: 2.0, height: 1.0 };
+        obj.execute();
+        assert_eq!(obj.local_bounds().unwrap().0.size(), Vec3::new(6.0, 2.0, 1.0));
+    }
 }
@@ -0,0 +1,497 @@
+//! 2D profiles and the solids swept from them
+//!
+//! A profile is a set of closed polygons in the XY plane. Contours nested an
+//! even number of times are outer boundaries and the rest are holes, so a
+//! washer is one profile of two circles. Curves are flattened into polygons
+//! by whoever builds the profile.
+
+use std::fmt;
+use glam::{Vec2, Vec3};
+use serde::Serialize;
+use crate::geometry::{triangle_normal, Mesh};
+
+/// Error raised when a profile cannot be swept into a solid
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProfileError {
+    NoClosedContours,
+    CrossesAxis,
+    NotPositive(String),
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProfileError::NoClosedContours => write!(f, "Profile has no closed contours"),
+            ProfileError::CrossesAxis => write!(f, "Profile crosses the axis of revolution"),
+            ProfileError::NotPositive(name) => write!(f, "{} must be > 0", name),
+        }
+    }
+}
+
+impl std::error::Error for ProfileError {}
+
+/// A closed polygon; the last point connects back to the first
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct Contour {
+    pub points: Vec<Vec2>,
+}
+
+impl Contour {
+    pub fn new(points: Vec<Vec2>) -> Self {
+        Self { points }
+    }
+
+    /// Signed area, positive for counter-clockwise contours
+    pub fn area(&self) -> f32 {
+        let n = self.points.len();
+        (0..n).map(|i| self.points[i].perp_dot(self.points[(i + 1) % n])).sum::<f32>() / 2.0
+    }
+
+    /// Whether a point lies inside, by the even-odd rule
+    pub fn contains(&self, p: Vec2) -> bool {
+        let n = self.points.len();
+        let mut inside = false;
+        for i in 0..n {
+            let (a, b) = (self.points[i], self.points[(i + 1) % n]);
+            if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+                inside = !inside;
+            }
+        }
+        inside
+    }
+
+    /// Copy wound counter-clockwise if `ccw` is true, clockwise otherwise
+    fn wound(&self, ccw: bool) -> Contour {
+        let mut contour = self.clone();
+        if (contour.area() > 0.0) != ccw {
+            contour.points.reverse();
+        }
+        contour
+    }
+}
+
+/// An outer boundary with the holes directly inside it
+#[derive(Debug, Clone, Serialize)]
+pub struct Region {
+    /// Counter-clockwise outer boundary
+    pub outer: Contour,
+    /// Clockwise holes
+    pub holes: Vec<Contour>,
+}
+
+/// A set of closed contours in the XY plane
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    pub contours: Vec<Contour>,
+}
+
+/// Join polylines that share end points into closed contours
+///
+/// Polylines may be joined in either direction. Returns the closed contours
+/// and the chains that could not be closed.
+pub fn chain(polylines: Vec<Vec<Vec2>>, tolerance: f32) -> (Vec<Contour>, Vec<Vec<Vec2>>) {
+    let mut remaining: Vec<Vec<Vec2>> = polylines.into_iter().filter(|p| p.len() >= 2).collect();
+    let mut closed = Vec::new();
+    let mut open = Vec::new();
+    let close = |a: Vec2, b: Vec2| a.distance(b) <= tolerance;
+
+    while let Some(mut current) = remaining.pop() {
+        loop {
+            let (first, last) = (current[0], *current.last().unwrap());
+            if current.len() > 2 && close(first, last) {
+                current.pop();
+                closed.push(Contour::new(current));
+                break;
+            }
+            let next = remaining.iter().position(|p| close(p[0], last) || close(*p.last().unwrap(), last));
+            match next {
+                Some(index) => {
+                    let mut next = remaining.swap_remove(index);
+                    if !close(next[0], last) {
+                        next.reverse();
+                    }
+                    current.extend_from_slice(&next[1..]);
+                }
+                None => {
+                    open.push(current);
+                    break;
+                }
+            }
+        }
+    }
+    (closed, open)
+}
+
+fn segments_cross(a: Vec2, b: Vec2, c: Vec2, d: Vec2) -> bool {
+    let side = |p: Vec2, q: Vec2, r: Vec2| (q - p).perp_dot(r - p);
+    let (d1, d2) = (side(c, d, a), side(c, d, b));
+    let (d3, d4) = (side(a, b, c), side(a, b, d));
+    ((d1 > 0.0 && d2 < 0.0) || (d1 < 0.0 && d2 > 0.0)) && ((d3 > 0.0 && d4 < 0.0) || (d3 < 0.0 && d4 > 0.0))
+}
+
+/// Whether `p` lies inside or on the boundary of a counter-clockwise triangle
+fn point_in_triangle(p: Vec2, a: Vec2, b: Vec2, c: Vec2) -> bool {
+    let d1 = (b - a).perp_dot(p - a);
+    let d2 = (c - b).perp_dot(p - b);
+    let d3 = (a - c).perp_dot(p - c);
+    d1 >= 0.0 && d2 >= 0.0 && d3 >= 0.0
+}
+
+/// Triangulate a region by ear clipping, after bridging its holes into the outer boundary
+///
+/// Returns triangles of indices into `points`, which lists the outer
+/// boundary followed by each hole.
+fn triangulate_region(region: &Region) -> (Vec<Vec2>, Vec<[u32; 3]>) {
+    let mut points = region.outer.points.clone();
+    let mut polygon: Vec<usize> = (0..points.len()).collect();
+    let mut edges: Vec<(usize, usize)> = (0..points.len()).map(|i| (i, (i + 1) % points.len())).collect();
+
+    let mut holes: Vec<(usize, usize)> = Vec::new();
+    for hole in &region.holes {
+        let start = points.len();
+        points.extend_from_slice(&hole.points);
+        let n = hole.points.len();
+        edges.extend((0..n).map(|i| (start + i, start + (i + 1) % n)));
+        holes.push((start, n));
+    }
+
+    // Bridge holes from the rightmost one, so later bridges cannot cross earlier ones
+    holes.sort_by(|a, b| {
+        let max_x = |(start, n): &(usize, usize)| points[*start..start + n].iter().map(|p| p.x).fold(f32::MIN, f32::max);
+        max_x(b).total_cmp(&max_x(a))
+    });
+    for (start, n) in holes {
+        let hole_vertex = (start..start + n).max_by(|a, b| points[*a].x.total_cmp(&points[*b].x)).unwrap();
+        let m = points[hole_vertex];
+        let visible = |v: usize| {
+            edges.iter().all(|&(a, b)| {
+                a == v || b == v || a == hole_vertex || b == hole_vertex || !segments_cross(m, points[v], points[a], points[b])
+            })
+        };
+        let Some(position) = polygon
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| visible(**v))
+            .min_by(|(_, a), (_, b)| points[**a].distance_squared(m).total_cmp(&points[**b].distance_squared(m)))
+            .map(|(i, _)| i)
+        else {
+            continue;
+        };
+        let outer_vertex = polygon[position];
+        // outer vertex -> around the hole -> back to the hole vertex -> outer vertex
+        let offset = hole_vertex - start;
+        let mut bridge: Vec<usize> = (0..=n).map(|i| start + (offset + i) % n).collect();
+        bridge.push(outer_vertex);
+        polygon.splice(position + 1..position + 1, bridge);
+        edges.push((outer_vertex, hole_vertex));
+    }
+
+    let mut triangles = Vec::new();
+    while polygon.len() > 3 {
+        let n = polygon.len();
+        let ear = (0..n).find(|&i| {
+            let (a, b, c) = (polygon[(i + n - 1) % n], polygon[i], polygon[(i + 1) % n]);
+            let (pa, pb, pc) = (points[a], points[b], points[c]);
+            if (pb - pa).perp_dot(pc - pb) <= 0.0 {
+                return false;
+            }
+            polygon.iter().all(|&v| {
+                let p = points[v];
+                p == pa || p == pb || p == pc || !point_in_triangle(p, pa, pb, pc)
+            })
+        });
+        // Degenerate input leaves no proper ear; clip anyway to terminate
+        let i = ear.unwrap_or(0);
+        let (a, b, c) = (polygon[(i + n - 1) % n], polygon[i], polygon[(i + 1) % n]);
+        if ear.is_some() {
+            triangles.push([a as u32, b as u32, c as u32]);
+        }
+        polygon.remove(i);
+    }
+    if let [a, b, c] = polygon[..] {
+        if (points[b] - points[a]).perp_dot(points[c] - points[b]) > 0.0 {
+            triangles.push([a as u32, b as u32, c as u32]);
+        }
+    }
+    (points, triangles)
+}
+
+impl Profile {
+    pub fn new(contours: Vec<Contour>) -> Self {
+        Self { contours }
+    }
+
+    /// Group contours into regions by how deeply they are nested
+    pub fn regions(&self) -> Vec<Region> {
+        let contours: Vec<&Contour> = self.contours.iter().filter(|c| c.points.len() >= 3 && c.area() != 0.0).collect();
+        let depth = |i: usize| {
+            let p = contours[i].points[0];
+            contours.iter().enumerate().filter(|(j, c)| *j != i && c.contains(p)).count()
+        };
+        let depths: Vec<usize> = (0..contours.len()).map(depth).collect();
+
+        let mut regions: Vec<(usize, Region)> = Vec::new();
+        for (i, contour) in contours.iter().enumerate() {
+            if depths[i].is_multiple_of(2) {
+                regions.push((i, Region { outer: contour.wound(true), holes: Vec::new() }));
+            }
+        }
+        for (i, contour) in contours.iter().enumerate() {
+            if !depths[i].is_multiple_of(2) {
+                // The hole belongs to the outer boundary one level up that contains it
+                let p = contour.points[0];
+                if let Some((_, region)) = regions.iter_mut().find(|(o, r)| depths[*o] + 1 == depths[i] && r.outer.contains(p)) {
+                    region.holes.push(contour.wound(false));
+                }
+            }
+        }
+        regions.into_iter().map(|(_, region)| region).collect()
+    }
+
+    /// Triangles covering the profile's regions, counter-clockwise
+    pub fn triangulate(&self) -> (Vec<Vec2>, Vec<[u32; 3]>) {
+        let mut points = Vec::new();
+        let mut triangles = Vec::new();
+        for region in self.regions() {
+            let (region_points, region_triangles) = triangulate_region(&region);
+            let offset = points.len() as u32;
+            points.extend(region_points);
+            triangles.extend(region_triangles.into_iter().map(|t| t.map(|i| i + offset)));
+        }
+        (points, triangles)
+    }
+
+    /// Solid swept along +Z from z = 0 to `height`
+    pub fn extrude(&self, height: f32) -> Result<Mesh, ProfileError> {
+        if height.is_nan() || height <= 0.0 {
+            return Err(ProfileError::NotPositive("Height".to_string()));
+        }
+        let regions = self.regions();
+        if regions.is_empty() {
+            return Err(ProfileError::NoClosedContours);
+        }
+
+        let mut mesh = Mesh::new();
+        let (points, triangles) = self.triangulate();
+        for (z, normal) in [(0.0, -Vec3::Z), (height, Vec3::Z)] {
+            let start = mesh.vertex_count() as u32;
+            for p in &points {
+                push_vertex(&mut mesh, p.extend(z), normal);
+            }
+            for [a, b, c] in &triangles {
+                let triangle = if z == 0.0 { [a, c, b] } else { [a, b, c] };
+                mesh.indices.extend(triangle.map(|i| start + i));
+            }
+        }
+
+        // Outer boundaries run counter-clockwise and holes clockwise, so
+        // the right-hand side of every edge faces out of the material
+        for region in &regions {
+            for contour in std::iter::once(&region.outer).chain(&region.holes) {
+                let n = contour.points.len();
+                for i in 0..n {
+                    let (a, b) = (contour.points[i], contour.points[(i + 1) % n]);
+                    let d = b - a;
+                    let normal = Vec3::new(d.y, -d.x, 0.0).normalize_or_zero();
+                    let start = mesh.vertex_count() as u32;
+                    for p in [a.extend(0.0), b.extend(0.0), b.extend(height), a.extend(height)] {
+                        push_vertex(&mut mesh, p, normal);
+                    }
+                    mesh.indices.extend_from_slice(&[start, start + 1, start + 2, start, start + 2, start + 3]);
+                }
+            }
+        }
+        Ok(mesh)
+    }
+
+    /// Solid swept around the Y axis by `angle` radians
+    ///
+    /// The profile's X coordinate is the distance from the axis and must not
+    /// be negative. Revolutions of less than a full turn are capped at both
+    /// ends.
+    pub fn revolve(&self, angle: f32) -> Result<Mesh, ProfileError> {
+        if angle.is_nan() || angle <= 0.0 {
+            return Err(ProfileError::NotPositive("Angle".to_string()));
+        }
+        let angle = angle.min(std::f32::consts::TAU);
+        let regions = self.regions();
+        if regions.is_empty() {
+            return Err(ProfileError::NoClosedContours);
+        }
+        let extent = self.contours.iter().flat_map(|c| &c.points).fold(0.0f32, |m, p| m.max(p.abs().max_element()));
+        let tolerance = extent * 1e-6;
+        if self.contours.iter().flat_map(|c| &c.points).any(|p| p.x < -tolerance) {
+            return Err(ProfileError::CrossesAxis);
+        }
+
+        let full = angle >= std::f32::consts::TAU - 1e-6;
+        let steps = ((64.0 * angle / std::f32::consts::TAU).ceil() as usize).max(2);
+        let rotate = |v: Vec3, theta: f32| Vec3::new(v.x * theta.cos(), v.y, -v.x * theta.sin());
+        let mut mesh = Mesh::new();
+
+        for region in &regions {
+            for contour in std::iter::once(&region.outer).chain(&region.holes) {
+                let n = contour.points.len();
+                for i in 0..n {
+                    let (a, b) = (contour.points[i], contour.points[(i + 1) % n]);
+                    if a.x <= tolerance && b.x <= tolerance {
+                        continue;
+                    }
+                    let d = b - a;
+                    let normal = Vec3::new(d.y, -d.x, 0.0).normalize_or_zero();
+                    for step in 0..steps {
+                        let (t0, t1) = (angle * step as f32 / steps as f32, angle * (step + 1) as f32 / steps as f32);
+                        let corners = [
+                            (rotate(a.extend(0.0), t0), t0),
+                            (rotate(b.extend(0.0), t0), t0),
+                            (rotate(b.extend(0.0), t1), t1),
+                            (rotate(a.extend(0.0), t1), t1),
+                        ];
+                        for [i, j, k] in [[0, 1, 2], [0, 2, 3]] {
+                            let triangle = [corners[i], corners[j], corners[k]];
+                            let face = triangle_normal(triangle[0].0, triangle[1].0, triangle[2].0);
+                            if face == Vec3::ZERO {
+                                continue;
+                            }
+                            let expected = rotate(normal, (t0 + t1) / 2.0);
+                            let ordered = if face.dot(expected) >= 0.0 { triangle } else { [triangle[0], triangle[2], triangle[1]] };
+                            let start = mesh.vertex_count() as u32;
+                            for (p, theta) in ordered {
+                                push_vertex(&mut mesh, p, rotate(normal, theta));
+                            }
+                            mesh.indices.extend_from_slice(&[start, start + 1, start + 2]);
+                        }
+                    }
+                }
+            }
+        }
+
+        if !full {
+            let (points, triangles) = self.triangulate();
+            // The start cap faces back along the sweep, the end cap forward
+            for (theta, sign) in [(0.0, -1.0), (angle, 1.0)] {
+                let normal = rotate(Vec3::Z, theta + std::f32::consts::FRAC_PI_2) * -sign;
+                let start = mesh.vertex_count() as u32;
+                for p in &points {
+                    push_vertex(&mut mesh, rotate(p.extend(0.0), theta), normal);
+                }
+                for triangle in &triangles {
+                    let [a, b, c] = triangle.map(|i| rotate(points[i as usize].extend(0.0), theta));
+                    let [i, j, k] = triangle.map(|i| start + i);
+                    if triangle_normal(a, b, c).dot(normal) >= 0.0 {
+                        mesh.indices.extend_from_slice(&[i, j, k]);
+                    } else {
+                        mesh.indices.extend_from_slice(&[i, k, j]);
+                    }
+                }
+            }
+        }
+        Ok(mesh)
+    }
+}
+
+fn push_vertex(mesh: &mut Mesh, p: Vec3, normal: Vec3) {
+    mesh.vertices.extend_from_slice(&p.to_array());
+    mesh.normals.extend_from_slice(&normal.to_array());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(center: Vec2, half: f32) -> Contour {
+        Contour::new(vec![
+            center + Vec2::new(-half, -half),
+            center + Vec2::new(half, -half),
+            center + Vec2::new(half, half),
+            center + Vec2::new(-half, half),
+        ])
+    }
+
+    fn area(points: &[Vec2], triangles: &[[u32; 3]]) -> f32 {
+        triangles
+            .iter()
+            .map(|t| {
+                let [a, b, c] = t.map(|i| points[i as usize]);
+                (b - a).perp_dot(c - a) / 2.0
+            })
+            .sum()
+    }
+
+    /// Signed volume of a closed mesh by the divergence theorem
+    fn volume(mesh: &Mesh) -> f32 {
+        mesh.triangles().map(|[a, b, c]| a.dot(b.cross(c)) / 6.0).sum()
+    }
+
+    #[test]
+    fn test_chain_joins_reversed_pieces() {
+        let pieces = vec![
+            vec![Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0)],
+            vec![Vec2::new(1.0, 1.0), Vec2::new(1.0, 0.0)],
+            vec![Vec2::new(1.0, 1.0), Vec2::new(0.0, 1.0), Vec2::new(0.0, 0.0)],
+            vec![Vec2::new(5.0, 5.0), Vec2::new(6.0, 5.0)],
+        ];
+        let (closed, open) = chain(pieces, 1e-6);
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].points.len(), 4);
+        assert!((closed[0].area().abs() - 1.0).abs() < 1e-6);
+        assert_eq!(open.len(), 1);
+    }
+
+    #[test]
+    fn test_regions_and_triangulation_with_hole() {
+        let profile = Profile::new(vec![square(Vec2::ZERO, 0.5), square(Vec2::ZERO, 2.0), square(Vec2::new(10.0, 0.0), 1.0)]);
+        let regions = profile.regions();
+        assert_eq!(regions.len(), 2);
+        assert_eq!(regions[0].holes.len(), 1);
+        assert!(regions[0].outer.area() > 0.0);
+        assert!(regions[0].holes[0].area() < 0.0);
+
+        let (points, triangles) = profile.triangulate();
+        assert!((area(&points, &triangles) - (16.0 - 1.0 + 4.0)).abs() < 1e-4);
+        assert!(triangles.iter().all(|t| area(&points, &[*t]) > 0.0));
+    }
+
+    #[test]
+    fn test_triangulate_concave() {
+        // An L shape
+        let l = Contour::new(vec![
+            Vec2::new(0.0, 0.0),
+            Vec2::new(2.0, 0.0),
+            Vec2::new(2.0, 1.0),
+            Vec2::new(1.0, 1.0),
+            Vec2::new(1.0, 2.0),
+            Vec2::new(0.0, 2.0),
+        ]);
+        let (points, triangles) = Profile::new(vec![l]).triangulate();
+        assert_eq!(triangles.len(), 4);
+        assert!((area(&points, &triangles) - 3.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_extrude_washer() {
+        let profile = Profile::new(vec![square(Vec2::ZERO, 2.0), square(Vec2::ZERO, 1.0)]);
+        let mesh = profile.extrude(3.0).unwrap();
+        assert!((volume(&mesh) - 12.0 * 3.0).abs() < 1e-3);
+        assert_eq!(profile.extrude(0.0).unwrap_err(), ProfileError::NotPositive("Height".to_string()));
+        assert_eq!(Profile::default().extrude(1.0).unwrap_err(), ProfileError::NoClosedContours);
+    }
+
+    #[test]
+    fn test_revolve() {
+        // A 1 x 2 rectangle touching the axis revolves into a cylinder
+        let rectangle = Contour::new(vec![Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(1.0, 2.0), Vec2::new(0.0, 2.0)]);
+        let profile = Profile::new(vec![rectangle]);
+        let mesh = profile.revolve(std::f32::consts::TAU).unwrap();
+        let expected = std::f32::consts::PI * 2.0;
+        assert!((volume(&mesh) - expected).abs() / expected < 0.01);
+
+        // Half a turn is capped and holds half the volume
+        let half = profile.revolve(std::f32::consts::PI).unwrap();
+        assert!((volume(&half) - expected / 2.0).abs() / expected < 0.01);
+
+        let crossing = Profile::new(vec![square(Vec2::ZERO, 1.0)]);
+        assert_eq!(crossing.revolve(1.0).unwrap_err(), ProfileError::CrossesAxis);
+    }
+}
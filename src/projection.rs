@@ -0,0 +1,381 @@
+//! Orthographic views of meshes
+//!
+//! A view looks at the model along a fixed direction and maps world points
+//! onto the 2D drawing plane, with X to the right and Y up.
+
+use glam::{Vec2, Vec3};
+use crate::bounds::Aabb;
+use crate::bvh::Bvh;
+use crate::geometry::{mesh_edges, triangle_normal, Mesh};
+
+/// Standard orthographic view directions, named after the side the viewer is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    Front,
+    Back,
+    Top,
+    Bottom,
+    Left,
+    Right,
+    Isometric,
+}
+
+impl View {
+    pub const ALL: [View; 7] = [
+        View::Front,
+        View::Back,
+        View::Top,
+        View::Bottom,
+        View::Left,
+        View::Right,
+        View::Isometric,
+    ];
+
+    /// Parse a view from its lowercase name, e.g. `"front"`
+    pub fn from_name(name: &str) -> Option<View> {
+        View::ALL.into_iter().find(|view| view.name() == name.to_ascii_lowercase())
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            View::Front => "front",
+            View::Back => "back",
+            View::Top => "top",
+            View::Bottom => "bottom",
+            View::Left => "left",
+            View::Right => "right",
+            View::Isometric => "isometric",
+        }
+    }
+
+    /// Drawing right, drawing up, and the direction towards the viewer
+    pub fn basis(self) -> (Vec3, Vec3, Vec3) {
+        match self {
+            View::Front => (Vec3::X, Vec3::Y, Vec3::Z),
+            View::Back => (-Vec3::X, Vec3::Y, -Vec3::Z),
+            View::Top => (Vec3::X, -Vec3::Z, Vec3::Y),
+            View::Bottom => (Vec3::X, Vec3::Z, -Vec3::Y),
+            View::Left => (Vec3::Z, Vec3::Y, -Vec3::X),
+            View::Right => (-Vec3::Z, Vec3::Y, Vec3::X),
+            View::Isometric => {
+                let toward = Vec3::ONE.normalize();
+                let right = Vec3::Y.cross(toward).normalize();
+                (right, toward.cross(right), toward)
+            }
+        }
+    }
+
+    /// Direction from the model towards the viewer
+    pub fn toward_viewer(self) -> Vec3 {
+        self.basis().2
+    }
+
+    /// Position of a world point on the drawing plane
+    pub fn project(self, p: Vec3) -> Vec2 {
+        let (right, up, _) = self.basis();
+        Vec2::new(p.dot(right), p.dot(up))
+    }
+}
+
+/// Edges forming the outline of a mesh seen from `view`, in world space
+///
+/// These are edges between a triangle facing the viewer and one facing away
+/// or seen edge-on, plus the boundary edges of open meshes.
+pub fn silhouette_edges(mesh: &Mesh, view: View) -> Vec<[Vec3; 2]> {
+    let toward = view.toward_viewer();
+    let (positions, triangles) = mesh.welded();
+    let facing: Vec<bool> = triangles
+        .iter()
+        .map(|t| {
+            let [a, b, c] = t.map(|i| positions[i as usize]);
+            triangle_normal(a, b, c).dot(toward) > 1e-6
+        })
+        .collect();
+    mesh_edges(&triangles)
+        .into_iter()
+        .filter(|edge| {
+            let front = edge.triangles.iter().filter(|t| facing[**t]).count();
+            edge.triangles.len() == 1 || (front > 0 && front < edge.triangles.len())
+        })
+        .map(|edge| edge.vertices.map(|i| positions[i as usize]))
+        .collect()
+}
+
+/// Dihedral angle in radians above which an edge between two faces is drawn
+pub const DEFAULT_CREASE_ANGLE: f32 = std::f32::consts::FRAC_PI_6;
+
+/// Edges to draw for a mesh seen from `view`, in world space
+///
+/// These are the silhouette edges, creases where the faces on either side
+/// meet at more than `crease_angle`, and boundary edges of open meshes.
+/// Smooth edges between the facets of curved surfaces are left out.
+pub fn feature_edges(mesh: &Mesh, view: View, crease_angle: f32) -> Vec<[Vec3; 2]> {
+    let toward = view.toward_viewer();
+    let (positions, triangles) = mesh.welded();
+    let normals: Vec<Vec3> = triangles
+        .iter()
+        .map(|t| {
+            let [a, b, c] = t.map(|i| positions[i as usize]);
+            triangle_normal(a, b, c)
+        })
+        .collect();
+    let min_cos = crease_angle.cos();
+    mesh_edges(&triangles)
+        .into_iter()
+        .filter(|edge| match edge.triangles[..] {
+            [a, b] => {
+                let (n0, n1) = (normals[a], normals[b]);
+                let silhouette = (n0.dot(toward) > 1e-6) != (n1.dot(toward) > 1e-6);
+                silhouette || n0.dot(n1) < min_cos
+            }
+            _ => true,
+        })
+        .map(|edge| edge.vertices.map(|i| positions[i as usize]))
+        .collect()
+}
+
+/// Projected edges split by whether they can be seen
+#[derive(Debug, Clone, Default)]
+pub struct ProjectedEdges {
+    pub visible: Vec<[Vec2; 2]>,
+    pub hidden: Vec<[Vec2; 2]>,
+}
+
+/// A triangle as seen from the view, for depth tests
+struct Occluder {
+    points: [Vec2; 3],
+    depths: [f32; 3],
+    min: Vec2,
+    max: Vec2,
+    /// Twice the signed projected area
+    area: f32,
+}
+
+impl Occluder {
+    /// Whether the triangle covers `q` in front of `depth`
+    fn hides(&self, q: Vec2, depth: f32, tolerance: f32) -> bool {
+        if q.cmplt(self.min).any() || q.cmpgt(self.max).any() {
+            return false;
+        }
+        let [a, b, c] = self.points;
+        let w0 = (b - q).perp_dot(c - q) / self.area;
+        let w1 = (c - q).perp_dot(a - q) / self.area;
+        let w2 = 1.0 - w0 - w1;
+        // Points on the triangle's outline count as covered, so edges that
+        // coincide with a nearer face's outline are hidden behind it
+        if w0 < -1e-4 || w1 < -1e-4 || w2 < -1e-4 {
+            return false;
+        }
+        let surface = w0 * self.depths[0] + w1 * self.depths[1] + w2 * self.depths[2];
+        surface > depth + tolerance
+    }
+}
+
+/// Parameter range along `a`..`b` covered by the collinear segment `c`..`d`
+fn overlap(a: Vec2, b: Vec2, c: Vec2, d: Vec2, tolerance: f32) -> Option<(f32, f32)> {
+    let direction = b - a;
+    let length_squared = direction.length_squared();
+    if length_squared == 0.0 {
+        return None;
+    }
+    let off_line = |p: Vec2| direction.perp_dot(p - a).abs() / length_squared.sqrt();
+    if off_line(c) > tolerance || off_line(d) > tolerance {
+        return None;
+    }
+    let (t0, t1) = ((c - a).dot(direction) / length_squared, (d - a).dot(direction) / length_squared);
+    let (t0, t1) = (t0.min(t1).max(0.0), t0.max(t1).min(1.0));
+    (t1 > t0).then_some((t0, t1))
+}
+
+/// Box around a region of the drawing plane grown by `margin`, flat in Z
+fn flat_box(min: Vec2, max: Vec2, margin: f32) -> Aabb {
+    Aabb { min: (min - margin).extend(0.0), max: (max + margin).extend(0.0) }
+}
+
+/// Feature edges of the meshes seen from `view`, with hidden parts separated
+///
+/// Every mesh hides the others. Hidden segments lying under visible ones are
+/// dropped, as visible lines take precedence in a drawing, and so are hidden
+/// segments drawn twice.
+pub fn hidden_lines(meshes: &[Mesh], view: View, crease_angle: f32) -> ProjectedEdges {
+    let toward = view.toward_viewer();
+    let extent = meshes.iter().flat_map(|m| &m.vertices).fold(0.0f32, |m, v| m.max(v.abs()));
+    let tolerance = (extent * 1e-4).max(1e-6);
+
+    let occluders: Vec<Occluder> = meshes
+        .iter()
+        .flat_map(|mesh| mesh.triangles())
+        .filter_map(|triangle| {
+            let points = triangle.map(|p| view.project(p));
+            let area = (points[1] - points[0]).perp_dot(points[2] - points[0]);
+            (area.abs() > tolerance * tolerance).then(|| Occluder {
+                points,
+                depths: triangle.map(|p| p.dot(toward)),
+                min: points[0].min(points[1]).min(points[2]),
+                max: points[0].max(points[1]).max(points[2]),
+                area,
+            })
+        })
+        .collect();
+    // Occluders are looked up by their projected boxes, flat in Z
+    let bvh = Bvh::from_boxes(&occluders.iter().map(|o| flat_box(o.min, o.max, 0.0)).collect::<Vec<_>>());
+    let visible_at = |p: Vec3| {
+        let (q, depth) = (view.project(p), p.dot(toward));
+        !bvh.any_overlapping(&flat_box(q, q, 0.0), |i| occluders[i].hides(q, depth, tolerance))
+    };
+
+    let mut edges = ProjectedEdges::default();
+    for [a, b] in meshes.iter().flat_map(|mesh| feature_edges(mesh, view, crease_angle)) {
+        let (pa, pb) = (view.project(a), view.project(b));
+        if pa.distance(pb) <= tolerance {
+            continue;
+        }
+        // Sample the middle of short pieces, then bisect between samples
+        // that differ to find where the edge passes behind a surface
+        let pieces = ((pa.distance(pb) / extent * 50.0).ceil() as usize).clamp(1, 64);
+        let samples: Vec<(f32, bool)> = (0..pieces)
+            .map(|i| {
+                let t = (i as f32 + 0.5) / pieces as f32;
+                (t, visible_at(a.lerp(b, t)))
+            })
+            .collect();
+        let mut start = 0.0;
+        for pair in samples.windows(2) {
+            let ((mut t0, visible), (mut t1, _)) = (pair[0], pair[1]);
+            if pair[0].1 == pair[1].1 {
+                continue;
+            }
+            for _ in 0..12 {
+                let t = (t0 + t1) / 2.0;
+                if visible_at(a.lerp(b, t)) == visible {
+                    t0 = t;
+                } else {
+                    t1 = t;
+                }
+            }
+            let end = (t0 + t1) / 2.0;
+            let target = if visible { &mut edges.visible } else { &mut edges.hidden };
+            target.push([pa.lerp(pb, start), pa.lerp(pb, end)]);
+            start = end;
+        }
+        let target = if samples.last().unwrap().1 { &mut edges.visible } else { &mut edges.hidden };
+        target.push([pa.lerp(pb, start), pb]);
+    }
+
+    // Remove the parts of hidden segments that are drawn as visible lines or
+    // already drawn as hidden ones. Together with the visible lines, earlier
+    // hidden segments cover the same ground as what was kept of them, so
+    // they are looked up whole.
+    let segments: Vec<[Vec2; 2]> = edges.visible.iter().chain(&edges.hidden).copied().collect();
+    let lines = Bvh::from_boxes(&segments.iter().map(|[a, b]| flat_box(a.min(*b), a.max(*b), tolerance)).collect::<Vec<_>>());
+    let mut hidden: Vec<[Vec2; 2]> = Vec::new();
+    for (i, [a, b]) in edges.hidden.iter().copied().enumerate() {
+        let mut covered: Vec<(f32, f32)> = Vec::new();
+        lines.any_overlapping(&flat_box(a.min(b), a.max(b), tolerance), |j| {
+            if j < edges.visible.len() + i {
+                let [c, d] = segments[j];
+                covered.extend(overlap(a, b, c, d, tolerance));
+            }
+            false
+        });
+        covered.sort_by(|x, y| x.0.total_cmp(&y.0));
+        let mut t = 0.0;
+        for (t0, t1) in covered {
+            if t0 > t {
+                hidden.push([a.lerp(b, t), a.lerp(b, t0)]);
+            }
+            t = t.max(t1);
+        }
+        if t < 1.0 {
+            hidden.push([a.lerp(b, t), b]);
+        }
+    }
+    edges.hidden = hidden.into_iter().filter(|[a, b]| a.distance(*b) > tolerance).collect();
+    edges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{generate_box_mesh, generate_cylinder_mesh};
+    use crate::math::Placement;
+
+    #[test]
+    fn test_basis_is_right_handed() {
+        for view in View::ALL {
+            let (right, up, toward) = view.basis();
+            assert!(right.cross(up).abs_diff_eq(toward, 1e-6), "{:?}", view);
+            assert_eq!(View::from_name(view.name()), Some(view));
+        }
+        assert_eq!(View::from_name("Front"), Some(View::Front));
+        assert_eq!(View::from_name("sideways"), None);
+    }
+
+    #[test]
+    fn test_project() {
+        let p = Vec3::new(1.0, 2.0, 3.0);
+        assert_eq!(View::Front.project(p), Vec2::new(1.0, 2.0));
+        assert_eq!(View::Top.project(p), Vec2::new(1.0, -3.0));
+        assert_eq!(View::Right.project(p), Vec2::new(-3.0, 2.0));
+    }
+
+    #[test]
+    fn test_box_silhouette() {
+        let mesh = generate_box_mesh(2.0, 4.0, 6.0);
+        let edges = silhouette_edges(&mesh, View::Front);
+        // The four edges around the front face
+        assert_eq!(edges.len(), 4);
+        assert!(edges.iter().flatten().all(|p| p.z > 0.0));
+        // Looking at a corner, six outer edges are visible
+        assert_eq!(silhouette_edges(&mesh, View::Isometric).len(), 6);
+    }
+
+    #[test]
+    fn test_feature_edges_skip_smooth_facets() {
+        let cylinder = generate_cylinder_mesh(1.0, 2.0);
+        let edges = feature_edges(&cylinder, View::Front, DEFAULT_CREASE_ANGLE);
+        // Both rims and the two silhouette lines along the sides
+        let rims = edges.iter().filter(|[a, b]| a.y == b.y).count();
+        let sides = edges.len() - rims;
+        assert_eq!(sides, 2);
+        assert!(rims >= 2 * 16);
+        assert_eq!(feature_edges(&generate_box_mesh(1.0, 1.0, 1.0), View::Front, DEFAULT_CREASE_ANGLE).len(), 12);
+    }
+
+    #[test]
+    fn test_hidden_lines_of_box() {
+        let mesh = generate_box_mesh(2.0, 2.0, 2.0);
+        let edges = hidden_lines(std::slice::from_ref(&mesh), View::Front, DEFAULT_CREASE_ANGLE);
+        // The back face outline coincides with the front one and is not drawn
+        assert_eq!(edges.visible.len(), 4);
+        assert!(edges.hidden.is_empty());
+
+        let edges = hidden_lines(&[mesh], View::Isometric, DEFAULT_CREASE_ANGLE);
+        assert_eq!(edges.visible.len(), 9);
+        assert_eq!(edges.hidden.len(), 3);
+    }
+
+    #[test]
+    fn test_hidden_lines_behind_another_mesh() {
+        // A small box behind a large one is entirely hidden from the front
+        let front = generate_box_mesh(10.0, 10.0, 1.0);
+        let back = generate_box_mesh(2.0, 2.0, 2.0).transformed(&Placement {
+            position: Vec3::new(0.0, 0.0, -5.0),
+            ..Placement::new()
+        });
+        let edges = hidden_lines(&[front, back], View::Front, DEFAULT_CREASE_ANGLE);
+        assert_eq!(edges.visible.len(), 4);
+        assert_eq!(edges.hidden.len(), 4);
+        assert!(edges.hidden.iter().flatten().all(|p| p.abs().max_element() <= 1.0 + 1e-4));
+
+        // Shifted half out from behind, its left edge and the left halves of
+        // its top and bottom edges stay hidden
+        let back = generate_box_mesh(2.0, 2.0, 2.0).transformed(&Placement {
+            position: Vec3::new(5.0, 0.0, -5.0),
+            ..Placement::new()
+        });
+        let edges = hidden_lines(&[generate_box_mesh(10.0, 10.0, 1.0), back], View::Front, DEFAULT_CREASE_ANGLE);
+        let hidden_length: f32 = edges.hidden.iter().map(|[a, b]| a.distance(*b)).sum();
+        assert!((hidden_length - 4.0).abs() < 0.01, "{}", hidden_length);
+    }
+}
@@ -0,0 +1,454 @@
+//! Repairs for meshes that do not quite bound a solid
+//!
+//! Meshes from STL files and other exporters often have every facet on its
+//! own vertices, slivers and doubled faces, small gaps and triangles wound
+//! the wrong way. Each repair here works on its own; `Mesh::repair` runs
+//! them all in order.
+
+use std::collections::HashMap;
+use glam::{IVec3, Vec3};
+use serde::Serialize;
+use crate::clash::winding_number;
+use crate::geometry::{mesh_edges, Mesh};
+use crate::projection::DEFAULT_CREASE_ANGLE;
+use crate::validation::{is_degenerate, patches};
+
+/// Largest hole `Mesh::repair` fills by default, in edges around it
+pub const DEFAULT_HOLE_EDGES: usize = 16;
+
+/// Which repairs `Mesh::repair` makes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RepairOptions {
+    /// Distance within which vertices are welded; at least a millionth of
+    /// the mesh size is always used
+    pub tolerance: f32,
+    /// Largest hole to fill, in edges around it; 0 fills none
+    pub max_hole_edges: usize,
+    /// Angle in radians between faces above which normals are split; 0
+    /// shades flat and π smooth
+    pub crease_angle: f32,
+}
+
+impl Default for RepairOptions {
+    fn default() -> Self {
+        Self { tolerance: 0.0, max_hole_edges: DEFAULT_HOLE_EDGES, crease_angle: DEFAULT_CREASE_ANGLE }
+    }
+}
+
+/// What `Mesh::repair` changed
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RepairReport {
+    pub welded_vertices: usize,
+    pub degenerate_triangles: usize,
+    pub duplicate_triangles: usize,
+    pub filled_holes: usize,
+    pub flipped_triangles: usize,
+}
+
+fn normal(mesh: &Mesh, i: usize) -> Vec3 {
+    mesh.normals.get(i * 3..i * 3 + 3).map_or(Vec3::ZERO, Vec3::from_slice)
+}
+
+/// Keep only the given vertices, in that order, renumbering the triangles
+/// by `remap` from old to new vertex indices
+fn reindex(mesh: &mut Mesh, kept: &[usize], remap: &[u32]) {
+    let pick = |data: &[f32]| -> Vec<f32> {
+        if data.is_empty() {
+            return Vec::new();
+        }
+        kept.iter().flat_map(|&i| data[i * 3..i * 3 + 3].iter().copied()).collect()
+    };
+    mesh.vertices = pick(&mesh.vertices);
+    mesh.normals = pick(&mesh.normals);
+    mesh.colors = pick(&mesh.colors);
+    for index in &mut mesh.indices {
+        *index = remap[*index as usize];
+    }
+}
+
+/// Merge vertices within `tolerance` of each other whose normals are within
+/// `crease_angle`, returning how many were merged
+///
+/// Vertices kept apart for their normals, as along the edges of a box, are
+/// still moved onto each other so the surface is connected.
+pub fn weld(mesh: &mut Mesh, tolerance: f32, crease_angle: f32) -> usize {
+    let extent = mesh.vertices.iter().fold(0.0f32, |m, v| m.max(v.abs()));
+    let tolerance = tolerance.max(extent * 1e-6).max(f32::MIN_POSITIVE);
+    let min_cos = crease_angle.cos() - 1e-6;
+
+    // Groups of welded vertices, as their shared position and the kept
+    // vertices split off for their normals, filed by the grid cell of that
+    // position. Cells are as wide as the tolerance, so any group within it
+    // is filed in one of the 27 cells around a vertex.
+    let mut groups: Vec<(Vec3, Vec<u32>)> = Vec::new();
+    let mut cells: HashMap<[i32; 3], Vec<usize>> = HashMap::new();
+    let cell = |p: Vec3| (p / tolerance).floor().as_ivec3();
+    let mut kept = Vec::new();
+    let mut remap = Vec::with_capacity(mesh.vertex_count());
+    for i in 0..mesh.vertex_count() {
+        let p = mesh.vertex(i);
+        let n = normal(mesh, i);
+        let center = cell(p);
+        let nearest = (0..27)
+            .filter_map(|k| cells.get(&(center + IVec3::new(k % 3 - 1, k / 3 % 3 - 1, k / 9 - 1)).to_array()))
+            .flatten()
+            .map(|&g| (g, groups[g].0.distance(p)))
+            .filter(|(_, distance)| *distance <= tolerance)
+            .min_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+        let group = match nearest {
+            Some((g, _)) => g,
+            None => {
+                groups.push((p, Vec::new()));
+                cells.entry(center.to_array()).or_default().push(groups.len() - 1);
+                groups.len() - 1
+            }
+        };
+        let (position, merged) = &mut groups[group];
+        mesh.vertices[i * 3..i * 3 + 3].copy_from_slice(&position.to_array());
+        let same = merged.iter().copied().find(|&j| {
+            let m = normal(mesh, kept[j as usize]);
+            n == Vec3::ZERO || m == Vec3::ZERO || n.dot(m) >= min_cos
+        });
+        remap.push(same.unwrap_or_else(|| {
+            kept.push(i);
+            merged.push(kept.len() as u32 - 1);
+            kept.len() as u32 - 1
+        }));
+    }
+    let merged = mesh.vertex_count() - kept.len();
+    reindex(mesh, &kept, &remap);
+    merged
+}
+
+/// Remove triangles with no area and all but the first of triangles on the
+/// same corners, returning how many of each were removed
+pub fn remove_degenerate(mesh: &mut Mesh) -> (usize, usize) {
+    let (positions, remap) = mesh.weld_map();
+    let mut seen = std::collections::HashSet::new();
+    let (mut degenerate, mut duplicate) = (0, 0);
+    let mut indices = Vec::with_capacity(mesh.indices.len());
+    for i in 0..mesh.triangle_count() {
+        let corners = mesh.triangle_indices(i);
+        let welded = corners.map(|v| remap[v as usize]);
+        let mut key = welded;
+        key.sort_unstable();
+        if is_degenerate(welded.map(|v| positions[v as usize])) || key[0] == key[1] || key[1] == key[2] {
+            degenerate += 1;
+        } else if !seen.insert(key) {
+            duplicate += 1;
+        } else {
+            indices.extend_from_slice(&corners);
+        }
+    }
+    mesh.indices = indices;
+    (degenerate, duplicate)
+}
+
+/// Close holes with at most `max_edges` edges around them, returning how
+/// many were filled
+///
+/// Each hole is closed by a fan from the middle of its rim, on vertices of
+/// its own with the normal of the patch. Rims that touch themselves or
+/// other holes are left open.
+pub fn fill_holes(mesh: &mut Mesh, max_edges: usize) -> usize {
+    let (positions, remap) = mesh.weld_map();
+    let triangles: Vec<[u32; 3]> = (0..mesh.triangle_count())
+        .map(|i| mesh.triangle_indices(i).map(|v| remap[v as usize]))
+        .filter(|[a, b, c]| a != b && b != c && a != c)
+        .collect();
+    // Open edges in the direction their triangle runs them
+    let mut next: HashMap<u32, Vec<u32>> = HashMap::new();
+    for edge in mesh_edges(&triangles).into_iter().filter(|e| e.triangles.len() == 1) {
+        let [u, v] = edge.vertices;
+        let triangle = triangles[edge.triangles[0]];
+        let forward = (0..3).any(|i| triangle[i] == u && triangle[(i + 1) % 3] == v);
+        let (from, to) = if forward { (u, v) } else { (v, u) };
+        next.entry(from).or_default().push(to);
+    }
+    // A vertex of the original mesh at each welded one, for its color
+    let mut original = vec![0; positions.len()];
+    for (i, &w) in remap.iter().enumerate().rev() {
+        original[w as usize] = i;
+    }
+
+    let mut starts: Vec<u32> = next.keys().copied().collect();
+    starts.sort_unstable();
+    let mut visited = std::collections::HashSet::new();
+    let mut filled = 0;
+    for start in starts {
+        let mut rim = vec![start];
+        let closed = loop {
+            let current = *rim.last().unwrap();
+            if !visited.insert(current) {
+                break false;
+            }
+            match next.get(&current).map(Vec::as_slice) {
+                Some(&[to]) if to == start => break true,
+                Some(&[to]) if rim.len() < max_edges => rim.push(to),
+                _ => break false,
+            }
+        };
+        if !closed || rim.len() < 3 {
+            continue;
+        }
+
+        let points: Vec<Vec3> = rim.iter().map(|&v| positions[v as usize]).collect();
+        // Newell's normal of the rim, turned to face the way the patch is wound
+        let patch_normal = -(0..points.len())
+            .map(|i| points[i].cross(points[(i + 1) % points.len()]))
+            .sum::<Vec3>()
+            .normalize_or_zero();
+        // Triangles need no middle vertex
+        let center = (points.len() > 3).then(|| points.iter().sum::<Vec3>() / points.len() as f32);
+        let offset = mesh.vertex_count() as u32;
+        let mut colors: Vec<Vec3> = rim.iter().filter_map(|&v| mesh.color(original[v as usize])).collect();
+        for p in points.iter().copied().chain(center) {
+            mesh.vertices.extend_from_slice(&p.to_array());
+            mesh.normals.extend_from_slice(&patch_normal.to_array());
+        }
+        if !colors.is_empty() {
+            if center.is_some() {
+                colors.push(colors.iter().sum::<Vec3>() / colors.len() as f32);
+            }
+            mesh.colors.extend(colors.iter().flat_map(|c| c.to_array()));
+        }
+        let n = rim.len() as u32;
+        if n == 3 {
+            mesh.indices.extend_from_slice(&[offset + 2, offset + 1, offset]);
+        } else {
+            for i in 0..n {
+                mesh.indices.extend_from_slice(&[offset + (i + 1) % n, offset + i, offset + n]);
+            }
+        }
+        filled += 1;
+    }
+    filled
+}
+
+/// Wind the triangles of each connected patch the same way, facing out of
+/// the solid it bounds, returning how many triangles were turned
+///
+/// Closed patches inside others, as the inner wall of a hollow part, face
+/// into the hollow. Open patches keep the winding most of their triangles
+/// have.
+pub fn orient(mesh: &mut Mesh) -> usize {
+    let (positions, remap) = mesh.weld_map();
+    let (kept, triangles): (Vec<usize>, Vec<[u32; 3]>) = (0..mesh.triangle_count())
+        .map(|i| (i, mesh.triangle_indices(i).map(|v| remap[v as usize])))
+        .filter(|(_, [a, b, c])| a != b && b != c && a != c)
+        .unzip();
+    let edges = mesh_edges(&triangles);
+    let mut open = vec![false; triangles.len()];
+    for edge in edges.iter().filter(|e| e.triangles.len() != 2) {
+        for &t in &edge.triangles {
+            open[t] = true;
+        }
+    }
+
+    // Wind each patch as most of it is, then find the closed ones facing in
+    let mut turn = vec![false; triangles.len()];
+    let mut closed = Vec::new();
+    for patch in patches(&triangles, &edges) {
+        for &(t, wrong) in &patch {
+            turn[t] = wrong;
+        }
+        if patch.iter().all(|&(t, _)| !open[t]) {
+            closed.push(patch.into_iter().map(|(t, _)| t).collect::<Vec<_>>());
+        }
+    }
+    let corners = |t: usize, turned: bool| {
+        let [a, b, c] = triangles[t].map(|v| positions[v as usize]);
+        if turned { [a, c, b] } else { [a, b, c] }
+    };
+    let surfaces: Vec<Vec<[Vec3; 3]>> = closed.iter().map(|patch| patch.iter().map(|&t| corners(t, turn[t])).collect()).collect();
+    for (i, patch) in closed.iter().enumerate() {
+        let volume: f64 = surfaces[i].iter().map(|[a, b, c]| a.as_dvec3().dot(b.as_dvec3().cross(c.as_dvec3()))).sum();
+        let [a, b, c] = surfaces[i][0];
+        let inside = (0..surfaces.len())
+            .filter(|&j| j != i && winding_number(&surfaces[j], (a + b + c) / 3.0).abs() > 0.5)
+            .count();
+        // Outer walls hold positive volume and the walls of hollows negative
+        if (volume < 0.0) == inside.is_multiple_of(2) {
+            for &t in patch {
+                turn[t] = !turn[t];
+            }
+        }
+    }
+
+    let mut turned = 0;
+    for (t, &index) in kept.iter().enumerate() {
+        if turn[t] {
+            mesh.indices.swap(index * 3 + 1, index * 3 + 2);
+            turned += 1;
+        }
+    }
+    turned
+}
+
+/// Replace the normals, averaging the faces around each vertex that meet
+/// the corner's own face at no more than `crease_angle`
+///
+/// Coincident vertices are merged where their corners get the same normal
+/// and color, and split where they do not. Vertices no triangle uses are
+/// dropped.
+pub fn recompute_normals(mesh: &mut Mesh, crease_angle: f32) {
+    let (positions, remap) = mesh.weld_map();
+    let min_cos = crease_angle.cos() - 1e-6;
+    // Area weighted normals, and the triangles at each welded vertex
+    let faces: Vec<Vec3> = mesh.triangles().map(|[a, b, c]| (b - a).cross(c - a)).collect();
+    let mut around: Vec<Vec<usize>> = vec![Vec::new(); positions.len()];
+    for t in 0..mesh.triangle_count() {
+        for v in mesh.triangle_indices(t) {
+            around[remap[v as usize] as usize].push(t);
+        }
+    }
+
+    let mut split: HashMap<(u32, [u32; 3], [u32; 3]), u32> = HashMap::new();
+    let mut kept = Vec::new();
+    let mut normals = Vec::new();
+    let mut indices = Vec::with_capacity(mesh.indices.len());
+    for t in 0..mesh.triangle_count() {
+        let own = faces[t].normalize_or_zero();
+        for v in mesh.triangle_indices(t) {
+            let n = around[remap[v as usize] as usize]
+                .iter()
+                .filter(|&&s| s == t || faces[s].normalize_or_zero().dot(own) >= min_cos)
+                .map(|&s| faces[s])
+                .sum::<Vec3>()
+                .normalize_or_zero();
+            let color = mesh.color(v as usize).unwrap_or_default().to_array().map(f32::to_bits);
+            let key = (remap[v as usize], n.to_array().map(f32::to_bits), color);
+            let index = *split.entry(key).or_insert_with(|| {
+                kept.push(v as usize);
+                normals.push(n);
+                kept.len() as u32 - 1
+            });
+            indices.push(index);
+        }
+    }
+    let remap: Vec<u32> = (0..mesh.vertex_count() as u32).collect();
+    reindex(mesh, &kept, &remap);
+    mesh.normals = normals.into_iter().flat_map(|n| n.to_array()).collect();
+    mesh.indices = indices;
+}
+
+pub(crate) fn repair(mesh: &mut Mesh, options: &RepairOptions) -> RepairReport {
+    let welded_vertices = weld(mesh, options.tolerance, options.crease_angle);
+    let (degenerate_triangles, duplicate_triangles) = remove_degenerate(mesh);
+    let filled_holes = fill_holes(mesh, options.max_hole_edges);
+    let flipped_triangles = orient(mesh);
+    recompute_normals(mesh, options.crease_angle);
+    RepairReport { welded_vertices, degenerate_triangles, duplicate_triangles, filled_holes, flipped_triangles }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{generate_box_mesh, generate_sphere_mesh, triangle_normal};
+    use crate::io::stl;
+
+    /// A mesh with every triangle on vertices of its own, as read from STL
+    fn unshared(mesh: &Mesh) -> Mesh {
+        let mut soup = Mesh::new();
+        for triangle in mesh.triangles() {
+            let normal = triangle_normal(triangle[0], triangle[1], triangle[2]);
+            for p in triangle {
+                soup.indices.push(soup.vertex_count() as u32);
+                soup.vertices.extend_from_slice(&p.to_array());
+                soup.normals.extend_from_slice(&normal.to_array());
+            }
+        }
+        soup
+    }
+
+    #[test]
+    fn test_weld_keeps_creases() {
+        let mut cube = unshared(&generate_box_mesh(2.0, 2.0, 2.0));
+        assert_eq!(weld(&mut cube, 0.0, DEFAULT_CREASE_ANGLE), 36 - 24);
+        assert_eq!(cube.vertex_count(), 24);
+        // Across every edge once normals no longer matter
+        assert_eq!(weld(&mut cube, 0.0, std::f32::consts::PI), 16);
+
+        // Vertices a little apart are snapped together within the tolerance
+        let mut near = generate_box_mesh(2.0, 2.0, 2.0);
+        near.vertices[0] += 0.001;
+        weld(&mut near, 0.01, DEFAULT_CREASE_ANGLE);
+        assert!(near.validate().is_watertight());
+    }
+
+    #[test]
+    fn test_weld_across_cell_boundaries() {
+        // Within the tolerance but either side of a multiple of it
+        let mut mesh = Mesh::new();
+        mesh.vertices = vec![0.0049, 0.0, 0.0, 0.0051, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0151, 0.0, 0.0];
+        mesh.indices = vec![0, 2, 3, 1, 3, 2, 4, 2, 3];
+        assert_eq!(weld(&mut mesh, 0.01, DEFAULT_CREASE_ANGLE), 1);
+        assert_eq!(mesh.vertex_count(), 4);
+        assert_eq!(&mesh.vertices[..3], &[0.0049, 0.0, 0.0]);
+        // The far point is within the tolerance of 0.0051 but not of where it was welded
+        assert_eq!(mesh.vertex(3), Vec3::new(0.0151, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_remove_degenerate_and_duplicates() {
+        let mut cube = generate_box_mesh(2.0, 2.0, 2.0);
+        let first: Vec<u32> = cube.indices[..6].to_vec();
+        cube.indices.extend_from_slice(&first);
+        cube.indices.extend_from_slice(&[0, 0, 1, 0, 1, 2]);
+        assert_eq!(remove_degenerate(&mut cube), (1, 3));
+        assert_eq!(cube.triangle_count(), 12);
+    }
+
+    #[test]
+    fn test_fill_holes() {
+        let mut open = generate_box_mesh(2.0, 2.0, 2.0);
+        open.indices.truncate(30);
+        assert_eq!(fill_holes(&mut open, 3), 0);
+        assert_eq!(fill_holes(&mut open, 4), 1);
+        let report = open.validate();
+        assert!(report.is_valid(), "{:?}", report);
+        // The square hole is fanned from its middle
+        assert_eq!(open.triangle_count(), 14);
+    }
+
+    #[test]
+    fn test_orient() {
+        let mut cube = generate_box_mesh(2.0, 2.0, 2.0);
+        cube.indices.swap(1, 2);
+        assert_eq!(orient(&mut cube), 1);
+        assert!(cube.validate().is_valid());
+
+        // A hollow ball faces out on the outside and in on the inside
+        let mut hollow = generate_sphere_mesh(2.0);
+        hollow.append(&generate_sphere_mesh(1.0));
+        for triangle in hollow.indices.chunks_mut(3) {
+            triangle.swap(1, 2);
+        }
+        let count = hollow.triangle_count();
+        assert_eq!(orient(&mut hollow), count / 2);
+        let volume: f32 = hollow.triangles().map(|[a, b, c]| a.dot(b.cross(c)) / 6.0).sum();
+        let expected = 4.0 / 3.0 * std::f32::consts::PI * (8.0 - 1.0);
+        assert!((volume - expected).abs() < 0.05 * expected, "{}", volume);
+    }
+
+    #[test]
+    fn test_repair_stl_soup() {
+        let mut cube = generate_box_mesh(2.0, 2.0, 2.0);
+        // Lose a face and turn another around
+        cube.indices.truncate(30);
+        cube.indices.swap(1, 2);
+        let mut mesh = stl::read(&stl::write_binary(&cube, "vendor")).unwrap().mesh;
+        assert!(!mesh.validate().is_watertight());
+
+        let report = mesh.repair(&RepairOptions::default());
+        assert_eq!(report.filled_holes, 1);
+        assert_eq!(report.flipped_triangles, 1);
+        let validation = mesh.validate();
+        assert!(validation.is_valid(), "{:?}", validation);
+        // Flat faces stay flat: every normal is along an axis
+        for i in 0..mesh.vertex_count() {
+            assert_eq!(normal(&mesh, i).abs().max_element(), 1.0);
+        }
+        assert_eq!(mesh.vertex_count(), 24 + 1);
+    }
+}
@@ -0,0 +1,220 @@
+//! Planar cross-sections of objects
+//!
+//! Each object's world space mesh is cut by the plane and the cut segments
+//! are joined into closed contours, in 2D coordinates on the plane. The
+//! contours are nested into regions of an outer loop and its holes, as seen
+//! from the side the plane's normal points to, so a section can be exported
+//! as a drawing or swept into a new solid like any other profile.
+
+use glam::{Mat3, Quat, Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+use crate::document::Document;
+use crate::math::Placement;
+use crate::profile::{chain, Contour, Profile, Region};
+use crate::projection::View;
+
+/// A cutting plane
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Plane {
+    pub origin: Vec3,
+    /// Unit normal
+    pub normal: Vec3,
+}
+
+impl Plane {
+    /// Plane through `origin` facing `normal`, or `None` if the normal is zero
+    pub fn new(origin: Vec3, normal: Vec3) -> Option<Plane> {
+        Some(Plane { origin, normal: normal.try_normalize()? })
+    }
+
+    /// Plane facing the viewer of `view`, `offset` from the origin towards them
+    pub fn from_view(view: View, offset: f32) -> Plane {
+        let toward = view.toward_viewer();
+        Plane { origin: toward * offset, normal: toward }
+    }
+
+    /// Unit X and Y axes of the plane's 2D coordinates, with X × Y along the
+    /// normal
+    ///
+    /// Planes facing a standard view share its axes, so sections line up
+    /// with drawings. Others keep X horizontal where they can.
+    pub fn axes(&self) -> (Vec3, Vec3) {
+        if let Some(view) = View::ALL.into_iter().find(|v| v.toward_viewer().abs_diff_eq(self.normal, 1e-6)) {
+            let (right, up, _) = view.basis();
+            return (right, up);
+        }
+        let up = if self.normal.z.abs() < 1.0 - 1e-6 { Vec3::Z } else { Vec3::Y };
+        let x = up.cross(self.normal).normalize();
+        (x, self.normal.cross(x))
+    }
+
+    /// 2D coordinates of a world point projected onto the plane
+    pub fn project(&self, p: Vec3) -> Vec2 {
+        let (x, y) = self.axes();
+        Vec2::new((p - self.origin).dot(x), (p - self.origin).dot(y))
+    }
+
+    /// World point of 2D plane coordinates
+    pub fn unproject(&self, p: Vec2) -> Vec3 {
+        let (x, y) = self.axes();
+        self.origin + x * p.x + y * p.y
+    }
+
+    /// Placement taking the XY plane onto this plane, with +Z along the normal
+    pub fn placement(&self) -> Placement {
+        let (x, y) = self.axes();
+        Placement::from_position_rotation(self.origin, Quat::from_mat3(&Mat3::from_cols(x, y, self.normal)))
+    }
+}
+
+/// Where a plane cuts one object
+#[derive(Debug, Clone, Serialize)]
+pub struct ObjectSection {
+    pub object: u32,
+    /// Area of material cut, in square document units
+    pub area: f32,
+    /// Outer loops, counter-clockwise in plane coordinates, with their
+    /// clockwise holes
+    pub regions: Vec<Region>,
+    /// Chains that do not close because the mesh is open
+    pub open: Vec<Vec<Vec2>>,
+}
+
+impl ObjectSection {
+    /// The section's loops as a profile in plane coordinates
+    pub fn profile(&self) -> Profile {
+        Profile::new(self.regions.iter().flat_map(|r| std::iter::once(&r.outer).chain(&r.holes)).cloned().collect())
+    }
+}
+
+/// Whether `b` lies on the segment from `a` to `c`, within `tolerance`
+fn is_between(a: Vec2, b: Vec2, c: Vec2, tolerance: f32) -> bool {
+    let chord = c - a;
+    chord.perp_dot(b - a).abs() <= tolerance * chord.length() && (b - a).dot(c - b) >= 0.0
+}
+
+/// Drop points of a closed contour lying on the line between their
+/// neighbours, as where a cut crosses the diagonals of quads
+fn drop_collinear(contour: Contour, tolerance: f32) -> Contour {
+    let mut points: Vec<Vec2> = Vec::with_capacity(contour.points.len());
+    for p in contour.points {
+        while let [.., a, b] = points[..] {
+            if !is_between(a, b, p, tolerance) {
+                break;
+            }
+            points.pop();
+        }
+        points.push(p);
+    }
+    // Then around the seam where the contour closes
+    while points.len() > 3 {
+        let n = points.len();
+        if is_between(points[n - 2], points[n - 1], points[0], tolerance) {
+            points.pop();
+        } else if is_between(points[n - 1], points[0], points[1], tolerance) {
+            points.remove(0);
+        } else {
+            break;
+        }
+    }
+    Contour::new(points)
+}
+
+/// Cut the given objects, or all objects if `ids` is empty, by a plane
+///
+/// Objects the plane misses are left out.
+pub fn section(doc: &Document, ids: &[u32], plane: &Plane) -> Vec<ObjectSection> {
+    doc.resolve_ids(ids)
+        .into_iter()
+        .filter_map(|object| {
+            let mesh = doc.world_mesh(object)?;
+            let segments: Vec<[Vec2; 2]> = mesh
+                .section(plane.origin, plane.normal)
+                .into_iter()
+                .map(|s| s.map(|p| plane.project(p)))
+                .collect();
+            if segments.is_empty() {
+                return None;
+            }
+            let extent = segments.iter().flatten().fold(0.0f32, |m, p| m.max(p.abs().max_element()));
+            let tolerance = (extent * 1e-5).max(1e-6);
+            // Triangles touching the plane at a corner give segments of no length
+            let segments = segments.into_iter().filter(|[a, b]| a.distance(*b) > tolerance).map(|s| s.to_vec()).collect();
+            let (contours, open) = chain(segments, tolerance);
+            let contours = contours.into_iter().map(|c| drop_collinear(c, tolerance)).collect();
+            let regions = Profile::new(contours).regions();
+            let area = regions.iter().map(|r| r.outer.area() + r.holes.iter().map(Contour::area).sum::<f32>()).sum();
+            Some(ObjectSection { object, area, regions, open })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::ShapeType;
+    use crate::property::Property;
+
+    #[test]
+    fn test_plane_axes() {
+        let front = Plane::from_view(View::Front, 2.0);
+        assert_eq!(front.axes(), (Vec3::X, Vec3::Y));
+        assert_eq!(front.project(Vec3::new(1.0, 2.0, 3.0)), Vec2::new(1.0, 2.0));
+
+        let plane = Plane::new(Vec3::new(1.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 0.0)).unwrap();
+        let (x, y) = plane.axes();
+        assert!(x.cross(y).abs_diff_eq(plane.normal, 1e-6));
+        assert!(x.z.abs() < 1e-6, "X stays horizontal");
+        let p = Vec3::new(0.0, 1.0, 5.0);
+        assert!(plane.unproject(plane.project(p)).abs_diff_eq(p, 1e-5));
+        assert!(plane.placement().transform_point(Vec3::new(2.0, 3.0, 0.0)).abs_diff_eq(plane.unproject(Vec2::new(2.0, 3.0)), 1e-5));
+        assert!(Plane::new(Vec3::ZERO, Vec3::ZERO).is_none());
+    }
+
+    #[test]
+    fn test_sections_per_object() {
+        let mut doc = Document::new();
+        let cube = doc.add_object(ShapeType::Box { length: 4.0, width: 2.0, height: 6.0 });
+        let cylinder = doc.add_object(ShapeType::Cylinder { radius: 1.0, height: 4.0 });
+        let placement = Placement::from_position_rotation(Vec3::new(10.0, 0.0, 0.0), Quat::IDENTITY);
+        doc.set_object_property(cylinder, "Placement".to_string(), Property::Placement(placement));
+        doc.recompute();
+
+        let sections = section(&doc, &[], &Plane::from_view(View::Front, 0.0));
+        assert_eq!(sections.iter().map(|s| s.object).collect::<Vec<_>>(), vec![cube, cylinder]);
+        assert_eq!(sections[0].regions.len(), 1);
+        assert!(sections[0].regions[0].holes.is_empty());
+        assert_eq!(sections[0].regions[0].outer.points.len(), 4);
+        assert!((sections[0].area - 8.0).abs() < 1e-4);
+        assert!(sections[0].regions[0].outer.area() > 0.0);
+        // The cylinder stands along Y, so the front plane cuts it lengthwise
+        assert!((sections[1].area - 8.0).abs() < 1e-4);
+        assert!(sections.iter().all(|s| s.open.is_empty()));
+
+        // Across its axis the cylinder cuts into a 32-gon
+        let sections = section(&doc, &[cylinder], &Plane::from_view(View::Top, 0.0));
+        assert_eq!(sections[0].regions[0].outer.points.len(), 32);
+        assert!((sections[0].area - std::f32::consts::PI).abs() < 0.03);
+        assert!(section(&doc, &[], &Plane::from_view(View::Front, 10.0)).is_empty());
+    }
+
+    #[test]
+    fn test_holes() {
+        // A square tube extruded from a profile with a hole
+        let square = |s: f32| Contour::new(vec![Vec2::new(-s, -s), Vec2::new(s, -s), Vec2::new(s, s), Vec2::new(-s, s)]);
+        let mesh = Profile::new(vec![square(2.0), square(1.0)]).extrude(5.0).unwrap();
+        let mut doc = Document::new();
+        let tube = doc.add_object(ShapeType::ImportedMesh { mesh });
+        doc.recompute();
+
+        let sections = section(&doc, &[], &Plane::new(Vec3::new(0.0, 0.0, 2.5), -Vec3::Z).unwrap());
+        assert_eq!(sections[0].object, tube);
+        let region = &sections[0].regions[0];
+        assert_eq!(region.holes.len(), 1);
+        assert!(region.holes[0].area() < 0.0);
+        assert!((sections[0].area - 12.0).abs() < 1e-4);
+        // The section sweeps back into the tube's wall
+        let wall = sections[0].profile().extrude(1.0).unwrap();
+        assert_eq!(wall.triangle_count(), Profile::new(vec![square(2.0), square(1.0)]).extrude(1.0).unwrap().triangle_count());
+    }
+}
@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+
+/// Length unit system of a document
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Units {
+    #[default]
+    Millimeter,
+    Centimeter,
+    Meter,
+    Inch,
+    Foot,
+}
+
+impl Units {
+    /// All unit systems, in the order of their binary codes
+    pub const ALL: [Units; 5] = [Units::Millimeter, Units::Centimeter, Units::Meter, Units::Inch, Units::Foot];
+
+    /// Length of one unit in meters
+    pub fn to_meters(self) -> f64 {
+        match self {
+            Units::Millimeter => 0.001,
+            Units::Centimeter => 0.01,
+            Units::Meter => 1.0,
+            Units::Inch => 0.0254,
+            Units::Foot => 0.3048,
+        }
+    }
+
+    /// Short symbol, e.g. "mm"
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Units::Millimeter => "mm",
+            Units::Centimeter => "cm",
+            Units::Meter => "m",
+            Units::Inch => "in",
+            Units::Foot => "ft",
+        }
+    }
+
+    /// Parse a unit symbol such as "mm" or "in"
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|u| u.symbol() == symbol)
+    }
+
+    /// Compact code used by binary formats
+    pub fn code(self) -> u8 {
+        Self::ALL.iter().position(|u| *u == self).unwrap() as u8
+    }
+
+    /// Inverse of `code`
+    pub fn from_code(code: u8) -> Option<Self> {
+        Self::ALL.get(code as usize).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symbols_and_codes_round_trip() {
+        for units in Units::ALL {
+            assert_eq!(Units::from_symbol(units.symbol()), Some(units));
+            assert_eq!(Units::from_code(units.code()), Some(units));
+        }
+        assert!(Units::from_symbol("furlong").is_none());
+    }
+
+    #[test]
+    fn test_to_meters() {
+        assert_eq!(Units::Millimeter.to_meters(), 0.001);
+        assert_eq!(Units::Inch.to_meters(), 0.0254);
+    }
+}
@@ -0,0 +1,340 @@
+//! Checks that a mesh bounds a solid
+//!
+//! Coincident vertices are welded first, as primitives duplicate them along
+//! seams and creases. Each edge of the welded mesh should then be shared by
+//! exactly two triangles running it in opposite directions, the triangles
+//! around each vertex should form a single fan, and no two triangles should
+//! meet away from their shared edges and vertices.
+
+use glam::Vec3;
+use serde::Serialize;
+use crate::bounds::Aabb;
+use crate::geometry::{mesh_edges, Mesh, MeshEdge};
+use crate::measure::{closest_between_segment_and_triangle, closest_between_triangles};
+
+/// Fraction of the mesh size within which triangles touch, and of a
+/// triangle's longest side squared below which its area counts as none
+const TOLERANCE: f32 = 1e-6;
+
+/// What keeps a mesh from bounding a solid
+///
+/// Triangles are given by their index in the mesh and edges and vertices by
+/// their positions.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ValidationReport {
+    /// Edges with a triangle on one side only, where the surface has holes
+    pub open_edges: Vec<[Vec3; 2]>,
+    /// Edges shared by more than two triangles
+    pub non_manifold_edges: Vec<[Vec3; 2]>,
+    /// Vertices where separate fans of triangles meet, as at the tips of two
+    /// cones
+    pub non_manifold_vertices: Vec<Vec3>,
+    /// Triangles wound against most of their connected neighbours
+    pub flipped_triangles: Vec<usize>,
+    /// Whether a closed, consistently wound surface faces inwards
+    pub inside_out: bool,
+    /// Triangles with no area
+    pub degenerate_triangles: Vec<usize>,
+    /// Pairs of triangles that cross or touch away from their shared edges
+    /// and vertices
+    pub self_intersections: Vec<[usize; 2]>,
+}
+
+impl ValidationReport {
+    /// Whether every edge joins exactly two triangles and every vertex a
+    /// single fan of them
+    pub fn is_watertight(&self) -> bool {
+        self.open_edges.is_empty() && self.non_manifold_edges.is_empty() && self.non_manifold_vertices.is_empty()
+    }
+
+    /// Whether the mesh bounds a solid, facing outwards
+    pub fn is_valid(&self) -> bool {
+        self.is_watertight()
+            && self.flipped_triangles.is_empty()
+            && !self.inside_out
+            && self.degenerate_triangles.is_empty()
+            && self.self_intersections.is_empty()
+    }
+}
+
+/// Whether a triangle runs from `u` to `v` along one of its sides
+fn runs(triangle: [u32; 3], u: u32, v: u32) -> bool {
+    (0..3).any(|i| triangle[i] == u && triangle[(i + 1) % 3] == v)
+}
+
+fn find(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+/// Pairs of triangles whose boxes, grown by `margin`, overlap, found by a
+/// sweep along X
+fn overlapping_pairs(boxes: &[Aabb], margin: f32) -> Vec<[usize; 2]> {
+    let mut order: Vec<usize> = (0..boxes.len()).collect();
+    order.sort_by(|&a, &b| boxes[a].min.x.total_cmp(&boxes[b].min.x));
+    let mut pairs = Vec::new();
+    for (k, &i) in order.iter().enumerate() {
+        for &j in &order[k + 1..] {
+            if boxes[j].min.x > boxes[i].max.x + margin {
+                break;
+            }
+            if boxes[i].gap(&boxes[j]) <= margin {
+                pairs.push([i.min(j), i.max(j)]);
+            }
+        }
+    }
+    pairs
+}
+
+/// Whether two triangles meet anywhere but at the welded vertices they share
+fn intersect(first: [Vec3; 3], second: [Vec3; 3], shared: &[(usize, usize)], tolerance: f32) -> bool {
+    let near = |(p, q): (Vec3, Vec3)| p.distance(q) <= tolerance;
+    match shared {
+        [] => near(closest_between_triangles(first, second)),
+        // Both contain the vertex, so any further overlap runs from it to the
+        // side across from it in one of them
+        [(i, j)] => {
+            near(closest_between_segment_and_triangle([first[(i + 1) % 3], first[(i + 2) % 3]], second))
+                || near(closest_between_segment_and_triangle([second[(j + 1) % 3], second[(j + 2) % 3]], first))
+        }
+        // Neighbours across an edge only overlap if folded onto each other,
+        // which shows as a flipped triangle
+        _ => false,
+    }
+}
+
+/// Whether a triangle has no area, relative to its longest side
+pub(crate) fn is_degenerate([a, b, c]: [Vec3; 3]) -> bool {
+    let longest = (b - a).length_squared().max((c - b).length_squared()).max((a - c).length_squared());
+    (b - a).cross(c - a).length() <= TOLERANCE * longest
+}
+
+/// Patches of triangles connected across manifold edges, each triangle
+/// with whether it is wound against most of its patch
+///
+/// Takes welded triangles and their edges, as from `mesh_edges`.
+pub(crate) fn patches(triangles: &[[u32; 3]], edges: &[MeshEdge]) -> Vec<Vec<(usize, bool)>> {
+    // Neighbours across manifold edges, and whether they are wound against
+    // each other
+    let mut neighbours: Vec<Vec<(usize, bool)>> = vec![Vec::new(); triangles.len()];
+    for edge in edges.iter().filter(|e| e.triangles.len() == 2) {
+        let [s, t] = [edge.triangles[0], edge.triangles[1]];
+        let [u, v] = edge.vertices;
+        let opposed = runs(triangles[s], u, v) == runs(triangles[t], u, v);
+        neighbours[s].push((t, opposed));
+        neighbours[t].push((s, opposed));
+    }
+    // Walk each patch, noting which triangles are wound against the first
+    let mut flipped: Vec<Option<bool>> = vec![None; triangles.len()];
+    let mut patches = Vec::new();
+    for start in 0..triangles.len() {
+        if flipped[start].is_some() {
+            continue;
+        }
+        flipped[start] = Some(false);
+        let mut patch = vec![start];
+        let mut stack = vec![start];
+        while let Some(s) = stack.pop() {
+            let flip = flipped[s] == Some(true);
+            for &(t, opposed) in &neighbours[s] {
+                if flipped[t].is_none() {
+                    flipped[t] = Some(flip != opposed);
+                    patch.push(t);
+                    stack.push(t);
+                }
+            }
+        }
+        // The minority of the patch is the one wound the wrong way
+        let against = patch.iter().filter(|&&t| flipped[t] == Some(true)).count();
+        let wrong = 2 * against <= patch.len();
+        patches.push(patch.into_iter().map(|t| (t, flipped[t] == Some(wrong))).collect());
+    }
+    patches
+}
+
+pub(crate) fn validate(mesh: &Mesh) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    let (positions, remap) = mesh.weld_map();
+    let extent = positions.iter().fold(0.0f32, |m, p| m.max(p.abs().max_element()));
+    let tolerance = extent * TOLERANCE;
+
+    // Triangles whose corners merged drop out of the topology
+    let mut kept = Vec::new();
+    let mut triangles = Vec::new();
+    for index in 0..mesh.triangle_count() {
+        let corners = mesh.triangle_indices(index).map(|v| remap[v as usize]);
+        if is_degenerate(corners.map(|v| positions[v as usize])) {
+            report.degenerate_triangles.push(index);
+        }
+        if corners[0] != corners[1] && corners[1] != corners[2] && corners[2] != corners[0] {
+            kept.push(index);
+            triangles.push(corners);
+        }
+    }
+
+    let edges = mesh_edges(&triangles);
+    // Corners of triangles, `3 * triangle + corner`, joined into the fans
+    // around each vertex through the edges they share
+    let mut parents: Vec<usize> = (0..triangles.len() * 3).collect();
+    let corner = |t: usize, v: u32| 3 * t + triangles[t].iter().position(|&c| c == v).unwrap_or(0);
+    for edge in &edges {
+        let [u, v] = edge.vertices;
+        let segment = [positions[u as usize], positions[v as usize]];
+        match edge.triangles.len() {
+            1 => report.open_edges.push(segment),
+            2 => {}
+            _ => report.non_manifold_edges.push(segment),
+        }
+        let first = edge.triangles[0];
+        for &other in &edge.triangles[1..] {
+            for w in [u, v] {
+                let (a, b) = (find(&mut parents, corner(first, w)), find(&mut parents, corner(other, w)));
+                parents[a] = b;
+            }
+        }
+    }
+    let mut fans: Vec<Vec<usize>> = vec![Vec::new(); positions.len()];
+    for (t, triangle) in triangles.iter().enumerate() {
+        for (k, &v) in triangle.iter().enumerate() {
+            let root = find(&mut parents, 3 * t + k);
+            if !fans[v as usize].contains(&root) {
+                fans[v as usize].push(root);
+            }
+        }
+    }
+    report.non_manifold_vertices = (0..positions.len()).filter(|&v| fans[v].len() > 1).map(|v| positions[v]).collect();
+
+    for patch in patches(&triangles, &edges) {
+        report.flipped_triangles.extend(patch.into_iter().filter(|&(_, wrong)| wrong).map(|(t, _)| kept[t]));
+    }
+    report.flipped_triangles.sort_unstable();
+
+    if report.is_watertight() && !triangles.is_empty() {
+        // Signed volume with the flipped triangles turned back
+        let volume: f64 = triangles
+            .iter()
+            .zip(&kept)
+            .map(|(triangle, index)| {
+                let [a, b, c] = triangle.map(|v| positions[v as usize].as_dvec3());
+                let sign = if report.flipped_triangles.binary_search(index).is_ok() { -1.0 } else { 1.0 };
+                sign * a.dot(b.cross(c))
+            })
+            .sum();
+        report.inside_out = volume < 0.0;
+    }
+
+    let solid: Vec<usize> = (0..triangles.len()).filter(|&t| report.degenerate_triangles.binary_search(&kept[t]).is_err()).collect();
+    let boxes: Vec<Aabb> = solid.iter().map(|&t| Aabb::from_points(triangles[t].map(|v| positions[v as usize]))).collect();
+    for [i, j] in overlapping_pairs(&boxes, tolerance) {
+        let (s, t) = (solid[i], solid[j]);
+        let shared: Vec<(usize, usize)> = (0..3)
+            .filter_map(|a| Some((a, triangles[t].iter().position(|&v| v == triangles[s][a])?)))
+            .collect();
+        let corners = |t: usize| triangles[t].map(|v| positions[v as usize]);
+        if intersect(corners(s), corners(t), &shared, tolerance) {
+            report.self_intersections.push([kept[s], kept[t]]);
+        }
+    }
+    report.self_intersections.sort_unstable();
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{generate_box_mesh, generate_cone_mesh, generate_cylinder_mesh, generate_sphere_mesh};
+
+    fn mesh(vertices: &[Vec3], indices: &[u32]) -> Mesh {
+        let mut mesh = Mesh::new();
+        mesh.vertices = vertices.iter().flat_map(|v| v.to_array()).collect();
+        mesh.normals = vec![0.0; mesh.vertices.len()];
+        mesh.indices = indices.to_vec();
+        mesh
+    }
+
+    #[test]
+    fn test_primitives_are_valid() {
+        // Seam vertices are welded, so the duplicated ones do not open the surface
+        for primitive in [
+            generate_box_mesh(2.0, 3.0, 4.0),
+            generate_cylinder_mesh(1.0, 2.0),
+            generate_sphere_mesh(1.0),
+            generate_cone_mesh(1.0, 0.5, 2.0),
+        ] {
+            let report = primitive.validate();
+            assert!(report.is_valid(), "{:?}", report);
+        }
+    }
+
+    #[test]
+    fn test_open_flipped_and_inside_out() {
+        let mut cube = generate_box_mesh(2.0, 2.0, 2.0);
+        // Turn the first triangle around
+        cube.indices.swap(1, 2);
+        let report = cube.validate();
+        assert!(report.is_watertight());
+        assert_eq!(report.flipped_triangles, vec![0]);
+        assert!(!report.inside_out);
+
+        // Turning every triangle around keeps it consistent but inside out
+        let mut inverted = generate_box_mesh(2.0, 2.0, 2.0);
+        for triangle in inverted.indices.chunks_mut(3) {
+            triangle.swap(1, 2);
+        }
+        let report = inverted.validate();
+        assert!(report.flipped_triangles.is_empty());
+        assert!(report.inside_out && !report.is_valid());
+
+        let mut open = generate_box_mesh(2.0, 2.0, 2.0);
+        open.indices.truncate(30);
+        let report = open.validate();
+        assert_eq!(report.open_edges.len(), 4);
+        assert!(!report.is_watertight() && !report.inside_out);
+    }
+
+    #[test]
+    fn test_non_manifold() {
+        // Three fins on one edge
+        let fins = mesh(
+            &[Vec3::ZERO, Vec3::Z, Vec3::X, Vec3::Y, Vec3::new(-1.0, -1.0, 0.0)],
+            &[0, 1, 2, 0, 1, 3, 0, 1, 4],
+        );
+        let report = fins.validate();
+        assert_eq!(report.non_manifold_edges, vec![[Vec3::ZERO, Vec3::Z]]);
+        assert_eq!(report.open_edges.len(), 6);
+
+        // Two triangles touching at a corner
+        let bowtie = mesh(
+            &[Vec3::ZERO, Vec3::X, Vec3::Y, Vec3::NEG_X, Vec3::NEG_Y],
+            &[0, 1, 2, 0, 3, 4],
+        );
+        let report = bowtie.validate();
+        assert_eq!(report.non_manifold_vertices, vec![Vec3::ZERO]);
+        assert!(report.non_manifold_edges.is_empty());
+        assert!(report.self_intersections.is_empty());
+    }
+
+    #[test]
+    fn test_degenerate_and_self_intersecting() {
+        let sliver = mesh(&[Vec3::ZERO, Vec3::X, Vec3::X * 2.0, Vec3::Y], &[0, 1, 2, 0, 1, 3, 1, 1, 3]);
+        assert_eq!(sliver.validate().degenerate_triangles, vec![0, 2]);
+
+        // Two boxes pushed into each other cross along a loop
+        let mut boxes = generate_box_mesh(2.0, 2.0, 2.0);
+        let moved = crate::math::Placement::from_position_rotation(Vec3::new(1.0, 0.5, 0.5), glam::Quat::IDENTITY);
+        boxes.append(&generate_box_mesh(2.0, 2.0, 2.0).transformed(&moved));
+        let report = boxes.validate();
+        assert!(report.is_watertight());
+        assert!(!report.self_intersections.is_empty());
+        assert!(report.self_intersections.iter().all(|[a, b]| *a < 12 && *b >= 12));
+
+        // Apart, they are two valid shells
+        let mut apart = generate_box_mesh(2.0, 2.0, 2.0);
+        let moved = crate::math::Placement::from_position_rotation(Vec3::new(3.0, 0.0, 0.0), glam::Quat::IDENTITY);
+        apart.append(&generate_box_mesh(2.0, 2.0, 2.0).transformed(&moved));
+        assert!(apart.validate().is_valid());
+    }
+}
@@ -1,4 +1,4 @@
-import { useRef, useEffect } from 'react';
+import { useRef, useEffect, useMemo } from 'react';
 import * as THREE from 'three';
 import { useCADStore } from '../store';
 
@@ -48,7 +48,14 @@ export function RustGeometry({ id }: RustGeometryProps) {
       geometry.setIndex(Array.from(indices));
     }
 
-    geometry.computeBoundingBox();
+    // Bounds come from the core, which caches them with the mesh
+    const bounds: Float32Array = wasm.get_bounding_box(documentId, id, false);
+    if (bounds.length === 6) {
+      geometry.boundingBox = new THREE.Box3(
+        new THREE.Vector3(bounds[0], bounds[1], bounds[2]),
+        new THREE.Vector3(bounds[3], bounds[4], bounds[5]),
+      );
+    }
     geometry.computeBoundingSphere();
 
     if (meshRef.current.geometry) {
@@ -59,6 +66,12 @@ export function RustGeometry({ id }: RustGeometryProps) {
 
   }, [id, documentId, wasm, objectData]);
 
+  // Color and finish from the object's material, refreshed on every sync
+  const appearance = useMemo(() => {
+    const json: string | undefined = wasm && documentId !== null ? wasm.get_object_appearance(documentId, id) : undefined;
+    return json ? (JSON.parse(json) as { color: string; roughness: number; metalness: number }) : null;
+  }, [id, documentId, wasm, objectData]);
+
   // Re-implementation using standard r3f events + simplified custom logic for LongPress/DoubleTap
   const longPressTimer = useRef<number | null>(null);
   const clickCount = useRef(0);
@@ -110,9 +123,9 @@ export function RustGeometry({ id }: RustGeometryProps) {
         }}
     >
       <meshStandardMaterial
-        color={isSelected ? "#3b82f6" : "#71717a"}
-        metalness={0.4}
-        roughness={0.5}
+        color={isSelected ? "#3b82f6" : appearance?.color ?? "#71717a"}
+        metalness={appearance?.metalness ?? 0.4}
+        roughness={appearance?.roughness ?? 0.5}
       />
     </mesh>
   );
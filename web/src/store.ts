@@ -4,13 +4,14 @@ import { persist, createJSONStorage } from 'zustand/middleware';
 // @ts-ignore
 import * as wasmBindings from '@core/rustycad';
 
-export type ShapeType = 'Box' | 'Cylinder' | 'Sphere';
+export type ShapeType = 'Box' | 'Cylinder' | 'Sphere' | 'ImportedMesh';
 
 export interface BaseObject {
   id: number;
   type: ShapeType;
   position: [number, number, number];
   rotation: [number, number, number, number];
+  error?: string | null;
 }
 
 export interface BoxObject extends BaseObject {
@@ -31,15 +32,213 @@ export interface SphereObject extends BaseObject {
   radius: number;
 }
 
-export type CADObject = BoxObject | CylinderObject | SphereObject;
+export interface MeshObject extends BaseObject {
+  type: 'ImportedMesh';
+  name: string;
+}
+
+export type CADObject = BoxObject | CylinderObject | SphereObject | MeshObject;
+
+/** Object description as returned by the core's `list_objects` */
+interface ObjectInfo {
+  id: number;
+  type: ShapeType;
+  params: Record<string, number>;
+  placement: {
+    position: [number, number, number];
+    rotation: [number, number, number, number];
+  };
+  properties?: Record<string, unknown>;
+  error?: string | null;
+}
+
+function fromObjectInfo(info: ObjectInfo): CADObject {
+  const base = {
+    id: info.id,
+    position: info.placement.position,
+    rotation: info.placement.rotation,
+    error: info.error ?? null,
+  };
+  switch (info.type) {
+    case 'Box':
+      return { ...base, type: 'Box', length: info.params.Length, width: info.params.Width, height: info.params.Height };
+    case 'Cylinder':
+      return { ...base, type: 'Cylinder', radius: info.params.Radius, height: info.params.Height };
+    case 'Sphere':
+      return { ...base, type: 'Sphere', radius: info.params.Radius };
+    case 'ImportedMesh': {
+      const name = (info.properties?.Name as { String?: string } | undefined)?.String ?? 'Mesh';
+      return { ...base, type: 'ImportedMesh', name };
+    }
+  }
+}
+
+function toObjectInfo(obj: CADObject): ObjectInfo {
+  let params: Record<string, number>;
+  if (obj.type === 'Box') {
+    params = { Length: obj.length, Width: obj.width, Height: obj.height };
+  } else if (obj.type === 'Cylinder') {
+    params = { Radius: obj.radius, Height: obj.height };
+  } else if (obj.type === 'Sphere') {
+    params = { Radius: obj.radius };
+  } else {
+    // Imported meshes are only restored through the saved document
+    params = {};
+  }
+  return {
+    id: obj.id,
+    type: obj.type,
+    params,
+    placement: { position: obj.position, rotation: obj.rotation },
+  };
+}
+
+/** Mass properties as returned by the core's `get_mass_properties` */
+export interface MassProperties {
+  /** Length unit symbol, e.g. "mm" */
+  units: string;
+  volume: number;
+  area: number;
+  /** Kilograms */
+  mass: number;
+  centroid: [number, number, number];
+  /** Inertia tensor about the centroid in kg·unit², by rows */
+  inertia: [number, number, number][];
+  principal_moments: [number, number, number];
+  principal_axes: [number, number, number][];
+}
+
+export interface PickHit {
+  object_id: number;
+  distance: number;
+  point: [number, number, number];
+  normal: [number, number, number];
+  /** Index of the triangle hit in the object's mesh */
+  triangle: number;
+}
+
+type Vec3 = [number, number, number];
+
+/** Something to measure; faces come from a picked triangle, edges and vertices from a point near them */
+export type MeasureEntity =
+  | { type: 'point'; point: Vec3 }
+  | { type: 'vertex' | 'edge'; object: number; point: Vec3 }
+  | { type: 'face'; object: number; triangle: number }
+  | { type: 'object'; object: number };
+
+export interface DistanceMeasurement {
+  distance: number;
+  /** Closest points on the first and second entity */
+  points: [Vec3, Vec3];
+}
+
+export interface AngleMeasurement {
+  degrees: number;
+  directions: [Vec3, Vec3];
+  /** Points on each entity to draw leaders from */
+  points: [Vec3, Vec3];
+}
+
+export interface Clash {
+  objects: [number, number];
+  kind: 'interference' | 'contact' | 'clearance';
+  /** Minimum distance between the surfaces, 0 where they cross */
+  distance: number;
+  points: [Vec3, Vec3];
+  /** Estimated volume shared by the solids */
+  volume: number;
+}
+
+export interface MeshValidation {
+  watertight: boolean;
+  valid: boolean;
+  open_edges: [Vec3, Vec3][];
+  non_manifold_edges: [Vec3, Vec3][];
+  non_manifold_vertices: Vec3[];
+  flipped_triangles: number[];
+  inside_out: boolean;
+  degenerate_triangles: number[];
+  self_intersections: [number, number][];
+}
+
+export interface MeshRepair {
+  welded_vertices: number;
+  degenerate_triangles: number;
+  duplicate_triangles: number;
+  filled_holes: number;
+  flipped_triangles: number;
+}
+
+export interface MeshDecimation {
+  triangles_before: number;
+  triangles_after: number;
+  /** Furthest the surface moved */
+  error: number;
+}
+
+export interface SectionRegion {
+  /** Counter-clockwise outer loop in the plane's axes */
+  outer: [number, number][];
+  /** Clockwise holes inside it */
+  holes: [number, number][][];
+}
+
+export interface Section {
+  plane: { origin: Vec3; normal: Vec3 };
+  x_axis: Vec3;
+  y_axis: Vec3;
+  objects: {
+    object: number;
+    area: number;
+    regions: SectionRegion[];
+    open: [number, number][][];
+  }[];
+}
+
+export interface CircleMeasurement {
+  center: Vec3;
+  axis: Vec3;
+  radius: number;
+  diameter: number;
+  point: Vec3;
+}
+
+/** Material as listed by the core's `list_materials` */
+export interface Material {
+  name: string;
+  /** Kilograms per cubic meter */
+  density: number;
+  /** Linear RGB */
+  color: [number, number, number];
+  roughness: number;
+  metalness: number;
+  /** Pascals */
+  youngs_modulus?: number;
+  yield_strength?: number;
+  poisson_ratio?: number;
+  builtin: boolean;
+}
+
+/** Offer a byte buffer from the core as a file download */
+function downloadBytes(bytes: Uint8Array, filename: string, mime: string) {
+  const url = URL.createObjectURL(new Blob([bytes as BlobPart], { type: mime }));
+  const link = document.createElement('a');
+  link.href = url;
+  link.download = filename;
+  link.click();
+  URL.revokeObjectURL(url);
+}
 
 interface CADStore {
   wasm: typeof wasmBindings | null;
   documentId: number | null;
   selectedId: number | null;
   objects: CADObject[];
+  /** Native document JSON, persisted between sessions */
+  documentData: string | null;
 
   init: () => Promise<void>;
+  sync: () => void;
 
   addBox: (l: number, w: number, h: number) => void;
   addCylinder: (r: number, h: number) => void;
@@ -51,6 +250,84 @@ interface CADStore {
 
   selectObject: (id: number | null) => void;
   deleteObject: (id: number) => void;
+
+  undo: () => void;
+  redo: () => void;
+
+  /** Import an STL, OBJ, PLY, OFF or STEP file as objects */
+  importMesh: (file: File) => Promise<void>;
+
+  /** Download the selection, or the whole document, as STL */
+  exportStl: (binary?: boolean) => void;
+
+  /** Download the selection, or the whole document, as PLY with vertex colors */
+  exportPly: (binary?: boolean) => void;
+
+  /** Download the selection, or the whole document, as OFF */
+  exportOff: () => void;
+
+  /** Download the selection, or the whole document, as GLB */
+  exportGlb: () => void;
+
+  /** Download the selection, or the whole document, as 3MF for printing */
+  export3mf: () => void;
+
+  /** Download the selection, or the whole document, as a STEP assembly */
+  exportStep: () => void;
+
+  /** Extrude by a height, or revolve by an angle in degrees, the contours of a DXF file */
+  importDxf: (file: File, mode: 'extrude' | 'revolve', amount: number) => Promise<void>;
+
+  /** Download a section or projected outline of the selection, or the whole document, as DXF */
+  exportDxf: (kind: 'section' | 'outline', view?: string, offset?: number) => void;
+
+  /** Download a dimensioned drawing of the selection, or the whole document, as SVG */
+  exportDrawing: (title: string, sheet?: string) => void;
+
+  /** Mass properties of the selection, or the whole document */
+  massProperties: () => MassProperties | null;
+
+  /** World space bounds of the selection, or the whole document, as min and max corners */
+  bounds: () => { min: [number, number, number]; max: [number, number, number] } | null;
+
+  /** Nearest object hit by a world space ray */
+  pick: (origin: [number, number, number], direction: [number, number, number]) => PickHit | null;
+
+  /** Minimum distance between two entities, with the closest points */
+  measureDistance: (a: MeasureEntity, b: MeasureEntity) => DistanceMeasurement | null;
+
+  /** Angle between two faces, edges or axes */
+  measureAngle: (a: MeasureEntity, b: MeasureEntity) => AngleMeasurement | null;
+
+  /** Radius and diameter of a circular edge */
+  measureCircle: (edge: MeasureEntity) => CircleMeasurement | null;
+
+  /** Pairs of objects that overlap, touch or come within a clearance of each other */
+  clashes: (clearance?: number) => Clash[];
+
+  /** Check whether an object's mesh bounds a solid */
+  validateMesh: (id: number) => MeshValidation | null;
+
+  /** Repair an imported mesh so that it bounds a solid */
+  repairMesh: (id: number, tolerance?: number, maxHoleEdges?: number, creaseAngle?: number) => MeshRepair | null;
+
+  /** Simplify an imported mesh to a triangle count or within an error */
+  decimateMesh: (id: number, targetTriangles: number, maxError?: number, creaseAngle?: number) => MeshDecimation | null;
+
+  /** Cut all objects by a plane into closed contours */
+  section: (origin: Vec3, normal: Vec3) => Section | null;
+
+  /** The cut of all objects by a plane as an SVG drawing */
+  exportSectionSvg: (origin: Vec3, normal: Vec3) => string;
+
+  /** Built-in and user-defined materials */
+  materials: () => Material[];
+
+  /** Add or replace a user-defined material */
+  addMaterial: (material: Omit<Material, 'builtin'>) => void;
+
+  /** Assign a material to an object, or clear it with an empty name */
+  setMaterial: (id: number, name: string) => void;
 }
 
 export const useCADStore = create<CADStore>()(
@@ -60,6 +337,7 @@ export const useCADStore = create<CADStore>()(
       documentId: null,
       selectedId: null,
       objects: [],
+      documentData: null,
 
       init: async () => {
         try {
@@ -70,8 +348,35 @@ export const useCADStore = create<CADStore>()(
           // Call the Rust-side init function
           wasmBindings.init();
 
-          // Create a new document
-          const docId = wasmBindings.create_document();
+          // Load the saved document, falling back to a new one
+          const { documentData, objects } = get();
+          let docId = 0;
+          if (documentData) {
+            docId = wasmBindings.load_document(new TextEncoder().encode(documentData));
+            if (!docId) {
+              console.error('Failed to load saved document:', wasmBindings.take_last_error());
+            } else {
+              const report = JSON.parse(wasmBindings.take_last_report() ?? 'null');
+              if (report && report.changes.length > 0) {
+                console.log(`Upgraded saved document from version ${report.from_version}:`, report.changes);
+              }
+            }
+          }
+          if (!docId) {
+            docId = wasmBindings.create_document();
+
+            // Restore objects persisted by older versions, which stored the object list
+            if (!documentData && objects.length > 0) {
+              console.log(`Restoring ${objects.length} objects from persistence...`);
+              objects.forEach((obj) => {
+                if (!wasmBindings.restore_object(docId, JSON.stringify(toObjectInfo(obj)))) {
+                  console.error(`Failed to restore object ${obj.id}:`, wasmBindings.take_last_error());
+                }
+              });
+              // Replaying the session is not something the user should undo
+              wasmBindings.clear_history(docId);
+            }
+          }
 
           set({
             wasm: wasmBindings,
@@ -79,53 +384,36 @@ export const useCADStore = create<CADStore>()(
           });
           console.log('RustyCAD Core Initialized. Document ID:', docId);
 
-          // Restore objects from persistence to WASM
-          const { objects } = get();
-          if (objects.length > 0) {
-            console.log(`Restoring ${objects.length} objects from persistence...`);
-            objects.forEach((obj) => {
-              if (obj.type === 'Box') {
-                const box = obj as BoxObject;
-                wasmBindings.restore_box(docId, box.id, box.length, box.width, box.height);
-              } else if (obj.type === 'Cylinder') {
-                const cyl = obj as CylinderObject;
-                wasmBindings.restore_cylinder(docId, cyl.id, cyl.radius, cyl.height);
-              } else if (obj.type === 'Sphere') {
-                const sph = obj as SphereObject;
-                wasmBindings.restore_sphere(docId, sph.id, sph.radius);
-              }
-
-              // Restore placement
-              wasmBindings.update_placement(
-                docId,
-                obj.id,
-                obj.position[0],
-                obj.position[1],
-                obj.position[2],
-                obj.rotation[0],
-                obj.rotation[1],
-                obj.rotation[2],
-                obj.rotation[3]
-              );
-            });
-          }
+          get().sync();
         } catch (err) {
           console.error('Failed to initialize RustyCAD Core:', err);
         }
       },
 
+      sync: () => {
+        const { wasm, documentId } = get();
+        if (!wasm || documentId === null) return;
+
+        const infos: ObjectInfo[] = JSON.parse(wasm.list_objects(documentId));
+        const objects = infos.map(fromObjectInfo);
+        set((state) => ({
+          objects,
+          documentData: wasm.save_document(documentId),
+          selectedId: objects.some((obj) => obj.id === state.selectedId) ? state.selectedId : null,
+        }));
+      },
+
       addBox: (l, w, h) => {
     const { wasm, documentId } = get();
     if (!wasm || documentId === null) return;
 
     const id = wasm.add_box(documentId, l, w, h);
-    set((state) => ({
-      objects: [...state.objects, {
-        id, type: 'Box', length: l, width: w, height: h,
-        position: [0, 0, 0], rotation: [0, 0, 0, 1]
-      }],
-      selectedId: id,
-    }));
+    if (!id) {
+      console.error('Invalid shape parameters:', wasm.take_last_error());
+      return;
+    }
+    get().sync();
+    set({ selectedId: id });
   },
 
   addCylinder: (r, h) => {
@@ -133,13 +421,12 @@ export const useCADStore = create<CADStore>()(
     if (!wasm || documentId === null) return;
 
     const id = wasm.add_cylinder(documentId, r, h);
-    set((state) => ({
-      objects: [...state.objects, {
-        id, type: 'Cylinder', radius: r, height: h,
-        position: [0, 0, 0], rotation: [0, 0, 0, 1]
-      }],
-      selectedId: id,
-    }));
+    if (!id) {
+      console.error('Invalid shape parameters:', wasm.take_last_error());
+      return;
+    }
+    get().sync();
+    set({ selectedId: id });
   },
 
   addSphere: (r) => {
@@ -147,13 +434,12 @@ export const useCADStore = create<CADStore>()(
     if (!wasm || documentId === null) return;
 
     const id = wasm.add_sphere(documentId, r);
-    set((state) => ({
-      objects: [...state.objects, {
-        id, type: 'Sphere', radius: r,
-        position: [0, 0, 0], rotation: [0, 0, 0, 1]
-      }],
-      selectedId: id,
-    }));
+    if (!id) {
+      console.error('Invalid shape parameters:', wasm.take_last_error());
+      return;
+    }
+    get().sync();
+    set({ selectedId: id });
   },
 
   updateShape: (id, p1, p2 = 0, p3 = 0) => {
@@ -164,20 +450,9 @@ export const useCADStore = create<CADStore>()(
     const success = wasm.update_shape_params(documentId, id, p1, p2, p3);
 
     if (success) {
-      set((state) => ({
-        objects: state.objects.map((obj) => {
-          if (obj.id !== id) return obj;
-
-          if (obj.type === 'Box') {
-            return { ...obj, length: p1, width: p2, height: p3 };
-          } else if (obj.type === 'Cylinder') {
-            return { ...obj, radius: p1, height: p2 };
-          } else if (obj.type === 'Sphere') {
-            return { ...obj, radius: p1 };
-          }
-          return obj;
-        }),
-      }));
+      get().sync();
+    } else {
+      console.error('Invalid shape parameters:', wasm.take_last_error());
     }
   },
 
@@ -194,11 +469,7 @@ export const useCADStore = create<CADStore>()(
         rotation[0], rotation[1], rotation[2], rotation[3]
     );
 
-    set((state) => ({
-      objects: state.objects.map((obj) =>
-        obj.id === id ? { ...obj, position, rotation } : obj
-      ),
-    }));
+    get().sync();
   },
 
   updateShapeParams: (id, params) => {
@@ -229,17 +500,322 @@ export const useCADStore = create<CADStore>()(
 
         const success = wasm.delete_object(documentId, id);
         if (success) {
-          set((state) => ({
-            objects: state.objects.filter((obj) => obj.id !== id),
-            selectedId: state.selectedId === id ? null : state.selectedId,
-          }));
+          get().sync();
+        }
+      },
+
+      undo: () => {
+        const { wasm, documentId } = get();
+        if (!wasm || documentId === null) return;
+
+        const affected: Uint32Array = wasm.undo(documentId);
+        if (affected.length > 0) {
+          get().sync();
+        }
+      },
+
+      redo: () => {
+        const { wasm, documentId } = get();
+        if (!wasm || documentId === null) return;
+
+        const affected: Uint32Array = wasm.redo(documentId);
+        if (affected.length > 0) {
+          get().sync();
+        }
+      },
+
+      importMesh: async (file) => {
+        const { wasm, documentId } = get();
+        if (!wasm || documentId === null) return;
+
+        const bytes = new Uint8Array(await file.arrayBuffer());
+        const name = file.name.toLowerCase();
+        let ids: number[];
+        if (name.endsWith('.obj')) {
+          ids = Array.from(wasm.import_obj(documentId, new TextDecoder().decode(bytes)) as Uint32Array);
+        } else if (name.endsWith('.ply')) {
+          ids = [wasm.import_ply(documentId, bytes)].filter((id) => id !== 0);
+        } else if (name.endsWith('.off')) {
+          ids = [wasm.import_off(documentId, new TextDecoder().decode(bytes))].filter((id) => id !== 0);
+        } else if (name.endsWith('.step') || name.endsWith('.stp')) {
+          ids = Array.from(wasm.import_step(documentId, new TextDecoder().decode(bytes)) as Uint32Array);
+          const report = wasm.take_last_report();
+          if (report) console.info(`Imported ${file.name}:`, JSON.parse(report));
+        } else {
+          ids = [wasm.import_stl(documentId, bytes)].filter((id) => id !== 0);
+        }
+        if (ids.length === 0) {
+          console.error(`Failed to import ${file.name}:`, wasm.take_last_error() ?? 'no solids found');
+          return;
+        }
+        get().sync();
+        set({ selectedId: ids[0] });
+      },
+
+      exportStl: (binary = true) => {
+        const { wasm, documentId, selectedId } = get();
+        if (!wasm || documentId === null) return;
+
+        const ids = new Uint32Array(selectedId !== null ? [selectedId] : []);
+        const bytes: Uint8Array = wasm.export_stl(documentId, ids, binary);
+        downloadBytes(bytes, 'rustycad.stl', 'model/stl');
+      },
+
+      exportPly: (binary = true) => {
+        const { wasm, documentId, selectedId } = get();
+        if (!wasm || documentId === null) return;
+
+        const ids = new Uint32Array(selectedId !== null ? [selectedId] : []);
+        const bytes: Uint8Array = wasm.export_ply(documentId, ids, binary, true);
+        downloadBytes(bytes, 'rustycad.ply', 'application/octet-stream');
+      },
+
+      exportOff: () => {
+        const { wasm, documentId, selectedId } = get();
+        if (!wasm || documentId === null) return;
+
+        const ids = new Uint32Array(selectedId !== null ? [selectedId] : []);
+        const text: string = wasm.export_off(documentId, ids);
+        downloadBytes(new TextEncoder().encode(text), 'rustycad.off', 'text/plain');
+      },
+
+      exportGlb: () => {
+        const { wasm, documentId, selectedId } = get();
+        if (!wasm || documentId === null) return;
+
+        const ids = new Uint32Array(selectedId !== null ? [selectedId] : []);
+        const bytes: Uint8Array = wasm.export_glb(documentId, ids);
+        downloadBytes(bytes, 'rustycad.glb', 'model/gltf-binary');
+      },
+
+      export3mf: () => {
+        const { wasm, documentId, selectedId } = get();
+        if (!wasm || documentId === null) return;
+
+        const ids = new Uint32Array(selectedId !== null ? [selectedId] : []);
+        const bytes: Uint8Array = wasm.export_3mf(documentId, ids);
+        downloadBytes(bytes, 'rustycad.3mf', 'model/3mf');
+      },
+
+      exportStep: () => {
+        const { wasm, documentId, selectedId } = get();
+        if (!wasm || documentId === null) return;
+
+        const ids = new Uint32Array(selectedId !== null ? [selectedId] : []);
+        const text: string = wasm.export_step(documentId, ids, new Date().toISOString().slice(0, 19));
+        downloadBytes(new TextEncoder().encode(text), 'rustycad.step', 'model/step');
+      },
+
+      importDxf: async (file, mode, amount) => {
+        const { wasm, documentId } = get();
+        if (!wasm || documentId === null) return;
+
+        const text = await file.text();
+        const id: number = mode === 'extrude'
+          ? wasm.extrude_dxf(documentId, text, amount)
+          : wasm.revolve_dxf(documentId, text, amount);
+        const report = wasm.take_last_report();
+        if (report) console.info(`Read ${file.name}:`, JSON.parse(report));
+        if (id === 0) {
+          console.error(`Failed to import ${file.name}:`, wasm.take_last_error());
+          return;
+        }
+        get().sync();
+        set({ selectedId: id });
+      },
+
+      exportDxf: (kind, view = 'front', offset = 0) => {
+        const { wasm, documentId, selectedId } = get();
+        if (!wasm || documentId === null) return;
+
+        const ids = new Uint32Array(selectedId !== null ? [selectedId] : []);
+        const text: string = kind === 'section'
+          ? wasm.export_dxf_section(documentId, ids, view, offset)
+          : wasm.export_dxf_outline(documentId, ids, view);
+        if (!text) {
+          console.error('Failed to export DXF:', wasm.take_last_error());
+          return;
+        }
+        downloadBytes(new TextEncoder().encode(text), `rustycad-${kind}.dxf`, 'image/vnd.dxf');
+      },
+
+      exportDrawing: (title, sheet = 'A3') => {
+        const { wasm, documentId, selectedId } = get();
+        if (!wasm || documentId === null) return;
+
+        const ids = new Uint32Array(selectedId !== null ? [selectedId] : []);
+        const date = new Date().toISOString().slice(0, 10);
+        const text: string = wasm.export_drawing_svg(documentId, ids, title, sheet, date, true);
+        if (!text) {
+          console.error('Failed to export drawing:', wasm.take_last_error());
+          return;
+        }
+        downloadBytes(new TextEncoder().encode(text), 'rustycad-drawing.svg', 'image/svg+xml');
+      },
+
+      massProperties: () => {
+        const { wasm, documentId, selectedId } = get();
+        if (!wasm || documentId === null) return null;
+
+        const ids = new Uint32Array(selectedId !== null ? [selectedId] : []);
+        const json: string | undefined = wasm.get_mass_properties(documentId, ids);
+        if (!json) {
+          console.error('Failed to compute mass properties:', wasm.take_last_error());
+          return null;
+        }
+        return JSON.parse(json) as MassProperties;
+      },
+
+      bounds: () => {
+        const { wasm, documentId, selectedId } = get();
+        if (!wasm || documentId === null) return null;
+
+        const ids = new Uint32Array(selectedId !== null ? [selectedId] : []);
+        const b: Float32Array = wasm.get_document_bounding_box(documentId, ids);
+        if (b.length !== 6) return null;
+        return { min: [b[0], b[1], b[2]], max: [b[3], b[4], b[5]] };
+      },
+
+      pick: (origin, direction) => {
+        const { wasm, documentId } = get();
+        if (!wasm || documentId === null) return null;
+
+        const json: string | undefined = wasm.pick(documentId, new Float32Array(origin), new Float32Array(direction));
+        return json ? (JSON.parse(json) as PickHit) : null;
+      },
+
+      measureDistance: (a, b) => {
+        const { wasm, documentId } = get();
+        if (!wasm || documentId === null) return null;
+
+        const json: string | undefined = wasm.measure_distance(documentId, JSON.stringify(a), JSON.stringify(b));
+        if (!json) {
+          console.error('Failed to measure distance:', wasm.take_last_error());
+          return null;
+        }
+        return JSON.parse(json) as DistanceMeasurement;
+      },
+
+      measureAngle: (a, b) => {
+        const { wasm, documentId } = get();
+        if (!wasm || documentId === null) return null;
+
+        const json: string | undefined = wasm.measure_angle(documentId, JSON.stringify(a), JSON.stringify(b));
+        if (!json) {
+          console.error('Failed to measure angle:', wasm.take_last_error());
+          return null;
+        }
+        return JSON.parse(json) as AngleMeasurement;
+      },
+
+      measureCircle: (edge) => {
+        const { wasm, documentId } = get();
+        if (!wasm || documentId === null) return null;
+
+        const json: string | undefined = wasm.measure_circle(documentId, JSON.stringify(edge));
+        if (!json) {
+          console.error('Failed to measure circle:', wasm.take_last_error());
+          return null;
+        }
+        return JSON.parse(json) as CircleMeasurement;
+      },
+
+      clashes: (clearance = 0) => {
+        const { wasm, documentId } = get();
+        if (!wasm || documentId === null) return [];
+
+        const json: string | undefined = wasm.check_clashes(documentId, new Uint32Array([]), clearance);
+        if (!json) {
+          console.error('Failed to check clashes:', wasm.take_last_error());
+          return [];
+        }
+        return JSON.parse(json) as Clash[];
+      },
+
+      validateMesh: (id) => {
+        const { wasm, documentId } = get();
+        if (!wasm || documentId === null) return null;
+
+        const json: string | undefined = wasm.validate_mesh(documentId, id);
+        return json ? (JSON.parse(json) as MeshValidation) : null;
+      },
+
+      repairMesh: (id, tolerance = 0, maxHoleEdges = 16, creaseAngle = 30) => {
+        const { wasm, documentId } = get();
+        if (!wasm || documentId === null) return null;
+
+        const json: string | undefined = wasm.repair_mesh(documentId, id, tolerance, maxHoleEdges, creaseAngle);
+        if (!json) {
+          console.error('Failed to repair mesh:', wasm.take_last_error());
+          return null;
+        }
+        get().sync();
+        return JSON.parse(json) as MeshRepair;
+      },
+
+      decimateMesh: (id, targetTriangles, maxError = Infinity, creaseAngle = 30) => {
+        const { wasm, documentId } = get();
+        if (!wasm || documentId === null) return null;
+
+        const json: string | undefined = wasm.decimate_mesh(documentId, id, targetTriangles, maxError, creaseAngle);
+        if (!json) {
+          console.error('Failed to decimate mesh:', wasm.take_last_error());
+          return null;
+        }
+        get().sync();
+        return JSON.parse(json) as MeshDecimation;
+      },
+
+      section: (origin, normal) => {
+        const { wasm, documentId } = get();
+        if (!wasm || documentId === null) return null;
+
+        const json: string | undefined = wasm.section(documentId, new Uint32Array([]), new Float32Array(origin), new Float32Array(normal));
+        if (!json) {
+          console.error('Failed to cut section:', wasm.take_last_error());
+          return null;
+        }
+        return JSON.parse(json) as Section;
+      },
+
+      exportSectionSvg: (origin, normal) => {
+        const { wasm, documentId } = get();
+        if (!wasm || documentId === null) return '';
+        return wasm.export_svg_section(documentId, new Uint32Array([]), new Float32Array(origin), new Float32Array(normal));
+      },
+
+      materials: () => {
+        const { wasm, documentId } = get();
+        if (!wasm || documentId === null) return [];
+        return JSON.parse(wasm.list_materials(documentId) ?? '[]') as Material[];
+      },
+
+      addMaterial: (material) => {
+        const { wasm, documentId } = get();
+        if (!wasm || documentId === null) return;
+
+        if (!wasm.add_material(documentId, JSON.stringify(material))) {
+          console.error('Invalid material:', wasm.take_last_error());
+          return;
+        }
+        get().sync();
+      },
+
+      setMaterial: (id, name) => {
+        const { wasm, documentId } = get();
+        if (!wasm || documentId === null) return;
+
+        if (!wasm.set_object_material(documentId, id, name)) {
+          console.error('Failed to set material:', wasm.take_last_error());
+          return;
         }
+        get().sync();
       },
     }),
     {
       name: 'rusty-cad-scene',
       storage: createJSONStorage(() => localStorage),
-      partialize: (state) => ({ objects: state.objects }),
+      partialize: (state) => ({ documentData: state.documentData }),
     }
   )
 );